@@ -0,0 +1,95 @@
+//! Thread contention: N threads churning allocations through one allocator.
+//!
+//! Every thread repeatedly allocates a small block, writes it and frees it,
+//! each call taking the wrapper's lock on its own, so the threads fight over
+//! the lock the way real programs do. Compared:
+//!
+//! ```text
+//!   per-thread       one BumpAllocator per thread, no sharing (the floor)
+//!   SharedArena      one arena behind a std Mutex
+//!   critical-section CriticalSectionAllocator, with `--features critical-section`
+//!                    (critical-section/std: one process-wide lock)
+//! ```
+//!
+//! ```bash
+//! cargo bench --bench contention --features critical-section
+//! ```
+//!
+//! Lock-free variants (an atomic bump pointer, per-thread shards) would slot
+//! in as further rows; until they exist, the gap between the first two rows
+//! is what they could win.
+
+use std::{alloc::Layout, hint::black_box, thread, time::Instant};
+
+#[cfg(feature = "critical-section")]
+use rallocator::CriticalSectionAllocator;
+use rallocator::{BumpAllocator, SharedArena};
+
+/// Allocate/free pairs per thread per measurement.
+const OPS: usize = 20_000;
+
+/// Thread counts measured.
+const THREADS: [usize; 4] = [1, 2, 4, 8];
+
+/// The churned allocation.
+const LAYOUT: Layout = Layout::new::<[u64; 4]>();
+
+/// Room for every block staying behind as a hole, headers and padding
+/// included: frees interleaved with another thread's allocations are not at
+/// the tail, so the heap cannot shrink back. Each measurement gets a fresh
+/// arena.
+const CAPACITY: usize = 8 * OPS * 128;
+
+/// `OPS` allocate, write, free cycles through `with`, which holds the lock
+/// for one call.
+fn churn(mut with: impl FnMut(&mut dyn FnMut(&mut BumpAllocator))) {
+  for i in 0..OPS {
+    let mut ptr = std::ptr::null_mut();
+    with(&mut |allocator| ptr = unsafe { allocator.allocate(LAYOUT) });
+    assert!(!ptr.is_null());
+    unsafe { ptr.write_volatile(i as u8) };
+    with(&mut |allocator| unsafe { allocator.deallocate(black_box(ptr)) });
+  }
+}
+
+/// Wall-clock nanoseconds per allocate/free pair, over all pairs of
+/// `threads` threads each running `work`.
+fn measure(
+  threads: usize,
+  work: impl Fn() + Sync,
+) -> f64 {
+  let start = Instant::now();
+  thread::scope(|scope| {
+    for _ in 0..threads {
+      scope.spawn(&work);
+    }
+  });
+  start.elapsed().as_nanos() as f64 / (threads * OPS) as f64
+}
+
+fn main() {
+  println!("{:<18} {:>8} {:>12}", "allocator", "threads", "ns/pair");
+
+  for threads in THREADS {
+    let time = measure(threads, || {
+      let mut arena = BumpAllocator::with_capacity(CAPACITY);
+      churn(|f| f(&mut arena));
+    });
+    println!("{:<18} {:>8} {:>12.1}", "per-thread", threads, time);
+  }
+
+  for threads in THREADS {
+    let shared = SharedArena::with_capacity(CAPACITY);
+    let time = measure(threads, || churn(|f| shared.with(|allocator| f(allocator))));
+    println!("{:<18} {:>8} {:>12.1}", "SharedArena", threads, time);
+  }
+
+  #[cfg(feature = "critical-section")]
+  for threads in THREADS {
+    let heap = CriticalSectionAllocator::new();
+    let region = Box::leak(vec![0u8; CAPACITY].into_boxed_slice());
+    unsafe { heap.init(region.as_mut_ptr(), region.len()) };
+    let time = measure(threads, || churn(|f| heap.with(|allocator| f(allocator))));
+    println!("{:<18} {:>8} {:>12.1}", "critical-section", threads, time);
+  }
+}
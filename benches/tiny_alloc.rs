@@ -0,0 +1,55 @@
+//! Tiny-allocation throughput of the bump fast path.
+//!
+//! Fills a region arena with small allocations, resets it, and repeats, so
+//! the timing covers `allocate` alone: no system calls and no free list.
+//!
+//! ```bash
+//! cargo bench --bench tiny_alloc
+//! ```
+//!
+//! Plain `harness = false` benchmark timed with `Instant`, so it runs on
+//! stable without extra dependencies.
+
+use std::{alloc::Layout, hint::black_box, time::Instant};
+
+use rallocator::BumpAllocator;
+
+/// Allocations per round, each followed by one `reset`.
+const PER_ROUND: usize = 4096;
+
+/// Rounds per measurement.
+const ROUNDS: usize = 2_000;
+
+/// Average nanoseconds per `allocate(layout)` call.
+fn measure(layout: Layout) -> f64 {
+  // Room for every block of a round, headers and padding included
+  let mut allocator = BumpAllocator::with_capacity(PER_ROUND * (128 + layout.size() + layout.align()));
+
+  let start = Instant::now();
+  for _ in 0..ROUNDS {
+    for _ in 0..PER_ROUND {
+      let ptr = unsafe { allocator.allocate(black_box(layout)) };
+      assert!(!ptr.is_null());
+      black_box(ptr);
+    }
+    unsafe { allocator.reset() };
+  }
+  start.elapsed().as_nanos() as f64 / (ROUNDS * PER_ROUND) as f64
+}
+
+fn main() {
+  let cases = [
+    ("u8", Layout::new::<u8>()),
+    ("u64", Layout::new::<u64>()),
+    ("[u8; 24]", Layout::new::<[u8; 24]>()),
+    ("16 B, align 16", Layout::from_size_align(16, 16).unwrap()),
+    ("8 B, align 64", Layout::from_size_align(8, 64).unwrap()),
+  ];
+
+  println!("{:<16} {:>10}", "layout", "ns/alloc");
+  for (name, layout) in cases {
+    // Warm up once so the first case does not pay for page faults
+    measure(layout);
+    println!("{:<16} {:>10.2}", name, measure(layout));
+  }
+}
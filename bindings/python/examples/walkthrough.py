@@ -0,0 +1,27 @@
+"""Watch fragmentation appear, step by step.
+
+    cd bindings/python && maturin develop && python examples/walkthrough.py
+"""
+
+import rallocator
+
+arena = rallocator.Arena(4096, mode="first_fit")
+
+print("1) Three allocations, bumped one after another")
+a = arena.allocate(64)
+b = arena.allocate(128)
+c = arena.allocate(32)
+print(arena.render_map(), end="\n\n")
+
+print("2) Freeing the middle block leaves a hole")
+arena.deallocate(b)
+print(arena.render_map(), end="\n\n")
+
+print("3) Freeing the tail block gives its memory back")
+arena.deallocate(c)
+print(arena.render_map(), end="\n\n")
+
+print("4) reset() frees everything at once")
+arena.reset()
+print(arena.render_map())
+print(arena.stats())
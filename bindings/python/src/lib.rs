@@ -0,0 +1,158 @@
+//! # Python bindings
+//!
+//! Exposes a [`BumpAllocator`] to Python so the block list can be driven
+//! and inspected interactively, e.g. from a notebook:
+//!
+//! ```text
+//!   >>> import rallocator
+//!   >>> arena = rallocator.Arena(4096, mode="best_fit")
+//!   >>> a = arena.allocate(37)
+//!   >>> b = arena.allocate(128)
+//!   >>> arena.deallocate(a)
+//!   >>> print(arena.render_map())
+//!      #  address             state        size
+//!      0  0x00007f3a5c000020  free           37
+//!      1  0x00007f3a5c000070  used          128
+//!   1 live (128 B) · 1 free (37 B) · heap 248 B
+//! ```
+//!
+//! Every arena owns a fixed region from the system allocator, never the
+//! program break - the Python interpreter's own heap must stay untouched.
+//!
+//! Addresses are plain integers on the Python side. The arena remembers
+//! which ones it handed out, so passing a wrong address to `deallocate`
+//! raises `ValueError` instead of corrupting the block list.
+
+use std::alloc::Layout;
+
+use pyo3::{
+  exceptions::{PyMemoryError, PyValueError},
+  prelude::*,
+  types::PyDict,
+};
+use rallocator::{BumpAllocator, SearchMode};
+
+/// A bump allocator over a fixed region, driven from Python.
+///
+/// Not thread-safe: an arena can only be used from the thread that
+/// created it.
+#[pyclass(unsendable, module = "rallocator")]
+struct Arena {
+  /// The allocator being driven.
+  inner: BumpAllocator,
+
+  /// Addresses handed out and not yet deallocated.
+  live: Vec<usize>,
+}
+
+#[pymethods]
+impl Arena {
+  /// Creates an arena of `capacity` bytes.
+  ///
+  /// `mode` is one of `"first_fit"`, `"next_fit"` or `"best_fit"`.
+  #[new]
+  #[pyo3(signature = (capacity = 64 * 1024, mode = "first_fit"))]
+  fn new(
+    capacity: usize,
+    mode: &str,
+  ) -> PyResult<Self> {
+    let mut inner = BumpAllocator::with_capacity(capacity);
+    inner.set_search_mode(parse_mode(mode)?);
+    Ok(Self { inner, live: Vec::new() })
+  }
+
+  /// Allocates `size` bytes aligned to `align` and returns the address.
+  ///
+  /// Raises `MemoryError` when the arena is exhausted.
+  #[pyo3(signature = (size, align = 8))]
+  fn allocate(
+    &mut self,
+    size: usize,
+    align: usize,
+  ) -> PyResult<usize> {
+    let layout = Layout::from_size_align(size, align).map_err(|err| PyValueError::new_err(err.to_string()))?;
+
+    // SAFETY: `&mut self` gives exclusive access to the allocator.
+    let ptr = unsafe { self.inner.allocate(layout) };
+    if ptr.is_null() {
+      return Err(PyMemoryError::new_err(format!("arena cannot fit {size} more bytes")));
+    }
+
+    self.live.push(ptr as usize);
+    Ok(ptr as usize)
+  }
+
+  /// Frees an address previously returned by `allocate`.
+  ///
+  /// Raises `ValueError` for unknown or already freed addresses.
+  fn deallocate(
+    &mut self,
+    address: usize,
+  ) -> PyResult<()> {
+    let Some(index) = self.live.iter().position(|&live| live == address) else {
+      return Err(PyValueError::new_err(format!("{address:#x} is not a live allocation")));
+    };
+    self.live.swap_remove(index);
+
+    // SAFETY: `address` came from `allocate` on this arena and is freed once.
+    unsafe { self.inner.deallocate(address as *mut u8) };
+    Ok(())
+  }
+
+  /// Frees every allocation at once.
+  fn reset(&mut self) {
+    self.live.clear();
+    // SAFETY: The only handles to the memory are integer addresses, which
+    // `live` no longer accepts.
+    unsafe { self.inner.reset() };
+  }
+
+  /// Returns the allocator statistics as a `dict`.
+  fn stats<'py>(
+    &self,
+    py: Python<'py>,
+  ) -> PyResult<Bound<'py, PyDict>> {
+    let stats = self.inner.stats();
+    let dict = PyDict::new(py);
+    dict.set_item("live_blocks", stats.live_blocks)?;
+    dict.set_item("free_blocks", stats.free_blocks)?;
+    dict.set_item("bytes_in_use", stats.bytes_in_use)?;
+    dict.set_item("bytes_free", stats.bytes_free)?;
+    dict.set_item("heap_bytes", stats.heap_bytes)?;
+    Ok(dict)
+  }
+
+  /// Renders the block list as a table, one line per block.
+  fn render_map(&self) -> String {
+    self.inner.heap_map().to_string()
+  }
+
+  fn __repr__(&self) -> String {
+    let stats = self.inner.stats();
+    format!(
+      "Arena(mode={:?}, live_blocks={}, heap_bytes={})",
+      self.inner.search_mode(),
+      stats.live_blocks,
+      stats.heap_bytes
+    )
+  }
+}
+
+/// Maps a Python-side mode name to a [`SearchMode`].
+fn parse_mode(mode: &str) -> PyResult<SearchMode> {
+  match mode {
+    "first_fit" => Ok(SearchMode::FirstFit),
+    "next_fit" => Ok(SearchMode::NextFit),
+    "best_fit" => Ok(SearchMode::BestFit),
+    _ => Err(PyValueError::new_err(format!(
+      "unknown mode {mode:?}, expected \"first_fit\", \"next_fit\" or \"best_fit\""
+    ))),
+  }
+}
+
+#[pymodule]
+#[pyo3(name = "rallocator")]
+fn rallocator_py(module: &Bound<'_, PyModule>) -> PyResult<()> {
+  module.add_class::<Arena>()?;
+  Ok(())
+}
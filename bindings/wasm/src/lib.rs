@@ -0,0 +1,131 @@
+//! # Browser demo
+//!
+//! A [`BumpAllocator`] compiled to WebAssembly and driven from a page
+//! (`www/index.html`): click "alloc 37 bytes", "free #3", and the heap map
+//! below the buttons is re-rendered after every step.
+//!
+//! ```text
+//!   index.html ── click ──► Heap::alloc(37) ──► BumpAllocator::allocate
+//!        ▲                                              │
+//!        └──────── Heap::render() ◄─── heap_map() ◄─────┘
+//! ```
+//!
+//! wasm32 has no program break, so the allocator uses the region backend
+//! (a fixed reservation inside the module's linear memory).
+//!
+//! Allocations are identified by small handles (`#1`, `#2`, ...) rather
+//! than raw addresses, so the page never passes a bogus pointer back.
+
+use std::{alloc::Layout, fmt::Write};
+
+use rallocator::{BumpAllocator, SearchMode};
+use wasm_bindgen::prelude::*;
+
+/// A bump allocator over a fixed region, plus the handles the page knows.
+#[wasm_bindgen]
+pub struct Heap {
+  /// The allocator being driven.
+  inner: BumpAllocator,
+
+  /// Live allocations as `(handle, address)`, in allocation order.
+  live: Vec<(u32, usize)>,
+
+  /// Handle given to the next allocation.
+  next_handle: u32,
+}
+
+#[wasm_bindgen]
+impl Heap {
+  /// Creates a heap of `capacity` bytes.
+  ///
+  /// `mode` is one of `"first_fit"`, `"next_fit"` or `"best_fit"`;
+  /// anything else falls back to first fit.
+  #[wasm_bindgen(constructor)]
+  pub fn new(
+    capacity: usize,
+    mode: &str,
+  ) -> Heap {
+    let mut inner = BumpAllocator::with_capacity(capacity);
+    inner.set_search_mode(match mode {
+      "next_fit" => SearchMode::NextFit,
+      "best_fit" => SearchMode::BestFit,
+      _ => SearchMode::FirstFit,
+    });
+
+    Heap {
+      inner,
+      live: Vec::new(),
+      next_handle: 1,
+    }
+  }
+
+  /// Allocates `size` bytes and returns the new handle, or `undefined`
+  /// when the heap is full.
+  pub fn alloc(
+    &mut self,
+    size: usize,
+  ) -> Option<u32> {
+    let layout = Layout::from_size_align(size, 8).ok()?;
+
+    // SAFETY: `&mut self` gives exclusive access to the allocator.
+    let ptr = unsafe { self.inner.allocate(layout) };
+    if ptr.is_null() {
+      return None;
+    }
+
+    let handle = self.next_handle;
+    self.next_handle += 1;
+    self.live.push((handle, ptr as usize));
+    Some(handle)
+  }
+
+  /// Frees the allocation behind `handle`. Returns `false` if the handle
+  /// is unknown or already freed.
+  ///
+  /// Not called `free`: wasm-bindgen already generates `free()` on every
+  /// exported class to drop the Rust value.
+  pub fn dealloc(
+    &mut self,
+    handle: u32,
+  ) -> bool {
+    let Some(index) = self.live.iter().position(|&(live, _)| live == handle) else {
+      return false;
+    };
+    let (_, address) = self.live.remove(index);
+
+    // SAFETY: `address` came from `allocate` and is freed exactly once.
+    unsafe { self.inner.deallocate(address as *mut u8) };
+    true
+  }
+
+  /// Frees every allocation at once.
+  pub fn reset(&mut self) {
+    self.live.clear();
+    // SAFETY: Only handles escaped to the page, and they are all forgotten.
+    unsafe { self.inner.reset() };
+  }
+
+  /// Renders the heap map followed by the handle table.
+  pub fn render(&self) -> String {
+    let mut out = self.inner.heap_map().to_string();
+    out.push_str("\n\nhandles:");
+    if self.live.is_empty() {
+      out.push_str(" (none)");
+    }
+    for (handle, address) in &self.live {
+      let _ = write!(out, "\n  #{handle:<4} {address:#x}");
+    }
+    out
+  }
+
+  /// Bytes currently obtained from the region, headers included.
+  #[wasm_bindgen(js_name = heapBytes)]
+  pub fn heap_bytes(&self) -> usize {
+    self.inner.stats().heap_bytes
+  }
+
+  /// Size of the region.
+  pub fn capacity(&self) -> usize {
+    self.inner.capacity().unwrap_or(0)
+  }
+}
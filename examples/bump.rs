@@ -1,4 +1,4 @@
-use std::{alloc::Layout, io::Read, ptr};
+use std::{alloc::Layout, io::BufRead, ptr};
 
 use libc::sbrk;
 use rallocator::{BumpAllocator, print_alloc};
@@ -8,7 +8,7 @@ use rallocator::{BumpAllocator, print_alloc};
 /// `gdb`, or just visually track how allocations change the program break.
 fn block_until_enter_pressed() {
   println!("\n>>> Press ENTER to continue...");
-  let _ = std::io::stdin().bytes().next();
+  let _ = std::io::stdin().lock().read_line(&mut String::new());
 }
 
 /// Prints the current program break using `sbrk(0)`.
@@ -60,8 +60,7 @@ fn main() {
     print_alloc(layout_12_bytes, second_block);
 
     // Initialize the block with a byte pattern.
-    let second_ptr = second_block as *mut u8;
-    ptr::write_bytes(second_ptr, 0xAB, layout_12_bytes.size());
+    ptr::write_bytes(second_block, 0xAB, layout_12_bytes.size());
     println!("[2] Initialized second block with 0xAB");
 
     block_until_enter_pressed();
@@ -0,0 +1,190 @@
+//! `heapviz` - replays an allocation trace under every search mode.
+//!
+//! Prints the heap map after each step, then a fragmentation summary per
+//! [`SearchMode`], so strategies can be compared on a recorded workload.
+//!
+//! ```bash
+//! cargo run --example heapviz                              # bundled trace
+//! cargo run --example heapviz -- my.trace                  # your own trace
+//! cargo run --example heapviz -- my.trace --summary        # table only
+//! cargo run --example heapviz -- my.trace --capacity 65536
+//! ```
+//!
+//! Trace format, one operation per line (`#` starts a comment):
+//!
+//! ```text
+//!   a <id> <size> [align]   allocate <size> bytes (default align 8) as <id>
+//!   f <id>                  free the allocation <id>
+//! ```
+
+use std::{alloc::Layout, collections::HashMap, fs, process};
+
+use rallocator::{BumpAllocator, SearchMode, Stats};
+
+/// Trace used when no file is given.
+const BUNDLED_TRACE: &str = include_str!("traces/fragmenting.trace");
+
+/// Region size used for every replay unless `--capacity` is given.
+const DEFAULT_CAPACITY: usize = 1024 * 1024;
+
+/// One line of a trace.
+enum Op {
+  Alloc { id: u64, layout: Layout },
+  Free { id: u64 },
+}
+
+/// Parses a whole trace, reporting the first bad line.
+fn parse_trace(text: &str) -> Result<Vec<Op>, String> {
+  let mut ops = Vec::new();
+
+  for (number, line) in text.lines().enumerate() {
+    let line = line.split('#').next().unwrap_or("").trim();
+    if line.is_empty() {
+      continue;
+    }
+
+    let bad = |what: &str| format!("line {}: {what}: {line:?}", number + 1);
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let number_at = |index: usize| -> Result<u64, String> {
+      fields
+        .get(index)
+        .ok_or_else(|| bad("missing field"))?
+        .parse()
+        .map_err(|_| bad("not a number"))
+    };
+
+    let op = match fields[0] {
+      "a" => {
+        let size = number_at(2)? as usize;
+        let align = if fields.len() > 3 { number_at(3)? as usize } else { 8 };
+        let layout = Layout::from_size_align(size, align).map_err(|_| bad("invalid layout"))?;
+        Op::Alloc { id: number_at(1)?, layout }
+      }
+      "f" => Op::Free { id: number_at(1)? },
+      _ => return Err(bad("unknown operation")),
+    };
+    ops.push(op);
+  }
+
+  Ok(ops)
+}
+
+/// Replays `ops` in a fresh arena using `mode`.
+///
+/// Returns the final stats and the number of allocations that failed.
+fn replay(
+  ops: &[Op],
+  mode: SearchMode,
+  capacity: usize,
+  verbose: bool,
+) -> (Stats, usize) {
+  let mut allocator = BumpAllocator::with_capacity(capacity);
+  allocator.set_search_mode(mode);
+
+  let mut live: HashMap<u64, *mut u8> = HashMap::new();
+  let mut failed = 0;
+
+  for (step, op) in ops.iter().enumerate() {
+    let description = match *op {
+      Op::Alloc { id, layout } => {
+        // SAFETY: The allocator is exclusively owned by this replay.
+        let ptr = unsafe { allocator.allocate(layout) };
+        if ptr.is_null() {
+          failed += 1;
+          format!("alloc #{id} ({} bytes) FAILED", layout.size())
+        } else {
+          live.insert(id, ptr);
+          format!("alloc #{id} ({} bytes)", layout.size())
+        }
+      }
+      Op::Free { id } => match live.remove(&id) {
+        Some(ptr) => {
+          // SAFETY: `ptr` came from this allocator and is freed once.
+          unsafe { allocator.deallocate(ptr) };
+          format!("free #{id}")
+        }
+        None => format!("free #{id} ignored: not live"),
+      },
+    };
+
+    if verbose {
+      println!("[{mode:?}] step {}: {description}", step + 1);
+      println!("{}\n", allocator.heap_map());
+    }
+  }
+
+  (allocator.stats(), failed)
+}
+
+/// Share of the heap's payload bytes that sit in free blocks.
+fn fragmentation(stats: &Stats) -> f64 {
+  let payload = stats.bytes_in_use + stats.bytes_free;
+  if payload == 0 {
+    0.0
+  } else {
+    stats.bytes_free as f64 / payload as f64 * 100.0
+  }
+}
+
+fn main() {
+  let mut path = None;
+  let mut capacity = DEFAULT_CAPACITY;
+  let mut verbose = true;
+
+  let mut args = std::env::args().skip(1);
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--summary" => verbose = false,
+      "--capacity" => {
+        capacity = args.next().and_then(|value| value.parse().ok()).unwrap_or_else(|| {
+          eprintln!("--capacity needs a byte count");
+          process::exit(2);
+        });
+      }
+      _ => path = Some(arg),
+    }
+  }
+
+  let text = match &path {
+    Some(path) => fs::read_to_string(path).unwrap_or_else(|err| {
+      eprintln!("cannot read {path}: {err}");
+      process::exit(1);
+    }),
+    None => BUNDLED_TRACE.to_string(),
+  };
+  let ops = parse_trace(&text).unwrap_or_else(|err| {
+    eprintln!("{err}");
+    process::exit(1);
+  });
+
+  let modes = [SearchMode::FirstFit, SearchMode::NextFit, SearchMode::BestFit];
+  let results: Vec<(SearchMode, Stats, usize)> = modes
+    .into_iter()
+    .map(|mode| {
+      let (stats, failed) = replay(&ops, mode, capacity, verbose);
+      (mode, stats, failed)
+    })
+    .collect();
+
+  // --------------------------------------------------------------------
+  // Summary: one row per search mode.
+  // --------------------------------------------------------------------
+  println!("{} operations from {}\n", ops.len(), path.as_deref().unwrap_or("the bundled trace"));
+  println!(
+    "{:<10} {:>6} {:>6} {:>10} {:>10} {:>10} {:>7} {:>7}",
+    "mode", "live", "free", "in use", "free B", "heap B", "frag %", "failed"
+  );
+  for (mode, stats, failed) in results {
+    println!(
+      "{:<10} {:>6} {:>6} {:>10} {:>10} {:>10} {:>7.1} {:>7}",
+      format!("{mode:?}"),
+      stats.live_blocks,
+      stats.free_blocks,
+      stats.bytes_in_use,
+      stats.bytes_free,
+      stats.heap_bytes,
+      fragmentation(&stats),
+      failed
+    );
+  }
+}
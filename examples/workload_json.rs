@@ -0,0 +1,237 @@
+//! JSON workload over the global allocator.
+//!
+//! Installs [`CriticalSectionAllocator`] as `#[global_allocator]`, then
+//! builds a JSON document, parses it back into a tree with a small
+//! recursive-descent parser, and checks the tree against the data it was
+//! built from - exiting non-zero on a mismatch. Parsing allocates a node
+//! per value and a `String` per key, the shape of a typical parser. Run by
+//! `scripts/workloads.sh`:
+//!
+//! ```bash
+//! cargo run --release --example workload_json --features critical-section
+//! ```
+
+use std::{collections::BTreeMap, fmt::Write, time::Instant};
+
+use rallocator::CriticalSectionAllocator;
+
+#[global_allocator]
+static HEAP: CriticalSectionAllocator = CriticalSectionAllocator::new();
+
+/// Records in the generated document.
+const RECORDS: usize = 2_000;
+
+/// A parsed JSON value.
+#[derive(Debug, PartialEq)]
+enum Json {
+  Null,
+  Bool(bool),
+  Number(f64),
+  String(String),
+  Array(Vec<Json>),
+  Object(BTreeMap<String, Json>),
+}
+
+/// Recursive-descent parser over a byte slice.
+struct Parser<'a> {
+  input: &'a [u8],
+  position: usize,
+}
+
+impl Parser<'_> {
+  fn parse(input: &str) -> Result<Json, String> {
+    let mut parser = Parser {
+      input: input.as_bytes(),
+      position: 0,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.position != parser.input.len() {
+      return Err(format!("trailing data at {}", parser.position));
+    }
+    Ok(value)
+  }
+
+  fn skip_whitespace(&mut self) {
+    while self.input.get(self.position).is_some_and(u8::is_ascii_whitespace) {
+      self.position += 1;
+    }
+  }
+
+  fn expect(
+    &mut self,
+    byte: u8,
+  ) -> Result<(), String> {
+    self.skip_whitespace();
+    if self.input.get(self.position) == Some(&byte) {
+      self.position += 1;
+      Ok(())
+    } else {
+      Err(format!("expected '{}' at {}", byte as char, self.position))
+    }
+  }
+
+  fn literal(
+    &mut self,
+    text: &str,
+    value: Json,
+  ) -> Result<Json, String> {
+    if self.input[self.position..].starts_with(text.as_bytes()) {
+      self.position += text.len();
+      Ok(value)
+    } else {
+      Err(format!("bad literal at {}", self.position))
+    }
+  }
+
+  fn value(&mut self) -> Result<Json, String> {
+    self.skip_whitespace();
+    match self.input.get(self.position) {
+      Some(b'n') => self.literal("null", Json::Null),
+      Some(b't') => self.literal("true", Json::Bool(true)),
+      Some(b'f') => self.literal("false", Json::Bool(false)),
+      Some(b'"') => self.string().map(Json::String),
+      Some(b'[') => self.array(),
+      Some(b'{') => self.object(),
+      Some(_) => self.number(),
+      None => Err("unexpected end of input".to_string()),
+    }
+  }
+
+  fn string(&mut self) -> Result<String, String> {
+    self.expect(b'"')?;
+    let mut out = String::new();
+    loop {
+      match self.input.get(self.position) {
+        Some(b'"') => {
+          self.position += 1;
+          return Ok(out);
+        },
+        Some(b'\\') => {
+          let escaped = *self.input.get(self.position + 1).ok_or("unterminated escape")?;
+          out.push(match escaped {
+            b'n' => '\n',
+            b't' => '\t',
+            other => other as char,
+          });
+          self.position += 2;
+        },
+        Some(&byte) => {
+          out.push(byte as char);
+          self.position += 1;
+        },
+        None => return Err("unterminated string".to_string()),
+      }
+    }
+  }
+
+  fn number(&mut self) -> Result<Json, String> {
+    let start = self.position;
+    while self.input.get(self.position).is_some_and(|&byte| byte == b'-' || byte == b'.' || byte.is_ascii_digit()) {
+      self.position += 1;
+    }
+    let text = std::str::from_utf8(&self.input[start..self.position]).map_err(|error| error.to_string())?;
+    text.parse().map(Json::Number).map_err(|_| format!("bad number at {start}"))
+  }
+
+  fn array(&mut self) -> Result<Json, String> {
+    self.expect(b'[')?;
+    let mut items = Vec::new();
+    self.skip_whitespace();
+    if self.input.get(self.position) == Some(&b']') {
+      self.position += 1;
+      return Ok(Json::Array(items));
+    }
+    loop {
+      items.push(self.value()?);
+      self.skip_whitespace();
+      match self.input.get(self.position) {
+        Some(b',') => self.position += 1,
+        Some(b']') => {
+          self.position += 1;
+          return Ok(Json::Array(items));
+        },
+        _ => return Err(format!("expected ',' or ']' at {}", self.position)),
+      }
+    }
+  }
+
+  fn object(&mut self) -> Result<Json, String> {
+    self.expect(b'{')?;
+    let mut fields = BTreeMap::new();
+    self.skip_whitespace();
+    if self.input.get(self.position) == Some(&b'}') {
+      self.position += 1;
+      return Ok(Json::Object(fields));
+    }
+    loop {
+      self.skip_whitespace();
+      let key = self.string()?;
+      self.expect(b':')?;
+      fields.insert(key, self.value()?);
+      self.skip_whitespace();
+      match self.input.get(self.position) {
+        Some(b',') => self.position += 1,
+        Some(b'}') => {
+          self.position += 1;
+          return Ok(Json::Object(fields));
+        },
+        _ => return Err(format!("expected ',' or '}}' at {}", self.position)),
+      }
+    }
+  }
+}
+
+/// The document: an array of records with nested arrays and escapes.
+fn document() -> String {
+  let mut out = String::from("[");
+  for id in 0..RECORDS {
+    if id > 0 {
+      out.push(',');
+    }
+    write!(
+      out,
+      r#"{{"id": {id}, "name": "user \"{id}\"", "score": {}.5, "active": {}, "tags": ["a{}", "b", null], "meta": {{}}}}"#,
+      id * 3,
+      id % 2 == 0,
+      id % 7
+    )
+    .unwrap();
+  }
+  out.push(']');
+  out
+}
+
+fn main() {
+  let started = Instant::now();
+  let text = document();
+  let Json::Array(records) = Parser::parse(&text).expect("document parses") else {
+    panic!("document is not an array");
+  };
+  assert_eq!(records.len(), RECORDS);
+
+  for (id, record) in records.iter().enumerate() {
+    let Json::Object(fields) = record else {
+      panic!("record {id} is not an object");
+    };
+    assert_eq!(fields["id"], Json::Number(id as f64));
+    assert_eq!(fields["name"], Json::String(format!("user \"{id}\"")));
+    assert_eq!(fields["score"], Json::Number(id as f64 * 3.0 + 0.5));
+    assert_eq!(fields["active"], Json::Bool(id % 2 == 0));
+    assert_eq!(
+      fields["tags"],
+      Json::Array(vec![Json::String(format!("a{}", id % 7)), Json::String("b".into()), Json::Null])
+    );
+    assert_eq!(fields["meta"], Json::Object(BTreeMap::new()));
+  }
+  assert!(Parser::parse("[1, 2").is_err(), "truncated input must not parse");
+
+  let stats = HEAP.with(|allocator| allocator.stats());
+  assert!(stats.heap_bytes > 0, "the global allocator served nothing");
+  println!(
+    "workload_json: ok, {RECORDS} records from {} bytes, heap {} bytes, {:?}",
+    text.len(),
+    stats.heap_bytes,
+    started.elapsed()
+  );
+}
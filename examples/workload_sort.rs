@@ -0,0 +1,69 @@
+//! Sort workload over the global allocator.
+//!
+//! Installs [`CriticalSectionAllocator`] as `#[global_allocator]`, so every
+//! `Vec`, `String` and `BTreeMap` below - and the standard library's own
+//! allocations - go through the `GlobalAlloc` path. Sorts pseudo-random
+//! numbers and strings several ways and checks every result, exiting
+//! non-zero on a mismatch. Run by `scripts/workloads.sh`:
+//!
+//! ```bash
+//! cargo run --release --example workload_sort --features critical-section
+//! ```
+
+use std::{collections::BTreeMap, time::Instant};
+
+use rallocator::CriticalSectionAllocator;
+
+#[global_allocator]
+static HEAP: CriticalSectionAllocator = CriticalSectionAllocator::new();
+
+/// Numbers sorted by each pass.
+const COUNT: usize = 100_000;
+
+/// A xorshift generator, so the workload is the same on every run.
+struct Rng(u64);
+
+impl Rng {
+  fn next(&mut self) -> u64 {
+    self.0 ^= self.0 << 13;
+    self.0 ^= self.0 >> 7;
+    self.0 ^= self.0 << 17;
+    self.0
+  }
+}
+
+fn main() {
+  let started = Instant::now();
+  let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+
+  // Numbers: a stable sort, an unstable sort and a BTreeMap must agree
+  let numbers: Vec<u64> = (0..COUNT).map(|_| rng.next() % 1_000_000).collect();
+  let mut stable = numbers.clone();
+  stable.sort();
+  let mut unstable = numbers.clone();
+  unstable.sort_unstable();
+  assert_eq!(stable, unstable, "stable and unstable sorts disagree");
+  assert!(stable.windows(2).all(|pair| pair[0] <= pair[1]), "numbers out of order");
+
+  let mut counts = BTreeMap::new();
+  for &number in &numbers {
+    *counts.entry(number).or_insert(0usize) += 1;
+  }
+  let expanded: Vec<u64> = counts.iter().flat_map(|(&number, &count)| std::iter::repeat_n(number, count)).collect();
+  assert_eq!(expanded, stable, "BTreeMap order disagrees with the sort");
+
+  // Strings: many small, short-lived allocations
+  let mut words: Vec<String> = (0..COUNT / 10).map(|_| format!("{:x}", rng.next())).collect();
+  words.sort();
+  assert!(words.windows(2).all(|pair| pair[0] <= pair[1]), "strings out of order");
+  let checksum: u64 = stable.iter().sum::<u64>() ^ words.iter().map(|word| word.len() as u64).sum::<u64>();
+
+  let stats = HEAP.with(|allocator| allocator.stats());
+  assert!(stats.heap_bytes > 0, "the global allocator served nothing");
+  println!(
+    "workload_sort: ok, {COUNT} numbers, {} strings, checksum {checksum:#x}, heap {} bytes, {:?}",
+    words.len(),
+    stats.heap_bytes,
+    started.elapsed()
+  );
+}
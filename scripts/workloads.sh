@@ -0,0 +1,22 @@
+#!/usr/bin/env bash
+# Runs real workloads with rallocator installed as the global allocator
+# and fails if any of them crashes or computes a wrong result.
+#
+#   scripts/workloads.sh            # all workloads
+#   scripts/workloads.sh sort       # only examples/workload_sort.rs
+set -euo pipefail
+
+cd "$(dirname "$0")/.."
+
+workloads=("${@:-sort json}")
+# shellcheck disable=SC2206
+workloads=(${workloads[*]})
+
+failed=0
+for workload in "${workloads[@]}"; do
+  if ! cargo run --quiet --release --features critical-section --example "workload_$workload"; then
+    echo "workload_$workload: FAILED" >&2
+    failed=1
+  fi
+done
+exit "$failed"
@@ -0,0 +1,181 @@
+//! # Adopted Regions
+//!
+//! Not every buffer an application cares about comes from its arena: a
+//! DMA window from a driver, a file mapped with `mmap`, a frame handed over
+//! by a C library. [`adopt`](BumpAllocator::adopt) registers such a region
+//! as one live block of the allocator, so the diagnostics see it too:
+//!
+//! ```text
+//!   heap (backend)                               adopted (foreign)
+//!   [hdr│ A ][hdr│ B ][hdr│ C ]   ...            [      mmap'd file      ]
+//!    ▲                                            ▲
+//!    └── owns(), allocation_info(), walk(), stats() cover both ──┘
+//! ```
+//!
+//! An adopted region has no header - its memory is never written - and is
+//! kept in a side map by address, apart from the block list. The allocator
+//! never returns it to the OS and never hands it out again:
+//! [`deallocate`](BumpAllocator::deallocate) on its start only forgets
+//! it, as does `reset`, and the owner unmaps it when done. Forks do not
+//! inherit adopted regions, and the map lives on the global heap (it needs
+//! `std`).
+
+use core::ops::Range;
+
+use crate::{BlockInfo, BumpAllocator};
+
+impl BumpAllocator {
+  /// Registers the `len` bytes at `ptr` as a live block of this
+  /// allocator. Returns `false`, adopting nothing, if the region is empty
+  /// or overlaps the heap or another adopted region.
+  ///
+  /// # Safety
+  ///
+  /// The region must stay valid until it is deallocated or the allocator
+  /// is reset, and must not be used by the heap's backend later on (a
+  /// region right past the program break, say).
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// let frame = driver.map_frame();
+  /// assert!(unsafe { allocator.adopt(frame.as_mut_ptr(), frame.len()) });
+  /// assert!(allocator.owns(frame.as_ptr()));
+  /// ```
+  pub unsafe fn adopt(
+    &mut self,
+    ptr: *mut u8,
+    len: usize,
+  ) -> bool {
+    let start = ptr as usize;
+    let Some(end) = start.checked_add(len) else {
+      return false;
+    };
+    let overlaps = |range: Range<usize>| range.start < end && start < range.end;
+    if len == 0 || ptr.is_null() || overlaps(self.heap_range()) || self.adopted_regions().any(overlaps) {
+      return false;
+    }
+    self.adopted.insert(start, len);
+    true
+  }
+
+  /// Address ranges of the adopted regions, lowest first.
+  pub fn adopted_regions(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+    self.adopted.iter().map(|(&start, &len)| start..start + len)
+  }
+
+  /// Forgets the adopted region starting at `address`. Returns whether
+  /// there was one.
+  pub(crate) fn forget_adopted(
+    &mut self,
+    address: *mut u8,
+  ) -> bool {
+    !self.adopted.is_empty() && self.adopted.remove(&(address as usize)).is_some()
+  }
+
+  /// The [`BlockInfo`] of the adopted region containing `ptr`, if any.
+  pub(crate) fn adopted_info(
+    &self,
+    ptr: *const u8,
+  ) -> Option<BlockInfo> {
+    let address = ptr as usize;
+    let (&start, &len) = self.adopted.range(..=address).next_back()?;
+    let index = self.adopted.range(..start).count();
+    (address < start + len).then(|| self.describe_adopted(index, start, len, address - start))
+  }
+
+  /// The [`BlockInfo`] of the `index`-th adopted region.
+  pub(crate) fn describe_adopted(
+    &self,
+    index: usize,
+    start: usize,
+    len: usize,
+    offset: usize,
+  ) -> BlockInfo {
+    BlockInfo {
+      address: start,
+      offset,
+      size: len,
+      is_free: false,
+      index,
+      from_reserve: false,
+      adopted: true,
+      age: None,
+      epoch: self.epoch(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::{alloc::Layout, ops::ControlFlow};
+
+  /// A foreign buffer, as a driver would hand it over.
+  fn foreign(len: usize) -> &'static mut [u8] {
+    Box::leak(vec![0u8; len].into_boxed_slice())
+  }
+
+  #[test]
+  fn adopted_regions_are_live_blocks() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    unsafe { allocator.allocate(Layout::array::<u8>(32).unwrap()) };
+    let frame = foreign(256);
+    assert!(unsafe { allocator.adopt(frame.as_mut_ptr(), frame.len()) });
+
+    let info = allocator.allocation_info(frame.as_ptr().wrapping_add(10)).unwrap();
+    assert_eq!((info.address, info.offset, info.size), (frame.as_ptr() as usize, 10, 256));
+    assert!(info.adopted && !info.is_free);
+    assert!(!allocator.owns(frame.as_ptr().wrapping_add(256)));
+
+    let stats = allocator.stats();
+    assert_eq!((stats.adopted_blocks, stats.adopted_bytes), (1, 256));
+    assert_eq!((stats.live_blocks, stats.bytes_in_use), (1, 32));
+
+    let mut walked = Vec::new();
+    let _ = allocator.walk(|info| {
+      walked.push((info.size, info.adopted));
+      ControlFlow::<()>::Continue(())
+    });
+    assert_eq!(walked, [(32, false), (256, true)]);
+  }
+
+  #[test]
+  fn overlapping_regions_are_refused() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let heap = unsafe { allocator.allocate(Layout::new::<u64>()) };
+    let frame = foreign(128);
+    let ptr = frame.as_mut_ptr();
+
+    unsafe {
+      assert!(!allocator.adopt(heap, 8));
+      assert!(!allocator.adopt(ptr, 0));
+      assert!(allocator.adopt(ptr.wrapping_add(64), 64));
+      assert!(!allocator.adopt(ptr, 65));
+      assert!(allocator.adopt(ptr, 64));
+    }
+    let start = ptr as usize;
+    assert_eq!(allocator.adopted_regions().collect::<Vec<_>>(), [start..start + 64, start + 64..start + 128]);
+    assert_eq!(allocator.allocation_info(ptr.wrapping_add(64)).unwrap().index, 1);
+  }
+
+  #[test]
+  fn deallocating_and_resetting_forget_regions() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let frame = foreign(64);
+    let ptr = frame.as_mut_ptr();
+    unsafe {
+      allocator.adopt(ptr, 64);
+      allocator.deallocate(ptr);
+    }
+    assert!(!allocator.owns(ptr));
+    // The memory itself is untouched and still the caller's
+    frame[0] = 1;
+
+    unsafe {
+      allocator.adopt(ptr, 64);
+      allocator.reset();
+    }
+    assert_eq!(allocator.adopted_regions().count(), 0);
+  }
+}
@@ -1,3 +1,24 @@
+//! Alignment helpers.
+//!
+//! The `align!`/`align_to!` macros round up any integer expression; the
+//! `const fn`s [`align_word`] and [`align_up`] do the same for `usize` and
+//! also work in constant expressions. [`align_down`] and [`is_aligned`]
+//! complete the set, and the `ptr` variants work on pointers directly:
+//!
+//! ```text
+//!   align = 16         align_down   value   align_up
+//!                           │         │        │
+//!   ──┬───────────────┬─────▼─────────▼────────▼──────┬──
+//!     0              16              32               48
+//!                          is_aligned(32, 16) == true
+//! ```
+//!
+//! All alignments must be powers of two; debug builds assert it. When the
+//! alignment comes from untrusted input, [`try_align_up`] checks it (and
+//! overflow) in release builds too.
+
+use core::{mem, ptr::NonNull};
+
 /// Calculates the machine word alignment for the given size.
 ///
 /// # Examples
@@ -16,19 +37,202 @@
 macro_rules! align {
   ($value:expr) => {{
     // Align to machine word size
-    let word = std::mem::size_of::<usize>();
+    let word = ::core::mem::size_of::<usize>();
     ($value + word - 1) & !(word - 1)
   }};
 }
 
+/// Rounds `value` up to a multiple of `align`.
+///
+/// `align` must be a power of two; debug builds panic otherwise, since the
+/// bit trick below gives meaningless results for any other value. Each
+/// argument is evaluated once.
+///
+/// # Examples
+///
+/// ```rust
+/// use rallocator::align_to;
+///
+/// assert_eq!(align_to!(13, 8), 16);
+/// assert_eq!(align_to!(13u32, 4), 16);
+/// ```
 #[macro_export]
 macro_rules! align_to {
-  ($value:expr, $align:expr) => {{ ($value + $align - 1) & !($align - 1) }};
+  ($value:expr, $align:expr) => {{
+    let align = $align;
+    ::core::debug_assert!(align != 0 && align & (align - 1) == 0, "alignment must be a power of two");
+    ($value + align - 1) & !(align - 1)
+  }};
+}
+
+/// Rounds `value` up to the machine word size.
+///
+/// `const` counterpart of [`align!`](crate::align!).
+///
+/// # Examples
+///
+/// ```rust
+/// use rallocator::align::align_word;
+///
+/// const HEADER: usize = align_word(13);
+/// assert_eq!(HEADER % core::mem::size_of::<usize>(), 0);
+/// ```
+pub const fn align_word(value: usize) -> usize {
+  align_up(value, mem::size_of::<usize>())
+}
+
+/// Rounds `value` up to a multiple of `align`.
+///
+/// `const` counterpart of [`align_to!`](crate::align_to!).
+///
+/// ```text
+///   align_up(13, 8)
+///
+///     13 + 7 = 20 = 0b10100
+///     !(8 - 1)    = 0b...11000
+///     20 & !7     = 0b10000 = 16
+/// ```
+///
+/// `align` must be a power of two, which debug builds assert. The result
+/// wraps around if `value` is within `align` of `usize::MAX`.
+///
+/// # Examples
+///
+/// ```rust
+/// use rallocator::align::align_up;
+///
+/// assert_eq!(align_up(13, 8), 16);
+/// assert_eq!(align_up(64, 64), 64);
+/// ```
+pub const fn align_up(
+  value: usize,
+  align: usize,
+) -> usize {
+  debug_assert!(align.is_power_of_two(), "alignment must be a power of two");
+  value.wrapping_add(align - 1) & !(align - 1)
+}
+
+/// Checked [`align_up`]: `None` if `align` is not a power of two or the
+/// result does not fit in a `usize`.
+///
+/// ```text
+///   try_align_up(13, 8)            Some(16)
+///   try_align_up(13, 12)           None     (not a power of two)
+///   try_align_up(13, 0)            None     (not a power of two)
+///   try_align_up(usize::MAX, 8)    None     (would wrap around)
+/// ```
+///
+/// # Examples
+///
+/// ```rust
+/// use rallocator::align::try_align_up;
+///
+/// assert_eq!(try_align_up(13, 8), Some(16));
+/// assert_eq!(try_align_up(13, 12), None);
+/// ```
+pub const fn try_align_up(
+  value: usize,
+  align: usize,
+) -> Option<usize> {
+  if !align.is_power_of_two() {
+    return None;
+  }
+  match value.checked_add(align - 1) {
+    Some(sum) => Some(sum & !(align - 1)),
+    None => None,
+  }
+}
+
+/// Rounds `value` down to a multiple of `align`.
+///
+/// # Examples
+///
+/// ```rust
+/// use rallocator::align::align_down;
+///
+/// assert_eq!(align_down(13, 8), 8);
+/// assert_eq!(align_down(64, 64), 64);
+/// ```
+pub const fn align_down(
+  value: usize,
+  align: usize,
+) -> usize {
+  debug_assert!(align.is_power_of_two(), "alignment must be a power of two");
+  value & !(align - 1)
+}
+
+/// Whether `value` is a multiple of `align`.
+///
+/// # Examples
+///
+/// ```rust
+/// use rallocator::align::is_aligned;
+///
+/// assert!(is_aligned(48, 16));
+/// assert!(!is_aligned(40, 16));
+/// ```
+pub const fn is_aligned(
+  value: usize,
+  align: usize,
+) -> bool {
+  debug_assert!(align.is_power_of_two(), "alignment must be a power of two");
+  value & (align - 1) == 0
+}
+
+/// Moves `ptr` up to the next multiple of `align`.
+///
+/// The result keeps the provenance of `ptr`; like [`align_up`] it wraps
+/// around at the top of the address space.
+pub fn align_ptr_up(
+  ptr: *mut u8,
+  align: usize,
+) -> *mut u8 {
+  let address = ptr as usize;
+  ptr.wrapping_add(align_up(address, align).wrapping_sub(address))
+}
+
+/// Moves `ptr` down to the previous multiple of `align`, keeping its
+/// provenance.
+pub fn align_ptr_down(
+  ptr: *mut u8,
+  align: usize,
+) -> *mut u8 {
+  let address = ptr as usize;
+  ptr.wrapping_sub(address - align_down(address, align))
+}
+
+/// Whether `ptr` is aligned to `align`.
+///
+/// Unlike `<*const T>::is_aligned`, `align` is any power of two, not the
+/// alignment of a type.
+pub fn is_ptr_aligned(
+  ptr: *const u8,
+  align: usize,
+) -> bool {
+  is_aligned(ptr as usize, align)
+}
+
+/// [`align_ptr_up`] for a non-null pointer, or `None` if rounding up
+/// wraps around to null.
+pub fn align_non_null_up(
+  ptr: NonNull<u8>,
+  align: usize,
+) -> Option<NonNull<u8>> {
+  NonNull::new(align_ptr_up(ptr.as_ptr(), align))
+}
+
+/// [`align_ptr_down`] for a non-null pointer, or `None` if the result
+/// would be null.
+pub fn align_non_null_down(
+  ptr: NonNull<u8>,
+  align: usize,
+) -> Option<NonNull<u8>> {
+  NonNull::new(align_ptr_down(ptr.as_ptr(), align))
 }
 
 #[cfg(test)]
 mod tests {
-  use std::mem;
+  use super::*;
 
   #[test]
   fn test_align_to_word_size() {
@@ -72,6 +276,101 @@ mod tests {
     }
   }
 
+  #[test]
+  fn test_const_fns_match_macros() {
+    const WORD_ALIGNED: usize = align_word(13);
+    assert_eq!(WORD_ALIGNED, align!(13));
+
+    for value in 0..200 {
+      assert_eq!(align_word(value), align!(value));
+      for align in [1, 2, 4, 8, 16, 64, 4096] {
+        assert_eq!(align_up(value, align), align_to!(value, align), "value={value} align={align}");
+      }
+    }
+  }
+
+  #[test]
+  #[cfg(debug_assertions)]
+  #[should_panic(expected = "alignment must be a power of two")]
+  fn test_align_up_rejects_non_powers_of_two() {
+    align_up(10, 12);
+  }
+
+  #[test]
+  #[cfg(debug_assertions)]
+  #[should_panic(expected = "alignment must be a power of two")]
+  fn test_align_to_rejects_non_powers_of_two() {
+    let _ = align_to!(10usize, 12);
+  }
+
+  #[test]
+  #[cfg(debug_assertions)]
+  #[should_panic(expected = "alignment must be a power of two")]
+  fn test_align_to_rejects_zero() {
+    let _ = align_to!(10usize, 0);
+  }
+
+  #[test]
+  fn test_try_align_up_matches_align_up() {
+    for value in 0..200 {
+      for align in [1, 2, 4, 8, 16, 64, 4096] {
+        assert_eq!(try_align_up(value, align), Some(align_up(value, align)));
+      }
+    }
+  }
+
+  #[test]
+  fn test_try_align_up_pathological_inputs() {
+    // Not powers of two
+    for align in [0, 3, 6, 12, 24, 100, usize::MAX] {
+      assert_eq!(try_align_up(13, align), None, "align={align}");
+    }
+
+    // Overflow near the top of the address space
+    let top_bit = 1 << (usize::BITS - 1);
+    assert_eq!(try_align_up(usize::MAX, 8), None);
+    assert_eq!(try_align_up(usize::MAX - 6, 8), None);
+    assert_eq!(try_align_up(usize::MAX - 7, 8), Some(usize::MAX - 7));
+    assert_eq!(try_align_up(usize::MAX, 1), Some(usize::MAX));
+    assert_eq!(try_align_up(1, top_bit), Some(top_bit));
+    assert_eq!(try_align_up(top_bit + 1, top_bit), None);
+    assert_eq!(try_align_up(0, top_bit), Some(0));
+  }
+
+  #[test]
+  fn test_align_down_and_is_aligned() {
+    for value in 0..200 {
+      for align in [1, 2, 8, 64] {
+        let down = align_down(value, align);
+        assert!(down <= value && value - down < align);
+        assert!(is_aligned(down, align));
+        assert_eq!(is_aligned(value, align), down == value);
+        assert!(align_up(value, align) >= value);
+      }
+    }
+  }
+
+  #[test]
+  fn test_pointer_variants_keep_the_pointer() {
+    let mut buffer = [0u8; 128];
+    let base = buffer.as_mut_ptr();
+    let ptr = base.wrapping_add(17);
+
+    let up = align_ptr_up(ptr, 16);
+    let down = align_ptr_down(ptr, 16);
+    assert!(is_ptr_aligned(up, 16) && is_ptr_aligned(down, 16));
+    assert!(up as usize - ptr as usize <= 15 && ptr as usize - down as usize <= 15);
+    // The result is still usable through the original allocation
+    unsafe { *up = 7 };
+    assert!(buffer.contains(&7));
+
+    let non_null = NonNull::new(ptr).unwrap();
+    assert_eq!(align_non_null_up(non_null, 16).unwrap().as_ptr(), up);
+    assert_eq!(align_non_null_down(non_null, 16).unwrap().as_ptr(), down);
+    assert!(align_non_null_down(NonNull::<u8>::dangling(), 64).is_none());
+    assert!(align_non_null_up(NonNull::new(usize::MAX as *mut u8).unwrap(), 16).is_none());
+  }
+
   #[test]
   fn test_align_exact_multiples() {
     let word = mem::size_of::<usize>();
@@ -0,0 +1,240 @@
+//! # `allocator-api2` Integration
+//!
+//! Lets stable-Rust users put collections inside the bump region today,
+//! through the [`allocator_api2`] crate's mirror of the unstable
+//! `core::alloc::Allocator` trait. Any `allocator-api2`-aware container
+//! (`allocator_api2::vec::Vec`, `allocator_api2::boxed::Box`, `hashbrown`,
+//! ...) accepts the allocator:
+//!
+//! ```rust,ignore
+//! use allocator_api2::vec::Vec;
+//! use rallocator::LocalBumpAllocator;
+//!
+//! let arena = LocalBumpAllocator::new();
+//! let mut numbers = Vec::new_in(&arena);
+//! numbers.extend([1, 2, 3]);
+//! ```
+//!
+//! ## Why a Wrapper?
+//!
+//! `Allocator` methods take `&self`, while [`BumpAllocator`] needs
+//! `&mut self`. [`LocalBumpAllocator`] bridges the two with a `RefCell`,
+//! and the trait is implemented for `&LocalBumpAllocator` so that many
+//! containers can share one arena:
+//!
+//! ```text
+//!   Vec<_, &arena> ──┐
+//!   Box<_, &arena> ──┼──► &LocalBumpAllocator ──► RefCell<BumpAllocator>
+//!   Vec<_, &arena> ──┘         (Copy)               (borrowed per call)
+//! ```
+//!
+//! The `RefCell` makes the wrapper single-threaded (`!Sync`). Borrows never
+//! escape a single `allocate`/`deallocate` call, so they cannot conflict.
+//!
+//! Across threads, use a [`SharedBumpAllocator`](crate::SharedBumpAllocator)
+//! (`std` only): the trait is implemented for the handle itself, and each
+//! container keeps its own clone.
+
+use core::{alloc::Layout, cell::RefCell, ptr::NonNull};
+
+use allocator_api2::alloc::{AllocError, Allocator};
+
+use crate::{BumpAllocator, SearchMode};
+
+/// A [`BumpAllocator`] usable through shared references on one thread.
+///
+/// Implements [`allocator_api2::alloc::Allocator`] for `&LocalBumpAllocator`.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use allocator_api2::boxed::Box;
+/// use rallocator::LocalBumpAllocator;
+///
+/// let arena = LocalBumpAllocator::with_capacity(64 * 1024);
+/// let answer = Box::new_in(42u64, &arena);
+/// assert_eq!(*answer, 42);
+/// ```
+pub struct LocalBumpAllocator {
+  /// The wrapped allocator. Borrowed mutably for the duration of each call.
+  inner: RefCell<BumpAllocator>,
+}
+
+impl LocalBumpAllocator {
+  /// Creates a new, empty arena on the platform's default backend.
+  pub const fn new() -> Self {
+    Self::from_allocator(BumpAllocator::new())
+  }
+
+  /// Creates a new, empty arena with the specified search mode.
+  pub const fn with_search_mode(search_mode: SearchMode) -> Self {
+    Self::from_allocator(BumpAllocator::with_search_mode(search_mode))
+  }
+
+  /// Creates a new, empty arena backed by a `capacity`-byte region from the
+  /// system allocator. See [`BumpAllocator::with_capacity`].
+  #[cfg(feature = "std")]
+  pub const fn with_capacity(capacity: usize) -> Self {
+    Self::from_allocator(BumpAllocator::with_capacity(capacity))
+  }
+
+  /// Wraps an existing allocator.
+  pub const fn from_allocator(allocator: BumpAllocator) -> Self {
+    Self {
+      inner: RefCell::new(allocator),
+    }
+  }
+
+  /// Runs `f` with exclusive access to the wrapped allocator.
+  ///
+  /// # Panics
+  ///
+  /// Panics if called re-entrantly from within `f`.
+  pub fn with<R>(
+    &self,
+    f: impl FnOnce(&mut BumpAllocator) -> R,
+  ) -> R {
+    f(&mut self.inner.borrow_mut())
+  }
+
+  /// Unwraps the arena, returning the underlying allocator.
+  pub fn into_inner(self) -> BumpAllocator {
+    self.inner.into_inner()
+  }
+}
+
+impl Default for LocalBumpAllocator {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+unsafe impl Allocator for &LocalBumpAllocator {
+  fn allocate(
+    &self,
+    layout: Layout,
+  ) -> Result<NonNull<[u8]>, AllocError> {
+    // SAFETY: The RefCell borrow gives `allocate` exclusive access.
+    let ptr = self.with(|allocator| unsafe { allocator.allocate_sized(layout) });
+    let ptr = NonNull::new(ptr).ok_or(AllocError)?;
+    Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+  }
+
+  unsafe fn deallocate(
+    &self,
+    ptr: NonNull<u8>,
+    layout: Layout,
+  ) {
+    // SAFETY: The caller guarantees `ptr` was allocated by this arena
+    // with `layout`.
+    self.with(|allocator| unsafe { allocator.deallocate_sized(ptr.as_ptr(), layout) });
+  }
+}
+
+#[cfg(feature = "std")]
+unsafe impl Allocator for crate::SharedBumpAllocator {
+  fn allocate(
+    &self,
+    layout: Layout,
+  ) -> Result<NonNull<[u8]>, AllocError> {
+    // SAFETY: The mutex gives `allocate` exclusive access.
+    let ptr = self.with(|allocator| unsafe { allocator.allocate_sized(layout) });
+    let ptr = NonNull::new(ptr).ok_or(AllocError)?;
+    Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+  }
+
+  unsafe fn deallocate(
+    &self,
+    ptr: NonNull<u8>,
+    layout: Layout,
+  ) {
+    // SAFETY: The caller guarantees `ptr` was allocated by this arena
+    // (through this handle or any of its clones) with `layout`.
+    self.with(|allocator| unsafe { allocator.deallocate_sized(ptr.as_ptr(), layout) });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use allocator_api2::{boxed::Box, vec::Vec};
+
+  #[test]
+  fn vec_in_arena_grows_and_keeps_contents() {
+    let arena = LocalBumpAllocator::with_capacity(64 * 1024);
+    let mut numbers = Vec::new_in(&arena);
+
+    for i in 0..1000u32 {
+      numbers.push(i);
+    }
+
+    assert_eq!(numbers.len(), 1000);
+    assert!(numbers.iter().copied().eq(0..1000));
+  }
+
+  #[test]
+  fn several_containers_share_one_arena() {
+    let arena = LocalBumpAllocator::with_capacity(4096);
+
+    let a = Box::new_in(1u64, &arena);
+    let b = Box::new_in([7u8; 100], &arena);
+    let mut v = Vec::with_capacity_in(4, &arena);
+    v.extend_from_slice(&[1u16, 2, 3, 4]);
+
+    assert_eq!(*a, 1);
+    assert_eq!(b.iter().map(|&x| x as u32).sum::<u32>(), 700);
+    assert_eq!(v.as_slice(), &[1, 2, 3, 4]);
+  }
+
+  #[test]
+  fn allocate_reports_requested_length_and_alignment() {
+    let arena = LocalBumpAllocator::with_capacity(4096);
+    let layout = Layout::from_size_align(24, 64).unwrap();
+
+    let block = (&arena).allocate(layout).unwrap();
+    assert_eq!(block.len(), 24);
+    assert!((block.as_ptr() as *mut u8 as usize).is_multiple_of(64));
+  }
+
+  #[test]
+  fn exhausted_arena_reports_alloc_error() {
+    let arena = LocalBumpAllocator::with_capacity(256);
+    let layout = Layout::array::<u8>(4096).unwrap();
+
+    assert!((&arena).allocate(layout).is_err());
+  }
+
+  #[test]
+  fn panicking_oom_handler_does_not_leave_the_arena_borrowed() {
+    let arena = LocalBumpAllocator::with_capacity(256);
+    arena.with(|allocator| allocator.set_oom_handler(Some(|_, _| panic!("no memory"))));
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+      (&arena).allocate(Layout::array::<u8>(4096).unwrap())
+    }));
+    assert!(result.is_err());
+
+    // The RefCell borrow was released during unwinding
+    assert!((&arena).allocate(Layout::new::<u64>()).is_ok());
+    assert_eq!(arena.with(|allocator| allocator.check_invariants()), Ok(()));
+  }
+
+  #[test]
+  fn shared_handle_backs_containers_on_several_threads() {
+    let arena = crate::SharedBumpAllocator::with_capacity(64 * 1024);
+
+    let worker = {
+      let arena = arena.clone();
+      std::thread::spawn(move || {
+        let mut squares = Vec::new_in(arena);
+        squares.extend((0..100u64).map(|i| i * i));
+        squares.iter().sum::<u64>()
+      })
+    };
+    let answer = Box::new_in(42u64, arena.clone());
+
+    assert_eq!(worker.join().unwrap(), 328_350);
+    assert_eq!(*answer, 42);
+    assert_eq!(arena.with(|allocator| allocator.check_invariants()), Ok(()));
+  }
+}
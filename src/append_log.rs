@@ -0,0 +1,291 @@
+//! # Append Logs
+//!
+//! An [`AppendLog`] is a fixed-capacity, append-only sequence that any
+//! number of threads can read while one thread appends - a trace buffer,
+//! an event log, a list of interned entries that only grows:
+//!
+//! ```text
+//!   ┌──────┬──────┬──────┬──────┬──────┬─────────────────────┐
+//!   │  e0  │  e1  │  e2  │  e3  │ (e4) │       (free)        │
+//!   └──────┴──────┴──────┴──────┴──────┴─────────────────────┘
+//!                               ▲       ▲
+//!                   published ──┘       └── being written by the LogWriter
+//!
+//!   writer:  write e4 into its slot, then published.store(5, Release)
+//!   readers: n = published.load(Acquire); entries 0..n are complete
+//! ```
+//!
+//! The published count is the bump pointer, kept in an atomic: appending
+//! is a plain write of the slot followed by a release store, and a reader
+//! that loads the count with acquire ordering sees every entry below it
+//! fully written. Entries never move, so references handed to readers stay
+//! valid while new entries arrive, and readers never wait.
+//!
+//! Only the [`LogWriter`] can append, and the log hands out one at a time;
+//! like an [`ArenaPool`](crate::ArenaPool), the memory is taken up front
+//! (owned with `std`, or a caller's buffer), so appends never allocate.
+
+use core::{
+  alloc::Layout,
+  marker::PhantomData,
+  ptr,
+  sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use crate::backend::Region;
+
+/// A fixed-capacity log with lock-free reads, see the `append_log` module.
+pub struct AppendLog<T> {
+  /// The reservation. Only held so owned regions are freed with the log.
+  _memory: Region,
+
+  /// First entry.
+  entries: *mut T,
+
+  /// Number of entry slots.
+  capacity: usize,
+
+  /// Entries below this are complete and visible to readers.
+  published: AtomicUsize,
+
+  /// Whether a `LogWriter` is alive.
+  writing: AtomicBool,
+
+  _owns: PhantomData<T>,
+}
+
+// SAFETY: The log owns its entries, so moving it moves `T`s.
+unsafe impl<T: Send> Send for AppendLog<T> {}
+
+// SAFETY: Readers share `&T` across threads, and the writer, which may be
+// on any thread, moves `T`s in; published entries are never written again.
+unsafe impl<T: Send + Sync> Sync for AppendLog<T> {}
+
+impl<T> AppendLog<T> {
+  /// Creates a log for up to `capacity` entries, reserved with a single
+  /// request to the system allocator.
+  ///
+  /// # Returns
+  ///
+  /// `None` if `capacity` is zero or the reservation cannot be obtained.
+  #[cfg(feature = "std")]
+  pub fn new(capacity: usize) -> Option<Self> {
+    let layout = Self::layout(capacity)?;
+    // Owned regions are only 16-aligned: reserve room to align by hand
+    Self::from_region(Region::owned(layout.size().checked_add(layout.align())?), capacity)
+  }
+
+  /// Creates a log over `buffer`, holding as many entries as fit.
+  pub fn from_buffer(buffer: &'static mut [u8]) -> Option<Self> {
+    let start = buffer.as_mut_ptr();
+    let skip = (start as usize).next_multiple_of(align_of::<T>()) - start as usize;
+    let usable = buffer.len().checked_sub(skip)?;
+    let capacity = usable.checked_div(size_of::<T>()).unwrap_or(usize::MAX);
+
+    // SAFETY: The exclusive 'static borrow keeps the memory valid and unused.
+    Self::from_region(unsafe { Region::borrowed(start, buffer.len()) }, capacity)
+  }
+
+  /// Layout of `capacity` entries.
+  fn layout(capacity: usize) -> Option<Layout> {
+    if capacity == 0 {
+      return None;
+    }
+    Layout::array::<T>(capacity).ok()
+  }
+
+  /// Takes the entry array out of `memory`.
+  fn from_region(
+    mut memory: Region,
+    capacity: usize,
+  ) -> Option<Self> {
+    let layout = Self::layout(capacity)?;
+    let reserved = memory.grow(0)?;
+    memory.grow((reserved as usize).next_multiple_of(layout.align()) - reserved as usize)?;
+    let entries = memory.grow(layout.size())? as *mut T;
+
+    Some(Self {
+      _memory: memory,
+      entries,
+      capacity,
+      published: AtomicUsize::new(0),
+      writing: AtomicBool::new(false),
+      _owns: PhantomData,
+    })
+  }
+
+  /// Maximum number of entries.
+  pub fn capacity(&self) -> usize {
+    self.capacity
+  }
+
+  /// Number of published entries. Only grows.
+  pub fn len(&self) -> usize {
+    self.published.load(Ordering::Acquire)
+  }
+
+  /// Whether nothing is published yet.
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// The published entry at `index`.
+  pub fn get(
+    &self,
+    index: usize,
+  ) -> Option<&T> {
+    // SAFETY: Entries below the acquired count are complete and never
+    // written again.
+    (index < self.len()).then(|| unsafe { &*self.entries.add(index) })
+  }
+
+  /// The entries published so far, as one slice. Later appends do not
+  /// show up in it; take a new one to see them.
+  pub fn as_slice(&self) -> &[T] {
+    // SAFETY: As in `get`.
+    unsafe { core::slice::from_raw_parts(self.entries, self.len()) }
+  }
+
+  /// The entries published so far, oldest first.
+  pub fn iter(&self) -> core::slice::Iter<'_, T> {
+    self.as_slice().iter()
+  }
+
+  /// The log's writer, or `None` while another one is alive.
+  pub fn writer(&self) -> Option<LogWriter<'_, T>> {
+    self
+      .writing
+      .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+      .ok()
+      .map(|_| LogWriter { log: self })
+  }
+}
+
+impl<T> Drop for AppendLog<T> {
+  fn drop(&mut self) {
+    let len = *self.published.get_mut();
+    // SAFETY: The first `len` entries are initialized and dropped once.
+    unsafe { ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.entries, len)) };
+  }
+}
+
+/// The single appender of an [`AppendLog`], see [`AppendLog::writer`].
+///
+/// `Send` whenever the log is `Sync`, so the writer can live on its own
+/// thread while the log is shared with readers.
+pub struct LogWriter<'log, T> {
+  log: &'log AppendLog<T>,
+}
+
+impl<T> LogWriter<'_, T> {
+  /// Appends `value` and publishes it. Returns the entry's index, or gives
+  /// `value` back if the log is full.
+  pub fn push(
+    &mut self,
+    value: T,
+  ) -> Result<usize, T> {
+    // Only this writer stores the count
+    let index = self.log.published.load(Ordering::Relaxed);
+    if index == self.log.capacity {
+      return Err(value);
+    }
+    // SAFETY: The slot at `index` is unpublished, so no reader looks at it,
+    // and this writer is the only one.
+    unsafe { self.log.entries.add(index).write(value) };
+    self.log.published.store(index + 1, Ordering::Release);
+    Ok(index)
+  }
+
+  /// Free entry slots left.
+  pub fn remaining(&self) -> usize {
+    self.log.capacity - self.log.published.load(Ordering::Relaxed)
+  }
+}
+
+impl<T> Drop for LogWriter<'_, T> {
+  fn drop(&mut self) {
+    self.log.writing.store(false, Ordering::Release);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::{rc::Rc, sync::Arc, thread};
+
+  #[test]
+  fn entries_are_published_in_order() {
+    let log = AppendLog::new(3).unwrap();
+    let mut writer = log.writer().unwrap();
+    assert!(log.writer().is_none());
+
+    assert_eq!(writer.push("a"), Ok(0));
+    let first = log.get(0).unwrap();
+    assert_eq!(writer.push("b"), Ok(1));
+    assert_eq!(writer.push("c"), Ok(2));
+    assert_eq!(writer.push("d"), Err("d"));
+    assert_eq!(writer.remaining(), 0);
+
+    // Earlier references survive later appends
+    assert_eq!(*first, "a");
+    assert_eq!(log.as_slice(), ["a", "b", "c"]);
+    assert_eq!(log.get(3), None);
+
+    drop(writer);
+    assert!(log.writer().is_some());
+  }
+
+  #[test]
+  fn readers_run_alongside_the_writer() {
+    let log = Arc::new(AppendLog::<u64>::new(10_000).unwrap());
+
+    let readers: Vec<_> = (0..3)
+      .map(|_| {
+        let log = log.clone();
+        thread::spawn(move || {
+          let mut seen = 0;
+          while seen < log.capacity() {
+            // Every visible entry is complete
+            let entries = log.as_slice();
+            for (index, &value) in entries.iter().enumerate() {
+              assert_eq!(value, index as u64 * 3);
+            }
+            seen = entries.len();
+          }
+        })
+      })
+      .collect();
+
+    let mut writer = log.writer().unwrap();
+    for index in 0..10_000u64 {
+      writer.push(index * 3).unwrap();
+    }
+    for reader in readers {
+      reader.join().unwrap();
+    }
+  }
+
+  #[test]
+  fn buffers_hold_what_fits() {
+    let buffer = Box::leak(vec![0u8; 100].into_boxed_slice());
+    let log = AppendLog::<u64>::from_buffer(buffer).unwrap();
+    assert!((11..=12).contains(&log.capacity()));
+    let mut writer = log.writer().unwrap();
+    while writer.push(7).is_ok() {}
+    assert_eq!(log.len(), log.capacity());
+    assert!(log.iter().all(|&value| value == 7));
+  }
+
+  #[test]
+  fn entries_are_dropped_with_the_log() {
+    let counter = Rc::new(());
+    let log = AppendLog::new(4).unwrap();
+    let mut writer = log.writer().unwrap();
+    writer.push(counter.clone()).unwrap();
+    writer.push(counter.clone()).unwrap();
+    drop(writer);
+    assert_eq!(Rc::strong_count(&counter), 3);
+    drop(log);
+    assert_eq!(Rc::strong_count(&counter), 1);
+  }
+}
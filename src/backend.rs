@@ -0,0 +1,794 @@
+//! Memory backends for the bump allocator.
+//!
+//! A backend hands out memory the way `sbrk` does: it owns a *break*
+//! pointer that only ever moves at the end of a contiguous region.
+//!
+//! ```text
+//!   start                         brk                         end
+//!     │                            │                            │
+//!     ▼                            ▼                            ▼
+//!     ┌────────────────────────────┬────────────────────────────┐
+//!     │      handed out (grow)     │        still available     │
+//!     └────────────────────────────┴────────────────────────────┘
+//!
+//!   grow(n):   brk += n, returns the old brk
+//!   shrink(n): brk -= n
+//! ```
+//!
+//! Two backends exist:
+//!
+//! - [`Backend::Sbrk`]: the real program break (`sbrk(2)`), Unix only.
+//!   It remembers the span `[start, end)` it obtained itself and refuses to
+//!   shrink once someone else (libc `malloc`, another allocator) has moved
+//!   the break past `end` - that memory is not ours to release.
+//! - [`Backend::Region`]: a fixed memory region, either borrowed (e.g. a
+//!   `static` buffer on embedded targets) or allocated from the system
+//!   allocator when the `std` feature is enabled.
+//!
+//! Unix targets default to `Sbrk`; every other target defaults to a
+//! `Region`, so the crate compiles everywhere.
+//!
+//! The `Sbrk` backend can also move the break in *growth chunks* larger
+//! than each request, keeping the surplus as slack for later requests:
+//!
+//! ```text
+//!   start             end              committed = program break
+//!     │                │                    │
+//!     ▼                ▼                    ▼
+//!     ┌────────────────┬────────────────────┐
+//!     │  handed out    │  slack (no sbrk)   │
+//!     └────────────────┴────────────────────┘
+//! ```
+//!
+//! A large chunk is often what makes `sbrk` fail, not the request itself,
+//! so a failed chunk is retried at half the size, down to the exact
+//! shortfall, before giving up (see `grow_with_backoff`).
+
+use core::ptr;
+
+#[cfg(unix)]
+use libc::{RLIM_INFINITY, RLIMIT_DATA, c_void, getrlimit, intptr_t, rlimit, sbrk};
+
+/// Capacity of the owned region reserved on first use by the default
+/// backend on non-Unix targets.
+#[cfg(all(feature = "std", not(unix)))]
+pub const DEFAULT_REGION_CAPACITY: usize = 1024 * 1024;
+
+/// Sentinel returned by `sbrk` on failure: `(void *) -1`.
+///
+/// Built from `usize::MAX`, so it is all-ones on both 32- and 64-bit targets.
+#[cfg(unix)]
+const SBRK_FAILED: *mut c_void = ptr::without_provenance_mut(usize::MAX);
+
+/// `limit` of an `Sbrk` backend that has not read `RLIMIT_DATA` yet.
+#[cfg(unix)]
+const LIMIT_UNQUERIED: usize = 0;
+
+/// The soft `RLIMIT_DATA` in bytes, or `usize::MAX` if unlimited or
+/// unknown.
+#[cfg(unix)]
+pub(crate) fn data_limit() -> usize {
+  let mut limit = rlimit {
+    rlim_cur: 0,
+    rlim_max: 0,
+  };
+  // SAFETY: `limit` is a valid, writable `rlimit`.
+  if unsafe { getrlimit(RLIMIT_DATA, &mut limit) } != 0 || limit.rlim_cur == RLIM_INFINITY {
+    return usize::MAX;
+  }
+  usize::try_from(limit.rlim_cur).unwrap_or(usize::MAX)
+}
+
+/// Moves the program break by `increment` bytes.
+///
+/// # Returns
+///
+/// * `Some(old_break)` on success (the start of the new memory when growing)
+/// * `None` if `sbrk` fails (`ENOMEM`, `RLIMIT_DATA`, ...)
+///
+/// # Safety
+///
+/// Shrinking the break releases memory; the caller must own the released range.
+#[cfg(unix)]
+unsafe fn sbrk_checked(increment: intptr_t) -> Option<*mut u8> {
+  let raw = unsafe { sbrk(increment) };
+  if raw == SBRK_FAILED { None } else { Some(raw as *mut u8) }
+}
+
+/// Obtains at least `needed` bytes, preferring a `chunk`-byte growth.
+///
+/// Tries `max(needed, chunk)` first and halves the request after every
+/// failure, never going below `needed`:
+///
+/// ```text
+///   needed = 100, chunk = 1000:   1000 ✗ ──► 500 ✗ ──► 250 ✓
+///   needed = 100, chunk = 0:       100
+/// ```
+///
+/// # Returns
+///
+/// The old break and the number of bytes actually obtained, or `None` if
+/// even `needed` bytes fail.
+#[cfg(unix)]
+fn grow_with_backoff(
+  needed: usize,
+  chunk: usize,
+  mut try_grow: impl FnMut(usize) -> Option<*mut u8>,
+) -> Option<(*mut u8, usize)> {
+  let mut request = needed.max(chunk);
+  loop {
+    if let Some(old) = try_grow(request) {
+      return Some((old, request));
+    }
+    if request == needed {
+      return None;
+    }
+    request = (request / 2).max(needed);
+  }
+}
+
+/// Source of memory for a [`BumpAllocator`](crate::BumpAllocator).
+pub(crate) enum Backend {
+  /// The process-wide program break, moved with `sbrk(2)`.
+  #[cfg(unix)]
+  Sbrk {
+    /// Old break returned by our first successful `grow` (null if none yet).
+    start: *mut u8,
+    /// End of the memory handed out so far.
+    end: *mut u8,
+    /// Where our last `sbrk` left the program break; `[end, committed)`
+    /// is slack obtained by a growth chunk but not handed out yet.
+    committed: *mut u8,
+    /// Minimum number of bytes to move the break by (0: exact requests).
+    chunk: usize,
+    /// `RLIMIT_DATA` read on first use (`usize::MAX`: unlimited).
+    limit: usize,
+  },
+
+  /// A fixed, contiguous memory region with a private break.
+  Region(Region),
+}
+
+impl Backend {
+  /// The backend used by [`BumpAllocator::new`](crate::BumpAllocator::new).
+  ///
+  /// `Sbrk` on Unix. Elsewhere an owned region of
+  /// [`DEFAULT_REGION_CAPACITY`] bytes (reserved lazily) with `std`, or an
+  /// empty region without it - every allocation fails until the user
+  /// provides memory with `from_buffer`/`from_raw_region`.
+  pub(crate) const fn platform_default() -> Self {
+    #[cfg(unix)]
+    {
+      Backend::Sbrk {
+        start: ptr::null_mut(),
+        end: ptr::null_mut(),
+        committed: ptr::null_mut(),
+        chunk: 0,
+        limit: LIMIT_UNQUERIED,
+      }
+    }
+    #[cfg(all(feature = "std", not(unix)))]
+    {
+      Backend::Region(Region::owned(DEFAULT_REGION_CAPACITY))
+    }
+    #[cfg(all(not(feature = "std"), not(unix)))]
+    {
+      Backend::Region(Region::empty())
+    }
+  }
+
+  /// Extends the break by `increment` bytes, returning the old break.
+  ///
+  /// Returns `None` if the backend cannot provide the memory.
+  ///
+  /// # Safety
+  ///
+  /// For `Sbrk`, this changes process-global state.
+  ///
+  /// Split in two so the common case stays small enough to inline into
+  /// `allocate`:
+  ///
+  /// ```text
+  ///   grow ──► fits in committed slack / region? ──► bump, no system call
+  ///                  │ no
+  ///                  ▼
+  ///            grow_slow (#[cold]): sbrk, RLIMIT_DATA query, reservation
+  /// ```
+  #[inline]
+  pub(crate) unsafe fn grow(
+    &mut self,
+    increment: usize,
+  ) -> Option<*mut u8> {
+    match self {
+      #[cfg(unix)]
+      Backend::Sbrk { end, committed, limit, .. }
+        if *limit != LIMIT_UNQUERIED && increment <= *committed as usize - *end as usize =>
+      {
+        let old = *end;
+        *end = unsafe { old.add(increment) };
+        Some(old)
+      }
+      Backend::Region(region) => region.grow(increment),
+      #[cfg(unix)]
+      Backend::Sbrk { .. } => unsafe { self.grow_slow(increment) },
+    }
+  }
+
+  /// Slow path of [`grow`](Self::grow): the request does not fit in what
+  /// the backend already holds.
+  ///
+  /// # Safety
+  ///
+  /// Same as [`grow`](Self::grow).
+  #[cfg(unix)]
+  #[cold]
+  #[inline(never)]
+  unsafe fn grow_slow(
+    &mut self,
+    increment: usize,
+  ) -> Option<*mut u8> {
+    match self {
+      Backend::Sbrk {
+        start,
+        end,
+        committed,
+        chunk,
+        limit,
+      } => {
+        if *limit == LIMIT_UNQUERIED {
+          *limit = data_limit();
+        }
+
+        let slack = *committed as usize - *end as usize;
+        if increment <= slack {
+          let old = *end;
+          *end = unsafe { old.add(increment) };
+          return Some(old);
+        }
+
+        // Slack only helps if the break still sits right after it
+        let contiguous = !committed.is_null() && ptr::eq(unsafe { sbrk(0) } as *mut u8, *committed);
+        let needed = if contiguous { increment - slack } else { increment };
+
+        // sbrk takes a signed intptr_t: requests above intptr_t::MAX would
+        // otherwise be reinterpreted as a negative increment (a shrink!)
+        let (old, obtained) = grow_with_backoff(needed, *chunk, |request| {
+          let delta = intptr_t::try_from(request).ok()?;
+          unsafe { sbrk_checked(delta) }
+        })?;
+
+        // A foreign break move since our last grow starts a new span;
+        // only memory above `start` can ever be released again
+        if !contiguous {
+          *start = old;
+          *end = old;
+        }
+        *committed = unsafe { old.add(obtained) };
+        let handed_out = *end;
+        *end = unsafe { handed_out.add(increment) };
+        Some(handed_out)
+      }
+      Backend::Region(region) => region.grow(increment),
+    }
+  }
+
+  /// Moves the break back by `decrement` bytes.
+  ///
+  /// # Safety
+  ///
+  /// The caller must own the released range and never touch it again.
+  pub(crate) unsafe fn shrink(
+    &mut self,
+    decrement: usize,
+  ) {
+    match self {
+      #[cfg(unix)]
+      Backend::Sbrk { start, end, committed, .. } => {
+        // Never release below our span or memory someone else obtained
+        let decrement = decrement.min(*end as usize - *start as usize);
+        if decrement == 0 || unsafe { sbrk(0) } as *mut u8 != *committed {
+          return;
+        }
+        // The slack above `end` goes back too
+        let release = decrement + (*committed as usize - *end as usize);
+        let Ok(delta) = intptr_t::try_from(release) else {
+          return;
+        };
+        if unsafe { sbrk_checked(-delta) }.is_some() {
+          *end = unsafe { end.sub(decrement) };
+          *committed = *end;
+        }
+      }
+      Backend::Region(region) => region.shrink(decrement),
+    }
+  }
+
+  /// Takes back the last `decrement` bytes handed out without returning
+  /// them to the system: for `Sbrk` they join the slack above `end`, for
+  /// the next `grow` or a [`trim`](Self::trim).
+  ///
+  /// # Safety
+  ///
+  /// The caller must own the retracted range and not touch it until it is
+  /// handed out again.
+  pub(crate) unsafe fn retract(
+    &mut self,
+    decrement: usize,
+  ) {
+    match self {
+      #[cfg(unix)]
+      Backend::Sbrk { start, end, .. } => {
+        let decrement = decrement.min(*end as usize - *start as usize);
+        *end = unsafe { end.sub(decrement) };
+      }
+      Backend::Region(region) => region.shrink(decrement),
+    }
+  }
+
+  /// Returns the slack above `end` to the system, if the program break
+  /// still sits right after it.
+  ///
+  /// # Safety
+  ///
+  /// For `Sbrk`, this changes process-global state.
+  pub(crate) unsafe fn trim(&mut self) {
+    #[cfg(unix)]
+    if let Backend::Sbrk { end, committed, .. } = self {
+      let slack = *committed as usize - *end as usize;
+      if slack == 0 || unsafe { sbrk(0) } as *mut u8 != *committed {
+        return;
+      }
+      let Ok(delta) = intptr_t::try_from(slack) else {
+        return;
+      };
+      if unsafe { sbrk_checked(-delta) }.is_some() {
+        *committed = *end;
+      }
+    }
+  }
+
+  /// Bytes obtained from the system but not handed out: growth chunk
+  /// surplus and retracted tail memory. Always `0` for regions, whose
+  /// memory is never returned anyway.
+  #[inline]
+  pub(crate) fn cached_bytes(&self) -> usize {
+    match self {
+      #[cfg(unix)]
+      Backend::Sbrk { end, committed, .. } => *committed as usize - *end as usize,
+      Backend::Region(_) => 0,
+    }
+  }
+
+  /// Gives back everything handed out so far, as far as possible.
+  ///
+  /// Regions rewind to their start. The program break is only moved back
+  /// if nobody else has moved it since our last `grow`.
+  ///
+  /// # Safety
+  ///
+  /// Every pointer into memory obtained from this backend becomes invalid.
+  pub(crate) unsafe fn release_all(&mut self) {
+    let used = self.used_bytes();
+    unsafe { self.shrink(used) };
+  }
+
+  /// Sets the minimum growth of the program break, see `grow_with_backoff`.
+  ///
+  /// Regions ignore it: their memory is reserved up front.
+  #[cfg_attr(not(unix), expect(unused_variables))]
+  pub(crate) const fn set_growth_chunk(
+    &mut self,
+    bytes: usize,
+  ) {
+    match self {
+      #[cfg(unix)]
+      Backend::Sbrk { chunk, .. } => *chunk = bytes,
+      Backend::Region(_) => {}
+    }
+  }
+
+  /// The minimum growth of the program break (0 for regions).
+  pub(crate) fn growth_chunk(&self) -> usize {
+    match self {
+      #[cfg(unix)]
+      Backend::Sbrk { chunk, .. } => *chunk,
+      Backend::Region(_) => 0,
+    }
+  }
+
+  /// Reads `RLIMIT_DATA` again, e.g. after `setrlimit`.
+  pub(crate) fn refresh_os_limit(&mut self) {
+    #[cfg(unix)]
+    if let Backend::Sbrk { limit, .. } = self {
+      *limit = data_limit();
+    }
+  }
+
+  /// The operating system's data segment limit, if the backend is subject
+  /// to one and it is finite.
+  pub(crate) fn os_limit(&self) -> Option<usize> {
+    match self {
+      #[cfg(unix)]
+      Backend::Sbrk { limit, .. } => {
+        let limit = if *limit == LIMIT_UNQUERIED { data_limit() } else { *limit };
+        (limit != usize::MAX).then_some(limit)
+      }
+      Backend::Region(_) => None,
+    }
+  }
+
+  /// Upper bound on the bytes the backend can still hand out, if bounded.
+  ///
+  /// For the program break this is the OS limit minus what we already
+  /// obtained; other users of the data segment (libc `malloc`, ...) count
+  /// against the limit too, so the real headroom may be smaller.
+  #[inline]
+  pub(crate) fn headroom(&self) -> Option<usize> {
+    match self {
+      #[cfg(unix)]
+      Backend::Sbrk { .. } => self.os_limit().map(|limit| limit.saturating_sub(self.used_bytes())),
+      Backend::Region(region) => Some(region.capacity() - self.used_bytes()),
+    }
+  }
+
+  /// Current break: the end of what has been handed out (null for a region
+  /// that is not reserved yet).
+  pub(crate) fn current_break(&self) -> *mut u8 {
+    match self {
+      #[cfg(unix)]
+      Backend::Sbrk { end, .. } => *end,
+      Backend::Region(region) => region.brk,
+    }
+  }
+
+  /// Bytes currently handed out from the memory source (`end - start`).
+  #[inline]
+  pub(crate) fn used_bytes(&self) -> usize {
+    match self {
+      #[cfg(unix)]
+      Backend::Sbrk { start, end, .. } => *end as usize - *start as usize,
+      Backend::Region(region) => region.used(),
+    }
+  }
+
+  /// Total bytes the backend can ever provide, if bounded.
+  ///
+  /// `None` for the program break, which is only limited by the OS.
+  pub(crate) fn capacity(&self) -> Option<usize> {
+    match self {
+      #[cfg(unix)]
+      Backend::Sbrk { .. } => None,
+      Backend::Region(region) => Some(region.capacity()),
+    }
+  }
+}
+
+/// A contiguous memory region `[start, end)` with a private break.
+///
+/// The region is either borrowed (the caller keeps ownership) or, with the
+/// `std` feature, owned: reserved from the system allocator on first use and
+/// released on drop.
+pub(crate) struct Region {
+  /// First byte of the region (null until an owned region is reserved).
+  start: *mut u8,
+
+  /// Current break: everything in `[start, brk)` has been handed out.
+  brk: *mut u8,
+
+  /// One past the last byte of the region.
+  end: *mut u8,
+
+  /// Capacity to reserve from the system allocator, `0` for borrowed regions.
+  #[cfg(feature = "std")]
+  owned_capacity: usize,
+}
+
+impl Region {
+  /// Alignment of owned regions. Matches the alignment `sbrk` provides.
+  #[cfg(feature = "std")]
+  const OWNED_ALIGN: usize = 16;
+
+  /// A region with no memory: every `grow` fails.
+  pub(crate) const fn empty() -> Self {
+    Self {
+      start: ptr::null_mut(),
+      brk: ptr::null_mut(),
+      end: ptr::null_mut(),
+      #[cfg(feature = "std")]
+      owned_capacity: 0,
+    }
+  }
+
+  /// A region over caller-provided memory.
+  ///
+  /// # Safety
+  ///
+  /// `start..start + len` must be valid for reads and writes, and must not
+  /// be used by anything else for as long as the region is in use.
+  pub(crate) const unsafe fn borrowed(
+    start: *mut u8,
+    len: usize,
+  ) -> Self {
+    let mut region = Self::empty();
+    region.start = start;
+    region.brk = start;
+    region.end = unsafe { start.add(len) };
+    region
+  }
+
+  /// A region of `capacity` bytes reserved from the system allocator on
+  /// first use and freed when the region is dropped.
+  #[cfg(feature = "std")]
+  pub(crate) const fn owned(capacity: usize) -> Self {
+    let mut region = Self::empty();
+    region.owned_capacity = capacity;
+    region
+  }
+
+  /// Size of the region in bytes (the reserved capacity for owned regions).
+  pub(crate) fn capacity(&self) -> usize {
+    #[cfg(feature = "std")]
+    if self.owned_capacity > 0 {
+      return self.owned_capacity;
+    }
+    self.end as usize - self.start as usize
+  }
+
+  /// Layout of the owned reservation, if any.
+  #[cfg(feature = "std")]
+  fn owned_layout(&self) -> Option<std::alloc::Layout> {
+    if self.owned_capacity == 0 {
+      return None;
+    }
+    std::alloc::Layout::from_size_align(self.owned_capacity, Self::OWNED_ALIGN).ok()
+  }
+
+  /// Hands out the next `increment` bytes, returning the old break.
+  #[inline]
+  pub(crate) fn grow(
+    &mut self,
+    increment: usize,
+  ) -> Option<*mut u8> {
+    let available = self.end as usize - self.brk as usize;
+    if self.start.is_null() || increment > available {
+      return self.grow_slow(increment);
+    }
+
+    let old = self.brk;
+    self.brk = unsafe { self.brk.add(increment) };
+    Some(old)
+  }
+
+  /// Slow path of [`grow`](Self::grow): reserves an owned region on first
+  /// use, otherwise fails.
+  #[cold]
+  #[inline(never)]
+  fn grow_slow(
+    &mut self,
+    increment: usize,
+  ) -> Option<*mut u8> {
+    #[cfg(feature = "std")]
+    if self.start.is_null() {
+      let layout = self.owned_layout()?;
+      // SAFETY: `owned_layout` only returns non-zero-sized layouts.
+      let start = unsafe { std::alloc::alloc(layout) };
+      if start.is_null() {
+        return None;
+      }
+      self.start = start;
+      self.brk = start;
+      self.end = unsafe { start.add(layout.size()) };
+    }
+
+    let available = self.end as usize - self.brk as usize;
+    if increment > available {
+      return None;
+    }
+
+    let old = self.brk;
+    self.brk = unsafe { self.brk.add(increment) };
+    Some(old)
+  }
+
+  /// Bytes handed out so far.
+  pub(crate) fn used(&self) -> usize {
+    self.brk as usize - self.start as usize
+  }
+
+  /// Gives back the last `decrement` bytes (never below `start`).
+  pub(crate) fn shrink(
+    &mut self,
+    decrement: usize,
+  ) {
+    let used = self.brk as usize - self.start as usize;
+    self.brk = unsafe { self.brk.sub(decrement.min(used)) };
+  }
+}
+
+#[cfg(feature = "std")]
+impl Drop for Region {
+  fn drop(&mut self) {
+    if let Some(layout) = self.owned_layout()
+      && !self.start.is_null()
+    {
+      // SAFETY: `start` was returned by `alloc` with this exact layout.
+      unsafe { std::alloc::dealloc(self.start, layout) };
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn borrowed_region_grows_until_full() {
+    let mut buffer = [0u8; 64];
+    let start = buffer.as_mut_ptr();
+    let mut region = unsafe { Region::borrowed(start, buffer.len()) };
+
+    assert_eq!(region.grow(16), Some(start));
+    assert_eq!(region.grow(48), Some(unsafe { start.add(16) }));
+    assert_eq!(region.grow(1), None);
+  }
+
+  #[test]
+  fn region_shrink_never_goes_below_start() {
+    let mut buffer = [0u8; 32];
+    let start = buffer.as_mut_ptr();
+    let mut region = unsafe { Region::borrowed(start, buffer.len()) };
+
+    region.grow(24).unwrap();
+    region.shrink(8);
+    assert_eq!(region.brk, unsafe { start.add(16) });
+
+    region.shrink(1000);
+    assert_eq!(region.brk, start);
+  }
+
+  #[test]
+  fn empty_region_always_fails() {
+    let mut region = Region::empty();
+    assert_eq!(region.grow(1), None);
+    assert_eq!(region.grow(64), None);
+  }
+
+  #[test]
+  #[cfg(feature = "std")]
+  fn owned_region_is_reserved_lazily() {
+    let mut region = Region::owned(4096);
+    assert!(region.start.is_null());
+
+    let first = region.grow(128).unwrap();
+    assert!(!first.is_null());
+    assert!((first as usize).is_multiple_of(Region::OWNED_ALIGN));
+    assert_eq!(region.grow(4096 - 128), Some(unsafe { first.add(128) }));
+    assert_eq!(region.grow(1), None);
+  }
+
+  #[test]
+  fn backend_release_all_rewinds_region() {
+    let mut buffer = [0u8; 128];
+    let start = buffer.as_mut_ptr();
+    let mut backend = Backend::Region(unsafe { Region::borrowed(start, buffer.len()) });
+
+    unsafe {
+      backend.grow(40).unwrap();
+      backend.grow(40).unwrap();
+      assert_eq!(backend.used_bytes(), 80);
+      assert_eq!(backend.capacity(), Some(128));
+
+      backend.release_all();
+      assert_eq!(backend.used_bytes(), 0);
+      assert_eq!(backend.grow(8), Some(start));
+    }
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn sbrk_backend_tracks_its_own_span() {
+    let mut backend = Backend::platform_default();
+
+    unsafe {
+      let first = backend.grow(64).unwrap();
+      assert!(backend.used_bytes() >= 64);
+      assert_eq!(backend.capacity(), None);
+
+      // Whatever happened to the break meanwhile, the span ends after us
+      if let Backend::Sbrk { end, .. } = backend {
+        assert_eq!(end, first.add(64));
+      }
+    }
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn backoff_halves_the_chunk_down_to_the_request() {
+    let mut attempts = Vec::new();
+    let grown = grow_with_backoff(100, 1000, |request| {
+      attempts.push(request);
+      (request <= 300).then_some(ptr::null_mut())
+    });
+    assert_eq!(grown.map(|(_, obtained)| obtained), Some(250));
+    assert_eq!(attempts, [1000, 500, 250]);
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn backoff_gives_up_after_the_exact_request() {
+    let mut attempts = Vec::new();
+    let grown = grow_with_backoff(100, 700, |request| {
+      attempts.push(request);
+      None
+    });
+    assert!(grown.is_none());
+    assert_eq!(attempts, [700, 350, 175, 100]);
+
+    attempts.clear();
+    assert!(grow_with_backoff(100, 0, |request| {
+      attempts.push(request);
+      None
+    })
+    .is_none());
+    assert_eq!(attempts, [100]);
+  }
+
+  #[test]
+  fn region_headroom_is_the_unused_capacity() {
+    let mut buffer = [0u8; 128];
+    let mut backend = Backend::Region(unsafe { Region::borrowed(buffer.as_mut_ptr(), buffer.len()) });
+    assert_eq!(backend.headroom(), Some(128));
+    assert_eq!(backend.os_limit(), None);
+
+    unsafe { backend.grow(40).unwrap() };
+    assert_eq!(backend.headroom(), Some(88));
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn sbrk_backend_reports_rlimit_data() {
+    let mut backend = Backend::platform_default();
+    let expected = (data_limit() != usize::MAX).then(data_limit);
+    assert_eq!(backend.os_limit(), expected);
+
+    unsafe { backend.grow(64).unwrap() };
+    assert_eq!(backend.os_limit(), expected);
+    assert_eq!(backend.headroom(), expected.map(|limit| limit - backend.used_bytes()));
+    unsafe { backend.release_all() };
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn retracted_memory_is_cached_and_handed_out_again() {
+    let mut backend = Backend::platform_default();
+
+    unsafe {
+      backend.grow(64).unwrap();
+      let second = backend.grow(64).unwrap();
+      backend.retract(64);
+      assert_eq!(backend.cached_bytes(), 64);
+
+      // Reused without a system call, whatever happened to the break
+      assert_eq!(backend.grow(64), Some(second));
+      assert_eq!(backend.cached_bytes(), 0);
+      backend.release_all();
+    }
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn sbrk_growth_chunk_leaves_slack() {
+    let mut backend = Backend::platform_default();
+    backend.set_growth_chunk(4096);
+    assert_eq!(backend.growth_chunk(), 4096);
+
+    unsafe {
+      let first = backend.grow(64).unwrap();
+      if let Backend::Sbrk { end, committed, .. } = backend {
+        assert_eq!(end, first.add(64));
+        assert!(committed as usize - end as usize >= 4096 - 64);
+      }
+      backend.release_all();
+    }
+  }
+}
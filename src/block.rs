@@ -1,7 +1,13 @@
 //! Block metadata for the bump allocator.
 //!
 //! Each allocation in the bump allocator is preceded by a `Block` header
-//! that stores metadata about the allocation.
+//! that stores metadata about the allocation. Free blocks additionally
+//! keep their free-list links in the payload they no longer use (see
+//! [`FreeLinks`]), so the header stays four machine words.
+
+use core::mem;
+
+use crate::canary::CANARY_SIZE;
 
 /// Metadata header for a single memory allocation.
 ///
@@ -17,22 +23,54 @@
 ///   ┌─────────────────────────────────────────────────────┐
 ///   │  Offset   │   Field   │   Size   │    Description   │
 ///   ├───────────┼───────────┼──────────┼──────────────────┤
-///   │   0x00    │   size    │  8 bytes │  Allocation size │
+///   │   0x00    │   next    │  8 bytes │  Next block ptr  │
+///   ├───────────┼───────────┼──────────┼──────────────────┤
+///   │   0x08    │   prev    │  8 bytes │  Prev block ptr  │
 ///   ├───────────┼───────────┼──────────┼──────────────────┤
-///   │   0x08    │  is_free  │  1 byte  │  Free flag       │
-///   │           │ (padding) │  7 bytes │  (alignment)     │
+///   │   0x10    │  is_free  │  1 byte  │  Free flag       │
+///   │   0x11    │  flags    │  1 byte  │  Canary, span    │
+///   │   0x12    │  epoch    │  2 bytes │  Allocation epoch│
+///   │           │ (padding) │  4 bytes │  (alignment)     │
 ///   ├───────────┼───────────┼──────────┼──────────────────┤
-///   │   0x10    │   next    │  8 bytes │  Next block ptr  │
+///   │   0x18    │   size    │  8 bytes │  Allocation size │
 ///   └───────────┴───────────┴──────────┴──────────────────┘
 ///
-///   Total size: 24 bytes (with padding for alignment)
+///   Total size: 32 bytes (with padding for alignment)
 ///
 ///   In-memory representation:
-///   ┌──────────┬──────────┬───────────────────┬──────────────┐
-///   │   size   │ is_free  │     (padding)     │     next     │
-///   │  8 bytes │  1 byte  │      7 bytes      │    8 bytes   │
-///   └──────────┴──────────┴───────────────────┴──────────────┘
-///    0x00       0x08       0x09                0x10      0x18
+///   ┌──────────────┬──────────────┬─────────┬────────┬─────────┬───────────┬──────────┐
+///   │     next     │     prev     │ is_free │ flags  │  epoch  │ (padding) │   size   │
+///   │    8 bytes   │    8 bytes   │ 1 byte  │ 1 byte │ 2 bytes │  4 bytes  │  8 bytes │
+///   └──────────────┴──────────────┴─────────┴────────┴─────────┴───────────┴──────────┘
+///    0x00           0x08           0x10      0x11     0x12      0x14        0x18  0x20
+///
+///   On a 32-bit system (i686, armv7, ILP32 ABIs):
+///
+///   ┌──────────┬──────────┬─────────┬────────┬─────────┬──────────┐
+///   │   next   │   prev   │ is_free │ flags  │  epoch  │   size   │
+///   │  4 bytes │  4 bytes │ 1 byte  │ 1 byte │ 2 bytes │  4 bytes │
+///   └──────────┴──────────┴─────────┴────────┴─────────┴──────────┘
+///    0x00       0x04       0x08      0x09     0x0A      0x0C   0x10
+///
+///   is_free, flags and epoch fill the third word exactly: no padding
+///
+///   Total size: 16 bytes - always four machine words
+/// ```
+///
+/// # Cache Layout
+///
+/// Free block searches read two words per block: `size`, and the
+/// `next_free` link at the start of the payload. `size` is the last header
+/// field so the two sit side by side and nearly always share a cache line:
+///
+/// ```text
+///   ... │ is_free │ size ║ next_free │ prev_free │ ...
+///                   0x18 ║ 0x20
+///                 header ║ payload (free block)
+///
+///   size first (0x00) ──► 32 bytes apart, split by a line boundary
+///                         for half of all word-aligned headers
+///   size last  (0x18) ──► 8 bytes apart, split for one in eight
 /// ```
 ///
 /// # Relationship to User Data
@@ -55,7 +93,8 @@
 ///
 /// # Linked List Structure
 ///
-/// Blocks form a singly-linked list, allowing traversal of all allocations:
+/// Blocks form a doubly-linked list, allowing traversal of all allocations
+/// and O(1) removal of the tail block:
 ///
 /// ```text
 ///   Block 1              Block 2              Block 3
@@ -63,6 +102,7 @@
 ///   │ size: 64     │     │ size: 128    │     │ size: 32     │
 ///   │ is_free: no  │     │ is_free: yes │     │ is_free: no  │
 ///   │ next: ───────┼────►│ next: ───────┼────►│ next: null   │
+///   │ prev: null   │◄────┼─── :prev     │◄────┼─── :prev     │
 ///   └──────────────┘     └──────────────┘     └──────────────┘
 ///   │   64 bytes   │     │  128 bytes   │     │   32 bytes   │
 ///   │  user data   │     │  user data   │     │  user data   │
@@ -74,13 +114,27 @@
 /// * `size` - The size of the user data region in bytes (not including the header)
 /// * `is_free` - Whether this block has been deallocated and is available for reuse
 /// * `next` - Pointer to the next block in the linked list, or null if this is the last block
+/// * `prev` - Pointer to the previous block in the linked list, or null if this is the first block
 #[repr(C)]
 pub struct Block {
-  /// Size of the user data region in bytes.
+  /// Pointer to the next block in the allocation list.
   ///
-  /// This is the size requested by the user, not the total allocation size.
-  /// The total memory used is approximately `size_of::<Block>() + size`.
-  pub size: usize,
+  /// - `null`: This is the last block (tail of the list)
+  /// - Non-null: Points to the next block's header
+  ///
+  /// Together with `prev` this forms a doubly-linked list for O(n)
+  /// traversal of all allocations.
+  pub next: *mut Block,
+
+  /// Pointer to the previous block in the allocation list.
+  ///
+  /// - `null`: This is the first block (head of the list)
+  /// - Non-null: Points to the previous block's header
+  ///
+  /// Lets the allocator find the new tail in O(1) when the last block
+  /// is released, and the block below a freed one when merging free
+  /// neighbours, instead of walking the list from `first`.
+  pub prev: *mut Block,
 
   /// Flag indicating whether this block is free (deallocated).
   ///
@@ -91,13 +145,23 @@ pub struct Block {
   /// released back to the OS if they are the last block in the list.
   pub is_free: bool,
 
-  /// Pointer to the next block in the allocation list.
+  /// [`CANARY`] and [`SPAN_START`] bits.
   ///
-  /// - `null`: This is the last block (tail of the list)
-  /// - Non-null: Points to the next block's header
+  /// Sits in what would otherwise be padding after `is_free`.
+  pub flags: u8,
+
+  /// Allocator epoch the block was allocated in, see the `leaks` module.
   ///
-  /// This forms a singly-linked list for O(n) traversal of all allocations.
-  pub next: *mut Block,
+  /// Also in padding: the header stays four words on 32-bit targets too.
+  pub epoch: u16,
+
+  /// Size of the user data region in bytes.
+  ///
+  /// This is the size requested by the user, not the total allocation size.
+  /// The total memory used is approximately `size_of::<Block>() + size`.
+  ///
+  /// Last, right before the payload, see "Cache Layout" above.
+  pub size: usize,
 }
 
 impl Block {
@@ -108,6 +172,7 @@ impl Block {
   /// * `size` - Size of the user data region
   /// * `is_free` - Initial free status
   /// * `next` - Pointer to the next block (or null)
+  /// * `prev` - Pointer to the previous block (or null)
   ///
   /// # Returns
   ///
@@ -118,16 +183,156 @@ impl Block {
   /// ```rust,ignore
   /// use std::ptr;
   ///
-  /// let block = Block::new(64, false, ptr::null_mut());
+  /// let block = Block::new(64, false, ptr::null_mut(), ptr::null_mut());
   /// assert_eq!(block.size, 64);
   /// assert_eq!(block.is_free, false);
   /// assert!(block.next.is_null());
+  /// assert!(block.prev.is_null());
   /// ```
+  #[allow(dead_code)]
   pub fn new(
     size: usize,
     is_free: bool,
     next: *mut Block,
+    prev: *mut Block,
   ) -> Self {
-    Self { size, is_free, next }
+    Self {
+      size,
+      is_free,
+      flags: 0,
+      epoch: 0,
+      next,
+      prev,
+    }
+  }
+
+  /// Whether a canary word follows the payload, see the `canary` module.
+  pub fn has_canary(&self) -> bool {
+    self.flags & CANARY != 0
+  }
+
+  /// Whether the memory below this header belongs to no predecessor, so a
+  /// freed predecessor must not grow into it.
+  pub fn starts_span(&self) -> bool {
+    self.flags & SPAN_START != 0
+  }
+
+  /// Bytes of the payload area the block occupies: its size, the canary
+  /// word if it has one, and never less than [`MIN_PAYLOAD`].
+  pub fn payload_extent(&self) -> usize {
+    let canary = if self.has_canary() { CANARY_SIZE } else { 0 };
+    (self.size + canary).max(MIN_PAYLOAD)
+  }
+
+  /// The free-list links stored in the payload of `block`.
+  ///
+  /// # Safety
+  ///
+  /// `block` must be a valid header. The links are only meaningful while
+  /// the block is free; for a live block they overlap user data.
+  pub unsafe fn free_links(block: *mut Block) -> *mut FreeLinks {
+    unsafe { (block as *mut u8).add(HEADER_SIZE) as *mut FreeLinks }
+  }
+
+  /// Next block of the free list after the free `block`, with its header
+  /// and links already requested from memory.
+  ///
+  /// Free list walks call this before examining `block`, so the load of
+  /// the next block overlaps with the work on the current one:
+  ///
+  /// ```text
+  ///   next = next_free(current)   ──► prefetch next ──┐
+  ///   examine current                                 │ in flight
+  ///   current = next              ◄── already cached ─┘
+  /// ```
+  ///
+  /// # Safety
+  ///
+  /// `block` must be a valid free header with valid free links.
+  #[inline(always)]
+  pub unsafe fn next_free_prefetched(block: *mut Block) -> *mut Block {
+    unsafe {
+      let next = (*Block::free_links(block)).next_free;
+      prefetch(next);
+      next
+    }
   }
 }
+
+/// Hints the CPU to load the cache line holding the end of `block`'s
+/// header (`size`) and the start of its payload (the free links).
+///
+/// Only a hint: prefetching never faults, even for null, and compiles to
+/// nothing on targets without a stable prefetch intrinsic.
+#[inline(always)]
+fn prefetch(block: *mut Block) {
+  #[cfg(target_arch = "x86_64")]
+  {
+    use core::arch::x86_64::{_MM_HINT_T0, _mm_prefetch};
+    // SAFETY: `prefetch` is available on every x86_64 CPU and never
+    // dereferences its argument.
+    unsafe { _mm_prefetch::<_MM_HINT_T0>(block.wrapping_byte_add(HEADER_SIZE - mem::size_of::<usize>()) as *const i8) };
+  }
+  #[cfg(not(target_arch = "x86_64"))]
+  let _ = block;
+}
+
+/// Links of the free list, kept in the payload of each free block.
+///
+/// A free block's payload is dead memory, so the list costs no header
+/// space. Free blocks are linked in address order, the same order as the
+/// block list, which keeps first-fit and next-fit semantics unchanged:
+///
+/// ```text
+///   block list:  [A used] ──► [B free] ──► [C used] ──► [D free] ──► [E used]
+///                              │ payload                 │ payload
+///                              ▼                         ▼
+///   free list:   free_head ──► ┌───────────┐ ──────────► ┌───────────┐
+///                              │ next_free ┼──► D        │ next_free ┼──► null
+///                              │ prev_free ┼──► null     │ prev_free ┼──► B
+///                              └───────────┘             └───────────┘
+/// ```
+///
+/// The same payload also holds the block's node in the size index used
+/// by best-fit searches (`smaller`/`larger`, see the `size_index` module).
+///
+/// Every payload is at least [`MIN_PAYLOAD`] bytes, so the links always fit.
+#[repr(C)]
+pub struct FreeLinks {
+  /// Next free block, at a higher address (null at the end).
+  pub next_free: *mut Block,
+
+  /// Previous free block, at a lower address (null at the head).
+  pub prev_free: *mut Block,
+
+  /// Size index: subtree of blocks with smaller `(size, address)` keys.
+  /// Only maintained in [`SearchMode::BestFitIndexed`](crate::SearchMode::BestFitIndexed).
+  pub smaller: *mut Block,
+
+  /// Size index: subtree of blocks with larger `(size, address)` keys.
+  pub larger: *mut Block,
+}
+
+/// Size of the [`Block`] header in front of every payload.
+///
+/// Always four machine words, a multiple of the word size, so adding it to
+/// a word-aligned address keeps the address word-aligned.
+pub const HEADER_SIZE: usize = mem::size_of::<Block>();
+
+/// [`Block::flags`] bit: a canary word follows the payload.
+pub const CANARY: u8 = 1 << 0;
+
+/// [`Block::flags`] bit: the block starts a new span of the backend's
+/// memory. The program break was moved by someone else (another `sbrk`
+/// user) since the predecessor was placed, or headerless slots were
+/// bumped right below, so what lies between the two is not free to take:
+///
+/// ```text
+///   [hdr│ pred ]│ foreign sbrk memory │[hdr│ block ]   block: SPAN_START
+///   [hdr│ pred ]│ headerless slots    │[hdr│ block ]   block: SPAN_START
+///               ◄── never absorbed ──►
+/// ```
+pub const SPAN_START: u8 = 1 << 1;
+
+/// Smallest payload reserved for any block: room for its [`FreeLinks`].
+pub const MIN_PAYLOAD: usize = mem::size_of::<FreeLinks>();
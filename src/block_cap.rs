@@ -0,0 +1,226 @@
+//! # Block List Cap
+//!
+//! Every scan of the block list - stats, invariant checks, heap maps, free
+//! block searches - is O(n) in its length. A program making millions of
+//! small allocations between resets pays for that on every scan. With
+//! [`BumpAllocator::set_max_blocks`], the allocator stops tracking once the
+//! list holds `N` blocks: further allocations are plain bumps with no
+//! header, and only a bulk reset gives them back.
+//!
+//! ```text
+//!   max_blocks = 3
+//!
+//!   │ hdr │ A │ hdr │ B │ hdr │ C │ d │ e │  f  │ g │ ──► break
+//!   └──────── tracked: 3 blocks ────┘└── untracked bumps ──┘
+//!                                    ▲
+//!                                    untracked start
+//!
+//!   deallocate(A)       marks A free, as always
+//!   deallocate(e)       no-op: e has no header
+//!   deallocate(C)       C is not popped - the bumps lie above it
+//!   reset()             everything, tracked or not, goes back
+//!   shrink_to(mark)     drops the bumps with the blocks after the mark
+//! ```
+//!
+//! A hybrid of a tracked allocator and an untracked arena: the first `N`
+//! allocations keep every diagnostic, the rest cost one pointer bump.
+//! Untracked memory shows up in [`Stats::untracked_bytes`] but not in the
+//! block-level reports (heap maps, snapshots, leak listings, counters of
+//! frees).
+//!
+//! [`Stats::untracked_bytes`]: crate::Stats::untracked_bytes
+
+use core::{alloc::Layout, ptr};
+
+use crate::{
+  AllocFailure, BumpAllocator,
+  align::align_up,
+  bump::MAX_REQUEST_SIZE,
+};
+
+impl BumpAllocator {
+  /// Tracks at most `max` blocks; past that, allocations are untracked
+  /// bumps until the next [`reset`](Self::reset). `None` (the default)
+  /// tracks every allocation.
+  ///
+  /// Lowering the cap below the current block count stops tracking at the
+  /// next allocation; blocks already in the list stay.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// allocator.set_max_blocks(Some(10_000));
+  /// for token in tokens {
+  ///     intern(&mut allocator, token);    // the first 10 000 keep a header
+  /// }
+  /// unsafe { allocator.reset() };         // the rest are freed here
+  /// ```
+  pub fn set_max_blocks(
+    &mut self,
+    max: Option<usize>,
+  ) {
+    self.max_blocks = max;
+  }
+
+  /// The cap set by [`set_max_blocks`](Self::set_max_blocks), if any.
+  pub fn max_blocks(&self) -> Option<usize> {
+    self.max_blocks
+  }
+
+  /// Bytes bumped past the block cap since the last reset, alignment
+  /// padding included.
+  pub fn untracked_bytes(&self) -> usize {
+    if self.untracked.is_null() {
+      0
+    } else {
+      self.backend.current_break() as usize - self.untracked as usize
+    }
+  }
+
+  /// Whether `address` was handed out past the block cap.
+  #[inline]
+  pub(crate) fn is_untracked(
+    &self,
+    address: *const u8,
+  ) -> bool {
+    !self.untracked.is_null() && address >= self.untracked.cast_const()
+  }
+
+  /// Whether as many blocks are tracked as the cap allows.
+  #[inline]
+  pub(crate) fn at_block_cap(&self) -> bool {
+    self.max_blocks.is_some_and(|max| self.tracked_blocks >= max)
+  }
+
+  /// Allocates `layout` as a tracked block, or as an untracked bump once
+  /// the cap is reached.
+  #[inline]
+  pub(crate) unsafe fn place(
+    &mut self,
+    layout: Layout,
+  ) -> *mut u8 {
+    let capped = self.at_block_cap();
+    // A bump in between would make the next block's memory untracked
+    if capped || !self.untracked.is_null() {
+      unsafe { self.bump_untracked(layout) }
+    } else {
+      unsafe { self.push_block(layout) }
+    }
+  }
+
+  /// Bumps the break for `layout`, without a header.
+  #[cold]
+  unsafe fn bump_untracked(
+    &mut self,
+    layout: Layout,
+  ) -> *mut u8 {
+    let align = layout.align().max(self.min_align);
+    let Some(size) = layout
+      .size()
+      .checked_add(align - 1)
+      .filter(|&size| size <= MAX_REQUEST_SIZE)
+    else {
+      self.last_failure = Some(AllocFailure::SizeOverflow { size: layout.size() });
+      return ptr::null_mut();
+    };
+
+    let raw = unsafe { self.grow_heap(size) };
+    if raw.is_null() {
+      return raw;
+    }
+    if self.untracked.is_null() {
+      self.untracked = raw;
+    }
+    align_up(raw as usize, align) as *mut u8
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn allocations_past_the_cap_are_untracked() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_max_blocks(Some(2));
+    let layout = Layout::new::<u64>();
+
+    unsafe {
+      let a = allocator.allocate(layout);
+      let b = allocator.allocate(layout);
+      let c = allocator.allocate(Layout::from_size_align(24, 32).unwrap());
+      let d = allocator.allocate(layout);
+      assert!(!c.is_null() && !d.is_null() && c < d);
+      assert!((c as usize).is_multiple_of(32));
+      c.write_bytes(0xAA, 24);
+
+      let stats = allocator.stats();
+      assert_eq!(stats.total_blocks(), 2);
+      assert_eq!(stats.untracked_bytes, allocator.untracked_bytes());
+      assert!(stats.untracked_bytes >= 32);
+      assert_eq!(allocator.check_invariants(), Ok(()));
+
+      // Freeing an untracked pointer is a no-op, the tail is not popped
+      let heap = allocator.heap_size();
+      allocator.deallocate(d);
+      allocator.deallocate(b);
+      assert_eq!(allocator.heap_size(), heap);
+      assert_eq!(allocator.block_count(), 2);
+      assert_eq!(allocator.live_blocks(), 1);
+      assert!(!a.is_null());
+
+      allocator.reset();
+    }
+    assert!(allocator.is_empty());
+    assert_eq!(allocator.untracked_bytes(), 0);
+
+    // The cap holds for the next round
+    unsafe {
+      allocator.allocate(layout);
+      allocator.allocate(layout);
+      allocator.allocate(layout);
+    }
+    assert_eq!(allocator.block_count(), 2);
+  }
+
+  #[test]
+  fn tail_pops_keep_the_count() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_max_blocks(Some(1));
+    let layout = Layout::new::<u64>();
+
+    unsafe {
+      for _ in 0..3 {
+        let ptr = allocator.allocate(layout);
+        allocator.deallocate(ptr);
+      }
+      allocator.allocate(layout);
+    }
+    assert_eq!(allocator.block_count(), 1);
+    assert_eq!(allocator.untracked_bytes(), 0);
+  }
+
+  #[test]
+  fn shrink_to_drops_the_untracked_bumps() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_max_blocks(Some(2));
+    let layout = Layout::new::<u64>();
+
+    unsafe {
+      allocator.allocate(layout);
+      let mark = allocator.checkpoint();
+      allocator.allocate(layout);
+      allocator.allocate(layout);
+      assert!(allocator.untracked_bytes() > 0);
+
+      allocator.shrink_to(mark);
+      assert_eq!(allocator.untracked_bytes(), 0);
+      assert_eq!(allocator.block_count(), 1);
+
+      // Tracking resumes
+      allocator.allocate(layout);
+    }
+    assert_eq!(allocator.block_count(), 2);
+    assert_eq!(allocator.check_invariants(), Ok(()));
+  }
+}
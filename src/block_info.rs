@@ -0,0 +1,338 @@
+//! # Allocation Queries
+//!
+//! [`BumpAllocator::allocation_info`] describes the block behind any
+//! pointer - a debugger watch expression, or context for an assertion
+//! message:
+//!
+//! ```rust,ignore
+//! let info = allocator.allocation_info(ptr).expect("not from this allocator");
+//! assert!(!info.is_free, "use after free: {info}");
+//! ```
+//!
+//! The pointer is never dereferenced. It is looked up by walking the block
+//! list and comparing it against each block's payload range, so a foreign
+//! or dangling pointer simply yields `None`:
+//!
+//! ```text
+//!   [hdr│ payload A ][hdr│ payload B        ][hdr│ payload C ]
+//!                          ▲        ▲
+//!                          │        └── ptr: block B, offset 9
+//!                          └── start of B: offset 0
+//! ```
+//!
+//! [`BumpAllocator::walk`] visits every block the same way, in the spirit
+//! of Windows' `HeapWalk`: the visitor sees each [`BlockInfo`] in list
+//! order and returns [`ControlFlow::Break`] to stop early, carrying out
+//! whatever it found.
+
+use core::{fmt, ops::ControlFlow};
+
+use crate::{
+  BumpAllocator,
+  block::{Block, HEADER_SIZE},
+};
+
+/// What the allocator knows about one block.
+///
+/// Returned by [`BumpAllocator::allocation_info`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BlockInfo {
+  /// Start of the payload (the pointer `allocate` returned).
+  pub address: usize,
+
+  /// How far into the payload the queried pointer points.
+  pub offset: usize,
+
+  /// Requested size of the block.
+  pub size: usize,
+
+  /// Whether the block has been deallocated.
+  pub is_free: bool,
+
+  /// Position of the block in the list, oldest first.
+  pub index: usize,
+
+  /// Whether the block came from the emergency reserve; `index` then
+  /// counts within the reserve.
+  pub from_reserve: bool,
+
+  /// Whether the block is a foreign region registered with
+  /// [`adopt`](BumpAllocator::adopt); `index` then counts adopted regions.
+  pub adopted: bool,
+
+  /// Operations performed since the block was allocated, if the
+  /// `flight-recorder` feature is enabled and the allocation is still in
+  /// the log.
+  pub age: Option<usize>,
+
+  /// Allocator epoch the block was allocated in, see
+  /// [`advance_epoch`](BumpAllocator::advance_epoch).
+  pub epoch: u16,
+}
+
+impl fmt::Display for BlockInfo {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    write!(
+      f,
+      "block #{} at {:#x}{}: {} bytes, {}",
+      self.index,
+      self.address,
+      if self.from_reserve {
+        " (reserve)"
+      } else if self.adopted {
+        " (adopted)"
+      } else {
+        ""
+      },
+      self.size,
+      if self.is_free { "free" } else { "live" }
+    )?;
+    if self.offset > 0 {
+      write!(f, ", pointer at +{}", self.offset)?;
+    }
+    if let Some(age) = self.age {
+      write!(f, ", allocated {age} ops ago")?;
+    }
+    Ok(())
+  }
+}
+
+/// Address handed to the user for `block`: right after its header.
+fn payload_address(block: &Block) -> usize {
+  block as *const Block as usize + HEADER_SIZE
+}
+
+impl BumpAllocator {
+  /// Describes the block containing `ptr`, if this allocator handed it out.
+  ///
+  /// Accepts interior pointers. O(n) in the number of blocks; like other
+  /// diagnostics it is allowed in real-time mode.
+  ///
+  /// # Returns
+  ///
+  /// `None` if `ptr` is not inside any block's payload.
+  pub fn allocation_info(
+    &self,
+    ptr: *const u8,
+  ) -> Option<BlockInfo> {
+    if let Some(reserve) = self.reserve.arena()
+      && let Some(info) = reserve.allocation_info(ptr)
+    {
+      return Some(BlockInfo {
+        from_reserve: true,
+        ..info
+      });
+    }
+
+    #[cfg(feature = "std")]
+    if let Some(info) = self.adopted_info(ptr) {
+      return Some(info);
+    }
+
+    let ptr = ptr as usize;
+    self.blocks().enumerate().find_map(|(index, block)| {
+      let address = payload_address(block);
+      // A zero-sized block still owns its start address
+      let end = address + block.size.max(1);
+      (address..end)
+        .contains(&ptr)
+        .then(|| self.describe(index, block, ptr - address))
+    })
+  }
+
+  /// The [`BlockInfo`] of the `index`-th block of this allocator's list.
+  pub(crate) fn describe(
+    &self,
+    index: usize,
+    block: &Block,
+    offset: usize,
+  ) -> BlockInfo {
+    let address = payload_address(block);
+    BlockInfo {
+      address,
+      offset,
+      size: block.size,
+      is_free: block.is_free,
+      index,
+      from_reserve: false,
+      adopted: false,
+      age: self.age_of(address),
+      epoch: block.epoch,
+    }
+  }
+
+  /// Calls `visit` with every block, live or free, oldest first, then
+  /// with the blocks of the emergency reserve (`from_reserve` set) and the
+  /// adopted regions (`adopted` set).
+  ///
+  /// Unlike collecting a report, the walk stops as soon as `visit` breaks,
+  /// and the break value is returned. The allocator stays borrowed, so the
+  /// visitor decides and the caller acts once the walk is over. O(n) in the
+  /// number of blocks; a diagnostic, allowed in real-time mode.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// // The first live block larger than a page
+  /// let big = allocator.walk(|info| match !info.is_free && info.size > 4096 {
+  ///   true => ControlFlow::Break(info.address),
+  ///   false => ControlFlow::Continue(()),
+  /// });
+  /// ```
+  pub fn walk<B>(
+    &self,
+    mut visit: impl FnMut(BlockInfo) -> ControlFlow<B>,
+  ) -> ControlFlow<B> {
+    for (index, block) in self.blocks().enumerate() {
+      visit(self.describe(index, block, 0))?;
+    }
+    if let Some(reserve) = self.reserve.arena() {
+      for (index, block) in reserve.blocks().enumerate() {
+        visit(BlockInfo {
+          from_reserve: true,
+          ..reserve.describe(index, block, 0)
+        })?;
+      }
+    }
+    #[cfg(feature = "std")]
+    for (index, region) in self.adopted_regions().enumerate() {
+      visit(self.describe_adopted(index, region.start, region.len(), 0))?;
+    }
+    ControlFlow::Continue(())
+  }
+
+  /// Whether `ptr` points into a block handed out by this allocator.
+  pub fn owns(
+    &self,
+    ptr: *const u8,
+  ) -> bool {
+    self.allocation_info(ptr).is_some()
+  }
+
+  /// Operations since `address` was last allocated, from the flight
+  /// recorder.
+  #[cfg(feature = "flight-recorder")]
+  fn age_of(
+    &self,
+    address: usize,
+  ) -> Option<usize> {
+    let ops = self.recent_ops();
+    let allocated_at = ops
+      .iter()
+      .enumerate()
+      .filter(|(_, op)| op.kind == crate::OpKind::Allocate && op.address == address)
+      .map(|(index, _)| index)
+      .last()?;
+    Some(ops.len() - 1 - allocated_at)
+  }
+
+  /// Without the flight recorder, ages are unknown.
+  #[cfg(not(feature = "flight-recorder"))]
+  fn age_of(
+    &self,
+    _address: usize,
+  ) -> Option<usize> {
+    None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::alloc::Layout;
+
+  #[test]
+  fn live_and_free_blocks_are_described() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let (a, b) = unsafe {
+      let a = allocator.allocate(Layout::array::<u8>(24).unwrap());
+      let b = allocator.allocate(Layout::array::<u8>(40).unwrap());
+      allocator.allocate(Layout::new::<u64>());
+      allocator.deallocate(a);
+      (a, b)
+    };
+
+    // A freed block takes the slack up to the next header
+    let span = crate::bump::grow_request_size(Layout::array::<u8>(24).unwrap()).unwrap() - HEADER_SIZE;
+    let info = allocator.allocation_info(a).unwrap();
+    assert_eq!((info.address, info.size, info.is_free, info.index), (a as usize, span, true, 0));
+
+    let info = allocator.allocation_info(b).unwrap();
+    assert_eq!((info.size, info.is_free, info.index, info.offset), (40, false, 1, 0));
+    assert!(!info.from_reserve);
+  }
+
+  #[test]
+  fn interior_pointers_resolve_to_their_block() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let ptr = unsafe { allocator.allocate(Layout::array::<u8>(40).unwrap()) };
+
+    let info = allocator.allocation_info(ptr.wrapping_add(9)).unwrap();
+    assert_eq!((info.address, info.offset), (ptr as usize, 9));
+    assert!(allocator.allocation_info(ptr.wrapping_add(40)).is_none());
+  }
+
+  #[test]
+  fn foreign_pointers_are_rejected() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    unsafe { allocator.allocate(Layout::new::<u64>()) };
+
+    let local = 0u64;
+    assert!(!allocator.owns(&local as *const u64 as *const u8));
+    assert!(!allocator.owns(core::ptr::null()));
+  }
+
+  #[test]
+  fn display_summarizes_the_block() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let ptr = unsafe { allocator.allocate(Layout::array::<u8>(16).unwrap()) };
+
+    let text = allocator.allocation_info(ptr.wrapping_add(4)).unwrap().to_string();
+    assert!(text.starts_with("block #0 at 0x"), "{text}");
+    assert!(text.contains("16 bytes, live, pointer at +4"), "{text}");
+  }
+
+  #[test]
+  fn walks_visit_every_block_and_stop_early() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let ptrs: Vec<_> = (1..=4).map(|n| unsafe { allocator.allocate(Layout::array::<u8>(n * 8).unwrap()) }).collect();
+    unsafe { allocator.deallocate(ptrs[1]) };
+
+    let mut seen = Vec::new();
+    let walked = allocator.walk(|info| {
+      seen.push((info.index, info.is_free));
+      ControlFlow::<()>::Continue(())
+    });
+    assert_eq!(walked, ControlFlow::Continue(()));
+    assert_eq!(seen, [(0, false), (1, true), (2, false), (3, false)]);
+
+    let mut visited = 0;
+    let found = allocator.walk(|info| {
+      visited += 1;
+      match info.size == 24 {
+        true => ControlFlow::Break(info.address),
+        false => ControlFlow::Continue(()),
+      }
+    });
+    assert_eq!(found, ControlFlow::Break(ptrs[2] as usize));
+    assert_eq!(visited, 3);
+  }
+
+  #[test]
+  #[cfg(feature = "flight-recorder")]
+  fn age_counts_operations_since_allocation() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let ptr = unsafe { allocator.allocate(Layout::new::<u64>()) };
+    assert_eq!(allocator.allocation_info(ptr).unwrap().age, Some(0));
+
+    unsafe {
+      allocator.allocate(Layout::new::<u64>());
+      allocator.allocate(Layout::new::<u64>());
+    }
+    assert_eq!(allocator.allocation_info(ptr).unwrap().age, Some(2));
+  }
+}
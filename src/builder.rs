@@ -0,0 +1,289 @@
+//! # Builder
+//!
+//! [`BumpAllocator::builder`] sets up an allocator in one expression, from
+//! the backend to its policies and hooks:
+//!
+//! ```rust,ignore
+//! let allocator = BumpAllocator::builder()
+//!     .capacity(1024 * 1024)
+//!     .search(SearchMode::BestFit)
+//!     .chunk_size(64 * 1024)
+//!     .limit(256 * 1024)
+//!     .oom_handler(drop_caches)
+//!     .build();
+//! ```
+//!
+//! Every setting has a default, so options added later never break
+//! existing builder chains.
+
+use crate::{
+  BumpAllocator, CanaryPolicy, Config, FailurePolicy, OomHandler, RateLimit, SearchMode, ShrinkPolicy,
+  backend::{Backend, Region},
+};
+
+/// Step-by-step construction of a [`BumpAllocator`].
+///
+/// Created by [`BumpAllocator::builder`]. Without a backend method, the
+/// allocator uses the same memory source as [`BumpAllocator::new`].
+pub struct BumpAllocatorBuilder {
+  /// Where memory comes from.
+  backend: Backend,
+
+  /// Policies, see [`Config`].
+  config: Config,
+
+  /// Out-of-memory handler to install.
+  oom_handler: Option<OomHandler>,
+
+  /// Rate limit to install.
+  rate_limit: Option<RateLimit>,
+}
+
+impl BumpAllocator {
+  /// Starts building an allocator, see [`BumpAllocatorBuilder`].
+  pub fn builder() -> BumpAllocatorBuilder {
+    BumpAllocatorBuilder {
+      backend: Backend::platform_default(),
+      config: Config::DEFAULT,
+      oom_handler: None,
+      rate_limit: None,
+    }
+  }
+}
+
+impl BumpAllocatorBuilder {
+  /// Allocates from `buffer`, like [`BumpAllocator::from_buffer`].
+  pub fn buffer(
+    mut self,
+    buffer: &'static mut [u8],
+  ) -> Self {
+    // SAFETY: The exclusive 'static borrow keeps the memory valid and unused
+    // by anything else for the allocator's lifetime.
+    self.backend = Backend::Region(unsafe { Region::borrowed(buffer.as_mut_ptr(), buffer.len()) });
+    self
+  }
+
+  /// Allocates from `start..start + len`, like
+  /// [`BumpAllocator::from_raw_region`].
+  ///
+  /// # Safety
+  ///
+  /// Same as [`BumpAllocator::from_raw_region`].
+  pub unsafe fn raw_region(
+    mut self,
+    start: *mut u8,
+    len: usize,
+  ) -> Self {
+    self.backend = Backend::Region(unsafe { Region::borrowed(start, len) });
+    self
+  }
+
+  /// Allocates from a `capacity`-byte region of the system allocator, like
+  /// [`BumpAllocator::with_capacity`].
+  #[cfg(feature = "std")]
+  pub fn capacity(
+    mut self,
+    capacity: usize,
+  ) -> Self {
+    self.backend = Backend::Region(Region::owned(capacity));
+    self
+  }
+
+  /// Replaces every policy at once.
+  pub fn config(
+    mut self,
+    config: Config,
+  ) -> Self {
+    self.config = config;
+    self
+  }
+
+  /// Free block search strategy, see [`Config::search`].
+  pub fn search(
+    mut self,
+    mode: SearchMode,
+  ) -> Self {
+    self.config.search = mode;
+    self
+  }
+
+  /// Starts in real-time mode, see [`Config::realtime`].
+  pub fn realtime(
+    mut self,
+    enabled: bool,
+  ) -> Self {
+    self.config.realtime = enabled;
+    self
+  }
+
+  /// Minimum program break growth, see [`Config::growth_chunk`].
+  pub fn chunk_size(
+    mut self,
+    bytes: usize,
+  ) -> Self {
+    self.config.growth_chunk = bytes;
+    self
+  }
+
+  /// Minimum payload alignment, see [`Config::min_align`].
+  pub fn min_align(
+    mut self,
+    align: usize,
+  ) -> Self {
+    self.config.min_align = align;
+    self
+  }
+
+  /// Random gaps between blocks, see [`Config::jitter`].
+  pub fn jitter(
+    mut self,
+    max_gap: usize,
+  ) -> Self {
+    self.config.jitter = max_gap;
+    self
+  }
+
+  /// Seed for [`SearchMode::Random`], see [`Config::random_seed`].
+  pub fn random_seed(
+    mut self,
+    seed: u64,
+  ) -> Self {
+    self.config.random_seed = seed;
+    self
+  }
+
+  /// When freed tail memory goes back to the system, see
+  /// [`Config::shrink`].
+  pub fn shrink_policy(
+    mut self,
+    policy: ShrinkPolicy,
+  ) -> Self {
+    self.config.shrink = policy;
+    self
+  }
+
+  /// Overflow canaries on some or all allocations, see
+  /// [`Config::canaries`].
+  pub fn canaries(
+    mut self,
+    policy: CanaryPolicy,
+  ) -> Self {
+    self.config.canaries = policy;
+    self
+  }
+
+  /// Caps the heap at `bytes`, see [`Config::heap_limit`].
+  pub fn limit(
+    mut self,
+    bytes: usize,
+  ) -> Self {
+    self.config.heap_limit = Some(bytes);
+    self
+  }
+
+  /// Tracks at most `count` blocks, see [`Config::max_blocks`].
+  pub fn max_blocks(
+    mut self,
+    count: usize,
+  ) -> Self {
+    self.config.max_blocks = Some(count);
+    self
+  }
+
+  /// Sets what typed helpers do on failure, see [`Config::failure`].
+  pub fn failure_policy(
+    mut self,
+    policy: FailurePolicy,
+  ) -> Self {
+    self.config.failure = policy;
+    self
+  }
+
+  /// Installs an out-of-memory handler, see [`OomHandler`].
+  pub fn oom_handler(
+    mut self,
+    handler: OomHandler,
+  ) -> Self {
+    self.oom_handler = Some(handler);
+    self
+  }
+
+  /// Throttles allocations, see [`RateLimit`].
+  pub fn rate_limit(
+    mut self,
+    limit: RateLimit,
+  ) -> Self {
+    self.rate_limit = Some(limit);
+    self
+  }
+
+  /// Creates the allocator. No memory is obtained until the first
+  /// allocation.
+  ///
+  /// # Panics
+  ///
+  /// If the minimum alignment is not a power of two.
+  pub fn build(self) -> BumpAllocator {
+    let mut allocator = BumpAllocator::with_backend(self.backend);
+    allocator.apply_config(self.config);
+    allocator.set_oom_handler(self.oom_handler);
+    allocator.set_rate_limit(self.rate_limit);
+    allocator
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::alloc::Layout;
+
+  #[test]
+  fn default_build_matches_new() {
+    let allocator = BumpAllocator::builder().build();
+    assert_eq!(allocator.config(), BumpAllocator::new().config());
+    assert!(allocator.oom_handler().is_none());
+    assert!(allocator.rate_limit().is_none());
+  }
+
+  #[test]
+  fn settings_reach_the_allocator() {
+    fn give_up(
+      _: &mut BumpAllocator,
+      _: Layout,
+    ) -> bool {
+      false
+    }
+
+    let allocator = BumpAllocator::builder()
+      .search(SearchMode::BestFit)
+      .chunk_size(64 * 1024)
+      .limit(1 << 20)
+      .min_align(16)
+      .max_blocks(1000)
+      .failure_policy(FailurePolicy::Panic)
+      .oom_handler(give_up)
+      .build();
+
+    let config = allocator.config();
+    assert_eq!(config.search, SearchMode::BestFit);
+    assert_eq!(config.heap_limit, Some(1 << 20));
+    assert_eq!(config.min_align, 16);
+    assert_eq!(config.max_blocks, Some(1000));
+    assert_eq!(config.failure, FailurePolicy::Panic);
+    #[cfg(unix)]
+    assert_eq!(config.growth_chunk, 64 * 1024);
+    assert!(allocator.oom_handler().is_some());
+  }
+
+  #[test]
+  fn region_backends_are_selectable() {
+    let mut allocator = BumpAllocator::builder().capacity(512).limit(256).build();
+    assert_eq!(allocator.headroom(), Some(256));
+    assert!(unsafe { allocator.allocate(Layout::array::<u8>(300).unwrap()) }.is_null());
+
+    let buffer = Box::leak(vec![0u8; 1024].into_boxed_slice());
+    let mut allocator = BumpAllocator::builder().buffer(buffer).build();
+    assert!(!unsafe { allocator.allocate(Layout::array::<u8>(500).unwrap()) }.is_null());
+    assert_eq!(allocator.stats().os_limit, None);
+  }
+}
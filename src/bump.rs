@@ -46,6 +46,7 @@
 //!  │ size: usize      │                         │    │
 //!  │ is_free: bool    │   [    N bytes    ]     │    │
 //!  │ next: *mut Block │                         │    │
+//!  │ prev: *mut Block │                         │    │
 //!  └──────────────────┴─────────────────────────┘    │
 //!     │                  ▲                           │
 //!     │                  │                           │
@@ -55,7 +56,7 @@
 //!
 //! ### Linked List of Blocks
 //!
-//! Multiple allocations form a singly-linked list:
+//! Multiple allocations form a doubly-linked list (`prev` links omitted below):
 //!
 //! ```text
 //!   BumpAllocator
@@ -89,7 +90,7 @@
 //!        ▼
 //!   ┌────┬───────────────────┬───────────────────────────────────────┐
 //!   │pad │   Block Header    │           User Data                   │
-//!   │    │   (24 bytes on    │           (aligned to                 │
+//!   │    │   (32 bytes on    │           (aligned to                 │
 //!   │    │    64-bit)        │            requested alignment)       │
 //!   └────┴───────────────────┴───────────────────────────────────────┘
 //!        │                   │
@@ -131,6 +132,7 @@
 //!   │  (*block).is_free = false                               │
 //!   │  (*block).size = user_size                              │
 //!   │  (*block).next = null                                   │
+//!   │  (*block).prev = last                                   │
 //!   └─────────────────────────────────────────────────────────┘
 //!
 //!   STEP 5: Update linked list
@@ -175,6 +177,32 @@
 //!         but NOT returned to the OS (cannot shrink the heap).
 //! ```
 //!
+//! The new tail is found through the freed block's `prev` link, so
+//! releasing the last block never walks the list. A freed middle block
+//! merges with free neighbours the same way: `next` and `prev` reach both
+//! in O(1), so the header already does the job of a boundary-tag footer
+//! and blocks carry none.
+//!
+//! ## Real-Time Mode
+//!
+//! Audio and other real-time code cares more about the *worst case* latency of
+//! a single operation than about memory reuse. [`BumpAllocator::realtime`]
+//! creates an allocator that never traverses the block list:
+//!
+//! ```text
+//!   ┌──────────────┬──────────────────────────────────────┬──────────┐
+//!   │  Operation   │  Real-time mode                      │  Bound   │
+//!   ├──────────────┼──────────────────────────────────────┼──────────┤
+//!   │  allocate    │  pure bump: one sbrk, append to tail │  O(1)    │
+//!   │  deallocate  │  mark free; tail pop via `prev`      │  O(1)    │
+//!   │  free search │  disabled                            │    -     │
+//!   └──────────────┴──────────────────────────────────────┴──────────┘
+//! ```
+//!
+//! Every list traversal carries a `debug_assert!` that fires in real-time
+//! mode, so a debug build turns any accidental O(n) path into a panic.
+//! The bounds exclude the cost of the `sbrk` system call itself.
+//!
 //! ## Trade-offs
 //!
 //! ### Advantages
@@ -185,8 +213,12 @@
 //! ### Disadvantages
 //! - **Limited deallocation**: Can only truly free the last block
 //! - **Memory waste**: Middle deallocations don't return memory to OS
-//! - **No reuse of freed blocks**: The `find_free_block` method exists but
-//!   `allocate` always requests new memory (potential optimization point)
+//! - **Coarse splitting**: A reused block keeps slack too small for a
+//!   header and a minimal payload; only merging freed neighbours makes
+//!   holes larger
+//! - **Header overhead**: Each block carries a 32-byte header (64-bit) and
+//!   a payload of at least 32 bytes, where free blocks keep their
+//!   free-list and size-index links
 //!
 //! ## System Calls
 //!
@@ -248,10 +280,101 @@
 //! }
 //! ```
 
-use std::{alloc, mem, ptr};
-use libc::{c_void, intptr_t, sbrk};
+use core::{alloc, fmt, marker::PhantomData, mem, ops::Range, ptr};
+#[cfg(all(feature = "std", unix))]
+use libc::sbrk;
+
+use crate::{
+  align::align_up,
+  backend::Backend,
+  backend::Region,
+  block::{Block, FreeLinks, HEADER_SIZE, MIN_PAYLOAD, SPAN_START},
+  canary::{CANARY_SIZE, CanaryPolicy, write_canary},
+  donate::Donation,
+  limits::{AllocFailure, FailurePolicy},
+  model,
+  rate::RateState,
+  reserve::EmergencyReserve,
+  shrink::ShrinkPolicy,
+  size_index::SizeIndex,
+  sized::HEADERLESS_CLASSES,
+  stats::Stats,
+};
+#[cfg(feature = "thread-check")]
+use crate::owner::Owner;
+#[cfg(feature = "shadow")]
+use crate::shadow::ShadowMap;
+#[cfg(feature = "counters")]
+use crate::{Counters, SizeClasses};
+#[cfg(feature = "flight-recorder")]
+use crate::{
+  invariants::Corruption,
+  recorder::{FlightRecorder, OpKind, flight_recorder},
+};
+
+/// Alignment actually used for an allocation.
+///
+/// At least the alignment of [`Block`], so the header placed right before
+/// the content is always properly aligned - even when the backend hands out
+/// memory at an odd address (e.g. a borrowed `[u8]` buffer).
+#[inline]
+pub(crate) fn effective_align(layout: alloc::Layout) -> usize {
+  layout.align().max(mem::align_of::<Block>())
+}
+
+/// Number of bytes to request from the backend for `layout`.
+///
+/// This is the header, the payload and the worst-case alignment padding,
+/// rounded up to the machine word. The payload is at least [`MIN_PAYLOAD`]
+/// so the block can hold its free-list links once freed:
+///
+/// ```text
+///   align!(header_size + max(layout.size(), MIN_PAYLOAD) + (align - 1))
+/// ```
+///
+/// Every step is checked, so layouts close to `usize::MAX` (reachable on
+/// 32-bit targets, where `usize` is only 4 GiB) yield `None` instead of
+/// wrapping around to a small request.
+#[inline]
+pub(crate) fn grow_request_size(layout: alloc::Layout) -> Option<usize> {
+  let word = mem::size_of::<usize>();
+
+  HEADER_SIZE
+    .checked_add(layout.size().max(MIN_PAYLOAD))?
+    .checked_add(effective_align(layout) - 1)?
+    .checked_add(word - 1)
+    .map(|size| size & !(word - 1))
+}
+
+/// `(block, size)` of each free list entry from `start` on, with the next
+/// entry prefetched while the caller examines the current one.
+///
+/// # Safety
+///
+/// `start` must be null or on the free list, and the list must not change
+/// while the iterator is in use.
+unsafe fn free_list(start: *mut Block) -> impl Iterator<Item = (*mut Block, usize)> {
+  let mut current = start;
+  core::iter::from_fn(move || {
+    if current.is_null() {
+      return None;
+    }
+    let block = current;
+    // SAFETY: The caller guarantees every entry is a valid free block.
+    unsafe {
+      current = Block::next_free_prefetched(block);
+      Some((block, (*block).size))
+    }
+  })
+}
+
+/// Largest number of bytes `push_block` asks a backend for. Anything
+/// larger cannot be addressed by pointer offsets, which are `isize`.
+pub(crate) const MAX_REQUEST_SIZE: usize = isize::MAX as usize;
 
-use crate::{align, align_to, block::Block};
+/// Seed of the [`SearchMode::Random`] generator until
+/// [`BumpAllocator::set_random_seed`] is called.
+pub(crate) const DEFAULT_RANDOM_SEED: u64 = 0x853c_49e6_748f_ea9b;
 
 /// Strategy for searching free blocks in the allocator.
 ///
@@ -307,8 +430,47 @@ use crate::{align, align_to, block::Block};
 ///   │  Pros: Minimizes wasted space within blocks                          │
 ///   │  Cons: Slower - always O(n), must check all blocks                   │
 ///   └──────────────────────────────────────────────────────────────────────┘
+///
+///   BEST FIT INDEXED: Same answer as BEST FIT, from a size-ordered tree
+///   ┌──────────────────────────────────────────────────────────────────────┐
+///   │              (128, B)        free blocks keyed by (size, address)    │
+///   │             /        \                                               │
+///   │        (32, C)     (256, D)  descend towards 50: C too small,        │
+///   │                              B is the smallest key above             │
+///   │                                                                      │
+///   │  Returns: B (smallest free block that fits)                          │
+///   │  Pros: O(log n) amortized search                                     │
+///   │  Cons: Every free and reuse also updates the tree                    │
+///   └──────────────────────────────────────────────────────────────────────┘
+///
+///   GOOD FIT (tolerance 20%): Like BEST FIT, but stop at a close enough block
+///   ┌──────────────────────────────────────────────────────────────────────┐
+///   │  Request 110: anything up to 110 + 20% = 132 bytes is good enough    │
+///   │                                                                      │
+///   │  [A:64] → [B:128,free] → [C:32,free] → [D:256,free] → [E:100]       │
+///   │              ↓                                                       │
+///   │          ✓ GOOD! (128 <= 132), stop searching                        │
+///   │                                                                      │
+///   │  Returns: B (first free block within the tolerance, else best fit)   │
+///   │  Pros: Usually stops early, wastes at most the tolerance             │
+///   │  Cons: O(n) when no block is close enough                            │
+///   └──────────────────────────────────────────────────────────────────────┘
+///
+///   RANDOM: Pick any fitting block at random
+///   ┌──────────────────────────────────────────────────────────────────────┐
+///   │  [A:64] → [B:128,free] → [C:32,free] → [D:256,free] → [E:100]       │
+///   │              ↓                             ↓                         │
+///   │          candidate                     candidate                     │
+///   │   uniform:   1/2                           1/2                       │
+///   │   weighted:  128/384                       256/384                   │
+///   │                                                                      │
+///   │  Returns: B or D                                                     │
+///   │  Pros: A policy-free baseline for fragmentation experiments          │
+///   │  Cons: O(n), and no placement strategy at all                        │
+///   └──────────────────────────────────────────────────────────────────────┘
 /// ```
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
 pub enum SearchMode {
   /// First Fit: Returns the first free block large enough.
   ///
@@ -341,8 +503,62 @@ pub enum SearchMode {
   /// - **Memory Efficiency**: Minimizes wasted space per allocation
   /// - **Best For**: Memory-constrained environments
   BestFit,
+
+  /// Best Fit Indexed: Best Fit answered by a size-ordered index.
+  ///
+  /// Free blocks are kept in a splay tree keyed by `(size, address)`,
+  /// threaded through their payloads. Returns the same block as
+  /// [`SearchMode::BestFit`] (the lowest address among equal sizes).
+  ///
+  /// - **Time Complexity**: O(log n) amortized, for the search and for
+  ///   keeping the index up to date on every deallocation
+  /// - **Memory Efficiency**: Same as Best Fit
+  /// - **Best For**: Heaps with many free blocks
+  BestFitIndexed,
+
+  /// Good Fit: Best Fit that settles for a block close enough in size.
+  ///
+  /// Walks the free list like [`SearchMode::BestFit`], but returns the
+  /// first block no larger than the request plus `tolerance_percent`
+  /// percent. Without such a block, the best fit found is returned. A
+  /// tolerance of `0` behaves exactly like Best Fit.
+  ///
+  /// - **Time Complexity**: O(n) worst case, usually much less
+  /// - **Memory Efficiency**: Wastes at most the tolerance when it stops early
+  /// - **Best For**: Best Fit behaviour at closer to First Fit speed
+  GoodFit {
+    /// How much larger than the request a block may be, in percent.
+    tolerance_percent: u8,
+  },
+
+  /// Random: Returns a random free block among those that fit.
+  ///
+  /// A baseline for fragmentation experiments: any strategy worth using
+  /// should beat it. Draws come from a small generator seeded with
+  /// [`BumpAllocator::set_random_seed`], so runs are reproducible.
+  ///
+  /// - **Time Complexity**: Always O(n) - every fitting block is a candidate
+  /// - **Memory Efficiency**: Whatever chance gives
+  /// - **Best For**: Experiments and teaching
+  Random {
+    /// Pick blocks with probability proportional to their size instead
+    /// of uniformly.
+    size_weighted: bool,
+  },
 }
 
+/// Called by [`BumpAllocator::allocate`] when the backend cannot provide
+/// memory for `layout`.
+///
+/// The handler receives the allocator itself, so it can make room (e.g.
+/// deallocate cached blocks or [`reset`](BumpAllocator::reset) a scratch
+/// arena). Returning `true` retries the allocation once; `false` gives up
+/// and `allocate` returns `null`.
+///
+/// The handler may panic: it runs before the allocator touches its block
+/// list, so unwinding leaves every invariant intact.
+pub type OomHandler = fn(&mut BumpAllocator, alloc::Layout) -> bool;
+
 /// Debug helper function that prints allocation information.
 ///
 /// Outputs the allocation size, the returned address, and the current
@@ -363,6 +579,7 @@ pub enum SearchMode {
 /// ```text
 /// Allocated 64 bytes, address = 0x5555557a1040, program break = 0x5555557a2000
 /// ```
+#[cfg(all(feature = "std", unix))]
 pub unsafe fn print_alloc(
   layout: alloc::Layout,
   addr: *mut u8,
@@ -379,18 +596,20 @@ pub unsafe fn print_alloc(
 ///
 /// # Memory Management Strategy
 ///
-/// The `BumpAllocator` maintains a singly-linked list of allocation blocks.
-/// Each block contains metadata (size, free status, next pointer) followed
-/// by the user's data.
+/// The `BumpAllocator` maintains a doubly-linked list of allocation blocks.
+/// Each block contains metadata (next and prev pointers, free status, size)
+/// followed by the user's data. The `prev` links let a block reach its
+/// predecessor in O(1), to pop the tail or merge with a free neighbour.
 ///
 /// ```text
 ///   ┌───────────────────────────────────────────────────────────┐
 ///   │                    BumpAllocator                          │
 ///   │                                                           │
-///   │   first ─────────►┌─────────┐                             │
-///   │                   │ Block 1 │──────►┌─────────┐           │
-///   │                   └─────────┘       │ Block 2 │──► null   │
-///   │   last ───────────────────────────► └─────────┘           │
+///   │   first ─────────►┌─────────┐  next   ┌─────────┐         │
+///   │                   │ Block 1 │────────►│ Block 2 │──► null │
+///   │          null ◄───│         │◄────────│         │         │
+///   │                   └─────────┘  prev   │         │         │
+///   │   last ──────────────────────────────►└─────────┘         │
 ///   │                                                           │
 ///   └───────────────────────────────────────────────────────────┘
 /// ```
@@ -401,31 +620,189 @@ pub unsafe fn print_alloc(
 /// * `last` - Pointer to the last block in the allocation list (tail)
 /// * `search_mode` - Strategy for finding free blocks (FirstFit, NextFit, BestFit)
 /// * `last_search` - Used by NextFit to remember where the last search ended
+/// * `realtime` - Whether list traversals are forbidden (see [`BumpAllocator::realtime`])
+/// * `backend` - Where memory comes from: the program break or a fixed region
 ///
 /// Both `first` and `last` pointers are `null` when the allocator is empty.
 ///
 /// # Thread Safety
 ///
-/// This allocator is **NOT** thread-safe. For multi-threaded usage,
-/// external synchronization (e.g., a `Mutex`) is required.
+/// This allocator is **NOT** thread-safe and is neither `Send` nor `Sync`.
+/// Wrap it in [`SendableArena`](crate::SendableArena) to move it between
+/// threads, or in `SharedArena` / `CriticalSectionAllocator` to share it.
 pub struct BumpAllocator {
   /// Pointer to the first (oldest) block in the linked list.
   /// Used as the starting point when searching for free blocks.
-  first: *mut Block,
+  pub(crate) first: *mut Block,
 
   /// Pointer to the last (newest) block in the linked list.
   /// New allocations are appended here. Deallocation of this
   /// block allows heap shrinking via `sbrk(-size)`.
-  last: *mut Block,
+  pub(crate) last: *mut Block,
 
   /// Strategy used to search for free blocks when reusing memory.
   /// See [`SearchMode`] for available strategies.
-  search_mode: SearchMode,
+  pub(crate) search_mode: SearchMode,
 
   /// Pointer to the block where the last successful search ended.
   /// Used exclusively by [`SearchMode::NextFit`] to remember the
   /// starting position for the next search.
   last_search: *mut Block,
+
+  /// Lowest-addressed free block. Free blocks link to each other through
+  /// their payloads, see [`FreeLinks`]; searches walk only this list.
+  pub(crate) free_head: *mut Block,
+
+  /// Block of the newest checkpoint, or null. Free blocks up to it are
+  /// not reused, see the `checkpoint` module.
+  pub(crate) reuse_floor: *mut Block,
+
+  /// Free blocks ordered by size, maintained only in
+  /// [`SearchMode::BestFitIndexed`].
+  size_index: SizeIndex,
+
+  /// State of the random generator used by [`SearchMode::Random`].
+  pub(crate) rng: u64,
+
+  /// Seed last given to [`set_random_seed`](Self::set_random_seed).
+  pub(crate) random_seed: u64,
+
+  /// Minimum payload alignment, see [`Config::min_align`](crate::Config::min_align).
+  pub(crate) min_align: usize,
+
+  /// Largest random gap before each new block, see the `jitter` module.
+  pub(crate) jitter: usize,
+
+  /// When freed tail memory goes back to the system, see the `shrink`
+  /// module.
+  pub(crate) shrink_policy: ShrinkPolicy,
+
+  /// Operations since the shrink cache was last refilled.
+  pub(crate) idle_ops: usize,
+
+  /// Which allocations get an overflow canary, see the `canary` module.
+  pub(crate) canary_policy: CanaryPolicy,
+
+  /// Allocations since the last sampled canary, modulo the sample rate.
+  pub(crate) canary_ticks: usize,
+
+  /// Most bytes the heap may obtain from the backend, see the `limits`
+  /// module.
+  pub(crate) heap_limit: Option<usize>,
+
+  /// Blocks in the list, so the cap check needs no walk.
+  pub(crate) tracked_blocks: usize,
+
+  /// Most blocks to track before allocations become plain bumps, see the
+  /// `block_cap` module.
+  pub(crate) max_blocks: Option<usize>,
+
+  /// Start of the untracked bumps past the block cap, or null.
+  pub(crate) untracked: *mut u8,
+
+  /// Largest layout served without a header by `allocate_sized`, see the
+  /// `sized` module. `0` when off.
+  pub(crate) headerless_max: usize,
+
+  /// Free headerless slots, one stack per size class.
+  pub(crate) headerless_free: [*mut u8; HEADERLESS_CLASSES],
+
+  /// End of the highest headerless slot, kept when the tail is released.
+  pub(crate) headerless_end: *mut u8,
+
+  /// Epoch stamped on new blocks, see the `leaks` module.
+  pub(crate) epoch: u16,
+
+  /// Real-time mode: every operation must complete in O(1).
+  /// Disables free block searching; traversals debug-assert on this flag.
+  pub(crate) realtime: bool,
+
+  /// Source of memory. `sbrk` on Unix, a fixed region elsewhere
+  /// or when constructed with [`BumpAllocator::from_buffer`].
+  pub(crate) backend: Backend,
+
+  /// Called when the backend runs out of memory, see [`OomHandler`].
+  oom_handler: Option<OomHandler>,
+
+  /// Why the heap last failed to grow, see the `limits` module.
+  pub(crate) last_failure: Option<AllocFailure>,
+
+  /// What typed helpers do when an allocation fails, see the `limits`
+  /// module.
+  pub(crate) failure_policy: FailurePolicy,
+
+  /// Set while a handler (OOM or rate limit) runs, so allocations it makes
+  /// do not call handlers again.
+  pub(crate) in_handler: bool,
+
+  /// Number of enclosing `forbid` sections, see the `forbid` module.
+  pub(crate) forbid_depth: usize,
+
+  /// Allocation throttle, see the `rate` module.
+  pub(crate) rate_limit: Option<RateState>,
+
+  /// Memory held back for when the backend runs out, see the `reserve`
+  /// module.
+  pub(crate) reserve: EmergencyReserve,
+
+  /// Memory donated by other allocators, newest first; see the `donate`
+  /// module.
+  pub(crate) donations: *mut Donation,
+
+  /// Thread allowed to use the allocator, see the `owner` module.
+  #[cfg(feature = "thread-check")]
+  pub(crate) owner: Owner,
+
+  /// The last operations, dumped when corruption is detected.
+  #[cfg(feature = "flight-recorder")]
+  pub(crate) recorder: FlightRecorder,
+
+  /// Where to write a heap dump on detected corruption, see the
+  /// `crash_dump` module.
+  #[cfg(feature = "std")]
+  pub(crate) crash_dump: Option<&'static std::path::Path>,
+
+  /// Type of each value placed with `alloc`, by address, while type
+  /// tracking is on; see the `typed` module.
+  #[cfg(feature = "std")]
+  pub(crate) types: Option<std::collections::BTreeMap<usize, core::any::TypeId>>,
+
+  /// Reference counts above one, by address; see the `refcount` module.
+  #[cfg(feature = "std")]
+  pub(crate) refcounts: std::collections::BTreeMap<usize, usize>,
+
+  /// Tag given to new blocks, see the `leaks` module.
+  #[cfg(feature = "std")]
+  pub(crate) tag: Option<std::sync::Arc<str>>,
+
+  /// Tag of each block allocated while one was set, by address.
+  #[cfg(feature = "std")]
+  pub(crate) tags: std::collections::BTreeMap<usize, std::sync::Arc<str>>,
+
+  /// Call site of each block, by address, while call site tracking is on;
+  /// see the `leaks` module.
+  #[cfg(feature = "std")]
+  pub(crate) callsites: Option<std::collections::BTreeMap<usize, &'static core::panic::Location<'static>>>,
+
+  /// Length of each adopted region, by start; see the `adopt` module.
+  #[cfg(feature = "std")]
+  pub(crate) adopted: std::collections::BTreeMap<usize, usize>,
+
+  /// Nested allocator for cold blocks, if set aside; see the `cold` module.
+  #[cfg(feature = "std")]
+  pub(crate) cold: Option<std::boxed::Box<BumpAllocator>>,
+
+  /// Per-byte record of the heap, see the `shadow` module.
+  #[cfg(feature = "shadow")]
+  pub(crate) shadow: ShadowMap,
+
+  /// Operation totals, see the `counters` module.
+  #[cfg(feature = "counters")]
+  pub(crate) counters: Counters,
+
+  /// Allocations per size class, see the `size_classes` module.
+  #[cfg(feature = "counters")]
+  pub(crate) size_classes: SizeClasses,
 }
 
 impl BumpAllocator {
@@ -458,12 +835,72 @@ impl BumpAllocator {
   ///   │  last_search: null        │
   ///   └───────────────────────────┘
   /// ```
-  pub fn new() -> Self {
+  pub const fn new() -> Self {
+    Self::with_backend(Backend::platform_default())
+  }
+
+  /// Creates a new, empty `BumpAllocator` drawing memory from `backend`.
+  pub(crate) const fn with_backend(backend: Backend) -> Self {
     Self {
       first: ptr::null_mut(),
       last: ptr::null_mut(),
-      search_mode: SearchMode::default(),
+      search_mode: SearchMode::FirstFit,
       last_search: ptr::null_mut(),
+      free_head: ptr::null_mut(),
+      reuse_floor: ptr::null_mut(),
+      size_index: SizeIndex::new(),
+      rng: DEFAULT_RANDOM_SEED,
+      random_seed: DEFAULT_RANDOM_SEED,
+      min_align: 1,
+      jitter: 0,
+      shrink_policy: ShrinkPolicy::EAGER,
+      idle_ops: 0,
+      canary_policy: CanaryPolicy::OFF,
+      canary_ticks: 0,
+      heap_limit: None,
+      tracked_blocks: 0,
+      max_blocks: None,
+      untracked: ptr::null_mut(),
+      headerless_max: 0,
+      headerless_free: [ptr::null_mut(); HEADERLESS_CLASSES],
+      headerless_end: ptr::null_mut(),
+      epoch: 0,
+      realtime: false,
+      backend,
+      oom_handler: None,
+      last_failure: None,
+      failure_policy: FailurePolicy::Error,
+      in_handler: false,
+      forbid_depth: 0,
+      rate_limit: None,
+      reserve: EmergencyReserve::none(),
+      donations: ptr::null_mut(),
+      #[cfg(feature = "thread-check")]
+      owner: Owner::Unclaimed,
+      #[cfg(feature = "flight-recorder")]
+      recorder: flight_recorder(),
+      #[cfg(feature = "std")]
+      crash_dump: None,
+      #[cfg(feature = "std")]
+      types: None,
+      #[cfg(feature = "std")]
+      refcounts: std::collections::BTreeMap::new(),
+      #[cfg(feature = "std")]
+      tag: None,
+      #[cfg(feature = "std")]
+      tags: std::collections::BTreeMap::new(),
+      #[cfg(feature = "std")]
+      callsites: None,
+      #[cfg(feature = "std")]
+      adopted: std::collections::BTreeMap::new(),
+      #[cfg(feature = "std")]
+      cold: None,
+      #[cfg(feature = "shadow")]
+      shadow: ShadowMap::new(),
+      #[cfg(feature = "counters")]
+      counters: Counters::ZERO,
+      #[cfg(feature = "counters")]
+      size_classes: SizeClasses::ZERO,
     }
   }
 
@@ -493,20 +930,123 @@ impl BumpAllocator {
   /// # Search Mode Comparison
   ///
   /// ```text
-  ///   ┌─────────────┬───────────────────────────────────────────────────────┐
-  ///   │   Mode      │   Description                                         │
-  ///   ├─────────────┼───────────────────────────────────────────────────────┤
-  ///   │ FirstFit    │ Fast, returns first adequate block                    │
-  ///   │ NextFit     │ Balanced, distributes allocations evenly              │
-  ///   │ BestFit     │ Memory-efficient, minimizes wasted space              │
-  ///   └─────────────┴───────────────────────────────────────────────────────┘
+  ///   ┌────────────────┬────────────────────────────────────────────────────┐
+  ///   │   Mode         │   Description                                      │
+  ///   ├────────────────┼────────────────────────────────────────────────────┤
+  ///   │ FirstFit       │ Fast, returns first adequate block                 │
+  ///   │ NextFit        │ Balanced, distributes allocations evenly           │
+  ///   │ BestFit        │ Memory-efficient, minimizes wasted space           │
+  ///   │ BestFitIndexed │ BestFit in O(log n) via a size-ordered index       │
+  ///   │ GoodFit        │ BestFit that stops at a block within a tolerance   │
+  ///   │ Random         │ Any fitting block, uniformly or by size            │
+  ///   └────────────────┴────────────────────────────────────────────────────┘
   /// ```
-  pub fn with_search_mode(search_mode: SearchMode) -> Self {
-    Self {
-      first: ptr::null_mut(),
-      last: ptr::null_mut(),
-      search_mode,
-      last_search: ptr::null_mut(),
+  pub const fn with_search_mode(search_mode: SearchMode) -> Self {
+    let mut allocator = Self::new();
+    allocator.search_mode = search_mode;
+    allocator
+  }
+
+  /// Creates a new, empty `BumpAllocator` that allocates from `buffer`
+  /// instead of the program break.
+  ///
+  /// This is the backend of choice on embedded targets (where `sbrk` does
+  /// not exist) and for tests that must not touch process-global state.
+  /// Allocations fail with `null` once the buffer is exhausted.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// use rallocator::BumpAllocator;
+  ///
+  /// static mut HEAP: [u8; 4096] = [0; 4096];
+  ///
+  /// let allocator = BumpAllocator::from_buffer(unsafe { &mut *core::ptr::addr_of_mut!(HEAP) });
+  /// ```
+  ///
+  /// # Region Backend
+  ///
+  /// ```text
+  ///   buffer
+  ///   ┌──────────┬──────────┬───────────────────────────────┐
+  ///   │ Block 1  │ Block 2  │          available            │
+  ///   └──────────┴──────────┴───────────────────────────────┘
+  ///   ▲                     ▲                               ▲
+  ///   start            private break                       end
+  /// ```
+  pub fn from_buffer(buffer: &'static mut [u8]) -> Self {
+    // SAFETY: The exclusive 'static borrow guarantees the memory stays
+    // valid and unused by anything else for the allocator's lifetime.
+    unsafe { Self::from_raw_region(buffer.as_mut_ptr(), buffer.len()) }
+  }
+
+  /// Creates a new, empty `BumpAllocator` over the raw region `start..start + len`.
+  ///
+  /// `const` counterpart of [`BumpAllocator::from_buffer`] for statics and
+  /// memory obtained outside Rust (linker symbols, `mmap`, drivers).
+  ///
+  /// # Safety
+  ///
+  /// The region must be valid for reads and writes and must not be used by
+  /// anything else for as long as the allocator (or any allocation made
+  /// from it) is alive.
+  pub const unsafe fn from_raw_region(
+    start: *mut u8,
+    len: usize,
+  ) -> Self {
+    Self::with_backend(Backend::Region(unsafe { Region::borrowed(start, len) }))
+  }
+
+  /// Creates a new, empty `BumpAllocator` backed by a `capacity`-byte
+  /// region from the system allocator.
+  ///
+  /// The region is reserved on the first allocation and released when the
+  /// allocator is dropped - invalidating every pointer it handed out.
+  /// This is the default backend on non-Unix targets.
+  #[cfg(feature = "std")]
+  pub const fn with_capacity(capacity: usize) -> Self {
+    Self::with_backend(Backend::Region(Region::owned(capacity)))
+  }
+
+  /// Creates a new, empty `BumpAllocator` in real-time mode.
+  ///
+  /// In real-time mode every operation has a constant-time bound:
+  /// allocation is a pure bump at the tail, deallocation only marks the
+  /// block free (popping it in O(1) if it is the tail), and free block
+  /// searching is disabled. Freed middle blocks are therefore never reused.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// use rallocator::BumpAllocator;
+  ///
+  /// let mut allocator = BumpAllocator::realtime();
+  /// assert!(allocator.is_realtime());
+  /// ```
+  pub const fn realtime() -> Self {
+    let mut allocator = Self::new();
+    allocator.realtime = true;
+    allocator
+  }
+
+  /// Returns `true` if the allocator is in real-time (O(1)) mode.
+  pub fn is_realtime(&self) -> bool {
+    self.realtime
+  }
+
+  /// Enables or disables real-time mode.
+  ///
+  /// See [`BumpAllocator::realtime`] for the guarantees of the mode.
+  /// Blocks freed while the mode was enabled become reuse candidates
+  /// again once it is disabled.
+  pub fn set_realtime(
+    &mut self,
+    enabled: bool,
+  ) {
+    let leaving = self.realtime && !enabled;
+    self.realtime = enabled;
+    if leaving {
+      self.rebuild_free_list();
     }
   }
 
@@ -527,8 +1067,12 @@ impl BumpAllocator {
   /// Sets the search mode for the allocator.
   ///
   /// This can be changed at any time and will affect subsequent allocations.
-  /// Note: Changing to [`SearchMode::NextFit`] resets the `last_search` pointer
-  /// to the beginning of the list.
+  /// Changing the mode - into, out of, or between any modes - resets the
+  /// NextFit cursor (see [`reset_search_cursor`](Self::reset_search_cursor)),
+  /// so NextFit always starts from the lowest free block after a switch.
+  /// Setting the mode already in use changes nothing, and keeps the cursor.
+  /// Changing to [`SearchMode::BestFitIndexed`] builds the size index from
+  /// the free list, O(f log f) in the number of free blocks.
   ///
   /// # Arguments
   ///
@@ -543,13 +1087,86 @@ impl BumpAllocator {
   /// allocator.set_search_mode(SearchMode::BestFit);
   /// ```
   pub fn set_search_mode(&mut self, mode: SearchMode) {
+    if mode == self.search_mode {
+      return;
+    }
+    let was_indexed = self.search_mode == SearchMode::BestFitIndexed;
     self.search_mode = mode;
-    // Reset last_search when changing modes to avoid stale pointers
-    if mode != SearchMode::NextFit {
-      self.last_search = ptr::null_mut();
+    // A cursor left by an earlier NextFit run means nothing to the new mode
+    self.reset_search_cursor();
+    if was_indexed != (mode == SearchMode::BestFitIndexed) {
+      self.rebuild_size_index();
     }
   }
 
+  /// Makes the next [`SearchMode::NextFit`] search start from the lowest
+  /// free block, as on a fresh allocator.
+  ///
+  /// Useful to make NextFit runs reproducible from a known point; the other
+  /// modes keep no cursor.
+  ///
+  /// ```text
+  ///   [A,free] → [B,used] → [C,free] → [D,free]
+  ///                           ▲ cursor
+  ///   reset_search_cursor()
+  ///   [A,free] → [B,used] → [C,free] → [D,free]
+  ///    ▲ next search starts here
+  /// ```
+  pub fn reset_search_cursor(&mut self) {
+    self.last_search = ptr::null_mut();
+  }
+
+  /// Installs (or with `None` removes) the out-of-memory handler.
+  ///
+  /// See [`OomHandler`] for when it runs and what it may do.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// fn drop_caches(allocator: &mut BumpAllocator, _layout: Layout) -> bool {
+  ///     unsafe { allocator.reset() }; // scratch arena: nothing must survive
+  ///     true                          // retry the allocation
+  /// }
+  ///
+  /// allocator.set_oom_handler(Some(drop_caches));
+  /// ```
+  pub fn set_oom_handler(
+    &mut self,
+    handler: Option<OomHandler>,
+  ) {
+    self.oom_handler = handler;
+  }
+
+  /// Returns the installed out-of-memory handler, if any.
+  pub fn oom_handler(&self) -> Option<OomHandler> {
+    self.oom_handler
+  }
+
+  /// Moves the program break by at least `bytes` at a time.
+  ///
+  /// Fewer, larger `sbrk` calls; the surplus serves later allocations
+  /// without a system call. If a chunk cannot be obtained, it is retried at
+  /// half the size, down to the exact shortfall, before the allocation
+  /// fails:
+  ///
+  /// ```text
+  ///   chunk 64 KiB ✗ ──► 32 KiB ✗ ──► 16 KiB ✓
+  /// ```
+  ///
+  /// `0` (the default) grows by exactly each request. Only the program
+  /// break backend uses chunks; region backends ignore the setting.
+  pub fn set_growth_chunk(
+    &mut self,
+    bytes: usize,
+  ) {
+    self.backend.set_growth_chunk(bytes);
+  }
+
+  /// The growth chunk set by [`set_growth_chunk`](Self::set_growth_chunk).
+  pub fn growth_chunk(&self) -> usize {
+    self.backend.growth_chunk()
+  }
+
   /// Searches the block list for a free block of sufficient size.
   ///
   /// This method uses the configured [`SearchMode`] to find a suitable block:
@@ -557,6 +1174,9 @@ impl BumpAllocator {
   /// - [`SearchMode::FirstFit`]: Returns the first free block that fits
   /// - [`SearchMode::NextFit`]: Starts from last allocation, wraps around
   /// - [`SearchMode::BestFit`]: Returns the smallest block that fits
+  /// - [`SearchMode::BestFitIndexed`]: The same, from the size index
+  /// - [`SearchMode::GoodFit`]: The first block within a tolerance, else the best
+  /// - [`SearchMode::Random`]: A random block among those that fit
   ///
   /// # Arguments
   ///
@@ -582,34 +1202,125 @@ impl BumpAllocator {
   ///   NextFit:  Depends on last_search position
   /// ```
   ///
-  /// # Note
+  /// Only free blocks are visited: they are chained through their payloads
+  /// (see [`FreeLinks`]), so used blocks cost nothing during a search.
   ///
-  /// This method exists but is currently unused by `allocate()`, which
-  /// always requests new memory from the OS. This is a potential
-  /// optimization point for reusing freed blocks.
+  /// [`allocate`](Self::allocate) calls it through
+  /// [`reuse_free_block`](Self::reuse_free_block) before growing the heap.
   ///
   /// # Safety
   ///
   /// The caller must ensure that the allocator's internal state is valid
   /// and that no other thread is modifying the block list concurrently.
+  ///
+  /// # Panics
+  ///
+  /// Debug builds panic if called in real-time mode, where searching is disabled.
   unsafe fn find_free_block(
     &mut self,
     size: usize,
   ) -> *mut Block {
+    debug_assert!(!self.realtime, "free block search in real-time mode");
+
+    // Jitter mode randomizes reuse whatever the configured strategy
+    let mode = if self.jitter > 0 {
+      SearchMode::Random { size_weighted: false }
+    } else {
+      self.search_mode
+    };
+
     // SAFETY: All called functions are unsafe but maintain the same invariants
     // as this function - they require valid internal state and no concurrent access.
     unsafe {
-      match self.search_mode {
+      match mode {
         SearchMode::FirstFit => self.find_free_block_first_fit(size),
         SearchMode::NextFit => self.find_free_block_next_fit(size),
         SearchMode::BestFit => self.find_free_block_best_fit(size),
+        SearchMode::BestFitIndexed => self.size_index.best_fit(size),
+        SearchMode::GoodFit { tolerance_percent } => self.find_free_block_good_fit(size, tolerance_percent),
+        SearchMode::Random { size_weighted } => self.find_free_block_random(size, size_weighted),
+      }
+    }
+  }
+
+  /// Hands out the free block [`find_free_block`](Self::find_free_block)
+  /// picks for `layout`, or null to grow the heap instead.
+  ///
+  /// ```text
+  ///   before   [hdr│ free, size 96 ............ ][hdr│ B ]
+  ///   after    [hdr│ used, size 40 │ slack      ][hdr│ B ]
+  ///                                 ▲ taken back by the next free
+  ///
+  ///   before   [hdr│ free, size 1000 ...................... ][hdr│ B ]
+  ///   after    [hdr│ used, size 40 ][hdr│ free, size 928 ... ][hdr│ B ]
+  ///                                 ▲ split off: room for a header and more
+  /// ```
+  ///
+  /// The block keeps its place in the list and records the requested size.
+  /// What is left after the payload (and canary) is split off as a free
+  /// block of its own when it can hold a header and [`MIN_PAYLOAD`], so a
+  /// small request never swallows a large merged run; smaller slack stays
+  /// with the block. A candidate whose payload is not aligned for `layout`
+  /// is passed over for growth, as is everything in real-time mode, where
+  /// searching is disabled. Below the newest checkpoint the first fit
+  /// above it is taken instead, so that `shrink_to` drops every block
+  /// allocated after the mark. A sampled canary is placed when the block
+  /// has room for it after the payload.
+  ///
+  /// # Safety
+  ///
+  /// As [`find_free_block`](Self::find_free_block).
+  unsafe fn reuse_free_block(
+    &mut self,
+    layout: alloc::Layout,
+  ) -> *mut u8 {
+    if self.realtime || self.free_head.is_null() {
+      return ptr::null_mut();
+    }
+    let Ok(layout) = layout.align_to(self.min_align) else {
+      return ptr::null_mut();
+    };
+
+    unsafe {
+      let mut block = self.find_free_block(layout.size());
+      if !block.is_null() && block <= self.reuse_floor {
+        block = self.first_fit_above_floor(layout.size());
+      }
+      let payload = block as usize + HEADER_SIZE;
+      if block.is_null() || !payload.is_multiple_of(effective_align(layout)) {
+        return ptr::null_mut();
+      }
+
+      self.unlink_free(block);
+      let span = (*block).size;
+      let canary = span - layout.size() >= CANARY_SIZE && self.wants_canary(layout);
+      let used = layout.size() + if canary { CANARY_SIZE } else { 0 };
+      let span = match model::split_at(payload, used, span).filter(|_| !self.at_block_cap()) {
+        Some(tail) => {
+          self.split_off(block, tail as *mut Block);
+          tail - payload
+        }
+        None => span,
+      };
+      (*block).is_free = false;
+      (*block).epoch = self.epoch;
+      (*block).size = layout.size();
+      #[cfg(feature = "shadow")]
+      self.shadow_reuse(block, span);
+      #[cfg(not(feature = "shadow"))]
+      let _ = span;
+      if canary {
+        write_canary(block);
+        #[cfg(feature = "shadow")]
+        self.shadow.set(payload + layout.size(), CANARY_SIZE, crate::ShadowState::Canary);
       }
+      payload as *mut u8
     }
   }
 
   /// First Fit: Returns the first free block that is large enough.
   ///
-  /// Searches from the beginning of the block list.
+  /// Searches the free list from its head, the lowest-addressed free block.
   ///
   /// # Time Complexity
   ///
@@ -618,20 +1329,53 @@ impl BumpAllocator {
     &self,
     size: usize,
   ) -> *mut Block {
-    unsafe {
-      let mut current: *mut Block = self.first;
+    let candidates = unsafe { free_list(self.free_head) };
+    model::first_fit(candidates, size).unwrap_or(ptr::null_mut())
+  }
 
-      while !current.is_null() {
-        if (*current).is_free && (*current).size >= size {
-          return current;
-        }
-        current = (*current).next;
+  /// Turns the end of the free, unlinked `block` into a free block with
+  /// its header at `tail`, linked after it and onto the free list.
+  ///
+  /// # Safety
+  ///
+  /// `tail` must be word-aligned and leave room for a header and
+  /// [`MIN_PAYLOAD`] bytes inside `block`'s payload.
+  unsafe fn split_off(
+    &mut self,
+    block: *mut Block,
+    tail: *mut Block,
+  ) {
+    unsafe {
+      let end = block as usize + HEADER_SIZE + (*block).size;
+      let next = (*block).next;
+      tail.write(Block::new(end - tail as usize - HEADER_SIZE, true, next, block));
+      (*tail).epoch = self.epoch;
+      (*block).next = tail;
+      if next.is_null() {
+        self.last = tail;
+      } else {
+        (*next).prev = tail;
       }
-
-      ptr::null_mut()
+      self.tracked_blocks += 1;
+      #[cfg(feature = "shadow")]
+      self.shadow.set(tail as usize, HEADER_SIZE, crate::ShadowState::Header);
+      self.link_free(tail);
     }
   }
 
+  /// First Fit among the free blocks above [`reuse_floor`](Self::reuse_floor).
+  ///
+  /// # Time Complexity
+  ///
+  /// O(n): the free list is walked from its head, whatever the search mode.
+  unsafe fn first_fit_above_floor(
+    &self,
+    size: usize,
+  ) -> *mut Block {
+    let candidates = unsafe { free_list(self.free_head) }.filter(|&(block, _)| block > self.reuse_floor);
+    model::first_fit(candidates, size).unwrap_or(ptr::null_mut())
+  }
+
   /// Next Fit: Like First Fit, but starts where the last search ended.
   ///
   /// This strategy distributes allocations more evenly across the heap,
@@ -640,9 +1384,9 @@ impl BumpAllocator {
   /// # Algorithm
   ///
   /// ```text
-  ///   1. Start from last_search (or first if null)
-  ///   2. Search forward until end of list
-  ///   3. If not found, wrap around and search from first to last_search
+  ///   1. Start from last_search (or free_head if null)
+  ///   2. Search forward until end of the free list
+  ///   3. If not found, wrap around and search from free_head to last_search
   ///   4. Update last_search to the found block (or leave unchanged if not found)
   /// ```
   ///
@@ -654,40 +1398,33 @@ impl BumpAllocator {
     size: usize,
   ) -> *mut Block {
     unsafe {
-      // Start from last_search position, or from the beginning if null
+      // Start from last_search position, or from the beginning if null.
+      // last_search is always a member of the free list (see unlink_free).
+      debug_assert!(
+        self.last_search.is_null() || (*self.last_search).is_free,
+        "NextFit cursor points at a block in use"
+      );
       let start = if self.last_search.is_null() {
-        self.first
+        self.free_head
       } else {
         self.last_search
       };
 
-      // First pass: search from start to end
-      let mut current = start;
-      while !current.is_null() {
-        if (*current).is_free && (*current).size >= size {
-          self.last_search = current;
-          return current;
-        }
-        current = (*current).next;
-      }
-
-      // Second pass: wrap around, search from first to start
-      current = self.first;
-      while !current.is_null() && current != start {
-        if (*current).is_free && (*current).size >= size {
-          self.last_search = current;
-          return current;
-        }
-        current = (*current).next;
-      }
-
-      ptr::null_mut()
+      // Search from start to the end, then wrap around from free_head
+      let after = free_list(start);
+      let before = free_list(self.free_head).take_while(|&(block, _)| block != start);
+      let Some(found) = model::next_fit(after, before, size) else {
+        return ptr::null_mut();
+      };
+      self.last_search = found;
+      found
     }
   }
 
   /// Best Fit: Returns the smallest free block that is large enough.
   ///
-  /// Searches the entire list to find the block that minimizes wasted space.
+  /// Searches the entire free list to find the block that minimizes wasted
+  /// space.
   ///
   /// # Algorithm
   ///
@@ -704,42 +1441,337 @@ impl BumpAllocator {
   ///
   /// # Time Complexity
   ///
-  /// Always O(n) - must check all blocks to find the best fit.
+  /// O(f) in the number of free blocks - must check them all to find the
+  /// best fit, unless one fits exactly.
   unsafe fn find_free_block_best_fit(
     &self,
     size: usize,
   ) -> *mut Block {
-    unsafe {
-      let mut best: *mut Block = ptr::null_mut();
-      let mut best_size: usize = usize::MAX;
-      let mut current: *mut Block = self.first;
-
-      while !current.is_null() {
-        let block_size = (*current).size;
-        // Check if this block is free, large enough, and better than current best
-        if (*current).is_free && block_size >= size && block_size < best_size {
-          best = current;
-          best_size = block_size;
-
-          // Perfect fit - no need to continue searching
-          if block_size == size {
-            return best;
-          }
-        }
-        current = (*current).next;
-      }
-
-      best
-    }
+    let candidates = unsafe { free_list(self.free_head) };
+    model::best_fit(candidates, size).unwrap_or(ptr::null_mut())
   }
 
-  /// Allocates a block of memory with the specified layout.
+  /// Good Fit: Best Fit that stops at the first block within the tolerance.
   ///
-  /// This is the primary allocation method. It extends the heap using `sbrk`,
-  /// creates a new block with metadata, and returns an aligned pointer to
-  /// the user data region.
+  /// # Algorithm
   ///
-  /// # Arguments
+  /// ```text
+  ///   good_enough = size + size * tolerance_percent / 100
+  ///
+  ///   for each free block that fits:
+  ///       size <= block.size <= good_enough  ──►  return it
+  ///       otherwise remember it if it is the best so far
+  ///   return the best
+  /// ```
+  ///
+  /// # Time Complexity
+  ///
+  /// O(n) worst case, when no free block is within the tolerance.
+  unsafe fn find_free_block_good_fit(
+    &self,
+    size: usize,
+    tolerance_percent: u8,
+  ) -> *mut Block {
+    let candidates = unsafe { free_list(self.free_head) };
+    model::good_fit(candidates, size, tolerance_percent).unwrap_or(ptr::null_mut())
+  }
+
+  /// Random: Returns a random fitting block, in a single pass.
+  ///
+  /// # Algorithm
+  ///
+  /// ```text
+  ///   Reservoir sampling over the free list:
+  ///
+  ///   for each free block that fits:
+  ///       weight  = 1 (uniform) or block.size (size weighted)
+  ///       total  += weight
+  ///       keep it instead of the current pick with probability weight / total
+  /// ```
+  ///
+  /// Every fitting block ends up picked with probability weight / total.
+  ///
+  /// # Time Complexity
+  ///
+  /// Always O(n).
+  unsafe fn find_free_block_random(
+    &mut self,
+    size: usize,
+    size_weighted: bool,
+  ) -> *mut Block {
+    let candidates = unsafe { free_list(self.free_head) };
+    model::random_fit(candidates, size, size_weighted, || self.next_random()).unwrap_or(ptr::null_mut())
+  }
+
+  /// Seeds the generator behind [`SearchMode::Random`] and jitter mode,
+  /// restarting its sequence.
+  ///
+  /// The same seed and the same sequence of operations give the same
+  /// choices, so experiments can be replayed.
+  pub const fn set_random_seed(
+    &mut self,
+    seed: u64,
+  ) {
+    self.random_seed = seed;
+    // Xorshift gets stuck at zero
+    self.rng = if seed == 0 { DEFAULT_RANDOM_SEED } else { seed };
+  }
+
+  /// The seed last given to [`set_random_seed`](Self::set_random_seed).
+  ///
+  /// Every random decision (the [`SearchMode::Random`] pick, jitter gaps)
+  /// comes from one generator started from this seed, so recording it is
+  /// enough to replay a run: the same seed and the same operations give the
+  /// same heap layout. Flight recorder dumps and snapshots include it.
+  pub fn random_seed(&self) -> u64 {
+    self.random_seed
+  }
+
+  /// Next value of the xorshift64* generator.
+  pub(crate) fn next_random(&mut self) -> u64 {
+    self.rng ^= self.rng >> 12;
+    self.rng ^= self.rng << 25;
+    self.rng ^= self.rng >> 27;
+    self.rng.wrapping_mul(0x2545_f491_4f6c_dd1d)
+  }
+
+  /// Grows the freed middle `block` up to its successor's header, so the
+  /// bytes in between are reused with it instead of lost:
+  ///
+  /// ```text
+  ///   │ hdr │ payload │ slack │ padding / gap │ hdr │ next ...
+  ///         ◄─ size ──►
+  ///         ◄──────────── size after ─────────►
+  /// ```
+  ///
+  /// The slack is the word rounding and the room kept for free links; the
+  /// padding is what the successor's alignment (or jitter) skipped. Only
+  /// the first block's leading padding belongs to no block; popping the
+  /// tail or `reset` gives it back.
+  ///
+  /// A successor that starts a new span (see [`SPAN_START`]) lies past
+  /// memory the backend never gave us, so the block stays as it is.
+  ///
+  /// # Safety
+  ///
+  /// `block` must be a free block of this allocator with a successor.
+  unsafe fn absorb_gap(
+    &mut self,
+    block: *mut Block,
+  ) {
+    unsafe {
+      if (*(*block).next).starts_span() {
+        return;
+      }
+      let payload = block as usize + HEADER_SIZE;
+      let span = model::absorbed_size(payload, (*block).next as usize);
+      #[cfg(feature = "shadow")]
+      self.shadow.set(payload + (*block).size, span - (*block).size, crate::ShadowState::Free);
+      (*block).size = span;
+    }
+  }
+
+  /// Merges the freed, not yet linked `block` with free neighbours on
+  /// either side, returning the block that now covers it:
+  ///
+  /// ```text
+  ///   before   [hdr│ P free ][hdr│ block ][hdr│ N free ][hdr│ live ]
+  ///   after    [hdr│ P free, size covering block and N  ][hdr│ live ]
+  /// ```
+  ///
+  /// Both neighbours are found through the block list's links, so this is
+  /// O(1) besides unlinking them. The headers in between become payload,
+  /// so a run of freed neighbours serves requests as large as the run.
+  /// Merging never crosses into a new span (see [`SPAN_START`]).
+  ///
+  /// # Safety
+  ///
+  /// `block` must be a free block of this allocator, not linked yet.
+  unsafe fn coalesce(
+    &mut self,
+    block: *mut Block,
+  ) -> *mut Block {
+    unsafe {
+      let next = (*block).next;
+      if !next.is_null() && (*next).is_free && !(*next).starts_span() {
+        self.unlink_free(next);
+        self.merge_next(block);
+      }
+      let prev = (*block).prev;
+      if !prev.is_null() && (*prev).is_free && !(*block).starts_span() {
+        self.unlink_free(prev);
+        self.merge_next(prev);
+        return prev;
+      }
+      block
+    }
+  }
+
+  /// Folds the free successor of the free `block` into it. Before a new
+  /// span the merged block ends where the successor did, like a tail.
+  ///
+  /// # Safety
+  ///
+  /// `block` and its successor must be free blocks, off the free list.
+  unsafe fn merge_next(
+    &mut self,
+    block: *mut Block,
+  ) {
+    unsafe {
+      let next = (*block).next;
+      let after = (*next).next;
+      let payload = block as usize + HEADER_SIZE;
+      let next_payload = next as usize + HEADER_SIZE;
+      (*block).size = if after.is_null() || (*after).starts_span() {
+        model::merged_tail_size(payload, next_payload, (*next).size)
+      } else {
+        model::absorbed_size(payload, after as usize)
+      };
+      #[cfg(feature = "shadow")]
+      self.shadow.set(payload, (*block).size, crate::ShadowState::Free);
+
+      (*block).next = after;
+      if after.is_null() {
+        self.last = block;
+      } else {
+        (*after).prev = block;
+      }
+      self.tracked_blocks -= 1;
+    }
+  }
+
+  /// Inserts the free `block` into the free list, keeping address order.
+  ///
+  /// O(f) in the number of free blocks; skipped in real-time mode, where
+  /// freed blocks are only marked (see [`set_realtime`](Self::set_realtime)).
+  ///
+  /// # Safety
+  ///
+  /// `block` must be a free block of this allocator, not already linked.
+  unsafe fn link_free(
+    &mut self,
+    block: *mut Block,
+  ) {
+    if self.realtime {
+      return;
+    }
+
+    unsafe {
+      // Find the last free block below `block`
+      let mut prev: *mut Block = ptr::null_mut();
+      let mut next = self.free_head;
+      while !next.is_null() && next < block {
+        prev = next;
+        next = (*Block::free_links(next)).next_free;
+      }
+
+      *Block::free_links(block) = FreeLinks {
+        next_free: next,
+        prev_free: prev,
+        smaller: ptr::null_mut(),
+        larger: ptr::null_mut(),
+      };
+      if prev.is_null() {
+        self.free_head = block;
+      } else {
+        (*Block::free_links(prev)).next_free = block;
+      }
+      if !next.is_null() {
+        (*Block::free_links(next)).prev_free = block;
+      }
+      if self.search_mode == SearchMode::BestFitIndexed {
+        self.size_index.insert(block);
+      }
+    }
+  }
+
+  /// Removes `block` from the free list in O(1).
+  ///
+  /// A NextFit cursor on `block` moves on to the next free block.
+  ///
+  /// # Safety
+  ///
+  /// `block` must be linked in this allocator's free list.
+  pub(crate) unsafe fn unlink_free(
+    &mut self,
+    block: *mut Block,
+  ) {
+    unsafe {
+      let FreeLinks { next_free, prev_free, .. } = *Block::free_links(block);
+      if prev_free.is_null() {
+        self.free_head = next_free;
+      } else {
+        (*Block::free_links(prev_free)).next_free = next_free;
+      }
+      if !next_free.is_null() {
+        (*Block::free_links(next_free)).prev_free = prev_free;
+      }
+      if self.last_search == block {
+        self.last_search = next_free;
+      }
+      if self.search_mode == SearchMode::BestFitIndexed {
+        self.size_index.remove(block);
+      }
+    }
+  }
+
+  /// Relinks every free block, e.g. after real-time mode skipped linking.
+  ///
+  /// One O(n) pass over the block list, which is already in address order.
+  pub(crate) fn rebuild_free_list(&mut self) {
+    let mut tail: *mut Block = ptr::null_mut();
+    self.free_head = ptr::null_mut();
+    self.last_search = ptr::null_mut();
+
+    let mut current = self.first;
+    // SAFETY: The block list is valid; free payloads hold at least MIN_PAYLOAD bytes.
+    unsafe {
+      while !current.is_null() {
+        if (*current).is_free {
+          *Block::free_links(current) = FreeLinks {
+            next_free: ptr::null_mut(),
+            prev_free: tail,
+            smaller: ptr::null_mut(),
+            larger: ptr::null_mut(),
+          };
+          if tail.is_null() {
+            self.free_head = current;
+          } else {
+            (*Block::free_links(tail)).next_free = current;
+          }
+          tail = current;
+        }
+        current = (*current).next;
+      }
+    }
+    self.rebuild_size_index();
+  }
+
+  /// Refills the size index from the free list, or empties it when the
+  /// search mode does not use it.
+  fn rebuild_size_index(&mut self) {
+    self.size_index.clear();
+    if self.search_mode != SearchMode::BestFitIndexed {
+      return;
+    }
+
+    let mut current = self.free_head;
+    // SAFETY: Every block on the free list is free and has room for its links.
+    unsafe {
+      while !current.is_null() {
+        self.size_index.insert(current);
+        current = (*Block::free_links(current)).next_free;
+      }
+    }
+  }
+
+  /// Allocates a block of memory with the specified layout.
+  ///
+  /// This is the primary allocation method. It extends the heap using `sbrk`,
+  /// creates a new block with metadata, and returns an aligned pointer to
+  /// the user data region.
+  ///
+  /// # Arguments
   ///
   /// * `layout` - The [`Layout`] describing size and alignment requirements
   ///
@@ -785,20 +1817,20 @@ impl BumpAllocator {
   ///
   ///   Example with 16-byte alignment:
   ///
-  ///     raw_address = 0x1000
-  ///     header_size = 24 bytes
+  ///     raw_address = 0x1008
+  ///     header_size = 32 bytes
   ///     align = 16
   ///
-  ///     unaligned = 0x1000 + 24 = 0x1018
-  ///     content_addr = align_to(0x1018, 16) = 0x1020
-  ///     block_addr = 0x1020 - 24 = 0x1008
+  ///     unaligned = 0x1008 + 32 = 0x1028
+  ///     content_addr = align_to(0x1028, 16) = 0x1030
+  ///     block_addr = 0x1030 - 32 = 0x1010
   ///
   ///     Memory:
-  ///     0x1000 ┌────────┐
+  ///     0x1008 ┌────────┐
   ///            │ unused │ (8 bytes of padding)
-  ///     0x1008 ├────────┤ ← Block header starts here
-  ///            │ header │ (24 bytes)
-  ///     0x1020 ├────────┤ ← Content starts here (16-byte aligned)
+  ///     0x1010 ├────────┤ ← Block header starts here
+  ///            │ header │ (32 bytes)
+  ///     0x1030 ├────────┤ ← Content starts here (16-byte aligned)
   ///            │  data  │
   ///            └────────┘
   /// ```
@@ -835,42 +1867,188 @@ impl BumpAllocator {
   /// # Errors
   ///
   /// Returns `null` if:
-  /// - `sbrk` fails (returns `(void*)-1`), typically due to:
+  /// - `sbrk` fails (returns `(void*)-1`) even for the exact size after
+  ///   backing off from the growth chunk, typically due to:
   ///   - Out of memory
   ///   - Resource limits (`RLIMIT_DATA`) exceeded
+  ///
+  /// and the [`OomHandler`], if any, declines to retry or the retry fails too,
+  /// and the emergency reserve (see
+  /// [`set_emergency_reserve`](Self::set_emergency_reserve)), if any, is
+  /// exhausted as well; or if the allocation exceeds the
+  /// [`RateLimit`](crate::RateLimit) and its handler does not allow it.
+  /// [`last_failure`](Self::last_failure) tells which.
+  ///
+  /// # Panic Safety
+  ///
+  /// The only user code `allocate` runs is the OOM handler, and it runs
+  /// between two complete attempts - never while a block is half linked:
+  ///
+  /// ```text
+  ///   push_block ──► null? ──► handler(self, layout) ──► retry push_block
+  ///   │                        ▲
+  ///   └─ grow, write header,   └─ may panic: the list is exactly as it
+  ///      link (no user code)      was before the call
+  /// ```
+  #[inline]
+  #[track_caller]
   pub unsafe fn allocate(
     &mut self,
     layout: alloc::Layout,
+  ) -> *mut u8 {
+    #[cfg(feature = "thread-check")]
+    self.check_owner();
+
+    if self.forbid_depth > 0 {
+      self.forbidden_allocation(layout);
+    }
+
+    // `Layout` guarantees this, but the padding math below silently
+    // misaligns for anything else
+    debug_assert!(layout.align().is_power_of_two(), "alignment must be a power of two");
+
+    let mut address = ptr::null_mut();
+    if self.within_rate_limit(layout) {
+      address = unsafe { self.reuse_free_block(layout) };
+      if address.is_null() {
+        address = unsafe { self.place(layout) };
+      } else {
+        #[cfg(feature = "counters")]
+        self.count_reuse(layout.size());
+      }
+      if address.is_null() {
+        address = unsafe { self.allocate_out_of_memory(layout) };
+      }
+    }
+    #[cfg(feature = "std")]
+    self.attribute(address, core::panic::Location::caller());
+    self.tick_shrink_idle();
+    #[cfg(feature = "flight-recorder")]
+    self.record(OpKind::Allocate, address as usize, layout.size(), !address.is_null());
+    #[cfg(feature = "counters")]
+    self.count_allocate(layout.size(), !address.is_null());
+    address
+  }
+
+  /// Slow path of [`allocate`](Self::allocate) once the backend is full.
+  ///
+  /// ```text
+  ///   donations ──► null? ──► unlock reserve ──► handler (not re-entered)
+  ///                                               │
+  ///                   reserve ◄─── null? ◄─── retry push_block
+  /// ```
+  #[cold]
+  unsafe fn allocate_out_of_memory(
+    &mut self,
+    layout: alloc::Layout,
+  ) -> *mut u8 {
+    let address = unsafe { self.allocate_donated(layout) };
+    if !address.is_null() {
+      return address;
+    }
+    self.reserve.unlock();
+
+    if !self.in_handler
+      && let Some(handler) = self.oom_handler
+      && self.call_handler(|allocator| handler(allocator, layout))
+    {
+      let address = unsafe { self.place(layout) };
+      if !address.is_null() {
+        return address;
+      }
+    }
+    unsafe { self.reserve.allocate(layout) }
+  }
+
+  /// Runs a user handler with `in_handler` set, clearing it afterwards even
+  /// if the handler unwinds.
+  pub(crate) fn call_handler<R>(
+    &mut self,
+    handler: impl FnOnce(&mut Self) -> R,
+  ) -> R {
+    /// Clears `in_handler` on drop. Holds the allocator itself, so the
+    /// handler's borrow is derived from it and ends before the drop.
+    struct Reentry<'a>(&'a mut BumpAllocator);
+    impl Drop for Reentry<'_> {
+      fn drop(&mut self) {
+        self.0.in_handler = false;
+      }
+    }
+
+    self.in_handler = true;
+    let reentry = Reentry(self);
+    handler(&mut *reentry.0)
+  }
+
+  /// Appends a new block for `layout` at the tail of the list.
+  ///
+  /// The body of [`allocate`](Self::allocate), see there for details.
+  /// Runs no user code, so it cannot unwind half-way through linking.
+  /// Only the bump within the backend's current memory is inlined here;
+  /// obtaining more (`sbrk`, reserving a region) is out of line and `#[cold]`.
+  #[inline]
+  pub(crate) unsafe fn push_block(
+    &mut self,
+    layout: alloc::Layout,
   ) -> *mut u8 {
     unsafe {
-      let align = layout.align();
-      let header_size = mem::size_of::<Block>();
+      // Raise the alignment to the configured minimum
+      let Ok(layout) = layout.align_to(self.min_align) else {
+        self.last_failure = Some(AllocFailure::SizeOverflow { size: layout.size() });
+        return ptr::null_mut();
+      };
+      let align = effective_align(layout);
 
       // Calculate total size needed:
       // - header_size: space for Block metadata
       // - layout.size(): user-requested allocation size
       // - (align - 1): worst-case padding for alignment
-      // The result is word-aligned via the align! macro
-      let size_for_sbrk = align!(header_size + layout.size() + (align - 1));
-
-      // Extend the heap by requesting more memory from the OS
-      // sbrk returns the OLD program break (start of new memory)
-      let raw_address = sbrk(size_for_sbrk as intptr_t);
-      if raw_address == usize::MAX as *mut c_void {
-        // sbrk returns (void*)-1 on failure
+      // The result is word-aligned. Overflow means the request is unsatisfiable.
+      // Jitter mode adds a random gap in front of the header
+      // and a sampled canary a word after the payload.
+      // Requests above isize::MAX fail here: no pointer offset (nor sbrk's
+      // signed increment) can span them
+      let gap = self.jitter_gap();
+      let canary = self.wants_canary(layout);
+      let extra = gap + if canary { CANARY_SIZE } else { 0 };
+      let Some(size_for_sbrk) = grow_request_size(layout)
+        .and_then(|size| size.checked_add(extra))
+        .filter(|&size| size <= MAX_REQUEST_SIZE)
+      else {
+        self.last_failure = Some(AllocFailure::SizeOverflow { size: layout.size() });
+        return ptr::null_mut();
+      };
+
+      // Extend the heap by requesting more memory from the backend
+      // Like sbrk, grow returns the OLD break (start of new memory)
+      let below = self.backend.current_break();
+      let raw_address = self.grow_heap(size_for_sbrk);
+      if raw_address.is_null() {
         return ptr::null_mut();
       }
+      // Not where our break was: a foreign `sbrk` moved it in between.
+      // Headerless slots right below are not ours to absorb either
+      let span_start = raw_address != below || (!self.headerless_end.is_null() && below == self.headerless_end);
 
       // Calculate the aligned address for user content
       // This ensures the returned pointer meets the layout's alignment requirements
-      let content_addr = align_to!((raw_address as usize) + header_size, align);
+      // `align` is a power of two, so this is an add and a mask
+      let content_addr = align_up((raw_address as usize) + gap + HEADER_SIZE, align);
 
       // Place the block header immediately before the content
       // This allows us to find the header given only the content pointer
-      let block = (content_addr - header_size) as *mut Block;
+      let block = (content_addr - HEADER_SIZE) as *mut Block;
       (*block).is_free = false;
+      (*block).flags = if span_start { SPAN_START } else { 0 };
+      (*block).epoch = self.epoch;
       (*block).size = layout.size();
       (*block).next = ptr::null_mut();
+      (*block).prev = self.last;
+      if canary {
+        write_canary(block);
+      }
+      #[cfg(feature = "shadow")]
+      self.shadow_allocate(block);
 
       // Update the linked list of blocks
       if self.first.is_null() {
@@ -882,11 +2060,53 @@ impl BumpAllocator {
         (*self.last).next = block;
         self.last = block;
       }
+      self.tracked_blocks += 1;
 
       content_addr as *mut u8
     }
   }
 
+  /// Obtains `size` more bytes from the backend, returning the old break,
+  /// or null with [`last_failure`](Self::last_failure) set.
+  ///
+  /// Requests above a limit cannot succeed: they fail with the limit as
+  /// the reason instead of letting `sbrk` fail without one.
+  #[inline]
+  pub(crate) unsafe fn grow_heap(
+    &mut self,
+    size: usize,
+  ) -> *mut u8 {
+    if let Some(limit) = self.heap_limit {
+      let headroom = limit.saturating_sub(self.backend.used_bytes());
+      if size > headroom {
+        self.last_failure = Some(AllocFailure::ExceedsHeapLimit {
+          requested: size,
+          headroom,
+          limit,
+        });
+        return ptr::null_mut();
+      }
+    }
+    if let Some(headroom) = self.backend.headroom()
+      && size > headroom
+    {
+      self.last_failure = Some(AllocFailure::ExceedsHeadroom {
+        requested: size,
+        headroom,
+        os_limit: self.backend.os_limit(),
+      });
+      return ptr::null_mut();
+    }
+
+    match unsafe { self.backend.grow(size) } {
+      Some(address) => address,
+      None => {
+        self.last_failure = Some(AllocFailure::BackendRefused { requested: size });
+        ptr::null_mut()
+      },
+    }
+  }
+
   /// Deallocates a previously allocated block of memory.
   ///
   /// This method marks the block as free. If the block is the **last** block
@@ -900,7 +2120,7 @@ impl BumpAllocator {
   /// # Behavior
   ///
   /// ```text
-  ///   CASE 1: Deallocating a middle block (only marks as free)
+  ///   CASE 1: Deallocating a middle block (marks free, links into free list)
   ///   ═══════════════════════════════════════════════════════════════
   ///
   ///   Before:
@@ -911,7 +2131,8 @@ impl BumpAllocator {
   ///   After:
   ///   [Block A: in_use] ──► [Block B: FREE] ──► [Block C: in_use]
   ///                                │
-  ///                         marked free, but
+  ///                         marked free and linked
+  ///                         into the free list, but
   ///                         memory NOT returned to OS
   ///
   ///   CASE 2: Deallocating the last block (shrinks heap)
@@ -929,27 +2150,31 @@ impl BumpAllocator {
   ///                                │
   ///                         now `last`
   ///
-  ///   Heap shrunk via: sbrk(-(block_C_size + overhead))
+  ///   Heap shrunk back to the end of B's payload, word-aligned:
+  ///
+  ///   │ hdr B │ payload B │·│ pad │ hdr C │ payload C │ pad │
+  ///                         ▲                             ▲
+  ///                   new heap end                   old heap end
+  ///
+  ///   Everything C took - its padding, jitter gap, header, payload and
+  ///   canary - goes back, measured from B rather than recorded in C, so
+  ///   no byte is lost or released twice.
   /// ```
   ///
   /// # List Update for Last Block Deallocation
   ///
   /// ```text
-  ///   Finding the new last block requires traversal:
+  ///   The new last block is the freed block's predecessor:
   ///
   ///   ┌─────────────────┐
   ///   │  BumpAllocator  │
-  ///   │  first ─────────┼──► [A] ──► [B] ──► [C]  ◄── last (to be freed)
+  ///   │  first ─────────┼──► [A] ◄─► [B] ◄─► [C]  ◄── last (to be freed)
   ///   └─────────────────┘
   ///
-  ///   Traversal: start at first, walk until current.next == last
-  ///
-  ///   current = A
-  ///     └─► A.next = B (not last) ──► continue
-  ///   current = B
-  ///     └─► B.next = C (== last) ──► STOP
+  ///   last = C.prev = B        (O(1), no traversal)
+  ///   B.next = null
   ///
-  ///   Set last = B, then shrink heap
+  ///   Then shrink heap
   /// ```
   ///
   /// # Special Case: Single Block
@@ -995,62 +2220,374 @@ impl BumpAllocator {
         return;
       }
 
+      #[cfg(feature = "thread-check")]
+      self.check_owner();
+
+      #[cfg(feature = "std")]
+      {
+        self.forget_type(address);
+        self.forget_refs(address);
+        self.forget_attribution(address);
+        // Adopted regions are only forgotten, never given back
+        if self.forget_adopted(address) || self.deallocate_cold(address) {
+          return;
+        }
+      }
+
+      if self.deallocate_donated(address) {
+        return;
+      }
+
+      if self.reserve.owns(address) {
+        #[cfg(feature = "counters")]
+        self.count_deallocate((*self.find_block(address)).size);
+        self.reserve.deallocate(address);
+        return;
+      }
+
+      // Past the block cap there is no header to free: wait for a reset
+      if self.is_untracked(address) {
+        return;
+      }
+
       // Find the block header by going back header_size bytes
       let block = self.find_block(address);
+
+      let double_free = (*block).is_free;
+
+      #[cfg(feature = "flight-recorder")]
+      {
+        self.record(OpKind::Deallocate, address as usize, (*block).size, !double_free);
+        if double_free {
+          self.report_corruption(Corruption::DoubleFree {
+            address: address as usize,
+          });
+        }
+      }
+
+      // Linking the block a second time would corrupt the free list
+      if double_free {
+        return;
+      }
+      #[cfg(feature = "counters")]
+      self.count_deallocate((*block).size);
+
+      // Only the last block can be returned to the OS, and only while no
+      // untracked memory lies above it
+      let pop = block == self.last && self.untracked.is_null();
+      #[cfg(feature = "shadow")]
+      self.shadow_free(block, pop);
+      self.retire_canary(block);
       (*block).is_free = true;
 
-      // Only the last block can be returned to the OS
-      // Middle blocks remain as "holes" in the heap
-      if block != self.last {
+      // Middle blocks remain as "holes" in the heap, merged with free
+      // neighbours and chained into the free list
+      if !pop {
+        if block != self.last {
+          self.absorb_gap(block);
+        }
+        let block = if self.realtime { block } else { self.coalesce(block) };
+        self.link_free(block);
+        self.tick_shrink_idle();
         return;
       }
 
+      // The popped block is never linked, but a cursor left on it would
+      // point into released memory
+      if self.last_search == block {
+        self.last_search = ptr::null_mut();
+      }
+
       // Update the linked list to remove the last block
       if self.first == self.last {
         // This was the only block - reset to empty state
         self.first = ptr::null_mut();
         self.last = ptr::null_mut();
       } else {
-        // Find the second-to-last block (new last)
-        // This requires O(n) traversal since we have a singly-linked list
-        let mut current: *mut Block = self.first;
-        while !(*current).next.is_null() && (*current).next != self.last {
-          current = (*current).next;
-        }
-        self.last = current;
+        // The predecessor becomes the new tail - O(1) thanks to `prev`
+        self.last = (*block).prev;
+        (*self.last).next = ptr::null_mut();
       }
+      self.tracked_blocks -= 1;
 
-      // Calculate how much memory to release
-      // Note: includes extra header_size for alignment padding considerations
-      let to_release: usize = align!((*block).size + mem::size_of::<Block>() + mem::size_of::<Block>());
-
-      // Shrink the heap by calling sbrk with a negative value
-      let decrement: isize = -(to_release as isize);
-
-      sbrk(decrement as intptr_t);
+      // Shrink the heap (a negative sbrk for the Sbrk backend), now or
+      // later as the shrink policy says
+      self.release_past_tail();
     }
   }
 
-  /// Finds the block header associated with a user data pointer.
+  /// Marks the live `block` free, merges it with free neighbours and links
+  /// it for reuse, even at the tail, where [`deallocate`](Self::deallocate)
+  /// would give it back - for blocks created only to be reused, see the
+  /// `preheat` module.
   ///
-  /// Given a pointer returned by `allocate`, this method calculates
-  /// the location of the corresponding `Block` metadata.
+  /// # Safety
   ///
-  /// # Arguments
+  /// `block` must be a live block of the list with no user pointer into it.
+  pub(crate) unsafe fn free_in_place(
+    &mut self,
+    block: *mut Block,
+  ) {
+    unsafe {
+      #[cfg(feature = "counters")]
+      self.count_deallocate((*block).size);
+      #[cfg(feature = "shadow")]
+      self.shadow_free(block, false);
+      self.retire_canary(block);
+      (*block).is_free = true;
+      if block != self.last {
+        self.absorb_gap(block);
+      }
+      let block = if self.realtime { block } else { self.coalesce(block) };
+      self.link_free(block);
+    }
+  }
+
+  /// Deallocates `address`, cross-checking `layout` against the block.
   ///
-  /// * `address` - Pointer to user data (as returned by `allocate`)
+  /// This is the `GlobalAlloc::dealloc` / `Allocator::deallocate` shape:
+  /// the caller passes back the layout it allocated with. The block stores
+  /// the requested size, so a mismatch (a classic sign of freeing the wrong
+  /// pointer or a type confusion) is caught by a debug assertion:
   ///
-  /// # Returns
+  /// ```text
+  ///   address ──► header.size == layout.size()        ✓ size matches
+  ///               address % layout.align() == 0       ✓ alignment honoured
+  /// ```
   ///
-  /// Pointer to the `Block` header for this allocation.
+  /// Release builds skip the checks and behave exactly like
+  /// [`deallocate`](Self::deallocate).
   ///
-  /// # Layout
+  /// # Safety
   ///
-  /// ```text
-  ///   Memory layout:
-  ///   ┌────────────────────┬────────────────────────────┐
-  ///   │    Block Header    │        User Data           │
-  ///   │    (header_size)   │                            │
+  /// Same as [`deallocate`](Self::deallocate), and `layout` must be the
+  /// layout `address` was allocated with.
+  pub unsafe fn deallocate_with_layout(
+    &mut self,
+    address: *mut u8,
+    layout: alloc::Layout,
+  ) {
+    if address.is_null() || self.is_untracked(address) {
+      return;
+    }
+
+    #[cfg(debug_assertions)]
+    {
+      // SAFETY: The caller guarantees `address` came from `allocate`.
+      let size = unsafe { (*self.find_block(address)).size };
+      debug_assert_eq!(
+        size,
+        layout.size(),
+        "deallocate_with_layout: {address:?} was allocated with {size} bytes, not {}",
+        layout.size()
+      );
+      debug_assert!(
+        crate::align::is_ptr_aligned(address, layout.align()),
+        "deallocate_with_layout: {address:?} is not aligned to {}",
+        layout.align()
+      );
+    }
+    #[cfg(not(debug_assertions))]
+    let _ = layout;
+
+    unsafe { self.deallocate(address) };
+  }
+
+  /// Returns a snapshot of the block list.
+  ///
+  /// Walks every block, so this is O(n). It is a diagnostic and is allowed
+  /// in real-time mode.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// let stats = allocator.stats();
+  /// println!("{} live blocks, {} bytes in use", stats.live_blocks, stats.bytes_in_use);
+  /// ```
+  pub fn stats(&self) -> Stats {
+    let mut stats = Stats {
+      heap_bytes: self.backend.used_bytes(),
+      os_limit: self.backend.os_limit(),
+      headroom: self.headroom(),
+      cached_bytes: self.backend.cached_bytes(),
+      untracked_bytes: self.untracked_bytes(),
+      ..Stats::default()
+    };
+    #[cfg(feature = "std")]
+    for region in self.adopted_regions() {
+      stats.adopted_blocks += 1;
+      stats.adopted_bytes += region.len();
+    }
+
+    for block in self.blocks() {
+      if block.is_free {
+        stats.free_blocks += 1;
+        stats.bytes_free += block.size;
+      } else {
+        stats.live_blocks += 1;
+        stats.bytes_in_use += block.size;
+      }
+    }
+
+    // Whatever is not a header or payload is padding
+    stats.header_bytes = stats.total_blocks() * HEADER_SIZE;
+    stats.padding_bytes = stats
+      .heap_bytes
+      .saturating_sub(stats.header_bytes + stats.bytes_in_use + stats.bytes_free + stats.untracked_bytes);
+
+    stats
+  }
+
+  /// The newest block, or null when the list is empty.
+  pub(crate) fn last_block(&self) -> *mut Block {
+    self.last
+  }
+
+  /// Where the next NextFit search starts, or null for the free head.
+  pub(crate) fn search_cursor(&self) -> *mut Block {
+    self.last_search
+  }
+
+  /// Iterates over the block list from `first` to `last`.
+  pub(crate) fn blocks(&self) -> Blocks<'_> {
+    Blocks {
+      current: self.first,
+      _allocator: PhantomData,
+    }
+  }
+
+  /// Total bytes the backend can provide, or `None` for the unbounded
+  /// program break.
+  ///
+  /// For allocators built with [`BumpAllocator::from_buffer`] or
+  /// [`BumpAllocator::with_capacity`] this is the region size - a hard quota.
+  pub fn capacity(&self) -> Option<usize> {
+    self.backend.capacity()
+  }
+
+  /// Addresses the heap spans: from its first byte to the current break.
+  ///
+  /// Every block, header included, lies inside it, so it serves as a
+  /// provenance check or to place guard regions around the arena. Empty
+  /// until the first allocation.
+  ///
+  /// ```text
+  ///   heap_range().start                       heap_range().end
+  ///   ▼                                        ▼
+  ///   │ hdr │ A │ hdr │ B │ ...  │ hdr │ Z │   │ headroom ...
+  /// ```
+  ///
+  /// On the program break, another user moving the break between two of
+  /// this allocator's growths leaves foreign memory inside the range; see
+  /// [`heap_size`](Self::heap_size) for the bytes actually obtained.
+  pub fn heap_range(&self) -> Range<usize> {
+    let end = self.backend.current_break() as usize;
+    let start = end - self.backend.used_bytes();
+    if self.first.is_null() {
+      start..end
+    } else {
+      (self.first as usize).min(start)..end
+    }
+  }
+
+  /// Bytes obtained from the backend and not given back, headers and
+  /// padding included. The same as [`Stats::heap_bytes`], without walking
+  /// the block list.
+  pub fn heap_size(&self) -> usize {
+    self.backend.used_bytes()
+  }
+
+  /// Number of blocks handed out and not yet freed.
+  ///
+  /// Walks the block list, like [`stats`](Self::stats).
+  pub fn live_blocks(&self) -> usize {
+    self.blocks().filter(|block| !block.is_free).count()
+  }
+
+  /// Number of blocks in the list, live and free.
+  pub fn block_count(&self) -> usize {
+    self.blocks().count()
+  }
+
+  /// `true` when the allocator manages no memory: nothing was allocated
+  /// yet, or everything was given back by [`reset`](Self::reset) or by
+  /// popping the tail. O(1).
+  pub fn is_empty(&self) -> bool {
+    self.first.is_null() && self.heap_size() == 0
+  }
+
+  /// Frees every block at once and gives the memory back to the backend.
+  ///
+  /// ```text
+  ///   Before:  first ──► [A] ──► [B] ──► [C] ◄── last
+  ///   After:   first: null, last: null   (break back at the start)
+  /// ```
+  ///
+  /// A region backend rewinds to its start. The program break is only
+  /// moved back if nothing else has moved it since this allocator last
+  /// grew; otherwise the memory stays mapped but is forgotten.
+  ///
+  /// # Safety
+  ///
+  /// Every pointer previously returned by `allocate` becomes dangling.
+  pub unsafe fn reset(&mut self) {
+    #[cfg(feature = "thread-check")]
+    self.check_owner();
+
+    self.first = ptr::null_mut();
+    self.last = ptr::null_mut();
+    self.tracked_blocks = 0;
+    self.untracked = ptr::null_mut();
+    self.clear_headerless();
+    #[cfg(feature = "std")]
+    if let Some(types) = &mut self.types {
+      types.clear();
+    }
+    #[cfg(feature = "std")]
+    {
+      self.refcounts.clear();
+      self.tags.clear();
+      if let Some(callsites) = &mut self.callsites {
+        callsites.clear();
+      }
+      self.adopted.clear();
+      self.reset_cold();
+    }
+    self.last_search = ptr::null_mut();
+    self.free_head = ptr::null_mut();
+    self.reuse_floor = ptr::null_mut();
+    self.size_index.clear();
+    self.reserve = EmergencyReserve::none();
+    self.donations = ptr::null_mut();
+    unsafe { self.backend.release_all() };
+    #[cfg(feature = "shadow")]
+    self.shadow.clear();
+    #[cfg(feature = "flight-recorder")]
+    self.record(OpKind::Reset, 0, 0, true);
+    #[cfg(feature = "counters")]
+    self.count_reset();
+  }
+
+  /// Finds the block header associated with a user data pointer.
+  ///
+  /// Given a pointer returned by `allocate`, this method calculates
+  /// the location of the corresponding `Block` metadata.
+  ///
+  /// # Arguments
+  ///
+  /// * `address` - Pointer to user data (as returned by `allocate`)
+  ///
+  /// # Returns
+  ///
+  /// Pointer to the `Block` header for this allocation.
+  ///
+  /// # Layout
+  ///
+  /// ```text
+  ///   Memory layout:
+  ///   ┌────────────────────┬────────────────────────────┐
+  ///   │    Block Header    │        User Data           │
+  ///   │    (header_size)   │                            │
   ///   └────────────────────┴────────────────────────────┘
   ///   ▲                    ▲
   ///   │                    │
@@ -1066,29 +2603,82 @@ impl BumpAllocator {
   /// - `address` points to valid memory
   ///
   /// Passing an invalid pointer results in undefined behavior.
+  #[inline]
   unsafe fn find_block(
     &self,
     address: *mut u8,
   ) -> *mut Block {
-    let block = unsafe { address.sub(mem::size_of::<Block>()) } as *mut Block;
-    block
+    unsafe { address.sub(HEADER_SIZE) as *mut Block }
+  }
+}
+
+impl Default for BumpAllocator {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// A summary instead of the raw list pointers:
+///
+/// ```text
+///   BumpAllocator { live_blocks: 3, free_blocks: 1, bytes_in_use: 96, bytes_free: 32,
+///                   heap_bytes: 256, break: 0x55d0c1e2a120, search_mode: FirstFit, realtime: false }
+/// ```
+///
+/// Walks the block list, like [`stats`](BumpAllocator::stats).
+impl fmt::Debug for BumpAllocator {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    let stats = self.stats();
+    f.debug_struct("BumpAllocator")
+      .field("live_blocks", &stats.live_blocks)
+      .field("free_blocks", &stats.free_blocks)
+      .field("bytes_in_use", &stats.bytes_in_use)
+      .field("bytes_free", &stats.bytes_free)
+      .field("heap_bytes", &stats.heap_bytes)
+      .field("break", &self.backend.current_break())
+      .field("search_mode", &self.search_mode)
+      .field("realtime", &self.realtime)
+      .finish()
+  }
+}
+
+/// Iterator over the blocks of a [`BumpAllocator`], oldest first.
+///
+/// Borrows the allocator, so the list cannot change while it is walked.
+pub(crate) struct Blocks<'a> {
+  /// Next block to yield, or null at the end of the list.
+  current: *mut Block,
+
+  /// Ties the yielded references to the allocator borrow.
+  _allocator: PhantomData<&'a BumpAllocator>,
+}
+
+impl<'a> Iterator for Blocks<'a> {
+  type Item = &'a Block;
+
+  fn next(&mut self) -> Option<&'a Block> {
+    if self.current.is_null() {
+      return None;
+    }
+    // SAFETY: Every block in the list was initialized by `allocate`, and the
+    // borrow of the allocator keeps the list unchanged.
+    let block = unsafe { &*self.current };
+    self.current = block.next;
+    Some(block)
   }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::align::is_ptr_aligned;
   use std::alloc::Layout;
+  #[cfg(unix)]
   use libc::sbrk;
 
-  /// Helper: check that a pointer is aligned to `align` bytes.
-  fn is_aligned(
-    ptr: *mut u8,
-    align: usize,
-  ) -> bool {
-    (ptr as usize) % align == 0
-  }
-
   #[test]
   fn basic_allocation_and_write_read() {
     let mut allocator = BumpAllocator::new();
@@ -1140,7 +2730,7 @@ mod tests {
         assert!(!ptr.is_null());
 
         assert!(
-          is_aligned(ptr, layout.align()),
+          is_ptr_aligned(ptr, layout.align()),
           "allocation must be {}-byte aligned, got {:p}",
           layout.align(),
           ptr
@@ -1183,6 +2773,7 @@ mod tests {
   }
 
   #[test]
+  #[cfg(unix)]
   fn deallocate_null_is_noop_and_deallocate_last_block_does_not_crash() {
     let mut allocator = BumpAllocator::new();
 
@@ -1274,6 +2865,34 @@ mod tests {
     assert_eq!(allocator.search_mode(), SearchMode::FirstFit);
   }
 
+  /// Marks the block of `ptr` free and links it, without popping the tail.
+  unsafe fn mark_free(
+    allocator: &mut BumpAllocator,
+    ptr: *mut u8,
+  ) -> *mut Block {
+    unsafe {
+      let block = allocator.find_block(ptr);
+      #[cfg(feature = "shadow")]
+      allocator.shadow_free(block, false);
+      (*block).is_free = true;
+      allocator.link_free(block);
+      block
+    }
+  }
+
+  /// Takes a found block out of the free list, as a reusing allocation would.
+  unsafe fn mark_used(
+    allocator: &mut BumpAllocator,
+    block: *mut Block,
+  ) {
+    unsafe {
+      #[cfg(feature = "shadow")]
+      allocator.shadow_reuse(block, (*block).size);
+      (*block).is_free = false;
+      allocator.unlink_free(block);
+    }
+  }
+
   /// Helper to create an allocator with multiple blocks and free some of them.
   /// Returns the allocator and the pointers to all allocated blocks.
   ///
@@ -1298,8 +2917,7 @@ mod tests {
 
       // Mark specified blocks as free
       for &idx in free_indices {
-        let block = allocator.find_block(ptrs[idx]);
-        (*block).is_free = true;
+        mark_free(&mut allocator, ptrs[idx]);
       }
 
       (allocator, ptrs)
@@ -1396,7 +3014,7 @@ mod tests {
       assert_eq!(found1, block0);
 
       // Mark block 0 as used
-      (*found1).is_free = false;
+      mark_used(&mut allocator, found1);
 
       // Second search for 50 bytes: should start from block 0, find block 1 (128 bytes)
       let found2 = allocator.find_free_block(50);
@@ -1405,7 +3023,7 @@ mod tests {
       assert_eq!(found2, block1);
 
       // Mark block 1 as used
-      (*found2).is_free = false;
+      mark_used(&mut allocator, found2);
 
       // Third search for 50 bytes: should continue from block 1, find block 4 (64 bytes)
       let found3 = allocator.find_free_block(50);
@@ -1415,6 +3033,104 @@ mod tests {
     }
   }
 
+  #[test]
+  fn next_fit_cursor_moves_off_a_reused_block() {
+    unsafe {
+      let (mut allocator, ptrs) = setup_allocator_with_blocks(SearchMode::NextFit, &[1, 4]);
+      let block1 = allocator.find_block(ptrs[1]);
+      let block4 = allocator.find_block(ptrs[4]);
+
+      assert_eq!(allocator.find_free_block(50), block1);
+      assert_eq!(allocator.last_search, block1);
+
+      // Reusing the cursor's block moves the cursor to the next free block
+      mark_used(&mut allocator, block1);
+      assert_eq!(allocator.last_search, block4);
+      assert_eq!(allocator.check_invariants(), Ok(()));
+      assert_eq!(allocator.find_free_block(50), block4);
+    }
+  }
+
+  #[test]
+  fn next_fit_cursor_never_points_at_a_popped_tail() {
+    unsafe {
+      let (mut allocator, ptrs) = setup_allocator_with_blocks(SearchMode::NextFit, &[4]);
+      let block4 = allocator.find_block(ptrs[4]);
+      assert_eq!(allocator.find_free_block(50), block4);
+
+      // Hand the tail out again, then free it: its memory goes back to
+      // the backend and no search may start there
+      mark_used(&mut allocator, block4);
+      allocator.deallocate(ptrs[4]);
+      assert!(allocator.last_search.is_null());
+      assert_eq!(allocator.check_invariants(), Ok(()));
+      assert!(allocator.find_free_block(50).is_null());
+
+      // Same when a bug left the cursor behind on a block in use
+      let tail = allocator.find_block(ptrs[3]);
+      allocator.last_search = tail;
+      assert_eq!(allocator.check_invariants(), Err(crate::Corruption::StaleSearchCursor));
+      allocator.deallocate(ptrs[3]);
+      assert!(allocator.last_search.is_null());
+
+      allocator.reset();
+    }
+  }
+
+  #[test]
+  fn next_fit_cursor_is_cleared_by_reset_and_rebuild() {
+    unsafe {
+      let (mut allocator, _) = setup_allocator_with_blocks(SearchMode::NextFit, &[1, 3]);
+      allocator.find_free_block(50);
+      assert!(!allocator.last_search.is_null());
+
+      allocator.rebuild_free_list();
+      assert!(allocator.last_search.is_null());
+
+      allocator.find_free_block(50);
+      allocator.reset();
+      assert!(allocator.last_search.is_null());
+    }
+  }
+
+  #[test]
+  fn switching_modes_resets_the_next_fit_cursor() {
+    unsafe {
+      let (mut allocator, ptrs) = setup_allocator_with_blocks(SearchMode::NextFit, &[0, 1, 4]);
+      let block0 = allocator.find_block(ptrs[0]);
+      let block1 = allocator.find_block(ptrs[1]);
+
+      allocator.find_free_block(100);
+      assert_eq!(allocator.last_search, block1);
+
+      // Re-setting the same mode keeps the cursor
+      allocator.set_search_mode(SearchMode::NextFit);
+      assert_eq!(allocator.last_search, block1);
+
+      // A round trip through another mode starts over
+      allocator.set_search_mode(SearchMode::BestFit);
+      assert!(allocator.last_search.is_null());
+      allocator.set_search_mode(SearchMode::NextFit);
+      assert!(allocator.last_search.is_null());
+      assert_eq!(allocator.find_free_block(50), block0);
+    }
+  }
+
+  #[test]
+  fn reset_search_cursor_restarts_next_fit_at_the_head() {
+    unsafe {
+      let (mut allocator, ptrs) = setup_allocator_with_blocks(SearchMode::NextFit, &[0, 4]);
+      let block0 = allocator.find_block(ptrs[0]);
+      let block4 = allocator.find_block(ptrs[4]);
+
+      allocator.last_search = block4;
+      assert_eq!(allocator.find_free_block(50), block4);
+
+      allocator.reset_search_cursor();
+      assert_eq!(allocator.find_free_block(50), block0);
+    }
+  }
+
   #[test]
   fn next_fit_wraps_around_to_beginning() {
     unsafe {
@@ -1424,7 +3140,7 @@ mod tests {
       // First search: find block 0
       let found1 = allocator.find_free_block(50);
       assert!(!found1.is_null());
-      (*found1).is_free = false;
+      mark_used(&mut allocator, found1);
 
       // Second search: find block 4 (continues from block 0)
       let found2 = allocator.find_free_block(50);
@@ -1433,9 +3149,8 @@ mod tests {
       assert_eq!(found2, block4);
 
       // Free block 0 again, keep block 4 as used
-      let block0 = allocator.find_block(ptrs[0]);
-      (*block0).is_free = true;
-      (*found2).is_free = false;
+      let block0 = mark_free(&mut allocator, ptrs[0]);
+      mark_used(&mut allocator, found2);
 
       // Third search: should wrap around and find block 0
       let found3 = allocator.find_free_block(50);
@@ -1458,7 +3173,14 @@ mod tests {
 
   #[test]
   fn all_modes_return_null_on_empty_allocator() {
-    for mode in [SearchMode::FirstFit, SearchMode::NextFit, SearchMode::BestFit] {
+    for mode in [
+      SearchMode::FirstFit,
+      SearchMode::NextFit,
+      SearchMode::BestFit,
+      SearchMode::BestFitIndexed,
+      SearchMode::GoodFit { tolerance_percent: 10 },
+      SearchMode::Random { size_weighted: true },
+    ] {
       let mut allocator = BumpAllocator::with_search_mode(mode);
 
       unsafe {
@@ -1470,7 +3192,14 @@ mod tests {
 
   #[test]
   fn all_modes_return_null_when_all_blocks_in_use() {
-    for mode in [SearchMode::FirstFit, SearchMode::NextFit, SearchMode::BestFit] {
+    for mode in [
+      SearchMode::FirstFit,
+      SearchMode::NextFit,
+      SearchMode::BestFit,
+      SearchMode::BestFitIndexed,
+      SearchMode::GoodFit { tolerance_percent: 10 },
+      SearchMode::Random { size_weighted: true },
+    ] {
       unsafe {
         // Setup with no free blocks
         let (mut allocator, _ptrs) = setup_allocator_with_blocks(mode, &[]);
@@ -1480,4 +3209,1098 @@ mod tests {
       }
     }
   }
+
+  #[test]
+  fn best_fit_indexed_prefers_smallest_then_lowest_address() {
+    unsafe {
+      // Setup: blocks [64, 128, 32, 256, 64], free all
+      let (mut allocator, ptrs) = setup_allocator_with_blocks(SearchMode::BestFitIndexed, &[0, 1, 2, 3, 4]);
+
+      // Two 64-byte blocks fit 50 bytes: the lower one wins, as in BestFit
+      assert_eq!(allocator.find_free_block(50), allocator.find_block(ptrs[0]));
+      assert_eq!(allocator.find_free_block(128), allocator.find_block(ptrs[1]));
+      assert_eq!(allocator.find_free_block(200), allocator.find_block(ptrs[3]));
+      assert!(allocator.find_free_block(257).is_null());
+
+      let block0 = allocator.find_block(ptrs[0]);
+      mark_used(&mut allocator, block0);
+      assert_eq!(allocator.find_free_block(50), allocator.find_block(ptrs[4]));
+    }
+  }
+
+  #[test]
+  fn best_fit_indexed_agrees_with_linear_best_fit() {
+    let mut allocator = BumpAllocator::with_capacity(64 * 1024);
+    allocator.set_search_mode(SearchMode::BestFitIndexed);
+    let mut seed = 0x2545_f491_u32;
+    let mut random = move |bound: usize| {
+      seed ^= seed << 13;
+      seed ^= seed >> 17;
+      seed ^= seed << 5;
+      seed as usize % bound
+    };
+
+    unsafe {
+      let ptrs: Vec<_> = (0..200)
+        .map(|_| allocator.allocate(Layout::array::<u8>(1 + random(300)).unwrap()))
+        .collect();
+      // Free two thirds of the blocks, keeping the tail so nothing is popped
+      for &ptr in &ptrs[..199] {
+        if random(3) > 0 {
+          allocator.deallocate(ptr);
+        }
+      }
+
+      for _ in 0..500 {
+        let size = random(320);
+        let found = allocator.find_free_block(size);
+        assert_eq!(found, allocator.find_free_block_best_fit(size), "size {size}");
+
+        // Reuse what was found, or give a block back
+        if !found.is_null() && random(2) == 0 {
+          mark_used(&mut allocator, found);
+        } else {
+          let ptr = ptrs[random(199)];
+          if !(*allocator.find_block(ptr)).is_free {
+            mark_free(&mut allocator, ptr);
+          }
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn good_fit_stops_at_the_first_block_within_tolerance() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+
+    unsafe {
+      // Each block is followed by a live one, so the freed blocks stay apart
+      let ptrs: Vec<_> = [200, 110, 100]
+        .iter()
+        .map(|&size| {
+          let ptr = allocator.allocate(Layout::array::<u8>(size).unwrap());
+          allocator.allocate(Layout::array::<u8>(8).unwrap());
+          ptr
+        })
+        .collect();
+      for &ptr in &ptrs {
+        allocator.deallocate(ptr);
+      }
+
+      // Freed, the blocks span 208, 120 and 112 bytes up to the next header.
+      // 120 is within 15% of 105 and comes before the closer fit
+      allocator.set_search_mode(SearchMode::GoodFit { tolerance_percent: 15 });
+      assert_eq!(allocator.find_free_block(105), allocator.find_block(ptrs[1]));
+
+      // Nothing within 5%: the best fit is returned
+      allocator.set_search_mode(SearchMode::GoodFit { tolerance_percent: 5 });
+      assert_eq!(allocator.find_free_block(90), allocator.find_block(ptrs[2]));
+
+      // Zero tolerance is Best Fit
+      allocator.set_search_mode(SearchMode::GoodFit { tolerance_percent: 0 });
+      assert_eq!(allocator.find_free_block(100), allocator.find_block(ptrs[2]));
+      assert!(allocator.find_free_block(209).is_null());
+    }
+  }
+
+  #[test]
+  fn good_fit_tolerance_saturates_for_huge_requests() {
+    unsafe {
+      let (allocator, _ptrs) = setup_allocator_with_blocks(SearchMode::FirstFit, &[3]);
+      assert!(allocator.find_free_block_good_fit(usize::MAX, 255).is_null());
+    }
+  }
+
+  /// How often each of `ptrs` is picked by 1000 searches for `size`.
+  unsafe fn random_picks(
+    allocator: &mut BumpAllocator,
+    ptrs: &[*mut u8],
+    size: usize,
+  ) -> Vec<usize> {
+    unsafe {
+      let mut counts = vec![0; ptrs.len()];
+      for _ in 0..1000 {
+        let found = allocator.find_free_block(size);
+        let index = ptrs.iter().position(|&ptr| allocator.find_block(ptr) == found);
+        counts[index.expect("picked a block outside the candidates")] += 1;
+      }
+      counts
+    }
+  }
+
+  #[test]
+  fn random_picks_only_fitting_blocks_uniformly() {
+    unsafe {
+      // Setup: blocks [64, 128, 32, 256, 64], free indices [1, 2, 3] (sizes 128, 32, 256)
+      let (mut allocator, ptrs) =
+        setup_allocator_with_blocks(SearchMode::Random { size_weighted: false }, &[1, 2, 3]);
+
+      let counts = random_picks(&mut allocator, &[ptrs[1], ptrs[3]], 50);
+      assert!(counts.iter().all(|&count| (400..600).contains(&count)), "{counts:?}");
+      assert!(allocator.find_free_block(257).is_null());
+    }
+  }
+
+  #[test]
+  fn random_can_weight_blocks_by_size() {
+    unsafe {
+      let (mut allocator, ptrs) = setup_allocator_with_blocks(SearchMode::Random { size_weighted: true }, &[1, 3]);
+
+      // 128 vs 256 bytes: one third against two thirds
+      let counts = random_picks(&mut allocator, &[ptrs[1], ptrs[3]], 50);
+      assert!((250..420).contains(&counts[0]), "{counts:?}");
+    }
+  }
+
+  #[test]
+  fn random_choices_replay_with_the_same_seed() {
+    unsafe {
+      let (mut allocator, ptrs) =
+        setup_allocator_with_blocks(SearchMode::Random { size_weighted: false }, &[0, 1, 3, 4]);
+
+      allocator.set_random_seed(7);
+      let first = random_picks(&mut allocator, &ptrs, 50);
+      allocator.set_random_seed(7);
+      assert_eq!(random_picks(&mut allocator, &ptrs, 50), first);
+    }
+  }
+
+  #[test]
+  fn switching_to_best_fit_indexed_builds_the_index() {
+    unsafe {
+      let (mut allocator, ptrs) = setup_allocator_with_blocks(SearchMode::FirstFit, &[1, 3]);
+      assert!(allocator.size_index.best_fit(0).is_null());
+
+      allocator.set_search_mode(SearchMode::BestFitIndexed);
+      assert_eq!(allocator.find_free_block(100), allocator.find_block(ptrs[1]));
+
+      allocator.set_search_mode(SearchMode::BestFit);
+      assert!(allocator.size_index.best_fit(0).is_null());
+    }
+  }
+
+  // ═══════════════════════════════════════════════════════════════════════════
+  // Free List Tests
+  // ═══════════════════════════════════════════════════════════════════════════
+
+  /// The free list from head to tail.
+  fn free_list(allocator: &BumpAllocator) -> Vec<*mut Block> {
+    let mut blocks = Vec::new();
+    let mut current = allocator.free_head;
+    while !current.is_null() {
+      blocks.push(current);
+      current = unsafe { (*Block::free_links(current)).next_free };
+    }
+    blocks
+  }
+
+  #[test]
+  fn freed_blocks_are_linked_in_address_order() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+
+    unsafe {
+      let layout = Layout::new::<u64>();
+      let ptrs: Vec<_> = (0..7).map(|_| allocator.allocate(layout)).collect();
+
+      // Not neighbours, so none of them merge
+      allocator.deallocate(ptrs[5]);
+      allocator.deallocate(ptrs[1]);
+      allocator.deallocate(ptrs[3]);
+
+      let expected: Vec<_> = [1, 3, 5].iter().map(|&i| allocator.find_block(ptrs[i])).collect();
+      assert_eq!(free_list(&allocator), expected);
+
+      // Back links mirror the forward links
+      assert!((*Block::free_links(expected[0])).prev_free.is_null());
+      assert_eq!((*Block::free_links(expected[2])).prev_free, expected[1]);
+    }
+  }
+
+  #[test]
+  fn free_links_live_in_the_payload() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+
+    unsafe {
+      let a = allocator.allocate(Layout::new::<u8>());
+      allocator.allocate(Layout::new::<u8>());
+      allocator.deallocate(a);
+
+      let block = allocator.find_block(a);
+      assert_eq!(Block::free_links(block) as *mut u8, a);
+      // A 1-byte request still reserves room for the links
+      assert_eq!(free_list(&allocator), [block]);
+      assert_eq!(allocator.find_free_block(1), block);
+    }
+    assert_eq!(mem::size_of::<Block>(), 4 * mem::size_of::<usize>());
+  }
+
+  #[test]
+  fn popped_tail_is_not_linked() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+
+    unsafe {
+      let layout = Layout::new::<u64>();
+      let a = allocator.allocate(layout);
+      let b = allocator.allocate(layout);
+      allocator.deallocate(a);
+      allocator.deallocate(b);
+
+      assert_eq!(free_list(&allocator), [allocator.find_block(a)]);
+      allocator.reset();
+    }
+    assert!(free_list(&allocator).is_empty());
+  }
+
+  #[test]
+  fn realtime_frees_are_linked_when_leaving_the_mode() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_realtime(true);
+
+    unsafe {
+      let layout = Layout::new::<u64>();
+      let ptrs: Vec<_> = (0..4).map(|_| allocator.allocate(layout)).collect();
+      allocator.deallocate(ptrs[2]);
+      allocator.deallocate(ptrs[0]);
+      assert!(free_list(&allocator).is_empty());
+
+      allocator.set_realtime(false);
+      let expected: Vec<_> = [0, 2].iter().map(|&i| allocator.find_block(ptrs[i])).collect();
+      assert_eq!(free_list(&allocator), expected);
+    }
+  }
+
+  #[test]
+  #[cfg(not(feature = "flight-recorder"))]
+  fn double_free_does_not_relink() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+
+    unsafe {
+      let layout = Layout::new::<u64>();
+      let a = allocator.allocate(layout);
+      allocator.allocate(layout);
+      allocator.deallocate(a);
+      allocator.deallocate(a);
+
+      assert_eq!(free_list(&allocator), [allocator.find_block(a)]);
+    }
+  }
+
+  /// Scan-heavy workload: a search that fits no block walks a free list of
+  /// `FREE_BLOCKS` scattered blocks, far larger than the caches.
+  ///
+  /// ```bash
+  /// cargo test --release -- --ignored --nocapture free_list_scan_benchmark
+  /// ```
+  #[test]
+  #[ignore = "benchmark, run it in release mode"]
+  fn free_list_scan_benchmark() {
+    use std::time::Instant;
+
+    const FREE_BLOCKS: usize = 100_000;
+    const SEARCHES: usize = 50;
+
+    let layout = Layout::new::<[u8; 256]>();
+    let mut allocator = BumpAllocator::with_capacity(2 * FREE_BLOCKS * 320);
+
+    unsafe {
+      let ptrs: Vec<_> = (0..2 * FREE_BLOCKS).map(|_| allocator.allocate(layout)).collect();
+      // Highest address first: each block becomes the new head in O(1)
+      for &ptr in ptrs.iter().step_by(2).rev() {
+        allocator.deallocate(ptr);
+      }
+
+      for mode in [SearchMode::FirstFit, SearchMode::BestFit, SearchMode::GoodFit { tolerance_percent: 10 }] {
+        allocator.set_search_mode(mode);
+        let start = Instant::now();
+        for _ in 0..SEARCHES {
+          assert!(allocator.find_free_block(layout.size() + 1).is_null());
+        }
+        let per_block = start.elapsed().as_nanos() as f64 / (SEARCHES * FREE_BLOCKS) as f64;
+        println!("{mode:?}: {per_block:.2} ns per free block");
+      }
+    }
+  }
+
+  // ═══════════════════════════════════════════════════════════════════════════
+  // Real-Time Mode Tests
+  // ═══════════════════════════════════════════════════════════════════════════
+
+  #[test]
+  fn realtime_constructor_and_toggle() {
+    let mut allocator = BumpAllocator::realtime();
+    assert!(allocator.is_realtime());
+    assert_eq!(allocator.search_mode(), SearchMode::FirstFit);
+
+    allocator.set_realtime(false);
+    assert!(!allocator.is_realtime());
+    assert!(!BumpAllocator::new().is_realtime());
+  }
+
+  #[test]
+  fn realtime_tail_pop_relinks_previous_block() {
+    let mut allocator = BumpAllocator::realtime();
+
+    unsafe {
+      let layout = Layout::new::<u64>();
+      let a = allocator.allocate(layout);
+      let b = allocator.allocate(layout);
+      let c = allocator.allocate(layout);
+      assert!(!a.is_null() && !b.is_null() && !c.is_null());
+
+      // Freeing a middle block only marks it
+      allocator.deallocate(b);
+      assert!((*allocator.find_block(b)).is_free);
+      assert_eq!(allocator.last, allocator.find_block(c));
+
+      // Freeing the tail pops it and relinks through `prev`
+      allocator.deallocate(c);
+      assert_eq!(allocator.last, allocator.find_block(b));
+      assert!((*allocator.last).next.is_null());
+
+      // The next allocation is appended after the new tail
+      let d = allocator.allocate(layout);
+      let block_d = allocator.find_block(d);
+      assert_eq!((*block_d).prev, allocator.find_block(b));
+      assert_eq!((*allocator.find_block(b)).next, block_d);
+      assert_eq!(allocator.last, block_d);
+    }
+  }
+
+  #[test]
+  #[cfg(debug_assertions)]
+  #[should_panic(expected = "real-time mode")]
+  fn realtime_forbids_free_block_search() {
+    let mut allocator = BumpAllocator::realtime();
+
+    unsafe {
+      allocator.find_free_block(8);
+    }
+  }
+
+  // ═══════════════════════════════════════════════════════════════════════════
+  // Pointer-Width Tests
+  // ═══════════════════════════════════════════════════════════════════════════
+
+  #[test]
+  fn header_is_four_machine_words() {
+    assert_eq!(mem::size_of::<Block>(), 4 * mem::size_of::<usize>());
+    assert_eq!(mem::align_of::<Block>(), mem::align_of::<usize>());
+  }
+
+  #[test]
+  fn size_sits_next_to_the_free_links() {
+    // Scans read `size` and `next_free` together, see `Block`
+    assert_eq!(mem::offset_of!(Block, size) + mem::size_of::<usize>(), HEADER_SIZE);
+    assert_eq!(mem::offset_of!(FreeLinks, next_free), 0);
+  }
+
+  #[test]
+  #[cfg(target_pointer_width = "64")]
+  fn header_is_32_bytes_on_64_bit() {
+    assert_eq!(mem::size_of::<Block>(), 32);
+  }
+
+  #[test]
+  #[cfg(target_pointer_width = "32")]
+  fn header_is_16_bytes_on_32_bit() {
+    assert_eq!(mem::size_of::<Block>(), 16);
+  }
+
+  #[test]
+  fn grow_request_size_is_word_aligned() {
+    let word = mem::size_of::<usize>();
+
+    for size in 1..64 {
+      for align in [1, 2, 4, 8, 16, 32] {
+        let layout = Layout::from_size_align(size, align).unwrap();
+        let request = grow_request_size(layout).unwrap();
+
+        assert!(request.is_multiple_of(word));
+        assert!(request >= mem::size_of::<Block>() + size + align - 1);
+      }
+    }
+  }
+
+  #[test]
+  fn grow_request_size_exceeds_isize_for_huge_layouts() {
+    // Largest size Layout accepts for this alignment
+    let layout = Layout::from_size_align(isize::MAX as usize - 15, 16).unwrap();
+
+    // Fits in usize, but not in intptr_t: the sbrk backend must refuse it
+    let request = grow_request_size(layout).unwrap();
+    assert!(isize::try_from(request).is_err());
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn allocation_larger_than_intptr_max_returns_null() {
+    let mut allocator = BumpAllocator::new();
+    let layout = Layout::from_size_align(isize::MAX as usize - 15, 16).unwrap();
+
+    unsafe {
+      let brk_before = sbrk(0);
+      assert!(allocator.allocate(layout).is_null());
+      assert!(allocator.first.is_null());
+      assert!(sbrk(0) >= brk_before, "a failed request must never shrink the break");
+    }
+    assert_eq!(
+      allocator.last_failure(),
+      Some(AllocFailure::SizeOverflow { size: layout.size() })
+    );
+  }
+
+  #[test]
+  fn requests_are_capped_at_isize_max() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let word = mem::size_of::<usize>();
+
+    // The largest payload whose request still fits under the cap, and one
+    // word more
+    let largest = (MAX_REQUEST_SIZE & !(word - 1)) - HEADER_SIZE - word;
+    let fits = Layout::from_size_align(largest, 1).unwrap();
+    let over = Layout::from_size_align(largest + word, 1).unwrap();
+    assert_eq!(grow_request_size(fits), Some(MAX_REQUEST_SIZE & !(word - 1)));
+    assert!(grow_request_size(over).unwrap() > MAX_REQUEST_SIZE);
+
+    unsafe {
+      // Within the cap, the region's own headroom is what refuses it
+      assert!(allocator.allocate(fits).is_null());
+      assert!(matches!(allocator.last_failure(), Some(AllocFailure::ExceedsHeadroom { .. })));
+
+      assert!(allocator.allocate(over).is_null());
+      assert_eq!(allocator.last_failure(), Some(AllocFailure::SizeOverflow { size: over.size() }));
+
+      // Extra bytes for jitter cannot push a request over the cap either
+      allocator.set_jitter(64);
+      assert!(allocator.allocate(over).is_null());
+      assert_eq!(allocator.last_failure(), Some(AllocFailure::SizeOverflow { size: over.size() }));
+    }
+  }
+
+  #[test]
+  fn heap_limit_boundary_is_inclusive() {
+    let layout = Layout::array::<u8>(100).unwrap();
+    let request = grow_request_size(layout).unwrap();
+
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_heap_limit(Some(request));
+    assert!(!unsafe { allocator.allocate(layout) }.is_null());
+
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_heap_limit(Some(request - 1));
+    assert!(unsafe { allocator.allocate(layout) }.is_null());
+    assert!(matches!(allocator.last_failure(), Some(AllocFailure::ExceedsHeapLimit { .. })));
+  }
+
+  // ═══════════════════════════════════════════════════════════════════════════
+  // Region Backend Tests
+  // ═══════════════════════════════════════════════════════════════════════════
+
+  /// Leaks a zeroed buffer so it can back a `from_buffer` allocator.
+  fn leaked_buffer(len: usize) -> &'static mut [u8] {
+    Box::leak(vec![0u8; len].into_boxed_slice())
+  }
+
+  #[test]
+  fn from_buffer_allocates_inside_the_buffer() {
+    let buffer = leaked_buffer(1024);
+    let range = buffer.as_ptr_range();
+    let mut allocator = BumpAllocator::from_buffer(buffer);
+
+    unsafe {
+      for layout in [Layout::new::<u8>(), Layout::new::<u64>(), Layout::new::<u128>()] {
+        let ptr = allocator.allocate(layout);
+        assert!(!ptr.is_null());
+        assert!(range.contains(&(ptr as *const u8)));
+        assert!(is_ptr_aligned(ptr, layout.align()));
+      }
+    }
+  }
+
+  #[test]
+  fn from_buffer_returns_null_when_exhausted() {
+    let mut allocator = BumpAllocator::from_buffer(leaked_buffer(128));
+
+    unsafe {
+      assert!(allocator.allocate(Layout::array::<u8>(256).unwrap()).is_null());
+      assert!(!allocator.allocate(Layout::new::<u64>()).is_null());
+    }
+  }
+
+  #[test]
+  fn from_buffer_aligns_headers_at_odd_start() {
+    let buffer = leaked_buffer(512);
+    // Skip one byte so the region starts at an odd address
+    let buffer = &mut buffer[1..];
+    let mut allocator = BumpAllocator::from_buffer(buffer);
+
+    unsafe {
+      let ptr = allocator.allocate(Layout::new::<u8>());
+      assert!(!ptr.is_null());
+      assert!(is_ptr_aligned(allocator.find_block(ptr) as *mut u8, mem::align_of::<Block>()));
+    }
+  }
+
+  #[test]
+  fn with_capacity_reuses_tail_after_deallocate() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+
+    unsafe {
+      let layout = Layout::new::<u64>();
+      let a = allocator.allocate(layout);
+      let b = allocator.allocate(layout);
+      assert!(!a.is_null() && !b.is_null());
+      assert!(b > a);
+
+      allocator.deallocate(b);
+      assert_eq!(allocator.last, allocator.find_block(a));
+    }
+  }
+
+  #[test]
+  fn heap_range_covers_every_block() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let empty = allocator.heap_range();
+    assert!(empty.is_empty());
+    assert_eq!(allocator.heap_size(), 0);
+
+    unsafe {
+      let a = allocator.allocate(Layout::new::<u64>());
+      let b = allocator.allocate(Layout::from_size_align(100, 64).unwrap());
+      let range = allocator.heap_range();
+      assert!(range.contains(&(allocator.find_block(a) as usize)));
+      assert!(range.contains(&(b as usize + 99)));
+      assert_eq!(range.len(), allocator.heap_size());
+      assert_eq!(allocator.heap_size(), allocator.stats().heap_bytes);
+
+      allocator.reset();
+    }
+    assert!(allocator.heap_range().is_empty());
+    assert_eq!(allocator.heap_size(), 0);
+  }
+
+  #[test]
+  fn block_counts_and_emptiness() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    assert!(allocator.is_empty());
+    assert_eq!((allocator.live_blocks(), allocator.block_count()), (0, 0));
+
+    unsafe {
+      let layout = Layout::new::<u64>();
+      let a = allocator.allocate(layout);
+      let b = allocator.allocate(layout);
+      allocator.allocate(layout);
+      allocator.deallocate(a);
+      assert!(!allocator.is_empty());
+      assert_eq!((allocator.live_blocks(), allocator.block_count()), (2, 3));
+
+      allocator.deallocate(b);
+      let stats = allocator.stats();
+      assert_eq!(allocator.live_blocks(), stats.live_blocks);
+      assert_eq!(allocator.block_count(), stats.total_blocks());
+
+      allocator.reset();
+    }
+    assert!(allocator.is_empty());
+  }
+
+  // ═══════════════════════════════════════════════════════════════════════════
+  // Stats and Reset Tests
+  // ═══════════════════════════════════════════════════════════════════════════
+
+  #[test]
+  fn debug_prints_a_summary() {
+    let mut allocator = BumpAllocator::default();
+    allocator.set_search_mode(SearchMode::BestFit);
+    let empty = format!("{allocator:?}");
+    assert!(empty.starts_with("BumpAllocator { live_blocks: 0, free_blocks: 0"), "{empty}");
+    assert!(empty.ends_with("search_mode: BestFit, realtime: false }"), "{empty}");
+
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    unsafe {
+      let a = allocator.allocate(Layout::array::<u8>(24).unwrap());
+      allocator.allocate(Layout::array::<u8>(40).unwrap());
+      allocator.deallocate(a);
+    }
+    let text = format!("{allocator:?}");
+    let span = grow_request_size(Layout::array::<u8>(24).unwrap()).unwrap() - HEADER_SIZE;
+    assert!(text.contains(&format!("live_blocks: 1, free_blocks: 1, bytes_in_use: 40, bytes_free: {span}")), "{text}");
+    assert!(!text.contains("first"), "{text}");
+
+    // Usable inside derived Debug impls
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    struct Frame {
+      arena: BumpAllocator,
+    }
+    assert!(format!("{:?}", Frame { arena: allocator }).starts_with("Frame { arena: BumpAllocator {"));
+  }
+
+  #[test]
+  fn stats_count_live_and_free_blocks() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let empty = Stats {
+      headroom: Some(4096),
+      ..Stats::default()
+    };
+    assert_eq!(allocator.stats(), empty);
+
+    unsafe {
+      let a = allocator.allocate(Layout::array::<u8>(64).unwrap());
+      let _b = allocator.allocate(Layout::array::<u8>(32).unwrap());
+      allocator.deallocate(a);
+    }
+
+    let stats = allocator.stats();
+    assert_eq!(stats.live_blocks, 1);
+    assert_eq!(stats.free_blocks, 1);
+    assert_eq!(stats.bytes_in_use, 32);
+    // The freed block takes the slack up to the next header
+    assert_eq!(stats.bytes_free, grow_request_size(Layout::array::<u8>(64).unwrap()).unwrap() - HEADER_SIZE);
+    assert_eq!(stats.total_blocks(), 2);
+    assert!(stats.heap_bytes >= 96 + 2 * mem::size_of::<Block>());
+  }
+
+  #[test]
+  fn stats_split_overhead_into_headers_and_padding() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    assert_eq!(allocator.stats().overhead_ratio(), 0.0);
+
+    unsafe {
+      allocator.allocate(Layout::array::<u8>(64).unwrap());
+      allocator.allocate(Layout::from_size_align(1, 64).unwrap());
+    }
+
+    let stats = allocator.stats();
+    assert_eq!(stats.header_bytes, 2 * HEADER_SIZE);
+    // The 1-byte block keeps room for free links, and pads to 64
+    assert!(stats.padding_bytes >= MIN_PAYLOAD - 1);
+    assert_eq!(stats.heap_bytes, stats.bytes_in_use + stats.overhead_bytes());
+
+    let ratio = stats.overhead_ratio();
+    assert!(ratio > 0.0 && ratio < 1.0);
+    assert_eq!(ratio, stats.overhead_bytes() as f64 / stats.heap_bytes as f64);
+  }
+
+  #[test]
+  fn popping_the_tail_leaves_the_previous_payload_intact() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+
+    unsafe {
+      let a = allocator.allocate(Layout::array::<u8>(64).unwrap());
+      a.write_bytes(0xaa, 64);
+      let b = allocator.allocate(Layout::array::<u8>(64).unwrap());
+      allocator.deallocate(b);
+
+      // The next header lands after `a`, not over its last bytes
+      let c = allocator.allocate(Layout::array::<u8>(64).unwrap());
+      c.write_bytes(0, 64);
+      assert!(core::slice::from_raw_parts(a, 64).iter().all(|&byte| byte == 0xaa));
+    }
+    allocator.assert_invariants();
+  }
+
+  #[test]
+  fn popping_the_tail_releases_exactly_what_it_took() {
+    let mut allocator = BumpAllocator::with_capacity(16 * 1024);
+
+    unsafe {
+      let a = allocator.allocate(Layout::array::<u8>(40).unwrap());
+      let end_of_a = crate::align::align_word(a as usize + 40);
+      let heap_end = allocator.backend.current_break() as usize;
+      assert!(heap_end >= end_of_a);
+
+      for align in [1, 8, 64, 256, 1024] {
+        let b = allocator.allocate(Layout::from_size_align(100, align).unwrap());
+        assert!(allocator.backend.current_break() as usize > heap_end);
+        allocator.deallocate(b);
+        // Back to the end of `a`: the padding and header of `b` go too
+        assert_eq!(allocator.backend.current_break() as usize, end_of_a, "align {align}");
+      }
+
+      allocator.deallocate(a);
+      assert_eq!(allocator.backend.used_bytes(), 0);
+    }
+  }
+
+  #[test]
+  fn freed_block_absorbs_the_padding_before_its_successor() {
+    let mut allocator = BumpAllocator::with_capacity(16 * 1024);
+
+    unsafe {
+      let a = allocator.allocate(Layout::new::<u8>());
+      let b = allocator.allocate(Layout::from_size_align(8, 1024).unwrap());
+      allocator.allocate(Layout::new::<u8>());
+      let padding = allocator.stats().padding_bytes;
+
+      allocator.deallocate(a);
+      // Every byte up to `b`'s header is reusable, none is padding any more
+      let span = b as usize - HEADER_SIZE - a as usize;
+      assert!(span >= MIN_PAYLOAD);
+      assert_eq!(allocator.free_blocks().next().unwrap().size, span);
+      assert_eq!(allocator.stats().bytes_free, span);
+      assert_eq!(allocator.stats().padding_bytes, padding + 1 - span);
+      allocator.assert_invariants();
+    }
+  }
+
+  #[test]
+  fn freed_neighbours_merge_into_one_block() {
+    let mut allocator = BumpAllocator::with_capacity(16 * 1024);
+    let layout = Layout::array::<u8>(64).unwrap();
+
+    unsafe {
+      let ptrs: Vec<_> = (0..5).map(|_| allocator.allocate(layout)).collect();
+      let heap = allocator.heap_size();
+
+      // Forward: 1 takes in 2, backward: 3 joins them
+      allocator.deallocate(ptrs[2]);
+      allocator.deallocate(ptrs[1]);
+      allocator.deallocate(ptrs[3]);
+      assert_eq!(allocator.block_count(), 3);
+      let merged = allocator.free_blocks().next().unwrap();
+      assert_eq!((merged.address, merged.size), (ptrs[1] as usize, ptrs[4] as usize - HEADER_SIZE - ptrs[1] as usize));
+      allocator.assert_invariants();
+
+      // The run serves a request none of its parts could
+      assert_eq!(allocator.allocate(Layout::array::<u8>(3 * 64).unwrap()), ptrs[1]);
+      assert_eq!(allocator.heap_size(), heap);
+      allocator.assert_invariants();
+    }
+  }
+
+  #[test]
+  fn small_requests_split_a_merged_run() {
+    let mut allocator = BumpAllocator::with_capacity(2 * 1024 * 1024);
+    let layout = Layout::array::<u8>(1024).unwrap();
+
+    unsafe {
+      let ptrs: Vec<_> = (0..999).map(|_| allocator.allocate(layout)).collect();
+      allocator.allocate(layout);
+      for &ptr in &ptrs {
+        allocator.deallocate(ptr);
+      }
+      assert_eq!(allocator.free_block_count(), 1);
+      let heap = allocator.heap_size();
+
+      assert_eq!(allocator.allocate(Layout::new::<u64>()), ptrs[0]);
+      assert_eq!(allocator.free_block_count(), 1);
+      let next = allocator.allocate(layout);
+      assert!(next > ptrs[0] && next < ptrs[998]);
+      assert_eq!(allocator.heap_size(), heap);
+      allocator.assert_invariants();
+    }
+  }
+
+  #[test]
+  fn freeing_next_to_a_free_tail_merges_into_the_tail() {
+    let mut allocator = BumpAllocator::with_capacity(16 * 1024);
+    let layout = Layout::array::<u8>(64).unwrap();
+
+    unsafe {
+      let ptrs: Vec<_> = (0..4).map(|_| allocator.allocate(layout)).collect();
+      allocator.deallocate(ptrs[2]);
+      // Popping 3 leaves 2 as a free tail, with the padding it absorbed
+      allocator.deallocate(ptrs[3]);
+      let tail = (*allocator.last_block()).size;
+      allocator.deallocate(ptrs[1]);
+
+      assert_eq!(allocator.block_count(), 2);
+      assert_eq!(allocator.last_block(), allocator.find_block(ptrs[1]));
+      assert_eq!(allocator.free_blocks().next().unwrap().size, ptrs[2] as usize + tail - ptrs[1] as usize);
+      allocator.assert_invariants();
+    }
+  }
+
+  #[test]
+  fn blocks_never_grow_into_a_new_span() {
+    let mut allocator = BumpAllocator::with_capacity(16 * 1024);
+    let layout = Layout::array::<u8>(40).unwrap();
+
+    unsafe {
+      let ptrs: Vec<_> = (0..4).map(|_| allocator.allocate(layout)).collect();
+      // As if a foreign `sbrk` had left memory between 1 and 2
+      (*allocator.find_block(ptrs[2])).flags |= SPAN_START;
+
+      allocator.deallocate(ptrs[1]);
+      assert_eq!((*allocator.find_block(ptrs[1])).size, 40);
+      allocator.deallocate(ptrs[2]);
+      assert_eq!(allocator.block_count(), 4);
+      assert_eq!((*allocator.find_block(ptrs[1])).size, 40);
+
+      // Freeing 0 still merges with 1, on its side of the boundary
+      allocator.deallocate(ptrs[0]);
+      assert_eq!(allocator.block_count(), 3);
+      assert_eq!(allocator.free_blocks().next().unwrap().size, ptrs[1] as usize + 40 - ptrs[0] as usize);
+      allocator.assert_invariants();
+    }
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn popping_the_tail_moves_the_break_exactly() {
+    let mut allocator = BumpAllocator::new();
+
+    unsafe {
+      let a = allocator.allocate(Layout::array::<u8>(40).unwrap());
+      let end_of_a = crate::align::align_word(a as usize + 40);
+      let b = allocator.allocate(Layout::from_size_align(4096, 256).unwrap());
+      allocator.deallocate(b);
+
+      assert_eq!(allocator.backend.current_break() as usize, end_of_a);
+      // `a` is live, so no one can have taken the break below it
+      assert!(sbrk(0) as usize >= end_of_a);
+      allocator.deallocate(a);
+    }
+  }
+
+  #[test]
+  fn reset_empties_list_and_rewinds_region() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    assert_eq!(allocator.capacity(), Some(4096));
+
+    unsafe {
+      let first = allocator.allocate(Layout::new::<u64>());
+      allocator.allocate(Layout::array::<u8>(100).unwrap());
+
+      allocator.reset();
+      let empty = Stats {
+        headroom: Some(4096),
+        ..Stats::default()
+      };
+      assert_eq!(allocator.stats(), empty);
+
+      // The region is handed out again from the start
+      assert_eq!(allocator.allocate(Layout::new::<u64>()), first);
+    }
+  }
+
+  // ═══════════════════════════════════════════════════════════════════════════
+  // Free Block Reuse Tests
+  // ═══════════════════════════════════════════════════════════════════════════
+
+  /// An allocator in `mode` with freed blocks of `sizes`, each followed
+  /// by a live block so they do not merge.
+  fn freed_blocks(
+    mode: SearchMode,
+    sizes: &[usize],
+  ) -> (BumpAllocator, Vec<*mut u8>) {
+    let mut allocator = BumpAllocator::with_capacity(16 * 1024);
+    allocator.set_search_mode(mode);
+    unsafe {
+      let ptrs: Vec<_> = sizes
+        .iter()
+        .map(|&size| {
+          let ptr = allocator.allocate(Layout::array::<u8>(size).unwrap());
+          allocator.allocate(Layout::new::<u64>());
+          ptr
+        })
+        .collect();
+      for &ptr in &ptrs {
+        allocator.deallocate(ptr);
+      }
+      (allocator, ptrs)
+    }
+  }
+
+  #[test]
+  fn first_fit_allocations_reuse_the_lowest_fitting_block() {
+    let (mut allocator, ptrs) = freed_blocks(SearchMode::FirstFit, &[256, 128]);
+    let heap = allocator.heap_size();
+
+    unsafe {
+      assert_eq!(allocator.allocate(Layout::array::<u8>(100).unwrap()), ptrs[0]);
+      // The rest of the first block was split off, and is lowest now
+      let rest = crate::align::align_word(ptrs[0] as usize + 100) + HEADER_SIZE;
+      assert_eq!(allocator.allocate(Layout::array::<u8>(100).unwrap()) as usize, rest);
+      assert_eq!(allocator.allocate(Layout::array::<u8>(100).unwrap()), ptrs[1]);
+    }
+    assert_eq!(allocator.heap_size(), heap);
+    assert_eq!(allocator.free_block_count(), 0);
+    allocator.assert_invariants();
+  }
+
+  #[test]
+  fn best_fit_allocations_reuse_the_smallest_fitting_block() {
+    let (mut allocator, ptrs) = freed_blocks(SearchMode::BestFit, &[256, 128]);
+
+    unsafe {
+      let ptr = allocator.allocate(Layout::array::<u8>(100).unwrap());
+      assert_eq!(ptr, ptrs[1]);
+      // The block records the new request, not its old span
+      assert_eq!((*allocator.find_block(ptr)).size, 100);
+    }
+    allocator.assert_invariants();
+  }
+
+  #[test]
+  fn next_fit_allocations_resume_after_the_last_reuse() {
+    let (mut allocator, ptrs) = freed_blocks(SearchMode::NextFit, &[128, 128, 128]);
+    let layout = Layout::array::<u8>(100).unwrap();
+
+    unsafe {
+      assert_eq!(allocator.allocate(layout), ptrs[0]);
+      assert_eq!(allocator.allocate(layout), ptrs[1]);
+      allocator.deallocate(ptrs[0]);
+      // First fit would go back to the start
+      assert_eq!(allocator.allocate(layout), ptrs[2]);
+      assert_eq!(allocator.allocate(layout), ptrs[0]);
+    }
+    allocator.assert_invariants();
+  }
+
+  #[test]
+  fn alloc_free_loops_stop_growing_the_heap() {
+    let mut allocator = BumpAllocator::with_capacity(16 * 1024);
+    let layout = Layout::array::<u8>(48).unwrap();
+
+    unsafe {
+      let mut kept = allocator.allocate(layout);
+      allocator.allocate(layout);
+      let heap = allocator.heap_size();
+      for _ in 0..1000 {
+        let fresh = allocator.allocate(layout);
+        allocator.deallocate(kept);
+        kept = fresh;
+      }
+      // At most one block more than the two the loop started with
+      assert!(allocator.heap_size() <= heap + grow_request_size(layout).unwrap());
+    }
+    allocator.assert_invariants();
+  }
+
+  #[test]
+  fn misaligned_or_small_blocks_are_passed_over() {
+    let (mut allocator, ptrs) = freed_blocks(SearchMode::FirstFit, &[64]);
+
+    unsafe {
+      let aligned = Layout::from_size_align(16, 4096).unwrap();
+      let ptr = allocator.allocate(aligned);
+      assert!(ptr != ptrs[0] && is_ptr_aligned(ptr, 4096));
+      assert_ne!(allocator.allocate(Layout::array::<u8>(512).unwrap()), ptrs[0]);
+      assert_eq!(allocator.free_block_count(), 1);
+
+      // Real-time mode never searches
+      allocator.set_realtime(true);
+      assert_ne!(allocator.allocate(Layout::array::<u8>(8).unwrap()), ptrs[0]);
+    }
+  }
+
+  // ═══════════════════════════════════════════════════════════════════════════
+  // OOM Handler and Panic Safety Tests
+  // ═══════════════════════════════════════════════════════════════════════════
+
+  #[test]
+  fn oom_handler_can_make_room_and_retry() {
+    fn reset_and_retry(
+      allocator: &mut BumpAllocator,
+      _layout: Layout,
+    ) -> bool {
+      unsafe { allocator.reset() };
+      true
+    }
+
+    let mut allocator = BumpAllocator::with_capacity(256);
+    allocator.set_oom_handler(Some(reset_and_retry));
+
+    unsafe {
+      allocator.allocate(Layout::array::<u8>(150).unwrap());
+      let ptr = allocator.allocate(Layout::array::<u8>(150).unwrap());
+      assert!(!ptr.is_null());
+    }
+    assert_eq!(allocator.stats().live_blocks, 1);
+  }
+
+  #[test]
+  fn declining_oom_handler_returns_null() {
+    let mut allocator = BumpAllocator::with_capacity(128);
+    allocator.set_oom_handler(Some(|_, _| false));
+    assert!(unsafe { allocator.allocate(Layout::array::<u8>(512).unwrap()) }.is_null());
+  }
+
+  #[test]
+  fn panicking_oom_handler_leaves_allocator_consistent() {
+    let mut allocator = BumpAllocator::with_capacity(256);
+    allocator.set_oom_handler(Some(|_, layout| panic!("out of memory for {layout:?}")));
+
+    let kept = unsafe { allocator.allocate(Layout::array::<u8>(64).unwrap()) };
+    let before = allocator.stats();
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| unsafe {
+      allocator.allocate(Layout::array::<u8>(1024).unwrap())
+    }));
+    assert!(result.is_err());
+    assert!(!allocator.in_handler);
+
+    // Nothing was linked, and the allocator keeps working
+    assert_eq!(allocator.stats(), before);
+    assert_eq!(allocator.check_invariants(), Ok(()));
+    unsafe {
+      assert!(!allocator.allocate(Layout::new::<u64>()).is_null());
+      allocator.deallocate(kept);
+    }
+    assert_eq!(allocator.check_invariants(), Ok(()));
+  }
+
+  #[test]
+  fn panicking_handler_that_mutated_the_list_still_leaves_it_consistent() {
+    let mut allocator = BumpAllocator::with_capacity(256);
+    allocator.set_oom_handler(Some(|allocator, _| {
+      // A half-finished cleanup: the handler changes the list, then panics
+      unsafe { allocator.allocate(Layout::new::<u64>()) };
+      panic!("cleanup failed");
+    }));
+
+    unsafe { allocator.allocate(Layout::array::<u8>(64).unwrap()) };
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| unsafe {
+      allocator.allocate(Layout::array::<u8>(1024).unwrap())
+    }));
+    assert!(result.is_err());
+    assert_eq!(allocator.check_invariants(), Ok(()));
+    assert_eq!(allocator.stats().live_blocks, 2);
+  }
+
+  // ═══════════════════════════════════════════════════════════════════════════
+  // Deallocate With Layout Tests
+  // ═══════════════════════════════════════════════════════════════════════════
+
+  #[test]
+  fn deallocate_with_matching_layout() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let layout = Layout::from_size_align(24, 64).unwrap();
+
+    unsafe {
+      let a = allocator.allocate(layout);
+      let b = allocator.allocate(Layout::new::<u8>());
+      allocator.deallocate_with_layout(a, layout);
+      allocator.deallocate_with_layout(b, Layout::new::<u8>());
+      allocator.deallocate_with_layout(ptr::null_mut(), layout);
+    }
+    assert_eq!(allocator.stats().live_blocks, 0);
+  }
+
+  #[test]
+  #[cfg(debug_assertions)]
+  #[should_panic(expected = "was allocated with 16 bytes, not 8")]
+  fn deallocate_with_wrong_size_panics_in_debug() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    unsafe {
+      let ptr = allocator.allocate(Layout::new::<u128>());
+      allocator.deallocate_with_layout(ptr, Layout::new::<u64>());
+    }
+  }
+
+  #[test]
+  #[cfg(debug_assertions)]
+  #[should_panic(expected = "is not aligned to 4096")]
+  fn deallocate_with_wrong_alignment_panics_in_debug() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    unsafe {
+      // Two blocks less than a page apart: at most one is page-aligned
+      let a = allocator.allocate(Layout::new::<u64>());
+      let b = allocator.allocate(Layout::new::<u64>());
+      let ptr = if (a as usize).is_multiple_of(4096) { b } else { a };
+      allocator.deallocate_with_layout(ptr, Layout::from_size_align(8, 4096).unwrap());
+    }
+  }
 }
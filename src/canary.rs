@@ -0,0 +1,268 @@
+//! # Overflow Canaries
+//!
+//! A canary is a known word written right after a block's payload. A write
+//! past the end of the payload changes it, and the allocator notices when
+//! the block is freed (or on [`check_invariants`](BumpAllocator::check_invariants)):
+//!
+//! ```text
+//!   ┌────────┬──────────────────────┬────────┬─────────┐
+//!   │ header │ payload (size bytes) │ canary │ padding │
+//!   └────────┴──────────────────────┴────────┴─────────┘
+//!                                   ▲
+//!                                   one known word - overwritten? panic
+//! ```
+//!
+//! A word on every block is affordable in tests but not always in
+//! production, so a [`CanaryPolicy`] *samples*: it protects one allocation
+//! in `one_in`, every allocation of `min_size` bytes or more, or both.
+//! Sampling is counted, not random, so a run protects the same blocks every
+//! time, and large buffers - where overflows are most often found - can be
+//! covered on their own:
+//!
+//! ```text
+//!   CanaryPolicy { one_in: 16, min_size: Some(4096) }
+//!
+//!   allocations  1  2 .. 16 17 .. 32   8 KiB  ...
+//!   canary       ✓  ·     ·  ✓     ·    ✓
+//! ```
+//!
+//! The canary word counts towards
+//! [`Stats::padding_bytes`](crate::Stats::padding_bytes). Blocks served by
+//! the emergency reserve never get one.
+
+use core::{alloc::Layout, mem, ptr};
+
+use crate::{
+  BumpAllocator, Corruption,
+  block::{Block, CANARY as CANARY_FLAG, HEADER_SIZE},
+};
+
+/// Size of the canary word after a protected payload.
+pub(crate) const CANARY_SIZE: usize = mem::size_of::<usize>();
+
+/// The canary word. Fixed rather than address-dependent, so blocks moved
+/// by a fork or a snapshot import keep valid canaries.
+const CANARY: usize = 0xC0FF_EE00_DEAD_BEEF_u64 as usize;
+
+/// Which allocations get a canary, see the `canary` module.
+///
+/// ```rust,ignore
+/// // Every allocation in tests, a sample plus all large buffers in production
+/// allocator.set_canary_policy(if cfg!(test) {
+///     CanaryPolicy::ALL
+/// } else {
+///     CanaryPolicy { one_in: 64, min_size: Some(4096) }
+/// });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CanaryPolicy {
+  /// Protects one allocation in this many: `1` protects all of them, `0`
+  /// none (apart from those `min_size` selects).
+  pub one_in: usize,
+
+  /// Also protects every allocation of at least this many bytes, if set.
+  pub min_size: Option<usize>,
+}
+
+impl CanaryPolicy {
+  /// No canaries, the default.
+  pub const OFF: Self = Self {
+    one_in: 0,
+    min_size: None,
+  };
+
+  /// A canary on every allocation.
+  pub const ALL: Self = Self {
+    one_in: 1,
+    min_size: None,
+  };
+
+  /// A canary on one allocation in `n`.
+  pub const fn sample(n: usize) -> Self {
+    Self {
+      one_in: n,
+      min_size: None,
+    }
+  }
+
+  /// A canary on every allocation of at least `bytes`.
+  pub const fn above(bytes: usize) -> Self {
+    Self {
+      one_in: 0,
+      min_size: Some(bytes),
+    }
+  }
+
+  /// Whether the policy protects anything at all.
+  pub const fn is_off(&self) -> bool {
+    self.one_in == 0 && self.min_size.is_none()
+  }
+}
+
+impl Default for CanaryPolicy {
+  fn default() -> Self {
+    Self::OFF
+  }
+}
+
+impl BumpAllocator {
+  /// Sets which allocations get an overflow canary. Applies to blocks
+  /// allocated from now on; existing canaries keep being checked.
+  pub fn set_canary_policy(
+    &mut self,
+    policy: CanaryPolicy,
+  ) {
+    self.canary_policy = policy;
+    self.canary_ticks = 0;
+  }
+
+  /// The policy set by [`set_canary_policy`](Self::set_canary_policy).
+  pub fn canary_policy(&self) -> CanaryPolicy {
+    self.canary_policy
+  }
+
+  /// Whether the next block, for `layout`, gets a canary. Counts the
+  /// allocation towards the `one_in` sample.
+  #[inline]
+  pub(crate) fn wants_canary(
+    &mut self,
+    layout: Layout,
+  ) -> bool {
+    let policy = self.canary_policy;
+    if policy.is_off() {
+      return false;
+    }
+    if policy.min_size.is_some_and(|min| layout.size() >= min) {
+      return true;
+    }
+    if policy.one_in == 0 {
+      return false;
+    }
+    let sampled = self.canary_ticks == 0;
+    self.canary_ticks = (self.canary_ticks + 1) % policy.one_in;
+    sampled
+  }
+
+  /// Panics with [`Corruption::CanaryOverwritten`] if `block` has a canary
+  /// and it changed, and drops the flag otherwise: the block is being
+  /// freed and its payload may hold free list links from now on.
+  ///
+  /// # Safety
+  ///
+  /// `block` must be a live block of this allocator.
+  pub(crate) unsafe fn retire_canary(
+    &self,
+    block: *mut Block,
+  ) {
+    unsafe {
+      if !canary_intact(block) {
+        self.report_corruption(Corruption::CanaryOverwritten {
+          address: block as usize + HEADER_SIZE,
+        });
+      }
+      (*block).flags &= !CANARY_FLAG;
+    }
+  }
+}
+
+/// Places the canary after the payload of `block` and flags it.
+///
+/// # Safety
+///
+/// `block` must be a live block with [`CANARY_SIZE`] bytes of room after
+/// its payload.
+pub(crate) unsafe fn write_canary(block: *mut Block) {
+  unsafe {
+    (*block).flags |= CANARY_FLAG;
+    ptr::write_unaligned(canary_slot(block), CANARY);
+  }
+}
+
+/// Whether `block` has no canary, or an unchanged one.
+///
+/// # Safety
+///
+/// `block` must be a valid header whose flagged canary lies in the heap.
+pub(crate) unsafe fn canary_intact(block: *const Block) -> bool {
+  unsafe { !(*block).has_canary() || ptr::read_unaligned(canary_slot(block)) == CANARY }
+}
+
+/// Address of the canary word of `block`, right after its payload.
+unsafe fn canary_slot(block: *const Block) -> *mut usize {
+  unsafe { (block as *mut u8).add(HEADER_SIZE + (*block).size) as *mut usize }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const WORD: Layout = Layout::new::<u64>();
+
+  #[test]
+  fn sampling_protects_one_in_n() {
+    let mut allocator = BumpAllocator::with_capacity(16 * 1024);
+    allocator.set_canary_policy(CanaryPolicy::sample(4));
+
+    let protected: Vec<_> = (0..12).map(|_| allocator.wants_canary(WORD)).collect();
+    let expected: Vec<_> = (0..12).map(|index| index % 4 == 0).collect();
+    assert_eq!(protected, expected);
+  }
+
+  #[test]
+  fn large_allocations_are_always_protected() {
+    let mut allocator = BumpAllocator::with_capacity(16 * 1024);
+    allocator.set_canary_policy(CanaryPolicy::above(256));
+    assert_eq!(allocator.canary_policy(), CanaryPolicy::above(256));
+
+    assert!(!allocator.wants_canary(WORD));
+    assert!(allocator.wants_canary(Layout::array::<u8>(256).unwrap()));
+    assert!(CanaryPolicy::default().is_off());
+  }
+
+  #[test]
+  fn intact_canaries_pass_silently() {
+    let mut allocator = BumpAllocator::with_capacity(16 * 1024);
+    allocator.set_canary_policy(CanaryPolicy::ALL);
+
+    unsafe {
+      let ptrs: Vec<_> = (1..=4).map(|i| allocator.allocate(Layout::array::<u8>(i * 5).unwrap())).collect();
+      for (i, &ptr) in ptrs.iter().enumerate() {
+        ptr.write_bytes(0xAA, (i + 1) * 5);
+      }
+      allocator.assert_invariants();
+      for &ptr in ptrs.iter().rev() {
+        allocator.deallocate(ptr);
+      }
+    }
+    assert_eq!(allocator.stats().total_blocks(), 0);
+  }
+
+  #[test]
+  fn overflow_is_reported_by_the_invariants() {
+    let mut allocator = BumpAllocator::with_capacity(16 * 1024);
+    allocator.set_canary_policy(CanaryPolicy::ALL);
+
+    unsafe {
+      let ptr = allocator.allocate(Layout::array::<u8>(10).unwrap());
+      allocator.allocate(WORD);
+      ptr.add(10).write(0);
+      assert_eq!(
+        allocator.check_invariants(),
+        Err(Corruption::CanaryOverwritten { address: ptr as usize })
+      );
+    }
+  }
+
+  #[test]
+  #[should_panic(expected = "canary")]
+  fn overflow_is_caught_on_free() {
+    let mut allocator = BumpAllocator::with_capacity(16 * 1024);
+    allocator.set_canary_policy(CanaryPolicy::above(16));
+
+    unsafe {
+      let ptr = allocator.allocate(Layout::array::<u8>(16).unwrap());
+      ptr.write_bytes(0x41, 17);
+      allocator.deallocate(ptr);
+    }
+  }
+}
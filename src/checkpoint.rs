@@ -0,0 +1,278 @@
+//! # Checkpoints
+//!
+//! [`BumpAllocator::checkpoint`] marks the newest block;
+//! [`BumpAllocator::shrink_to`] later drops every block allocated after it
+//! in one pass - the primitive under scopes and per-frame arenas:
+//!
+//! ```text
+//!   mark = checkpoint()
+//!        │
+//!   [A] [B] │ [C] [D,free] [E]        ◄── frame allocations
+//!        ▼
+//!   shrink_to(mark)
+//!   [A] [B]                           ◄── C, D, E gone, one break move
+//! ```
+//!
+//! Live and free blocks after the mark alike leave the list; free ones
+//! leave the free list (and the size index, and the NextFit cursor) in O(1)
+//! each. The memory goes back to the backend with a single shrink, subject
+//! to the [`ShrinkPolicy`](crate::ShrinkPolicy).
+//!
+//! Compared with [`reset`](BumpAllocator::reset), blocks older than the
+//! mark survive; compared with freeing each block, nothing is searched and
+//! the break moves once.
+//!
+//! Free blocks below the newest checkpoint are not reused: an allocation
+//! after the mark that landed in one would sit outside the dropped suffix
+//! and outlive the rollback. Allocation takes the first fit above the mark
+//! instead, or grows the heap:
+//!
+//! ```text
+//!   [A,free] [B] │ [C] [D,free]
+//!   allocate ──────────► D, never A
+//! ```
+//!
+//! The floor drops with `shrink_to` below it and clears on `reset`.
+
+use core::ptr;
+
+use crate::{
+  BumpAllocator,
+  block::{Block, HEADER_SIZE},
+  reserve::EmergencyReserve,
+};
+#[cfg(feature = "flight-recorder")]
+use crate::recorder::OpKind;
+
+impl BumpAllocator {
+  /// A mark for [`shrink_to`](Self::shrink_to): the newest block's
+  /// payload, or null when the allocator is empty. Free blocks up to the
+  /// mark are not reused from now on, see the module docs.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// let frame = allocator.checkpoint();
+  /// let scratch = unsafe { allocator.allocate(layout) };
+  /// // ...
+  /// unsafe { allocator.shrink_to(frame) };   // scratch is gone
+  /// ```
+  pub fn checkpoint(&mut self) -> *mut u8 {
+    self.reuse_floor = self.last;
+    if self.last.is_null() {
+      ptr::null_mut()
+    } else {
+      self.last.wrapping_byte_add(HEADER_SIZE) as *mut u8
+    }
+  }
+
+  /// Frees every block allocated after `mark`, a pointer returned by
+  /// [`checkpoint`](Self::checkpoint) or [`allocate`](Self::allocate); the
+  /// block at `mark` itself is kept. A null `mark` frees every block.
+  ///
+  /// An emergency reserve armed after the mark is discarded with its block,
+  /// as by [`reset`](Self::reset). In real-time mode the free list is
+  /// rebuilt afterwards, which walks the remaining blocks.
+  ///
+  /// # Safety
+  ///
+  /// `mark` must be null or a live block of this allocator, and no pointer
+  /// into a dropped block may be used again.
+  pub unsafe fn shrink_to(
+    &mut self,
+    mark: *mut u8,
+  ) {
+    #[cfg(feature = "thread-check")]
+    self.check_owner();
+
+    let keep: *mut Block = if mark.is_null() {
+      ptr::null_mut()
+    } else {
+      mark.wrapping_sub(HEADER_SIZE) as *mut Block
+    };
+    let reserve = self.reserve.arena().map_or(0, |arena| arena as *const BumpAllocator as usize);
+
+    // SAFETY: `keep` is null or a live block, so everything after it is a
+    // valid suffix of the block list.
+    unsafe {
+      let mut current = if keep.is_null() { self.first } else { (*keep).next };
+      let mut released = 0usize;
+      let mut relink = false;
+
+      while !current.is_null() {
+        let next = (*current).next;
+        if (*current).is_free {
+          // Real-time frees are not linked, so they cannot be unlinked
+          if self.realtime {
+            relink = true;
+          } else {
+            self.unlink_free(current);
+          }
+          #[cfg(feature = "shadow")]
+          self.shadow_drop_free(current);
+        } else {
+          #[cfg(feature = "shadow")]
+          self.shadow_free(current, true);
+          self.retire_canary(current);
+          released += (*current).size;
+          if current as usize + HEADER_SIZE == reserve {
+            self.reserve = EmergencyReserve::none();
+          }
+          #[cfg(feature = "counters")]
+          self.count_deallocate((*current).size);
+        }
+        self.tracked_blocks -= 1;
+        current = next;
+      }
+
+      // Untracked bumps past the block cap are newer than any mark
+      self.untracked = ptr::null_mut();
+      #[cfg(feature = "std")]
+      {
+        let cut = if mark.is_null() { 0 } else { mark as usize + 1 };
+        self.forget_types_from(cut);
+        self.forget_refs_from(cut);
+        self.forget_attributions_from(cut);
+      }
+      self.last = keep;
+      self.reuse_floor = self.reuse_floor.min(keep);
+      if keep.is_null() {
+        self.first = ptr::null_mut();
+      } else {
+        (*keep).next = ptr::null_mut();
+      }
+      if relink {
+        self.rebuild_free_list();
+      }
+      self.release_past_tail();
+
+      #[cfg(feature = "flight-recorder")]
+      self.record(OpKind::ShrinkTo, mark as usize, released, true);
+      #[cfg(not(feature = "flight-recorder"))]
+      let _ = released;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SearchMode;
+  use core::alloc::Layout;
+
+  const WORD: Layout = Layout::new::<u64>();
+
+  #[test]
+  fn blocks_after_the_mark_are_dropped() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+
+    unsafe {
+      let a = allocator.allocate(WORD);
+      let mark = allocator.checkpoint();
+      assert_eq!(mark, a);
+      let heap = allocator.stats().heap_bytes;
+
+      for _ in 0..4 {
+        allocator.allocate(Layout::array::<u8>(100).unwrap());
+      }
+      allocator.shrink_to(mark);
+
+      assert_eq!(allocator.stats().live_blocks, 1);
+      // Back to where the mark was, less any padding past its payload
+      assert!(allocator.stats().heap_bytes <= heap);
+      assert_eq!(allocator.checkpoint(), a);
+      allocator.assert_invariants();
+
+      // The next allocation lands right where the frame began
+      let b = allocator.allocate(Layout::array::<u8>(100).unwrap());
+      assert!(b > a && (b as usize) < a as usize + 128);
+    }
+  }
+
+  #[test]
+  fn free_blocks_after_the_mark_leave_the_free_list() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_search_mode(SearchMode::BestFitIndexed);
+
+    unsafe {
+      let a = allocator.allocate(WORD);
+      allocator.allocate(WORD);
+      allocator.deallocate(a);
+      let mark = allocator.checkpoint();
+
+      let ptrs: Vec<_> = (0..4).map(|_| allocator.allocate(WORD)).collect();
+      allocator.deallocate(ptrs[0]);
+      allocator.deallocate(ptrs[2]);
+      assert_eq!(allocator.free_block_count(), 3);
+
+      allocator.shrink_to(mark);
+      let free: Vec<_> = allocator.free_blocks().map(|block| block.address).collect();
+      assert_eq!(free, [a as usize]);
+      allocator.assert_invariants();
+    }
+  }
+
+  #[test]
+  fn allocations_after_the_mark_never_reuse_blocks_below_it() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+
+    unsafe {
+      let a = allocator.allocate(WORD);
+      allocator.allocate(WORD);
+      allocator.deallocate(a);
+      let mark = allocator.checkpoint();
+
+      let b = allocator.allocate(WORD);
+      assert_ne!(b, a);
+      // Freed after the mark, so reusable
+      let c = allocator.allocate(WORD);
+      allocator.allocate(WORD);
+      allocator.deallocate(c);
+      assert_eq!(allocator.allocate(WORD), c);
+
+      allocator.shrink_to(mark);
+      assert_eq!(allocator.stats().live_blocks, 1);
+      allocator.assert_invariants();
+
+      // A null mark clears the floor
+      allocator.shrink_to(ptr::null_mut());
+      assert!(allocator.reuse_floor.is_null());
+    }
+  }
+
+  #[test]
+  fn null_mark_frees_everything() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let empty = allocator.checkpoint();
+    assert!(empty.is_null());
+
+    unsafe {
+      allocator.allocate(WORD);
+      allocator.set_emergency_reserve(256);
+      allocator.shrink_to(empty);
+    }
+    assert_eq!(allocator.stats().total_blocks(), 0);
+    assert_eq!(allocator.stats().heap_bytes, 0);
+    assert_eq!(allocator.emergency_reserve_remaining(), 0);
+  }
+
+  #[test]
+  fn realtime_frees_after_the_mark_are_dropped() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_realtime(true);
+
+    unsafe {
+      let a = allocator.allocate(WORD);
+      let b = allocator.allocate(WORD);
+      allocator.deallocate(a);
+      let ptrs: Vec<_> = (0..3).map(|_| allocator.allocate(WORD)).collect();
+      allocator.deallocate(ptrs[1]);
+
+      allocator.shrink_to(b);
+    }
+    allocator.set_realtime(false);
+    assert_eq!(allocator.free_block_count(), 1);
+    assert_eq!(allocator.stats().live_blocks, 1);
+    allocator.assert_invariants();
+  }
+}
@@ -0,0 +1,171 @@
+//! # Clock Sources
+//!
+//! Timing features - the [`Sampler`](crate::Sampler) time series, the
+//! [`RateLimit`](crate::RateLimit) windows - read time through a
+//! [`Clock`]. Three sources cover the usual cases:
+//!
+//! ```text
+//!   MonotonicClock      std::time::Instant, hosted targets (`std`)
+//!   TickClock           a user tick counter: SysTick, DWT cycles, a timer
+//!   ManualClock         set by hand, for deterministic tests
+//! ```
+//!
+//! Any `fn() -> Duration` is a clock too, which is what [`RateLimit`]
+//! stores so it stays `Copy` and `const`-constructible.
+//!
+//! [`RateLimit`]: crate::RateLimit
+
+use core::{cell::Cell, time::Duration};
+
+/// A monotonic time source.
+///
+/// `now` must never go backwards; its zero point is up to the clock.
+pub trait Clock {
+  /// Time since the clock's zero point.
+  fn now(&self) -> Duration;
+}
+
+impl Clock for fn() -> Duration {
+  fn now(&self) -> Duration {
+    self()
+  }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+  fn now(&self) -> Duration {
+    (**self).now()
+  }
+}
+
+/// Time since the first call, from [`std::time::Instant`].
+#[cfg(feature = "std")]
+pub fn monotonic_clock() -> Duration {
+  static EPOCH: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+  EPOCH.get_or_init(std::time::Instant::now).elapsed()
+}
+
+/// [`monotonic_clock`] as a [`Clock`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MonotonicClock;
+
+#[cfg(feature = "std")]
+impl Clock for MonotonicClock {
+  fn now(&self) -> Duration {
+    monotonic_clock()
+  }
+}
+
+/// A clock over a free-running tick counter, for targets without `std`.
+///
+/// ```rust,ignore
+/// fn systick() -> u64 { TICKS.load(Ordering::Relaxed) }
+///
+/// let clock = TickClock::new(systick, 1_000); // 1 kHz SysTick
+/// sampler.sample_with(&clock, &allocator);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct TickClock {
+  /// Reads the counter.
+  pub ticks: fn() -> u64,
+
+  /// Counter frequency in Hz.
+  pub ticks_per_second: u64,
+}
+
+impl TickClock {
+  /// A clock reading `ticks`, which counts `ticks_per_second` per second.
+  pub const fn new(
+    ticks: fn() -> u64,
+    ticks_per_second: u64,
+  ) -> Self {
+    assert!(ticks_per_second > 0, "tick frequency must not be zero");
+    Self { ticks, ticks_per_second }
+  }
+}
+
+impl Clock for TickClock {
+  fn now(&self) -> Duration {
+    let ticks = (self.ticks)();
+    let hz = self.ticks_per_second;
+    // The remainder is below `hz`, so the product fits a u128
+    let nanos = (ticks % hz) as u128 * 1_000_000_000 / hz as u128;
+    Duration::new(ticks / hz, nanos as u32)
+  }
+}
+
+/// A clock that moves only when told to.
+///
+/// ```rust,ignore
+/// let clock = ManualClock::new();
+/// sampler.sample_with(&clock, &allocator);   // t = 0
+/// clock.advance(Duration::from_millis(5));
+/// sampler.sample_with(&clock, &allocator);   // t = 5000 µs
+/// ```
+#[derive(Debug, Default)]
+pub struct ManualClock {
+  /// The current time.
+  now: Cell<Duration>,
+}
+
+impl ManualClock {
+  /// A clock at zero.
+  pub const fn new() -> Self {
+    Self {
+      now: Cell::new(Duration::ZERO),
+    }
+  }
+
+  /// Moves the clock forward by `step`.
+  pub fn advance(
+    &self,
+    step: Duration,
+  ) {
+    self.now.set(self.now.get() + step);
+  }
+}
+
+impl Clock for ManualClock {
+  fn now(&self) -> Duration {
+    self.now.get()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn tick_clocks_convert_to_durations() {
+    fn ticks() -> u64 {
+      2_500
+    }
+    assert_eq!(TickClock::new(ticks, 1_000).now(), Duration::from_millis(2_500));
+    assert_eq!(TickClock::new(ticks, 3).now(), Duration::new(833, 333_333_333));
+  }
+
+  #[test]
+  fn manual_clocks_move_only_when_advanced() {
+    let clock = ManualClock::new();
+    assert_eq!(clock.now(), Duration::ZERO);
+    clock.advance(Duration::from_micros(7));
+    clock.advance(Duration::from_micros(3));
+    assert_eq!(clock.now(), Duration::from_micros(10));
+  }
+
+  #[test]
+  fn function_pointers_are_clocks() {
+    fn fixed() -> Duration {
+      Duration::from_secs(4)
+    }
+    let clock: fn() -> Duration = fixed;
+    assert_eq!(clock.now(), Duration::from_secs(4));
+  }
+
+  #[test]
+  #[cfg(feature = "std")]
+  fn monotonic_clock_does_not_go_backwards() {
+    let first = MonotonicClock.now();
+    assert!(monotonic_clock() >= first);
+  }
+}
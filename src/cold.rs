@@ -0,0 +1,215 @@
+//! # Hot/Cold Segregation
+//!
+//! A long-lived cache touches a small share of its entries most of the
+//! time. Mixed in one heap, the rarely used ones keep every page resident.
+//! With a *cold chunk* - a second region set aside with
+//! [`set_cold_chunk`](BumpAllocator::set_cold_chunk) - cold data goes to
+//! memory of its own, which the OS can page out, compress or `madvise`
+//! as a unit, while the main heap stays dense with hot blocks:
+//!
+//! ```text
+//!   main heap (hot)                     cold chunk
+//!   [A][B][ ─ ][D][E]                   [c1][c2][c3]          ──► madvise(
+//!         ▲                               ▲                        cold_range(),
+//!         └── mark_cold(C): copied ───────┘ (C's old block freed)  MADV_COLD)
+//!
+//!   allocate_cold(layout)  ──► straight into the cold chunk
+//! ```
+//!
+//! The allocator cannot observe accesses, so recency comes from the
+//! program: [`mark_cold`](BumpAllocator::mark_cold) moves a block it no
+//! longer expects to use into the cold chunk and returns the new address,
+//! and [`allocate_cold`](BumpAllocator::allocate_cold) places data known
+//! to be cold from the start. Cold pointers are freed with the usual
+//! [`deallocate`](BumpAllocator::deallocate).
+//!
+//! The chunk is a nested allocator over its own region from the system
+//! allocator, so the feature needs `std`. `reset` empties it, and the
+//! main heap's statistics and diagnostics leave it out: see
+//! [`cold_stats`](BumpAllocator::cold_stats).
+
+use core::{alloc::Layout, ops::Range, ptr};
+use std::boxed::Box;
+
+use crate::{BumpAllocator, Stats};
+
+/// Largest alignment [`mark_cold`](BumpAllocator::mark_cold) preserves.
+const MAX_COLD_ALIGN: usize = 4096;
+
+impl BumpAllocator {
+  /// Sets aside a cold chunk of `capacity` bytes, replacing an empty one.
+  /// Returns `false` if the current chunk still holds live blocks.
+  pub fn set_cold_chunk(
+    &mut self,
+    capacity: usize,
+  ) -> bool {
+    if self.cold.as_ref().is_some_and(|cold| cold.live_blocks() > 0) {
+      return false;
+    }
+    self.cold = Some(Box::new(BumpAllocator::with_capacity(capacity)));
+    true
+  }
+
+  /// Allocates `layout` in the cold chunk. Returns null if there is no
+  /// chunk or it is full; the main heap is never used instead.
+  ///
+  /// # Safety
+  ///
+  /// As [`allocate`](Self::allocate).
+  pub unsafe fn allocate_cold(
+    &mut self,
+    layout: Layout,
+  ) -> *mut u8 {
+    match &mut self.cold {
+      // SAFETY: As the caller's contract.
+      Some(cold) => unsafe { cold.allocate(layout) },
+      None => ptr::null_mut(),
+    }
+  }
+
+  /// Moves the live block at `ptr` into the cold chunk and frees it in the
+  /// main heap. Returns the block's new address, or `ptr` itself if it is
+  /// already cold or the chunk has no room.
+  ///
+  /// The block keeps its contents and size, and its alignment up to 4 KiB.
+  ///
+  /// # Safety
+  ///
+  /// `ptr` must be a live pointer returned by this allocator. When the
+  /// returned address differs, every copy of `ptr` must be replaced by it.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// for entry in cache.idle_for(Duration::from_secs(600)) {
+  ///     entry.data = unsafe { allocator.mark_cold(entry.data) };
+  /// }
+  /// ```
+  pub unsafe fn mark_cold(
+    &mut self,
+    ptr: *mut u8,
+  ) -> *mut u8 {
+    if self.is_cold(ptr) {
+      return ptr;
+    }
+    let Some(info) = self.allocation_info(ptr).filter(|info| !info.from_reserve && !info.adopted && info.offset == 0) else {
+      return ptr;
+    };
+    let align = (1 << (ptr as usize).trailing_zeros().min(MAX_COLD_ALIGN.trailing_zeros())) as usize;
+    let Ok(layout) = Layout::from_size_align(info.size, align) else {
+      return ptr;
+    };
+
+    // SAFETY: A fresh cold block receives the live block's bytes before
+    // the old block is freed.
+    unsafe {
+      let moved = self.allocate_cold(layout);
+      if moved.is_null() {
+        return ptr;
+      }
+      ptr::copy_nonoverlapping(ptr, moved, info.size);
+      self.deallocate(ptr);
+      moved
+    }
+  }
+
+  /// Whether `ptr` points into the cold chunk.
+  pub fn is_cold(
+    &self,
+    ptr: *const u8,
+  ) -> bool {
+    self.cold_range().contains(&(ptr as usize))
+  }
+
+  /// Addresses the cold chunk spans so far, for `madvise` and the like;
+  /// empty without a chunk.
+  pub fn cold_range(&self) -> Range<usize> {
+    self.cold.as_ref().map_or(0..0, |cold| cold.heap_range())
+  }
+
+  /// Statistics of the cold chunk, if there is one.
+  pub fn cold_stats(&self) -> Option<Stats> {
+    self.cold.as_ref().map(|cold| cold.stats())
+  }
+
+  /// Frees `address` if it is cold. Returns whether it was.
+  pub(crate) unsafe fn deallocate_cold(
+    &mut self,
+    address: *mut u8,
+  ) -> bool {
+    if !self.is_cold(address) {
+      return false;
+    }
+    if let Some(cold) = &mut self.cold {
+      // SAFETY: A live cold pointer, by `deallocate`'s contract.
+      unsafe { cold.deallocate(address) };
+    }
+    true
+  }
+
+  /// Empties the cold chunk, for a reset.
+  pub(crate) fn reset_cold(&mut self) {
+    if let Some(cold) = &mut self.cold {
+      // SAFETY: The reset of the main heap invalidates cold pointers too.
+      unsafe { cold.reset() };
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn marked_blocks_move_to_the_cold_chunk() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    assert!(allocator.set_cold_chunk(4096));
+    let layout = Layout::from_size_align(48, 16).unwrap();
+    let hot = unsafe { allocator.allocate(layout) };
+    unsafe { allocator.allocate(layout) };
+    unsafe { hot.write_bytes(0x5A, 48) };
+
+    let cold = unsafe { allocator.mark_cold(hot) };
+    assert_ne!(cold, hot);
+    assert!(allocator.is_cold(cold) && !allocator.is_cold(hot));
+    assert!((cold as usize).is_multiple_of(16));
+    assert!(unsafe { core::slice::from_raw_parts(cold, 48) }.iter().all(|&byte| byte == 0x5A));
+    assert!(allocator.allocation_info(hot).unwrap().is_free);
+    assert_eq!(allocator.cold_stats().unwrap().bytes_in_use, 48);
+
+    // Already cold: nothing moves
+    assert_eq!(unsafe { allocator.mark_cold(cold) }, cold);
+    unsafe { allocator.deallocate(cold) };
+    assert_eq!(allocator.cold_stats().unwrap().live_blocks, 0);
+  }
+
+  #[test]
+  fn cold_allocations_never_spill_into_the_main_heap() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let layout = Layout::array::<u8>(64).unwrap();
+    assert!(unsafe { allocator.allocate_cold(layout) }.is_null());
+
+    allocator.set_cold_chunk(256);
+    let mut cold = 0;
+    while !unsafe { allocator.allocate_cold(layout) }.is_null() {
+      cold += 1;
+    }
+    assert!(cold > 0);
+    assert_eq!(allocator.live_blocks(), 0);
+    assert!(!allocator.set_cold_chunk(1024));
+
+    // A full chunk leaves marked blocks where they are
+    let hot = unsafe { allocator.allocate(layout) };
+    assert_eq!(unsafe { allocator.mark_cold(hot) }, hot);
+  }
+
+  #[test]
+  fn resets_empty_the_cold_chunk() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_cold_chunk(1024);
+    unsafe { allocator.allocate_cold(Layout::new::<u64>()) };
+    unsafe { allocator.reset() };
+    assert_eq!(allocator.cold_stats().unwrap().live_blocks, 0);
+    assert!(allocator.set_cold_chunk(2048));
+  }
+}
@@ -0,0 +1,93 @@
+//! # Arena-Backed Collections
+//!
+//! Type aliases for [`hashbrown`] maps and sets whose tables live entirely
+//! in the bump region of a [`LocalBumpAllocator`].
+//!
+//! ```text
+//!   ArenaHashMap<'a, K, V>
+//!   ┌───────────────────────┐
+//!   │ hasher                │        LocalBumpAllocator region
+//!   │ alloc: &'a arena ─────┼──┐     ┌────────┬─────────────────────┐
+//!   │ table ptr ────────────┼──┼────►│ header │ control bytes +     │
+//!   └───────────────────────┘  │     │        │ (K, V) buckets      │
+//!                              └────►└────────┴─────────────────────┘
+//! ```
+//!
+//! Growing the table allocates a new, larger block from the arena and
+//! frees the old one, exactly like any other allocation.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use rallocator::{LocalBumpAllocator, collections::ArenaHashMap};
+//!
+//! let arena = LocalBumpAllocator::new();
+//! let mut ages = ArenaHashMap::new_in(&arena);
+//! ages.insert("alice", 31);
+//! assert_eq!(ages["alice"], 31);
+//! ```
+
+use crate::LocalBumpAllocator;
+
+pub use hashbrown::DefaultHashBuilder;
+
+/// A [`hashbrown::HashMap`] whose table is allocated from a [`LocalBumpAllocator`].
+pub type ArenaHashMap<'a, K, V, S = DefaultHashBuilder> = hashbrown::HashMap<K, V, S, &'a LocalBumpAllocator>;
+
+/// A [`hashbrown::HashSet`] whose table is allocated from a [`LocalBumpAllocator`].
+pub type ArenaHashSet<'a, T, S = DefaultHashBuilder> = hashbrown::HashSet<T, S, &'a LocalBumpAllocator>;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::BumpAllocator;
+  use core::ops::Range;
+
+  /// An arena over a leaked buffer, plus the buffer's address range.
+  fn arena_with_range(len: usize) -> (LocalBumpAllocator, Range<usize>) {
+    let buffer = Box::leak(vec![0u8; len].into_boxed_slice());
+    let range = buffer.as_ptr_range();
+    let arena = LocalBumpAllocator::from_allocator(BumpAllocator::from_buffer(buffer));
+    (arena, range.start as usize..range.end as usize)
+  }
+
+  #[test]
+  fn map_table_lives_in_the_arena() {
+    let (arena, range) = arena_with_range(256 * 1024);
+    let mut squares = ArenaHashMap::new_in(&arena);
+
+    for i in 0..1000u64 {
+      squares.insert(i, i * i);
+    }
+
+    assert_eq!(squares.len(), 1000);
+    for i in 0..1000u64 {
+      let value = squares.get(&i).unwrap();
+      assert_eq!(*value, i * i);
+      assert!(range.contains(&(value as *const u64 as usize)));
+    }
+  }
+
+  #[test]
+  fn set_table_lives_in_the_arena() {
+    let (arena, range) = arena_with_range(64 * 1024);
+    let mut words = ArenaHashSet::new_in(&arena);
+
+    for word in ["alloc", "free", "bump", "alloc", "free"] {
+      words.insert(word);
+    }
+
+    assert_eq!(words.len(), 3);
+    let stored = words.get("bump").unwrap();
+    assert!(range.contains(&(stored as *const &str as usize)));
+  }
+
+  #[test]
+  fn map_insert_fails_cleanly_when_arena_is_exhausted() {
+    let (arena, _) = arena_with_range(512);
+    let mut map: ArenaHashMap<u64, u64> = ArenaHashMap::new_in(&arena);
+
+    assert!(map.try_reserve(10_000).is_err());
+    assert!(map.is_empty());
+  }
+}
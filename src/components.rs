@@ -0,0 +1,515 @@
+//! # Component Arenas
+//!
+//! A [`ComponentArena`] stores the values of one component type for an
+//! entity-component system: a fixed number of them in one reservation,
+//! reached through generation-checked [`Handle`]s and iterated as a dense
+//! slice.
+//!
+//! ```text
+//!   slots (stable, what handles point at)     dense (packed, what loops see)
+//!   ┌──────────┬──────────┬──────────┐        ┌──────┬──────┐
+//!   │ gen 3    │ gen 2    │ gen 1    │        │  C   │  A   │ components
+//!   │ dense 1  │ free ─┐  │ dense 0  │        ├──────┼──────┤
+//!   └──────────┴───────┼──┴──────────┘        │  2   │  0   │ owners
+//!        ▲             │  free_head           └──────┴──────┘
+//!   Handle{0, gen 3}   └─► next free
+//! ```
+//!
+//! Every slot keeps a generation counter, odd while the slot is occupied.
+//! A handle records the generation it was issued with, so a handle to a
+//! removed component never reaches the component that reused its slot.
+//! Removing swaps the last dense component into the hole, keeping the
+//! components packed for cache-friendly loops; the slot of the moved one is
+//! updated, so handles stay valid.
+//!
+//! Like an [`ArenaPool`](crate::ArenaPool), the arena takes all of its
+//! memory up front - owned with `std`, or a caller's buffer - and threads
+//! free slots through an intrusive stack, so it never allocates again.
+//! Components are dropped when removed, cleared or when the arena goes.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use rallocator::ComponentArena;
+//!
+//! let mut positions = ComponentArena::new(10_000).unwrap();
+//! let player = positions.insert(Position { x: 0.0, y: 0.0 }).unwrap();
+//!
+//! for position in positions.as_mut_slice() {
+//!     position.x += 1.0;
+//! }
+//! positions.remove(player);
+//! assert!(positions.get(player).is_none());
+//! ```
+
+use core::{
+  alloc::Layout,
+  fmt,
+  hash::{Hash, Hasher},
+  marker::PhantomData,
+  ptr, slice,
+};
+
+use crate::backend::Region;
+
+/// Marks the end of the free-slot stack.
+const NO_SLOT: u32 = u32::MAX;
+
+/// Stable bookkeeping for one component position.
+#[derive(Clone, Copy)]
+struct Slot {
+  /// Odd while occupied, even while free; bumped on every change.
+  generation: u32,
+
+  /// Dense index of the component while occupied, next free slot while
+  /// free.
+  link: u32,
+}
+
+/// A generation-checked reference to a component of a [`ComponentArena`].
+///
+/// Cheap to copy and compare, and `'static`: store it in other components
+/// instead of a pointer.
+pub struct Handle<T> {
+  /// Slot the component was inserted into.
+  index: u32,
+
+  /// Generation of the slot at insertion.
+  generation: u32,
+
+  _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+  /// Slot index of the component, stable while it lives.
+  pub fn index(self) -> u32 {
+    self.index
+  }
+
+  /// Generation of the slot when the component was inserted.
+  pub fn generation(self) -> u32 {
+    self.generation
+  }
+}
+
+impl<T> Clone for Handle<T> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+  fn eq(
+    &self,
+    other: &Self,
+  ) -> bool {
+    (self.index, self.generation) == (other.index, other.generation)
+  }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+  fn hash<H: Hasher>(
+    &self,
+    state: &mut H,
+  ) {
+    (self.index, self.generation).hash(state);
+  }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    write!(f, "Handle({}v{})", self.index, self.generation)
+  }
+}
+
+/// A fixed-capacity store of `T` components with stable handles and dense
+/// iteration, see the `components` module.
+pub struct ComponentArena<T> {
+  /// The reservation. Only held so owned regions are freed with the arena.
+  _memory: Region,
+
+  /// Dense components; the first `len` are initialized.
+  components: *mut T,
+
+  /// Slot of each dense component.
+  owners: *mut u32,
+
+  /// One per possible component.
+  slots: *mut Slot,
+
+  /// Number of slots.
+  capacity: usize,
+
+  /// Number of live components.
+  len: usize,
+
+  /// First free slot, or `NO_SLOT`.
+  free_head: u32,
+}
+
+impl<T> ComponentArena<T> {
+  /// Creates an arena for up to `capacity` components, reserved with a
+  /// single request to the system allocator.
+  ///
+  /// # Returns
+  ///
+  /// `None` if `capacity` is zero or above `u32::MAX - 1`, or the
+  /// reservation cannot be obtained.
+  #[cfg(feature = "std")]
+  pub fn new(capacity: usize) -> Option<Self> {
+    let (layout, ..) = Self::layout(capacity)?;
+    // Owned regions are only 16-aligned: reserve room to align by hand
+    let region = Region::owned(layout.size().checked_add(layout.align())?);
+    Self::from_region(region, capacity)
+  }
+
+  /// Creates an arena over `buffer`, holding as many components as fit.
+  pub fn from_buffer(buffer: &'static mut [u8]) -> Option<Self> {
+    let start = buffer.as_mut_ptr();
+    let len = buffer.len();
+    let per_component = size_of::<T>() + size_of::<u32>() + size_of::<Slot>();
+    let mut capacity = (len / per_component).min(NO_SLOT as usize - 1);
+
+    // Alignment padding may cost a few components
+    let fits = |capacity| {
+      Self::layout(capacity)
+        .is_some_and(|(layout, ..)| (start as usize).next_multiple_of(layout.align()) - start as usize + layout.size() <= len)
+    };
+    while capacity > 0 && !fits(capacity) {
+      capacity -= 1;
+    }
+
+    // SAFETY: The exclusive 'static borrow keeps the memory valid and unused.
+    Self::from_region(unsafe { Region::borrowed(start, len) }, capacity)
+  }
+
+  /// Layout of the three arrays for `capacity` components, with the
+  /// offsets of the second and third.
+  fn layout(capacity: usize) -> Option<(Layout, usize, usize)> {
+    if capacity == 0 || capacity >= NO_SLOT as usize {
+      return None;
+    }
+    let (layout, owners) = Layout::array::<T>(capacity).ok()?.extend(Layout::array::<u32>(capacity).ok()?).ok()?;
+    let (layout, slots) = layout.extend(Layout::array::<Slot>(capacity).ok()?).ok()?;
+    Some((layout, owners, slots))
+  }
+
+  /// Carves the arrays out of `memory` and threads every slot onto the
+  /// free stack.
+  fn from_region(
+    mut memory: Region,
+    capacity: usize,
+  ) -> Option<Self> {
+    let (layout, owners, slots) = Self::layout(capacity)?;
+    let reserved = memory.grow(0)?;
+    memory.grow((reserved as usize).next_multiple_of(layout.align()) - reserved as usize)?;
+    let start = memory.grow(layout.size())?;
+
+    // SAFETY: `start` is aligned for the layout and `layout.size()` bytes
+    // long, so every array lies inside it.
+    let mut arena = unsafe {
+      Self {
+        _memory: memory,
+        components: start as *mut T,
+        owners: start.add(owners) as *mut u32,
+        slots: start.add(slots) as *mut Slot,
+        capacity,
+        len: 0,
+        free_head: NO_SLOT,
+      }
+    };
+
+    // Push in reverse so slot 0 is handed out first
+    for index in (0..capacity as u32).rev() {
+      // SAFETY: `index < capacity`.
+      unsafe { arena.slots.add(index as usize).write(Slot { generation: 0, link: NO_SLOT }) };
+      arena.push_free(index);
+    }
+    Some(arena)
+  }
+
+  /// Maximum number of components.
+  pub fn capacity(&self) -> usize {
+    self.capacity
+  }
+
+  /// Number of live components.
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  /// Whether there are no live components.
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  /// Stores `value` and returns its handle, or gives `value` back if the
+  /// arena is full.
+  pub fn insert(
+    &mut self,
+    value: T,
+  ) -> Result<Handle<T>, T> {
+    let index = self.free_head;
+    if index == NO_SLOT {
+      return Err(value);
+    }
+
+    // SAFETY: `index` came off the free stack, so it is a free slot, and
+    // `len < capacity` while one is free.
+    unsafe {
+      let slot = &mut *self.slots.add(index as usize);
+      self.free_head = slot.link;
+      slot.generation = slot.generation.wrapping_add(1);
+      slot.link = self.len as u32;
+      self.components.add(self.len).write(value);
+      self.owners.add(self.len).write(index);
+      self.len += 1;
+      Ok(Handle {
+        index,
+        generation: slot.generation,
+        _marker: PhantomData,
+      })
+    }
+  }
+
+  /// Whether `handle` refers to a live component of this arena.
+  pub fn contains(
+    &self,
+    handle: Handle<T>,
+  ) -> bool {
+    self.dense_index(handle).is_some()
+  }
+
+  /// The component behind `handle`, unless it was removed.
+  pub fn get(
+    &self,
+    handle: Handle<T>,
+  ) -> Option<&T> {
+    let dense = self.dense_index(handle)?;
+    // SAFETY: Dense indices of live slots are below `len`.
+    Some(unsafe { &*self.components.add(dense) })
+  }
+
+  /// Mutable access to the component behind `handle`.
+  pub fn get_mut(
+    &mut self,
+    handle: Handle<T>,
+  ) -> Option<&mut T> {
+    let dense = self.dense_index(handle)?;
+    // SAFETY: As in `get`; `&mut self` makes the access exclusive.
+    Some(unsafe { &mut *self.components.add(dense) })
+  }
+
+  /// Removes the component behind `handle` and returns it. Every copy of
+  /// the handle stops resolving.
+  pub fn remove(
+    &mut self,
+    handle: Handle<T>,
+  ) -> Option<T> {
+    let dense = self.dense_index(handle)?;
+    let last = self.len - 1;
+
+    // SAFETY: `dense` and `last` are below `len`; moving the last component
+    // into the hole keeps `0..len - 1` initialized.
+    unsafe {
+      let value = self.components.add(dense).read();
+      if dense != last {
+        ptr::copy_nonoverlapping(self.components.add(last), self.components.add(dense), 1);
+        let moved = *self.owners.add(last);
+        *self.owners.add(dense) = moved;
+        (*self.slots.add(moved as usize)).link = dense as u32;
+      }
+      self.len = last;
+
+      let slot = &mut *self.slots.add(handle.index as usize);
+      slot.generation = slot.generation.wrapping_add(1);
+      self.push_free(handle.index);
+      Some(value)
+    }
+  }
+
+  /// Drops every component; every handle stops resolving.
+  pub fn clear(&mut self) {
+    while self.len > 0 {
+      // SAFETY: The last dense component is live.
+      let index = unsafe { *self.owners.add(self.len - 1) };
+      let generation = unsafe { (*self.slots.add(index as usize)).generation };
+      self.remove(Handle {
+        index,
+        generation,
+        _marker: PhantomData,
+      });
+    }
+  }
+
+  /// The live components, packed, in no particular order.
+  pub fn as_slice(&self) -> &[T] {
+    // SAFETY: The first `len` components are initialized.
+    unsafe { slice::from_raw_parts(self.components, self.len) }
+  }
+
+  /// The live components, packed, for in-place updates.
+  pub fn as_mut_slice(&mut self) -> &mut [T] {
+    // SAFETY: As in `as_slice`; `&mut self` makes the access exclusive.
+    unsafe { slice::from_raw_parts_mut(self.components, self.len) }
+  }
+
+  /// The live components with their handles, in dense order.
+  pub fn iter(&self) -> impl Iterator<Item = (Handle<T>, &T)> {
+    self.as_slice().iter().enumerate().map(|(dense, value)| (self.handle_at(dense), value))
+  }
+
+  /// Handle of the component at dense position `dense`.
+  fn handle_at(
+    &self,
+    dense: usize,
+  ) -> Handle<T> {
+    // SAFETY: Callers pass `dense < len`, whose owner is a live slot.
+    unsafe {
+      let index = *self.owners.add(dense);
+      Handle {
+        index,
+        generation: (*self.slots.add(index as usize)).generation,
+        _marker: PhantomData,
+      }
+    }
+  }
+
+  /// Dense position of the component behind `handle`, if it is live.
+  fn dense_index(
+    &self,
+    handle: Handle<T>,
+  ) -> Option<usize> {
+    if handle.index as usize >= self.capacity {
+      return None;
+    }
+    // SAFETY: `index < capacity`.
+    let slot = unsafe { *self.slots.add(handle.index as usize) };
+    (slot.generation == handle.generation && slot.generation % 2 == 1).then_some(slot.link as usize)
+  }
+
+  /// Pushes the free slot `index` onto the free stack.
+  fn push_free(
+    &mut self,
+    index: u32,
+  ) {
+    // SAFETY: `index < capacity`.
+    unsafe { (*self.slots.add(index as usize)).link = self.free_head };
+    self.free_head = index;
+  }
+}
+
+impl<T> Drop for ComponentArena<T> {
+  fn drop(&mut self) {
+    // SAFETY: The first `len` components are initialized and dropped once.
+    unsafe { ptr::drop_in_place(self.as_mut_slice() as *mut [T]) };
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::rc::Rc;
+
+  #[test]
+  fn handles_resolve_until_removed() {
+    let mut arena = ComponentArena::new(4).unwrap();
+    let a = arena.insert("a").unwrap();
+    let b = arena.insert("b").unwrap();
+    assert_eq!((arena.get(a), arena.get(b)), (Some(&"a"), Some(&"b")));
+
+    assert_eq!(arena.remove(a), Some("a"));
+    assert_eq!(arena.get(a), None);
+    assert_eq!(arena.remove(a), None);
+    assert_eq!(arena.get(b), Some(&"b"));
+
+    *arena.get_mut(b).unwrap() = "B";
+    assert_eq!(arena.as_slice(), ["B"]);
+  }
+
+  #[test]
+  fn stale_handles_miss_reused_slots() {
+    let mut arena = ComponentArena::new(1).unwrap();
+    let old = arena.insert(1).unwrap();
+    arena.remove(old);
+    let new = arena.insert(2).unwrap();
+
+    assert_eq!(old.index(), new.index());
+    assert_ne!(old, new);
+    assert!(!arena.contains(old));
+    assert_eq!(arena.get(new), Some(&2));
+  }
+
+  #[test]
+  fn components_stay_dense() {
+    let mut arena = ComponentArena::new(8).unwrap();
+    let handles: Vec<_> = (0..5).map(|i| arena.insert(i).unwrap()).collect();
+    arena.remove(handles[1]);
+    arena.remove(handles[3]);
+
+    let mut values = arena.as_slice().to_vec();
+    values.sort();
+    assert_eq!(values, [0, 2, 4]);
+    for (handle, &value) in arena.iter() {
+      assert_eq!(handles[value], handle);
+    }
+    // The component moved into a hole is still found through its handle
+    assert_eq!(arena.get(handles[4]), Some(&4));
+  }
+
+  #[test]
+  fn full_arenas_give_the_value_back() {
+    let mut arena = ComponentArena::new(2).unwrap();
+    arena.insert(1).unwrap();
+    arena.insert(2).unwrap();
+    assert_eq!(arena.insert(3), Err(3));
+    assert_eq!(arena.len(), 2);
+  }
+
+  #[test]
+  fn components_are_dropped() {
+    let counter = Rc::new(());
+    let mut arena = ComponentArena::new(4).unwrap();
+    let a = arena.insert(counter.clone()).unwrap();
+    arena.insert(counter.clone()).unwrap();
+    arena.insert(counter.clone()).unwrap();
+
+    drop(arena.remove(a));
+    assert_eq!(Rc::strong_count(&counter), 3);
+    arena.clear();
+    assert_eq!(Rc::strong_count(&counter), 1);
+    assert!(arena.is_empty());
+
+    arena.insert(counter.clone()).unwrap();
+    drop(arena);
+    assert_eq!(Rc::strong_count(&counter), 1);
+  }
+
+  #[test]
+  fn buffers_hold_what_fits() {
+    #[derive(Debug)]
+    #[repr(align(64))]
+    struct Wide(#[allow(dead_code)] [u8; 64]);
+
+    let buffer = Box::leak(vec![0u8; 1000].into_boxed_slice());
+    let range = buffer.as_ptr_range();
+    let mut arena = ComponentArena::from_buffer(buffer).unwrap();
+    let capacity = arena.capacity();
+    assert!((10..=13).contains(&capacity), "{capacity}");
+
+    for _ in 0..capacity {
+      let handle = arena.insert(Wide([0; 64])).unwrap();
+      let address = arena.get(handle).unwrap() as *const Wide;
+      assert_eq!(address as usize % 64, 0);
+      assert!(range.contains(&(address as *const u8)));
+    }
+    assert!(arena.insert(Wide([0; 64])).is_err());
+  }
+}
@@ -0,0 +1,211 @@
+//! # Allocator Configuration
+//!
+//! Policy knobs gathered in one [`Config`], so new options become new
+//! fields instead of new constructors:
+//!
+//! ```rust,ignore
+//! let allocator = BumpAllocator::with_config(Config {
+//!     search: SearchMode::BestFitIndexed,
+//!     growth_chunk: 64 * 1024,
+//!     ..Config::DEFAULT
+//! });
+//! ```
+//!
+//! Always build a `Config` from [`Config::DEFAULT`] (or `Default`) with
+//! `..`: fields will be added as the allocator grows new policies.
+
+use crate::{BumpAllocator, CanaryPolicy, FailurePolicy, SearchMode, ShrinkPolicy};
+
+/// Policy settings of a [`BumpAllocator`].
+///
+/// ```text
+///   Config::DEFAULT
+///   ┌───────────────┬───────────────────┬──────────────────────────────┐
+///   │ search        │ FirstFit          │ free block search strategy   │
+///   │ realtime      │ false             │ O(1) operations only         │
+///   │ growth_chunk  │ 0                 │ minimum program break growth │
+///   │ min_align     │ 1                 │ minimum payload alignment    │
+///   │ jitter        │ 0                 │ largest random gap per block │
+///   │ shrink        │ EAGER             │ when tail frees reach the OS │
+///   │ canaries      │ OFF               │ overflow canary sampling     │
+///   │ random_seed   │ (fixed)           │ seed for Random and jitter   │
+///   │ heap_limit    │ None              │ cap on the heap size         │
+///   │ max_blocks    │ None              │ cap on the tracked blocks    │
+///   │ failure       │ Error             │ typed helpers on failure     │
+///   └───────────────┴───────────────────┴──────────────────────────────┘
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+  /// Strategy for finding free blocks, see [`SearchMode`].
+  pub search: SearchMode,
+
+  /// Start in real-time mode, see [`BumpAllocator::realtime`].
+  pub realtime: bool,
+
+  /// Minimum growth of the program break, see
+  /// [`BumpAllocator::set_growth_chunk`]. Ignored by region backends.
+  pub growth_chunk: usize,
+
+  /// Minimum alignment of every payload, on top of what each layout asks
+  /// for. Must be a power of two.
+  ///
+  /// Raising it to a cache line (64) keeps allocations from sharing lines;
+  /// the default of 1 adds nothing to the word alignment blocks always have.
+  pub min_align: usize,
+
+  /// Largest random gap inserted before each new block, `0` for none, see
+  /// [`BumpAllocator::set_jitter`].
+  pub jitter: usize,
+
+  /// When freed tail memory is returned to the system, see
+  /// [`BumpAllocator::set_shrink_policy`].
+  pub shrink: ShrinkPolicy,
+
+  /// Which allocations get an overflow canary, see
+  /// [`BumpAllocator::set_canary_policy`].
+  pub canaries: CanaryPolicy,
+
+  /// Seed of the generator behind [`SearchMode::Random`] and jitter gaps,
+  /// see [`BumpAllocator::set_random_seed`].
+  pub random_seed: u64,
+
+  /// Most bytes the heap may obtain, see [`BumpAllocator::set_heap_limit`].
+  pub heap_limit: Option<usize>,
+
+  /// Most blocks to track before allocations become untracked bumps, see
+  /// [`BumpAllocator::set_max_blocks`].
+  pub max_blocks: Option<usize>,
+
+  /// What typed helpers do when an allocation fails, see
+  /// [`BumpAllocator::set_failure_policy`].
+  pub failure: FailurePolicy,
+}
+
+impl Config {
+  /// The settings of [`BumpAllocator::new`].
+  pub const DEFAULT: Self = Self {
+    search: SearchMode::FirstFit,
+    realtime: false,
+    growth_chunk: 0,
+    min_align: 1,
+    jitter: 0,
+    shrink: ShrinkPolicy::EAGER,
+    canaries: CanaryPolicy::OFF,
+    random_seed: crate::bump::DEFAULT_RANDOM_SEED,
+    heap_limit: None,
+    max_blocks: None,
+    failure: FailurePolicy::Error,
+  };
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self::DEFAULT
+  }
+}
+
+impl BumpAllocator {
+  /// Creates a new, empty `BumpAllocator` on the default backend with the
+  /// given policies.
+  ///
+  /// # Panics
+  ///
+  /// If `config.min_align` is not a power of two.
+  pub const fn with_config(config: Config) -> Self {
+    let mut allocator = Self::new();
+    allocator.apply_config(config);
+    allocator
+  }
+
+  /// The current policies.
+  ///
+  /// Settings changed through individual setters (such as
+  /// [`set_search_mode`](Self::set_search_mode)) are reflected here.
+  pub fn config(&self) -> Config {
+    Config {
+      search: self.search_mode,
+      realtime: self.realtime,
+      growth_chunk: self.backend.growth_chunk(),
+      min_align: self.min_align,
+      jitter: self.jitter,
+      shrink: self.shrink_policy,
+      canaries: self.canary_policy,
+      random_seed: self.random_seed,
+      heap_limit: self.heap_limit,
+      max_blocks: self.max_blocks,
+      failure: self.failure_policy,
+    }
+  }
+
+  /// Stores `config` in a freshly created allocator.
+  pub(crate) const fn apply_config(
+    &mut self,
+    config: Config,
+  ) {
+    assert!(config.min_align.is_power_of_two(), "min_align must be a power of two");
+
+    self.search_mode = config.search;
+    self.realtime = config.realtime;
+    self.backend.set_growth_chunk(config.growth_chunk);
+    self.min_align = config.min_align;
+    self.jitter = config.jitter;
+    self.shrink_policy = config.shrink;
+    self.canary_policy = config.canaries;
+    self.set_random_seed(config.random_seed);
+    self.heap_limit = config.heap_limit;
+    self.max_blocks = config.max_blocks;
+    self.failure_policy = config.failure;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::alloc::Layout;
+
+  #[test]
+  fn default_config_matches_new() {
+    assert_eq!(BumpAllocator::new().config(), Config::default());
+    assert_eq!(BumpAllocator::with_config(Config::DEFAULT).config(), Config::DEFAULT);
+  }
+
+  #[test]
+  fn config_sets_policies() {
+    let config = Config {
+      search: SearchMode::GoodFit { tolerance_percent: 25 },
+      realtime: true,
+      growth_chunk: 64 * 1024,
+      ..Config::DEFAULT
+    };
+    let allocator = BumpAllocator::with_config(config);
+
+    assert_eq!(allocator.search_mode(), config.search);
+    assert!(allocator.is_realtime());
+    #[cfg(unix)]
+    assert_eq!(allocator.growth_chunk(), 64 * 1024);
+    assert_eq!(allocator.config().search, config.search);
+  }
+
+  #[test]
+  fn min_align_raises_every_payload() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.apply_config(Config {
+      min_align: 64,
+      ..Config::DEFAULT
+    });
+
+    for _ in 0..4 {
+      let ptr = unsafe { allocator.allocate(Layout::new::<u8>()) };
+      assert!(crate::align::is_ptr_aligned(ptr, 64));
+    }
+  }
+
+  #[test]
+  #[should_panic(expected = "min_align must be a power of two")]
+  fn min_align_must_be_a_power_of_two() {
+    BumpAllocator::with_config(Config {
+      min_align: 24,
+      ..Config::DEFAULT
+    });
+  }
+}
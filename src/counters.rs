@@ -0,0 +1,218 @@
+//! # Operation Counters
+//!
+//! With the `counters` feature every allocator keeps running totals of
+//! its operations, for telemetry that [`Stats`](crate::Stats) cannot give:
+//! a `Stats` snapshot is computed from the block list as it is *now*, while
+//! counters remember what happened since the allocator was created:
+//!
+//! ```text
+//!   allocate(64)  allocate(32)  deallocate(64)  reset()  allocate(8)
+//!
+//!   allocations         3        bytes_allocated    104
+//!   deallocations       1        bytes_in_use       8
+//!   resets              1        peak_bytes_in_use  96
+//! ```
+//!
+//! Without the feature the counters and every line updating them are
+//! compiled out - there is no runtime switch to test - so the plain
+//! allocator runs exactly the code it did before they existed.
+
+use crate::{BumpAllocator, SizeClasses};
+
+/// Running totals of an allocator's operations.
+///
+/// Returned by [`BumpAllocator::counters`]. Counts are `u64` so they do
+/// not wrap on 32-bit targets in long-running programs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Counters {
+  /// Successful calls to `allocate`, including ones served by the
+  /// emergency reserve.
+  pub allocations: u64,
+
+  /// Calls to `allocate` that returned null.
+  pub failed_allocations: u64,
+
+  /// Successful allocations served from freed memory rather than by
+  /// growing the heap, see [`reuse_rate`](Self::reuse_rate).
+  pub reused_allocations: u64,
+
+  /// Blocks freed by `deallocate` (double frees are not counted).
+  pub deallocations: u64,
+
+  /// Calls to `reset`.
+  pub resets: u64,
+
+  /// Sum of the sizes of all successful allocations.
+  pub bytes_allocated: u64,
+
+  /// Requested bytes currently live, as `Stats::bytes_in_use` would report
+  /// them (plus the emergency reserve's own allocations).
+  pub bytes_in_use: usize,
+
+  /// Highest `bytes_in_use` seen.
+  pub peak_bytes_in_use: usize,
+}
+
+impl Counters {
+  /// Nothing counted yet.
+  pub const ZERO: Self = Self {
+    allocations: 0,
+    failed_allocations: 0,
+    reused_allocations: 0,
+    deallocations: 0,
+    resets: 0,
+    bytes_allocated: 0,
+    bytes_in_use: 0,
+    peak_bytes_in_use: 0,
+  };
+}
+
+impl Counters {
+  /// Share of the successful allocations that reused freed memory, from
+  /// `0.0` to `1.0`; `0.0` before the first allocation.
+  ///
+  /// Both reused free blocks of [`allocate`](BumpAllocator::allocate) and
+  /// headerless slots popped by
+  /// [`allocate_sized`](BumpAllocator::allocate_sized) count.
+  pub fn reuse_rate(&self) -> f64 {
+    if self.allocations == 0 {
+      return 0.0;
+    }
+    self.reused_allocations as f64 / self.allocations as f64
+  }
+}
+
+impl BumpAllocator {
+  /// Operation totals since creation or the last
+  /// [`clear_counters`](Self::clear_counters).
+  pub fn counters(&self) -> Counters {
+    self.counters
+  }
+
+  /// Zeroes the totals and the [`size_classes`](Self::size_classes), for
+  /// measuring one phase of a program.
+  ///
+  /// `bytes_in_use` is kept, since those bytes are still live, and becomes
+  /// the new peak.
+  pub fn clear_counters(&mut self) {
+    let bytes_in_use = self.counters.bytes_in_use;
+    self.counters = Counters {
+      bytes_in_use,
+      peak_bytes_in_use: bytes_in_use,
+      ..Counters::ZERO
+    };
+    self.size_classes = SizeClasses::ZERO;
+  }
+
+  /// Counts a call to `allocate` of `size` bytes.
+  #[inline]
+  pub(crate) fn count_allocate(
+    &mut self,
+    size: usize,
+    ok: bool,
+  ) {
+    let counters = &mut self.counters;
+    if !ok {
+      counters.failed_allocations += 1;
+      return;
+    }
+    counters.allocations += 1;
+    counters.bytes_allocated += size as u64;
+    self.size_classes.record(size);
+    counters.bytes_in_use += size;
+    counters.peak_bytes_in_use = counters.peak_bytes_in_use.max(counters.bytes_in_use);
+  }
+
+  /// Marks the allocation of `size` bytes just counted as served from
+  /// freed memory.
+  #[inline]
+  pub(crate) fn count_reuse(
+    &mut self,
+    size: usize,
+  ) {
+    self.counters.reused_allocations += 1;
+    self.size_classes.record_reuse(size);
+  }
+
+  /// Counts the release of a `size`-byte block.
+  #[inline]
+  pub(crate) fn count_deallocate(
+    &mut self,
+    size: usize,
+  ) {
+    self.counters.deallocations += 1;
+    self.counters.bytes_in_use = self.counters.bytes_in_use.saturating_sub(size);
+  }
+
+  /// Counts a `reset`, which frees everything at once.
+  pub(crate) fn count_reset(&mut self) {
+    self.counters.resets += 1;
+    self.counters.bytes_in_use = 0;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::alloc::Layout;
+
+  #[test]
+  fn operations_are_counted() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+
+    unsafe {
+      let a = allocator.allocate(Layout::array::<u8>(64).unwrap());
+      allocator.allocate(Layout::array::<u8>(32).unwrap());
+      assert!(allocator.allocate(Layout::array::<u8>(1 << 20).unwrap()).is_null());
+      allocator.deallocate(a);
+      allocator.reset();
+      allocator.allocate(Layout::array::<u8>(8).unwrap());
+    }
+
+    assert_eq!(
+      allocator.counters(),
+      Counters {
+        allocations: 3,
+        failed_allocations: 1,
+        reused_allocations: 0,
+        deallocations: 1,
+        resets: 1,
+        bytes_allocated: 104,
+        bytes_in_use: 8,
+        peak_bytes_in_use: 96,
+      }
+    );
+  }
+
+  #[test]
+  #[cfg(not(feature = "flight-recorder"))]
+  fn double_free_is_not_counted() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+
+    unsafe {
+      let a = allocator.allocate(Layout::new::<u64>());
+      allocator.allocate(Layout::new::<u64>());
+      allocator.deallocate(a);
+      allocator.deallocate(a);
+    }
+
+    assert_eq!(allocator.counters().deallocations, 1);
+    assert_eq!(allocator.counters().bytes_in_use, 8);
+  }
+
+  #[test]
+  fn clearing_keeps_live_bytes() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    unsafe { allocator.allocate(Layout::array::<u8>(40).unwrap()) };
+
+    allocator.clear_counters();
+    assert_eq!(
+      allocator.counters(),
+      Counters {
+        bytes_in_use: 40,
+        peak_bytes_in_use: 40,
+        ..Counters::ZERO
+      }
+    );
+  }
+}
@@ -0,0 +1,133 @@
+//! # Crash-Time Heap Dumps
+//!
+//! When an allocator detects corruption - a failed
+//! [`assert_invariants`](BumpAllocator::assert_invariants), or a double
+//! free with the `flight-recorder` feature - it panics. With a crash dump
+//! path set, it first writes what it knows to that file, for post-mortem
+//! analysis without a core dump:
+//!
+//! ```text
+//!   corruption detected
+//!        │
+//!        ▼
+//!   crash dump file                              then panic!("heap corruption: ...")
+//!   ┌─────────────────────────────────────────┐
+//!   │ heap snapshot (see the snapshot module) │ ◄── BumpAllocator::import reads this
+//!   ├─────────────────────────────────────────┤
+//!   │ text report:                            │
+//!   │   heap corruption: <what was found>     │
+//!   │   recent operations (flight-recorder)   │
+//!   └─────────────────────────────────────────┘
+//! ```
+//!
+//! The snapshot covers the intact prefix of the block list; the report
+//! follows it, so `import` on the file restores the heap and ignores the
+//! text. Writing is best effort: an I/O error is mentioned in the panic
+//! message but never hides the corruption itself.
+
+use std::{
+  fs::File,
+  io::{self, BufWriter, Write},
+  path::Path,
+};
+
+use crate::{BumpAllocator, Corruption};
+
+impl BumpAllocator {
+  /// Writes a heap dump to `path` whenever this allocator detects
+  /// corruption, just before it panics. `None` turns dumps off (the
+  /// default).
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// allocator.set_crash_dump(Some(Path::new("/tmp/heap.dump")));
+  /// ```
+  pub fn set_crash_dump(
+    &mut self,
+    path: Option<&'static Path>,
+  ) {
+    self.crash_dump = path;
+  }
+
+  /// The crash dump path, see [`set_crash_dump`](Self::set_crash_dump).
+  pub fn crash_dump(&self) -> Option<&'static Path> {
+    self.crash_dump
+  }
+
+  /// Writes the crash dump for `corruption`, if a path is set.
+  ///
+  /// Returns a note for the panic message: where the dump went, or why
+  /// it could not be written. Empty without a path.
+  #[cold]
+  pub(crate) fn write_crash_dump(
+    &self,
+    corruption: &Corruption,
+  ) -> String {
+    let Some(path) = self.crash_dump else {
+      return String::new();
+    };
+    match self.dump_to(path, corruption) {
+      Ok(()) => format!("\nheap dumped to {}", path.display()),
+      Err(error) => format!("\ncould not write heap dump to {}: {error}", path.display()),
+    }
+  }
+
+  /// Snapshot, then the text report.
+  fn dump_to(
+    &self,
+    path: &Path,
+    corruption: &Corruption,
+  ) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    self.export(&mut out)?;
+    writeln!(out, "\nheap corruption: {corruption}")?;
+    #[cfg(feature = "flight-recorder")]
+    writeln!(out, "{}", self.recent_ops())?;
+    out.flush()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::block::{Block, HEADER_SIZE};
+  use core::alloc::Layout;
+  use std::panic::{self, AssertUnwindSafe};
+
+  #[test]
+  fn corruption_writes_a_dump() {
+    let path: &'static Path = Box::leak(std::env::temp_dir().join(format!("rallocator-dump-{}", std::process::id())).into_boxed_path());
+
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_crash_dump(Some(path));
+    let ptrs = unsafe {
+      let ptrs = [allocator.allocate(Layout::new::<u64>()), allocator.allocate(Layout::new::<u64>())];
+      (ptrs[0] as *mut u64).write(7);
+      ptrs
+    };
+    // Break the tail's back link
+    unsafe { (*(ptrs[1].sub(HEADER_SIZE) as *mut Block)).prev = core::ptr::null_mut() };
+
+    let message = panic::catch_unwind(AssertUnwindSafe(|| allocator.assert_invariants())).unwrap_err();
+    let message = message.downcast_ref::<String>().unwrap();
+    assert!(message.contains("heap dumped to"), "{message}");
+
+    let dump = std::fs::read(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let restored = BumpAllocator::import(&mut dump.as_slice()).unwrap();
+    assert_eq!(restored.stats().live_blocks, 2);
+    let report = String::from_utf8_lossy(&dump);
+    assert!(report.contains("heap corruption:"));
+  }
+
+  #[test]
+  fn unwritable_path_is_reported() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_crash_dump(Some(Path::new("/nonexistent-dir/heap.dump")));
+
+    let note = allocator.write_crash_dump(&Corruption::DoubleFree { address: 0 });
+    assert!(note.contains("could not write heap dump"));
+  }
+}
@@ -0,0 +1,283 @@
+//! # Critical-Section Global Allocator
+//!
+//! An interrupt-safe wrapper that lets a [`BumpAllocator`] back
+//! `#[global_allocator]` on single-core embedded targets (e.g. Cortex-M).
+//!
+//! ## How It Works
+//!
+//! Every `alloc`/`dealloc` runs inside a [`critical_section::with`] closure.
+//! On bare-metal targets the `critical-section` implementation masks
+//! interrupts, so an interrupt handler can never observe (or mutate) the
+//! block list while the main thread is halfway through an update:
+//!
+//! ```text
+//!   main thread                          interrupt handler
+//!   ───────────                          ─────────────────
+//!   alloc(layout)
+//!     ├── critical_section::with ──┐
+//!     │     interrupts MASKED      │     (pending, cannot run)
+//!     │     allocator.allocate()   │
+//!     ├── ◄────────────────────────┘
+//!     │     interrupts restored    ───►  alloc(layout)
+//!     ▼                                    └── critical_section::with ...
+//! ```
+//!
+//! The platform must provide a `critical-section` implementation (for
+//! example `cortex-m` with its `critical-section-single-core` feature, or
+//! `critical-section/std` on hosted targets).
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use rallocator::CriticalSectionAllocator;
+//!
+//! #[global_allocator]
+//! static HEAP: CriticalSectionAllocator = CriticalSectionAllocator::new();
+//! ```
+
+use core::{
+  alloc::{GlobalAlloc, Layout},
+  cell::RefCell,
+};
+use critical_section::Mutex;
+
+#[cfg(feature = "counters")]
+use crate::SizeClasses;
+use crate::{BumpAllocator, SearchMode};
+
+/// A [`BumpAllocator`] guarded by a `critical-section` mutex.
+///
+/// Implements [`GlobalAlloc`], so it can be installed with
+/// `#[global_allocator]`. Construction is `const`, which allows placing the
+/// allocator in a `static`.
+pub struct CriticalSectionAllocator {
+  /// The wrapped allocator. Only ever borrowed inside a critical section.
+  inner: Mutex<RefCell<BumpAllocator>>,
+}
+
+// SAFETY: `BumpAllocator` is not `Send` because it stores raw pointers into
+// the heap. Those pointers are only dereferenced while the critical section
+// is held, which serializes every access across threads and interrupt
+// handlers, so sharing the wrapper is sound.
+unsafe impl Sync for CriticalSectionAllocator {}
+
+impl CriticalSectionAllocator {
+  /// Creates a new, empty allocator with the default search mode.
+  pub const fn new() -> Self {
+    Self::with_search_mode(SearchMode::FirstFit)
+  }
+
+  /// Creates a new, empty allocator with the specified search mode.
+  pub const fn with_search_mode(search_mode: SearchMode) -> Self {
+    let allocator = BumpAllocator::with_search_mode(search_mode);
+    // The critical section serializes access from any thread
+    #[cfg(feature = "thread-check")]
+    let allocator = allocator.into_shared();
+
+    Self {
+      inner: Mutex::new(RefCell::new(allocator)),
+    }
+  }
+
+  /// Hands the memory region `start..start + len` to the allocator.
+  ///
+  /// On targets without `sbrk` this must be called once, before the first
+  /// allocation - typically at the top of `main` with a `static` buffer:
+  ///
+  /// ```rust,ignore
+  /// static mut HEAP_MEM: [u8; 16 * 1024] = [0; 16 * 1024];
+  ///
+  /// unsafe { HEAP.init(core::ptr::addr_of_mut!(HEAP_MEM) as *mut u8, 16 * 1024) };
+  /// ```
+  ///
+  /// # Safety
+  ///
+  /// The region must be valid for reads and writes, unused by anything
+  /// else, and outlive every allocation. Calling `init` after allocations
+  /// were made leaks them and invalidates the old block list.
+  pub unsafe fn init(
+    &self,
+    start: *mut u8,
+    len: usize,
+  ) {
+    let search_mode = self.with(|allocator| allocator.search_mode());
+    self.with(|allocator| {
+      *allocator = unsafe { BumpAllocator::from_raw_region(start, len) };
+      #[cfg(feature = "thread-check")]
+      {
+        *allocator = core::mem::take(allocator).into_shared();
+      }
+      allocator.set_search_mode(search_mode);
+    });
+  }
+
+  /// Runs `f`, aborting the program if anything allocates from this
+  /// allocator meanwhile.
+  ///
+  /// Unlike [`with`](Self::with), `f` runs outside the critical section, so
+  /// it covers arbitrary code - collections, formatting, library calls -
+  /// when this is the `#[global_allocator]`. A global allocator must not
+  /// unwind, so a violation prints the panic message and aborts instead of
+  /// panicking.
+  ///
+  /// The allocator is shared, so the section applies to every thread and
+  /// interrupt handler allocating from it while `f` runs.
+  ///
+  /// ```rust,ignore
+  /// HEAP.forbid(|| mix_audio(&mut buffer));
+  /// ```
+  pub fn forbid<R>(
+    &self,
+    f: impl FnOnce() -> R,
+  ) -> R {
+    /// Leaves the section on drop.
+    struct Leave<'a>(&'a CriticalSectionAllocator, usize);
+    impl Drop for Leave<'_> {
+      fn drop(&mut self) {
+        let previous = self.1;
+        self.0.with(|allocator| allocator.forbid_depth = previous);
+      }
+    }
+
+    let previous = self.with(BumpAllocator::raise_forbid_depth);
+    let _leave = Leave(self, previous);
+    f()
+  }
+
+  /// Runs `f` with exclusive access to the wrapped allocator.
+  ///
+  /// Interrupts stay masked for the duration of `f`, so keep it short.
+  pub fn with<R>(
+    &self,
+    f: impl FnOnce(&mut BumpAllocator) -> R,
+  ) -> R {
+    critical_section::with(|cs| f(&mut self.inner.borrow_ref_mut(cs)))
+  }
+
+  /// The program-wide allocation histogram, when this is the
+  /// `#[global_allocator]`.
+  ///
+  /// Copies the histogram inside the critical section and returns it, so
+  /// it can be printed - which may allocate - outside it. Call it on
+  /// demand, at exit, or from a timer thread for periodic reports:
+  ///
+  /// ```rust,ignore
+  /// std::thread::spawn(|| loop {
+  ///     std::thread::sleep(Duration::from_secs(10));
+  ///     eprintln!("{}", HEAP.size_classes());
+  /// });
+  /// ```
+  #[cfg(feature = "counters")]
+  pub fn size_classes(&self) -> SizeClasses {
+    self.with(|allocator| allocator.size_classes())
+  }
+}
+
+impl Default for CriticalSectionAllocator {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// Reports an allocation inside [`CriticalSectionAllocator::forbid`].
+///
+/// `extern "C"` functions cannot unwind: the panic hook prints the message,
+/// then the process aborts instead of unwinding through `GlobalAlloc::alloc`.
+#[cold]
+extern "C" fn forbidden_global_allocation(
+  size: usize,
+  align: usize,
+) -> ! {
+  panic!("allocation of {size} bytes (align {align}) in a forbidden section");
+}
+
+unsafe impl GlobalAlloc for CriticalSectionAllocator {
+  unsafe fn alloc(
+    &self,
+    layout: Layout,
+  ) -> *mut u8 {
+    // SAFETY: `GlobalAlloc` guarantees a non-zero-sized layout, and the
+    // critical section provides the exclusive access `allocate` requires.
+    let address = self.with(|allocator| {
+      if allocator.allocations_forbidden() {
+        // Let the panic message be formatted and printed
+        allocator.forbid_depth = 0;
+        return None;
+      }
+      Some(unsafe { allocator.allocate_sized(layout) })
+    });
+    address.unwrap_or_else(|| forbidden_global_allocation(layout.size(), layout.align()))
+  }
+
+  unsafe fn dealloc(
+    &self,
+    ptr: *mut u8,
+    layout: Layout,
+  ) {
+    // SAFETY: The caller guarantees `ptr` came from `alloc` on this
+    // allocator with `layout`.
+    self.with(|allocator| unsafe { allocator.deallocate_sized(ptr, layout) })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn global_alloc_round_trip() {
+    static HEAP: CriticalSectionAllocator = CriticalSectionAllocator::new();
+
+    unsafe {
+      let layout = Layout::new::<u64>();
+      let ptr = HEAP.alloc(layout) as *mut u64;
+      assert!(!ptr.is_null());
+      assert!(crate::align::is_ptr_aligned(ptr as *const u8, layout.align()));
+
+      ptr.write(0xC0FFEE);
+      assert_eq!(ptr.read(), 0xC0FFEE);
+
+      HEAP.dealloc(ptr as *mut u8, layout);
+    }
+  }
+
+  #[test]
+  fn with_search_mode_is_forwarded() {
+    let heap = CriticalSectionAllocator::with_search_mode(SearchMode::BestFit);
+    assert_eq!(heap.with(|allocator| allocator.search_mode()), SearchMode::BestFit);
+  }
+
+  #[test]
+  fn init_switches_to_region_backend() {
+    let heap = CriticalSectionAllocator::with_search_mode(SearchMode::NextFit);
+    let buffer = Box::leak(vec![0u8; 1024].into_boxed_slice());
+    let range = buffer.as_ptr_range();
+
+    unsafe {
+      heap.init(buffer.as_mut_ptr(), buffer.len());
+
+      let ptr = heap.alloc(Layout::new::<u32>());
+      assert!(range.contains(&(ptr as *const u8)));
+    }
+    assert_eq!(heap.with(|allocator| allocator.search_mode()), SearchMode::NextFit);
+  }
+
+  #[test]
+  fn forbid_allows_frees_and_restores_on_exit() {
+    let heap = CriticalSectionAllocator::new();
+    let buffer = Box::leak(vec![0u8; 1024].into_boxed_slice());
+
+    unsafe {
+      heap.init(buffer.as_mut_ptr(), buffer.len());
+      let layout = Layout::new::<u64>();
+      let ptr = heap.alloc(layout);
+
+      heap.forbid(|| {
+        assert!(heap.with(|allocator| allocator.allocations_forbidden()));
+        heap.dealloc(ptr, layout);
+      });
+      assert!(!heap.with(|allocator| allocator.allocations_forbidden()));
+      assert!(!heap.alloc(layout).is_null());
+    }
+  }
+}
@@ -0,0 +1,221 @@
+//! # Donating Memory Between Arenas
+//!
+//! Per-thread or per-subsystem arenas rarely fill up evenly: one runs dry
+//! while its neighbour sits on free blocks. [`donate_to`] moves memory
+//! across without going back to the OS. The donor allocates one block,
+//! and the recipient keeps it as a *donation*, a nested allocator it falls back on once its own
+//! backend is full:
+//!
+//! ```text
+//!   donor heap                               recipient
+//!   ┌────────┬──────────────────────┬───┐    heap: [A][B][C]  full
+//!   │ blk X  │ donated block        │ Y │          │
+//!   └────────┴──────────────────────┴───┘          ▼ place fails
+//!            │                                donations ──► try each chunk
+//!            ▼                                          │     still null?
+//!            ┌──────────┬─────────────────┐             ▼
+//!            │ Donation │ region (`bytes`)│  ◄── allocate from here
+//!            └──────────┴─────────────────┘     ──► OOM handler, reserve
+//! ```
+//!
+//! The layout is the one of the emergency reserve: the nested allocator
+//! lives at the start of the block and manages the rest. Donations are
+//! tried before the recipient unlocks its reserve, and its
+//! [`deallocate`](BumpAllocator::deallocate) recognises their pointers by
+//! address. Stealing is the same call from the other side:
+//! `busy.donate_to(&mut idle, bytes)` becomes `idle.donate_to(&mut busy,
+//! bytes)`.
+//!
+//! The donated block stays a live block of the donor, so the donor must
+//! outlive the recipient's use of it. The recipient's `reset` forgets its
+//! donations; its diagnostics and forks cover its own heap only.
+//!
+//! [`donate_to`]: BumpAllocator::donate_to
+
+use core::{alloc::Layout, mem, ptr};
+
+use crate::BumpAllocator;
+
+/// Alignment of a donated block (and so of its nested allocator).
+const DONATION_ALIGN: usize = 16;
+
+/// Head of a donated block: a nested allocator over the rest of it.
+pub(crate) struct Donation {
+  /// Allocator over the `len` bytes after this header.
+  arena: BumpAllocator,
+
+  /// Length of the region managed by `arena`.
+  len: usize,
+
+  /// Next donation of the recipient, or null.
+  next: *mut Donation,
+}
+
+impl Donation {
+  /// Whether `address` lies in this donation's region.
+  fn owns(
+    &self,
+    address: usize,
+  ) -> bool {
+    let start = self as *const Donation as usize + mem::size_of::<Donation>();
+    (start..start + self.len).contains(&address)
+  }
+}
+
+impl BumpAllocator {
+  /// Moves `bytes` of this allocator's memory to `other`, which uses them
+  /// once its own backend is full. Returns `false`, donating nothing, if
+  /// this allocator cannot provide the block.
+  ///
+  /// # Safety
+  ///
+  /// The donated block stays live in this allocator: until `other` is
+  /// reset or dropped, this allocator must not be reset, dropped or shrunk
+  /// past the block, and `other` must not be moved to where the block's
+  /// memory is freed.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// // The parser arena is idle, the renderer is running out
+  /// if renderer.stats().headroom < Some(64 * 1024) {
+  ///     unsafe { parser.donate_to(&mut renderer, 256 * 1024) };
+  /// }
+  /// ```
+  pub unsafe fn donate_to(
+    &mut self,
+    other: &mut BumpAllocator,
+    bytes: usize,
+  ) -> bool {
+    let Some(total) = mem::size_of::<Donation>().checked_add(bytes) else {
+      return false;
+    };
+    let Ok(layout) = Layout::from_size_align(total, DONATION_ALIGN.max(mem::align_of::<Donation>())) else {
+      return false;
+    };
+
+    // SAFETY: `&mut self` gives exclusive access.
+    let block = unsafe { self.allocate(layout) };
+    if block.is_null() {
+      return false;
+    }
+
+    let donation = block as *mut Donation;
+    // SAFETY: The block is live, suitably aligned, and large enough for the
+    // header followed by `bytes` of region.
+    unsafe {
+      let region = block.add(mem::size_of::<Donation>());
+      donation.write(Donation {
+        arena: BumpAllocator::from_raw_region(region, bytes),
+        len: bytes,
+        next: other.donations,
+      });
+    }
+    other.donations = donation;
+    true
+  }
+
+  /// Bytes donated to this allocator and not forgotten by a reset.
+  pub fn donated_bytes(&self) -> usize {
+    let mut total = 0;
+    let mut current = self.donations;
+    while !current.is_null() {
+      // SAFETY: Donations stay valid by the contract of `donate_to`.
+      unsafe {
+        total += (*current).len;
+        current = (*current).next;
+      }
+    }
+    total
+  }
+
+  /// Allocates from the donations, newest first; null if none has room.
+  pub(crate) unsafe fn allocate_donated(
+    &mut self,
+    layout: Layout,
+  ) -> *mut u8 {
+    let mut current = self.donations;
+    while !current.is_null() {
+      // SAFETY: As in `donated_bytes`; the recipient's `&mut self` makes
+      // the access exclusive.
+      unsafe {
+        let address = (*current).arena.allocate(layout);
+        if !address.is_null() {
+          return address;
+        }
+        current = (*current).next;
+      }
+    }
+    ptr::null_mut()
+  }
+
+  /// Frees `address` if a donation handed it out. Returns whether one did.
+  pub(crate) unsafe fn deallocate_donated(
+    &mut self,
+    address: *mut u8,
+  ) -> bool {
+    let mut current = self.donations;
+    while !current.is_null() {
+      // SAFETY: As in `allocate_donated`.
+      unsafe {
+        if (*current).owns(address as usize) {
+          (*current).arena.deallocate(address);
+          return true;
+        }
+        current = (*current).next;
+      }
+    }
+    false
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// A donor with room for a few donations of `bytes`.
+  fn donor(bytes: usize) -> BumpAllocator {
+    BumpAllocator::with_capacity(4 * (mem::size_of::<Donation>() + bytes))
+  }
+
+  /// Allocates 64-byte blocks from `allocator` until it fails.
+  fn fill(allocator: &mut BumpAllocator) -> Vec<*mut u8> {
+    let layout = Layout::array::<u8>(64).unwrap();
+    core::iter::from_fn(|| Some(unsafe { allocator.allocate(layout) }).filter(|ptr| !ptr.is_null())).collect()
+  }
+
+  #[test]
+  fn recipients_allocate_from_donations_when_full() {
+    let mut donor = donor(2048);
+    let mut recipient = BumpAllocator::with_capacity(1024);
+    let own = fill(&mut recipient).len();
+
+    assert!(unsafe { donor.donate_to(&mut recipient, 2048) });
+    assert_eq!(recipient.donated_bytes(), 2048);
+    assert_eq!(donor.live_blocks(), 1);
+
+    let donated = fill(&mut recipient);
+    assert!(!donated.is_empty());
+    assert_eq!(recipient.live_blocks(), own);
+    assert!(!recipient.emergency_reserve_unlocked());
+
+    // Freed donated blocks go back to their donation
+    unsafe { recipient.deallocate(*donated.last().unwrap()) };
+    assert_eq!(fill(&mut recipient).len(), 1);
+    recipient.assert_invariants();
+  }
+
+  #[test]
+  fn failed_donations_change_nothing() {
+    let mut donor = donor(256);
+    let mut recipient = BumpAllocator::with_capacity(1024);
+    assert!(!unsafe { donor.donate_to(&mut recipient, 8 * (mem::size_of::<Donation>() + 256)) });
+    assert!(!unsafe { donor.donate_to(&mut recipient, usize::MAX) });
+    assert_eq!(recipient.donated_bytes(), 0);
+    assert_eq!(donor.live_blocks(), 0);
+
+    assert!(unsafe { donor.donate_to(&mut recipient, 256) });
+    unsafe { recipient.reset() };
+    assert_eq!(recipient.donated_bytes(), 0);
+  }
+}
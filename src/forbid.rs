@@ -0,0 +1,137 @@
+//! # Allocation-Free Sections
+//!
+//! Latency-sensitive code (an audio callback, an interrupt handler, the
+//! inner loop of a frame) often must not allocate. [`BumpAllocator::forbid`]
+//! turns that rule into a check: any allocation inside the closure panics,
+//! pointing at the offending call.
+//!
+//! ```text
+//!   allocator.forbid(|a| {            forbid depth 1
+//!       a.forbid(|a| { ... });        forbid depth 2, back to 1
+//!       a.allocate(layout);           ──► panic: allocation in a forbidden section
+//!   });                               forbid depth 0 (also after a panic)
+//! ```
+//!
+//! Deallocation stays allowed: freeing is bounded work. When the allocator
+//! backs the global allocator, `CriticalSectionAllocator::forbid` covers
+//! every allocation made by the closure; since a global allocator must not
+//! unwind, a violation prints the panic message and aborts.
+
+use core::alloc::Layout;
+
+use crate::BumpAllocator;
+
+/// Restores the forbid depth on drop, including while unwinding.
+struct RestoreDepth {
+  /// The allocator's `forbid_depth`.
+  depth: *mut usize,
+
+  /// Depth to restore.
+  previous: usize,
+}
+
+impl Drop for RestoreDepth {
+  fn drop(&mut self) {
+    // SAFETY: Points into an allocator that outlives the guard.
+    unsafe { *self.depth = self.previous };
+  }
+}
+
+impl BumpAllocator {
+  /// Runs `f`, panicking if it allocates from this allocator.
+  ///
+  /// Sections nest. The check costs one comparison per allocation.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// allocator.forbid(|allocator| process_samples(allocator, &mut buffer));
+  /// ```
+  pub fn forbid<R>(
+    &mut self,
+    f: impl FnOnce(&mut Self) -> R,
+  ) -> R {
+    let _restore = self.enter_forbidden();
+    f(self)
+  }
+
+  /// Whether allocations are currently forbidden.
+  pub fn allocations_forbidden(&self) -> bool {
+    self.forbid_depth > 0
+  }
+
+  /// Enters a forbidden section until the returned guard drops.
+  fn enter_forbidden(&mut self) -> RestoreDepth {
+    let previous = self.raise_forbid_depth();
+    RestoreDepth {
+      depth: &mut self.forbid_depth,
+      previous,
+    }
+  }
+
+  /// Enters one more forbidden section, returning the previous depth.
+  pub(crate) fn raise_forbid_depth(&mut self) -> usize {
+    let previous = self.forbid_depth;
+    self.forbid_depth += 1;
+    previous
+  }
+
+  /// Reports an allocation inside a forbidden section.
+  #[cold]
+  #[track_caller]
+  pub(crate) fn forbidden_allocation(
+    &self,
+    layout: Layout,
+  ) -> ! {
+    panic!(
+      "allocation of {} bytes (align {}) in a forbidden section",
+      layout.size(),
+      layout.align()
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::panic::{AssertUnwindSafe, catch_unwind};
+
+  #[test]
+  fn sections_without_allocations_pass() {
+    let mut allocator = BumpAllocator::with_capacity(1024);
+    let ptr = unsafe { allocator.allocate(Layout::new::<u64>()) };
+
+    let value = allocator.forbid(|allocator| {
+      assert!(allocator.allocations_forbidden());
+      // Freeing is allowed
+      unsafe { allocator.deallocate(ptr) };
+      7
+    });
+    assert_eq!(value, 7);
+    assert!(!allocator.allocations_forbidden());
+  }
+
+  #[test]
+  #[should_panic(expected = "allocation of 8 bytes (align 8) in a forbidden section")]
+  fn allocating_in_a_section_panics() {
+    let mut allocator = BumpAllocator::with_capacity(1024);
+    allocator.forbid(|allocator| unsafe { allocator.allocate(Layout::new::<u64>()) });
+  }
+
+  #[test]
+  fn sections_nest_and_unwind_cleanly() {
+    let mut allocator = BumpAllocator::with_capacity(1024);
+
+    allocator.forbid(|allocator| {
+      allocator.forbid(|_| {});
+      assert!(allocator.allocations_forbidden());
+
+      let result = catch_unwind(AssertUnwindSafe(|| unsafe { allocator.allocate(Layout::new::<u64>()) }));
+      assert!(result.is_err());
+      assert!(allocator.allocations_forbidden());
+    });
+
+    assert!(!allocator.allocations_forbidden());
+    assert!(!unsafe { allocator.allocate(Layout::new::<u64>()) }.is_null());
+  }
+}
@@ -0,0 +1,270 @@
+//! # Forking
+//!
+//! [`BumpAllocator::fork`] copies an allocator's heap into a new one, for
+//! speculative work that may be thrown away: run it on the fork, then keep
+//! the fork or drop it.
+//!
+//! ```text
+//!   original (sbrk or region)            fork (owned region)
+//!   ┌──────┬──────┬──────┬──────┐        ┌──────┬──────┬──────┬──────┐
+//!   │ A    │ B    │ C    │ D    │  copy  │ A'   │ B'   │ C'   │ D'   │
+//!   │ live │ free │ live │ live │ ─────► │ live │ free │ live │ live │
+//!   └──────┴──────┴──────┴──────┘        └──────┴──────┴──────┴──────┘
+//!   ▲ start                              ▲ start + offset
+//!
+//!   A' = A + offset for every block; alignment is kept up to FORK_ALIGN
+//! ```
+//!
+//! The heap is copied as one span, so every block keeps its position
+//! relative to the others and [`ForkedArena::translate`] maps any pointer
+//! of the original to its copy. Pointers *stored inside* payloads are
+//! copied as plain bytes and still point into the original: relocating them
+//! needs handles or a relocation map, which is out of scope.
+//!
+//! The copy is eager: every byte of the heap is duplicated up front. A
+//! copy-on-write fork (a second `MAP_PRIVATE` mapping of a `memfd`, sharing
+//! pages until written) needs the heap to live in a file-backed mapping,
+//! which neither `sbrk` nor region memory is; it waits for the `mmap`
+//! backend.
+
+use core::{
+  ops::{Deref, DerefMut},
+  ptr,
+};
+
+use crate::{
+  BumpAllocator,
+  backend::{Backend, Region},
+  block::{Block, HEADER_SIZE},
+};
+
+/// Largest alignment a fork preserves: the copy starts at the same offset
+/// modulo this value as the original heap.
+pub const FORK_ALIGN: usize = 4096;
+
+/// A copy of an allocator, made by [`BumpAllocator::fork`].
+///
+/// Dereferences to the forked [`BumpAllocator`], which owns its memory and
+/// is independent of the original from then on.
+pub struct ForkedArena {
+  /// The copy.
+  arena: BumpAllocator,
+
+  /// Distance from an original block to its copy, in bytes.
+  offset: isize,
+}
+
+impl BumpAllocator {
+  /// Copies this allocator's heap, live and free blocks alike, into a new
+  /// allocator backed by an owned region.
+  ///
+  /// The fork has the same [`config`](Self::config), except that it has no
+  /// emergency reserve (the reserve's block becomes a free block), and no
+  /// handlers installed. Its region keeps the original's unused capacity,
+  /// or [`FORK_ALIGN`] bytes of room to grow for a program break heap.
+  ///
+  /// # Returns
+  ///
+  /// `None` if the memory for the copy cannot be reserved, or if the heap
+  /// is not one contiguous span (the program break was moved by someone
+  /// else between two of this allocator's blocks).
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// let board = unsafe { allocator.allocate(Layout::new::<Board>()) } as *mut Board;
+  ///
+  /// let mut attempt = allocator.fork().unwrap();
+  /// let copy = attempt.translate(board);
+  /// unsafe { (*copy).play(next_move) };   // the original is untouched
+  /// ```
+  pub fn fork(&self) -> Option<ForkedArena> {
+    let used = self.backend.used_bytes();
+    let end = self.backend.current_break();
+    let start = end.wrapping_sub(used);
+
+    // Every block must lie in the span being copied
+    if !self.first.is_null() && (self.first as usize) < start as usize {
+      return None;
+    }
+
+    // Room for the alignment padding, the copy, and what is left to grow
+    let spare = self.backend.capacity().map_or(FORK_ALIGN, |capacity| capacity.saturating_sub(used));
+    let mut region = Region::owned(used.checked_add(FORK_ALIGN)?.checked_add(spare)?);
+    // Skip ahead so the copy has the same alignment as the original
+    let reserved = region.grow(0)?;
+    let padding = (start as usize).wrapping_sub(reserved as usize) % FORK_ALIGN;
+    region.grow(padding)?;
+    let base = region.grow(used)?;
+
+    let offset = (base as isize).wrapping_sub(start as isize);
+    let mut arena = BumpAllocator::with_backend(Backend::Region(region));
+    arena.apply_config(self.config());
+
+    if used > 0 {
+      // SAFETY: `start..end` is this allocator's heap and `base` has room
+      // for `used` bytes in a separate reservation.
+      unsafe { ptr::copy_nonoverlapping(start, base, used) };
+    }
+
+    let relocate = |block: *mut Block| {
+      if block.is_null() {
+        block
+      } else {
+        block.wrapping_byte_offset(offset)
+      }
+    };
+
+    // SAFETY: The copy is a byte-for-byte image of a valid block list, so
+    // relocating each link by `offset` yields a valid list in the copy.
+    unsafe {
+      arena.first = relocate(self.first);
+      arena.last = relocate(self.last);
+      arena.reuse_floor = relocate(self.reuse_floor);
+      arena.tracked_blocks = self.tracked_blocks;
+      if !self.untracked.is_null() {
+        arena.untracked = self.untracked.wrapping_offset(offset);
+      }
+      arena.headerless_max = self.headerless_max;
+      arena.headerless_free = self.headerless_free;
+      arena.headerless_end = self.headerless_end;
+      arena.relocate_headerless(offset);
+      arena.types = self.types.clone();
+      arena.relocate_types(offset);
+      arena.refcounts = self.refcounts.clone();
+      arena.relocate_refs(offset);
+      arena.tag = self.tag.clone();
+      arena.tags = self.tags.clone();
+      arena.callsites = self.callsites.clone();
+      arena.relocate_attributions(offset);
+      let mut current = arena.first;
+      while !current.is_null() {
+        (*current).next = relocate((*current).next);
+        (*current).prev = relocate((*current).prev);
+        current = (*current).next;
+      }
+
+      // The reserve's nested allocator points into the original: drop it
+      if let Some(reserve) = self.reserve.arena() {
+        let block = (reserve as *const BumpAllocator as *mut u8).sub(HEADER_SIZE) as *mut Block;
+        (*relocate(block)).is_free = true;
+      }
+    }
+    arena.rebuild_free_list();
+    #[cfg(feature = "shadow")]
+    arena.rebuild_shadow();
+
+    Some(ForkedArena { arena, offset })
+  }
+}
+
+impl ForkedArena {
+  /// The copy of `ptr`, a pointer into the original allocator's heap.
+  ///
+  /// Only the address changes; the result is meaningful for pointers that
+  /// were live in the original when it was forked.
+  pub fn translate<T>(
+    &self,
+    ptr: *mut T,
+  ) -> *mut T {
+    ptr.wrapping_byte_offset(self.offset)
+  }
+
+  /// Distance in bytes from an original block to its copy.
+  pub fn offset(&self) -> isize {
+    self.offset
+  }
+
+  /// The forked allocator, without the translation helper.
+  pub fn into_inner(self) -> BumpAllocator {
+    self.arena
+  }
+}
+
+impl Deref for ForkedArena {
+  type Target = BumpAllocator;
+
+  fn deref(&self) -> &BumpAllocator {
+    &self.arena
+  }
+}
+
+impl DerefMut for ForkedArena {
+  fn deref_mut(&mut self) -> &mut BumpAllocator {
+    &mut self.arena
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::alloc::Layout;
+
+  #[test]
+  fn fork_copies_live_data() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+
+    unsafe {
+      let a = allocator.allocate(Layout::new::<u64>()) as *mut u64;
+      let b = allocator.allocate(Layout::new::<u64>()) as *mut u64;
+      a.write(1);
+      b.write(2);
+
+      let mut fork = allocator.fork().unwrap();
+      assert_eq!(*fork.translate(a), 1);
+      assert_eq!(*fork.translate(b), 2);
+
+      // The copies are independent
+      fork.translate(a).write(10);
+      assert_eq!(*a, 1);
+      assert_eq!(fork.stats().live_blocks, 2);
+      assert_eq!(fork.stats().bytes_in_use, allocator.stats().bytes_in_use);
+      fork.assert_invariants();
+
+      // Discarding the fork leaves the original intact
+      let copy = fork.translate(b);
+      fork.deallocate(copy as *mut u8);
+      drop(fork);
+      assert_eq!(allocator.stats().live_blocks, 2);
+    }
+  }
+
+  #[test]
+  fn fork_keeps_free_blocks() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+
+    unsafe {
+      let layout = Layout::new::<[u8; 64]>();
+      let a = allocator.allocate(layout);
+      allocator.allocate(layout);
+      allocator.deallocate(a);
+
+      let fork = allocator.fork().unwrap();
+      assert!(fork.allocation_info(fork.translate(a)).unwrap().is_free);
+      assert_eq!(fork.stats().free_blocks, 1);
+      fork.assert_invariants();
+    }
+  }
+
+  #[test]
+  fn fork_preserves_alignment() {
+    let mut allocator = BumpAllocator::with_capacity(8192);
+
+    unsafe {
+      allocator.allocate(Layout::new::<u8>());
+      let page = allocator.allocate(Layout::from_size_align(16, 1024).unwrap());
+
+      let fork = allocator.fork().unwrap();
+      assert!(crate::align::is_ptr_aligned(fork.translate(page), 1024));
+    }
+  }
+
+  #[test]
+  fn empty_allocator_forks() {
+    let allocator = BumpAllocator::with_capacity(256);
+    let mut fork = allocator.fork().unwrap();
+
+    assert_eq!(fork.stats().live_blocks, 0);
+    assert!(!unsafe { fork.allocate(Layout::new::<u64>()) }.is_null());
+  }
+}
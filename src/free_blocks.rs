@@ -0,0 +1,182 @@
+//! # Free Block Iteration
+//!
+//! [`BumpAllocator::free_blocks`] lists the reuse candidates the free
+//! block search looks at, lowest address first, for external placement
+//! policies, visualizers and tests:
+//!
+//! ```text
+//!   block list:  [A,used] → [B,free] → [C,used] → [D,free] → [E,used]
+//!   free list:              [B] ───────────────► [D]
+//!
+//!   free_blocks() yields B, D without visiting A, C or E
+//! ```
+//!
+//! The iterator follows the free list, so it costs O(f) in the number of
+//! free blocks, not O(n) in all of them. In real-time mode, where freed
+//! blocks are only marked (see
+//! [`set_realtime`](BumpAllocator::set_realtime)), it walks the block list
+//! instead, so it never misses a free block.
+
+use core::{fmt, marker::PhantomData};
+
+use crate::{
+  BumpAllocator,
+  block::{Block, HEADER_SIZE},
+};
+
+/// A free block, yielded by [`BumpAllocator::free_blocks`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FreeBlock {
+  /// Start of the payload (the pointer `allocate` once returned).
+  pub address: usize,
+
+  /// Payload bytes available for reuse.
+  pub size: usize,
+}
+
+impl fmt::Display for FreeBlock {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    write!(f, "free block at {:#x}: {} bytes", self.address, self.size)
+  }
+}
+
+/// Iterator over the free blocks of an allocator, lowest address first.
+///
+/// Created by [`BumpAllocator::free_blocks`].
+pub struct FreeBlocks<'a> {
+  /// Next block to look at.
+  current: *mut Block,
+
+  /// Whether `current` is on the free list (`true`) or the block list.
+  linked: bool,
+
+  /// Ties the iterator to a borrow of the allocator.
+  _allocator: PhantomData<&'a BumpAllocator>,
+}
+
+impl Iterator for FreeBlocks<'_> {
+  type Item = FreeBlock;
+
+  fn next(&mut self) -> Option<FreeBlock> {
+    // SAFETY: The allocator is borrowed for the iterator's lifetime, so
+    // both lists are valid and unchanged.
+    unsafe {
+      while !self.current.is_null() {
+        let block = self.current;
+        if self.linked {
+          self.current = (*Block::free_links(block)).next_free;
+        } else {
+          self.current = (*block).next;
+          if !(*block).is_free {
+            continue;
+          }
+        }
+        return Some(FreeBlock {
+          address: block as usize + HEADER_SIZE,
+          size: (*block).size,
+        });
+      }
+      None
+    }
+  }
+}
+
+impl BumpAllocator {
+  /// Iterates over the free blocks, lowest address first.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// let largest = allocator.free_blocks().map(|block| block.size).max();
+  /// ```
+  pub fn free_blocks(&self) -> FreeBlocks<'_> {
+    let (current, linked) = if self.realtime {
+      (self.first, false)
+    } else {
+      (self.free_head, true)
+    };
+    FreeBlocks {
+      current,
+      linked,
+      _allocator: PhantomData,
+    }
+  }
+
+  /// Number of free blocks, O(f) outside real-time mode.
+  pub fn free_block_count(&self) -> usize {
+    self.free_blocks().count()
+  }
+
+  /// Whether any freed block is waiting for reuse.
+  pub fn has_free_blocks(&self) -> bool {
+    self.free_blocks().next().is_some()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::alloc::Layout;
+
+  /// Five blocks of 8, 16, .. 40 bytes, with the ones at `free` freed.
+  fn heap(
+    allocator: &mut BumpAllocator,
+    free: &[usize],
+  ) -> Vec<*mut u8> {
+    let ptrs: Vec<_> = (1..=5).map(|i| unsafe { allocator.allocate(Layout::array::<u64>(i).unwrap()) }).collect();
+    for &index in free {
+      unsafe { allocator.deallocate(ptrs[index]) };
+    }
+    ptrs
+  }
+
+  #[test]
+  fn free_blocks_are_listed_in_address_order() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    assert!(!allocator.has_free_blocks());
+
+    let ptrs = heap(&mut allocator, &[3, 1]);
+    // A freed block takes the slack up to the next header
+    let span = |words| crate::bump::grow_request_size(Layout::array::<u64>(words).unwrap()).unwrap() - HEADER_SIZE;
+    let free: Vec<_> = allocator.free_blocks().collect();
+    assert_eq!(
+      free,
+      [
+        FreeBlock {
+          address: ptrs[1] as usize,
+          size: span(2)
+        },
+        FreeBlock {
+          address: ptrs[3] as usize,
+          size: span(4)
+        },
+      ]
+    );
+    assert_eq!(allocator.free_block_count(), allocator.stats().free_blocks);
+    assert!(allocator.has_free_blocks());
+  }
+
+  #[test]
+  fn realtime_frees_are_listed_too() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_realtime(true);
+
+    let ptrs = heap(&mut allocator, &[0, 2]);
+    let addresses: Vec<_> = allocator.free_blocks().map(|block| block.address).collect();
+    assert_eq!(addresses, [ptrs[0] as usize, ptrs[2] as usize]);
+
+    // Same answer once the blocks are linked
+    allocator.set_realtime(false);
+    assert_eq!(allocator.free_blocks().map(|block| block.address).collect::<Vec<_>>(), addresses);
+  }
+
+  #[test]
+  fn popped_tail_is_not_listed() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    heap(&mut allocator, &[4]);
+    assert_eq!(allocator.free_block_count(), 0);
+  }
+}
@@ -0,0 +1,304 @@
+//! # Frozen Arenas
+//!
+//! [`BumpAllocator::freeze`] ends an allocator's mutable life: it consumes
+//! the allocator and returns a [`FrozenArena`] that can only be read. A
+//! data structure built in an arena can then be shared across threads
+//! without locks:
+//!
+//! ```text
+//!   build (one thread)          freeze()          share (many threads)
+//!   ┌───────────────────┐                   ┌───────────────────────────┐
+//!   │ BumpAllocator     │ ────────────────► │ FrozenArena: Send + Sync  │
+//!   │ allocate/dealloc  │   one way, no     │ get, stats, heap_map, ... │
+//!   └───────────────────┘   way back        └───────────────────────────┘
+//! ```
+//!
+//! On Unix, [`BumpAllocator::freeze_read_only`] also `mprotect`s the pages
+//! that lie entirely inside the heap, so a stray write through an old
+//! `*mut` faults instead of racing with readers. The protection is lifted
+//! when the frozen arena is dropped, before its memory is released.
+//!
+//! A program break heap may be split into spans by other `sbrk` users
+//! (see [`SPAN_START`](crate::block::SPAN_START)). Each span is protected
+//! on its own, so memory between them - someone else's - stays writable:
+//!
+//! ```text
+//!   [ span 1: blocks ]  foreign sbrk memory  [ span 2: blocks ... break ]
+//!    ▲ read-only ▲         untouched          ▲ read-only ...........▲
+//! ```
+
+use crate::{BlockInfo, BumpAllocator, Corruption, HeapMap, Stats, TopAllocations};
+#[cfg(unix)]
+use crate::{
+  align::{align_down, align_up, align_word},
+  block::HEADER_SIZE,
+};
+
+/// A read-only allocator, made by [`BumpAllocator::freeze`].
+///
+/// Offers no way to allocate, free or reset, so every thread holding a
+/// reference sees the same, unchanging heap.
+pub struct FrozenArena {
+  /// The frozen allocator. Only `&self` methods are ever called on it.
+  inner: BumpAllocator,
+
+  /// Page size `freeze_read_only` protected the spans with, if it did.
+  #[cfg(unix)]
+  protected: Option<usize>,
+}
+
+// SAFETY: Nothing mutates the allocator or its heap after `freeze` (the
+// API only hands out shared access), and dropping it touches no
+// thread-local state: region memory goes back to the global allocator,
+// and program break memory is left as is.
+unsafe impl Send for FrozenArena {}
+
+// SAFETY: See `Send`; concurrent readers of unchanging memory never race.
+unsafe impl Sync for FrozenArena {}
+
+impl BumpAllocator {
+  /// Consumes the allocator, leaving its heap readable but immutable.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// let root = build_tree(&mut allocator);
+  /// let frozen = Arc::new(allocator.freeze());
+  ///
+  /// let reader = Arc::clone(&frozen);
+  /// thread::spawn(move || unsafe { reader.get(root).depth() });
+  /// ```
+  pub fn freeze(self) -> FrozenArena {
+    #[cfg(feature = "thread-check")]
+    let this = self.into_shared();
+    #[cfg(not(feature = "thread-check"))]
+    let this = self;
+
+    FrozenArena {
+      inner: this,
+      #[cfg(unix)]
+      protected: None,
+    }
+  }
+
+  /// [`freeze`](Self::freeze), then makes every page that lies entirely
+  /// inside one of the heap's spans read-only with `mprotect`.
+  ///
+  /// Headers and payloads on the partial pages at either end of a span
+  /// stay writable; they are shared with memory this allocator does not own.
+  #[cfg(unix)]
+  pub fn freeze_read_only(self) -> FrozenArena {
+    let mut frozen = self.freeze();
+
+    let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    let Ok(page) = usize::try_from(page) else {
+      return frozen;
+    };
+    frozen.inner.for_each_span_page(page, |start, len| {
+      // SAFETY: The pages lie inside this allocator's heap, which nothing
+      // writes to any more; they are made writable again on drop.
+      if unsafe { libc::mprotect(start as *mut libc::c_void, len, libc::PROT_READ) } == 0 {
+        frozen.protected = Some(page);
+      }
+    });
+    frozen
+  }
+
+  /// Calls `f` with `(start, len)` of the whole pages of every span of the
+  /// heap: runs of blocks up to the next [`SPAN_START`] block, and the
+  /// backend's current span up to the break. Spans never overlap.
+  ///
+  /// [`SPAN_START`]: crate::block::SPAN_START
+  #[cfg(unix)]
+  fn for_each_span_page(
+    &self,
+    page: usize,
+    mut f: impl FnMut(usize, usize),
+  ) {
+    let mut pages = |start: usize, end: usize| {
+      let first = align_up(start, page);
+      let last = align_down(end, page);
+      if first < last {
+        f(first, last - first);
+      }
+    };
+    let end = self.backend.current_break() as usize;
+    // Blocks in the current span are covered by it as a whole
+    let current = end - self.backend.used_bytes();
+
+    let mut span: Option<(usize, usize)> = None;
+    let mut block = self.first;
+    // SAFETY: The list of a frozen allocator is intact and unchanging.
+    unsafe {
+      while !block.is_null() {
+        if (*block).starts_span() || span.is_none() {
+          if let Some((start, end)) = span {
+            pages(start, end.min(current));
+          }
+          span = Some((block as usize, 0));
+        }
+        if let Some((_, end)) = &mut span {
+          *end = align_word(block as usize + HEADER_SIZE + (*block).payload_extent());
+        }
+        block = (*block).next;
+      }
+    }
+    if let Some((start, end)) = span {
+      pages(start, end.min(current));
+    }
+    pages(current, end);
+  }
+}
+
+impl FrozenArena {
+  /// The value at `ptr`, for as long as the arena lives.
+  ///
+  /// # Safety
+  ///
+  /// `ptr` must point to an initialized `T` inside a live block of this
+  /// arena.
+  pub unsafe fn get<T>(
+    &self,
+    ptr: *const T,
+  ) -> &T {
+    debug_assert!(
+      self.inner.allocation_info(ptr as *const u8).is_some_and(|info| !info.is_free),
+      "{ptr:?} is not inside a live block of this arena"
+    );
+    unsafe { &*ptr }
+  }
+
+  /// Whether some of the heap was made read-only by
+  /// [`freeze_read_only`](BumpAllocator::freeze_read_only).
+  pub fn is_protected(&self) -> bool {
+    #[cfg(unix)]
+    return self.protected.is_some();
+    #[cfg(not(unix))]
+    false
+  }
+
+  /// See [`BumpAllocator::stats`].
+  pub fn stats(&self) -> Stats {
+    self.inner.stats()
+  }
+
+  /// See [`BumpAllocator::heap_map`].
+  pub fn heap_map(&self) -> HeapMap<'_> {
+    self.inner.heap_map()
+  }
+
+  /// See [`BumpAllocator::allocation_info`].
+  pub fn allocation_info(
+    &self,
+    ptr: *const u8,
+  ) -> Option<BlockInfo> {
+    self.inner.allocation_info(ptr)
+  }
+
+  /// See [`BumpAllocator::top_allocations`].
+  pub fn top_allocations<const N: usize>(&self) -> TopAllocations<N> {
+    self.inner.top_allocations()
+  }
+
+  /// See [`BumpAllocator::check_invariants`].
+  pub fn check_invariants(&self) -> Result<(), Corruption> {
+    self.inner.check_invariants()
+  }
+}
+
+impl Drop for FrozenArena {
+  fn drop(&mut self) {
+    #[cfg(unix)]
+    if let Some(page) = self.protected {
+      // The heap is unchanged, so these are the pages that were protected
+      self.inner.for_each_span_page(page, |start, len| {
+        // SAFETY: Restores the protection `freeze_read_only` changed, before
+        // the region (if owned) is handed back to the system allocator.
+        unsafe { libc::mprotect(start as *mut libc::c_void, len, libc::PROT_READ | libc::PROT_WRITE) };
+      });
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::alloc::Layout;
+
+  fn build(allocator: &mut BumpAllocator) -> *mut u64 {
+    unsafe {
+      let ptr = allocator.allocate(Layout::new::<u64>()) as *mut u64;
+      ptr.write(42);
+      ptr
+    }
+  }
+
+  #[test]
+  fn frozen_arena_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<FrozenArena>();
+  }
+
+  #[test]
+  fn frozen_data_is_readable_from_threads() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let ptr = build(&mut allocator) as usize;
+    let live_blocks = allocator.stats().live_blocks;
+
+    let frozen = allocator.freeze();
+    std::thread::scope(|scope| {
+      for _ in 0..4 {
+        scope.spawn(|| {
+          assert_eq!(unsafe { *frozen.get(ptr as *const u64) }, 42);
+          assert_eq!(frozen.stats().live_blocks, live_blocks);
+        });
+      }
+    });
+    assert!(!frozen.is_protected());
+    assert!(frozen.check_invariants().is_ok());
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn read_only_freeze_protects_whole_pages() {
+    let mut allocator = BumpAllocator::with_capacity(64 * 1024);
+    unsafe { allocator.allocate(Layout::array::<u8>(32 * 1024).unwrap()) };
+    let ptr = build(&mut allocator);
+
+    let frozen = allocator.freeze_read_only();
+    assert!(frozen.is_protected());
+    assert_eq!(unsafe { *frozen.get(ptr) }, 42);
+    // Dropping restores write access before the region is freed
+    drop(frozen);
+    assert!(!std::hint::black_box(Box::new([0u8; 64 * 1024])).is_empty());
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn read_only_freeze_leaves_memory_between_spans_alone() {
+    let page = 4096;
+    let mut allocator = BumpAllocator::new();
+    let layout = Layout::array::<u8>(4 * page).unwrap();
+
+    unsafe {
+      let a = allocator.allocate(layout) as usize;
+      // Someone else's memory, between this allocator's two spans
+      let foreign = libc::sbrk(2 * page as libc::intptr_t) as usize;
+      let b = allocator.allocate(layout) as usize;
+      assert!(b > foreign);
+
+      let mut ranges = Vec::new();
+      allocator.for_each_span_page(page, |start, len| ranges.push(start..start + len));
+      assert!(ranges.iter().all(|range| range.end <= foreign || range.start >= foreign + 2 * page));
+      for payload in [a, b] {
+        let inside = align_up(payload, page);
+        assert!(ranges.iter().any(|range| range.contains(&inside)));
+      }
+
+      let frozen = allocator.freeze_read_only();
+      assert!(frozen.is_protected());
+      // Still writable: only this allocator's spans were protected
+      (foreign as *mut u8).write_bytes(0xAB, 2 * page);
+    }
+  }
+}
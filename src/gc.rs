@@ -0,0 +1,236 @@
+//! # A Conservative Collector (experimental)
+//!
+//! A teaching toy built on the block list: [`BumpAllocator::collect_garbage`]
+//! finds the blocks still reachable from some *roots* - typically the
+//! stack - and frees the rest, without knowing a single type:
+//!
+//! ```text
+//!   roots (stack words)        heap
+//!   ┌──────────────┐           [A: 0x..B0 ─┐ ][B ][C: 0x..A0 ][D ]
+//!   │ 0x0000002a   │  ✗                    │    ▲    │         ▲
+//!   │ 0x55..a020 ──┼───────────► A ────────┘    │    └──► A    │
+//!   │ 0x55..a1f8 ──┼────────────────────────────┼──────────────┘ (interior)
+//!   └──────────────┘                            │
+//!   mark:   A, D from the roots; B through A    │
+//!   sweep:  C is unreachable (it only points at A) and is freed
+//! ```
+//!
+//! **Mark.** Every aligned word of the root ranges is read as a potential
+//! pointer. A word that falls inside a live block's payload marks that
+//! block, and marked blocks are scanned the same way, so reachability is
+//! transitive. **Sweep.** Every live block left unmarked is freed, as in
+//! [`free_all_unreachable`](BumpAllocator::free_all_unreachable).
+//!
+//! The scan is *conservative*: an integer that happens to look like an
+//! arena address keeps its block alive, and a pointer the compiler keeps
+//! only in a register, or hides (tagged, XOR-ed, compressed), is missed.
+//! Real collectors pair this with precise type maps or compiler support;
+//! here it shows the idea in a few dozen lines. Lookups binary-search the
+//! address-ordered block list, and the mark bits live in a side table, so
+//! the feature needs `std`.
+
+use core::{fmt, ops::Range};
+
+use crate::{BumpAllocator, ByteSize, Corruption, block::HEADER_SIZE};
+
+/// What one [`collect_garbage`](BumpAllocator::collect_garbage) did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GcReport {
+  /// Words read from the roots and from marked blocks.
+  pub scanned_words: usize,
+
+  /// Live blocks found reachable.
+  pub marked: usize,
+
+  /// Live blocks freed.
+  pub freed: usize,
+
+  /// Requested bytes of the freed blocks.
+  pub freed_bytes: usize,
+}
+
+impl fmt::Display for GcReport {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    write!(
+      f,
+      "gc: scanned {} words, kept {} blocks, freed {} ({})",
+      self.scanned_words,
+      self.marked,
+      self.freed,
+      ByteSize(self.freed_bytes)
+    )
+  }
+}
+
+impl BumpAllocator {
+  /// Frees every live block not reachable from `roots`, scanning them
+  /// and the blocks they reach for anything that looks like a pointer,
+  /// see the `gc` module.
+  ///
+  /// # Errors
+  ///
+  /// The [`Corruption`] found by
+  /// [`check_invariants`](Self::check_invariants) before marking; nothing
+  /// is freed then.
+  ///
+  /// # Safety
+  ///
+  /// Every root range must be readable. Every block in use must be
+  /// reachable from the roots through pointers stored whole and aligned:
+  /// any other block is freed.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// // Everything the current frame and its callees can still see
+  /// let stack_top = 0usize;
+  /// let here = &stack_top as *const usize as *const u8;
+  /// let report = unsafe { allocator.collect_garbage(&[here..stack_base])? };
+  /// println!("{report}");
+  /// ```
+  pub unsafe fn collect_garbage(
+    &mut self,
+    roots: &[Range<*const u8>],
+  ) -> Result<GcReport, Corruption> {
+    self.check_invariants()?;
+
+    // Payload ranges in address order, which is list order
+    let blocks: Vec<(usize, usize, bool)> = self
+      .blocks()
+      .map(|block| {
+        let payload = block as *const _ as usize + HEADER_SIZE;
+        (payload, payload + block.size.max(1), block.is_free)
+      })
+      .collect();
+    let mut marks = vec![false; blocks.len()];
+    let mut work = Vec::new();
+    let mut report = GcReport::default();
+
+    let mut mark = |word: usize, work: &mut Vec<usize>| {
+      let index = blocks.partition_point(|&(start, _, _)| start <= word).checked_sub(1)?;
+      let (_, end, is_free) = blocks[index];
+      if word < end && !is_free && !marks[index] {
+        marks[index] = true;
+        work.push(index);
+      }
+      Some(())
+    };
+
+    for root in roots {
+      // SAFETY: The caller promises the roots are readable.
+      report.scanned_words += unsafe { scan(root.start as usize, root.end as usize, |word| mark(word, &mut work)) };
+    }
+    while let Some(index) = work.pop() {
+      let (start, end, _) = blocks[index];
+      // SAFETY: A live payload of this allocator. Its uninitialized bytes
+      // are read as plain integers, which is the conservative bargain.
+      report.scanned_words += unsafe { scan(start, end, |word| mark(word, &mut work)) };
+    }
+
+    report.marked = marks.iter().filter(|&&marked| marked).count();
+    // Backwards, as in `free_epoch`, so popping the tail never skips a block
+    for (index, &(payload, _, is_free)) in blocks.iter().enumerate().rev() {
+      if is_free || marks[index] {
+        continue;
+      }
+      // SAFETY: A live block of the list, unreachable by the contract.
+      unsafe {
+        report.freed_bytes += (*((payload - HEADER_SIZE) as *const crate::block::Block)).size;
+        self.deallocate(payload as *mut u8);
+      }
+      report.freed += 1;
+    }
+    Ok(report)
+  }
+}
+
+/// Calls `visit` with every aligned word of `start..end`. Returns the
+/// number of words read.
+///
+/// # Safety
+///
+/// The range must be readable.
+unsafe fn scan(
+  start: usize,
+  end: usize,
+  mut visit: impl FnMut(usize) -> Option<()>,
+) -> usize {
+  let word = size_of::<usize>();
+  let mut address = start.next_multiple_of(word);
+  let mut count = 0;
+  while address + word <= end {
+    // SAFETY: Aligned and inside the readable range. Volatile, so the
+    // compiler cannot assume what the bytes hold.
+    let _ = visit(unsafe { core::ptr::read_volatile(address as *const usize) });
+    address += word;
+    count += 1;
+  }
+  count
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::alloc::Layout;
+
+  /// A root range over `words`, standing in for a stack.
+  fn roots(words: &[usize]) -> Range<*const u8> {
+    let range = words.as_ptr_range();
+    range.start as *const u8..range.end as *const u8
+  }
+
+  #[test]
+  fn reachable_blocks_survive() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let layout = Layout::new::<[usize; 2]>();
+    let [a, b, c, d] = [(); 4].map(|_| unsafe { allocator.allocate(layout) as *mut [usize; 2] });
+    unsafe {
+      // The diagram of the module docs: A points at B, C at A
+      *a = [b as usize, 0];
+      *b = [0, 0];
+      *c = [a as usize, 0];
+      *d = [0, 0];
+    }
+
+    let stack = [42, a as usize, d as usize + 8];
+    let report = unsafe { allocator.collect_garbage(&[roots(&stack)]) }.unwrap();
+    assert_eq!((report.marked, report.freed, report.freed_bytes), (3, 1, 16));
+    assert_eq!(report.scanned_words, 3 + 3 * 2);
+    assert!(allocator.allocation_info(c as *const u8).unwrap().is_free);
+    assert!([a, b, d].iter().all(|&ptr| !allocator.allocation_info(ptr as *const u8).unwrap().is_free));
+    allocator.assert_invariants();
+  }
+
+  #[test]
+  fn cycles_without_roots_are_collected() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let layout = Layout::new::<usize>();
+    let kept = unsafe { allocator.allocate(layout) as *mut usize };
+    let x = unsafe { allocator.allocate(layout) as *mut usize };
+    let y = unsafe { allocator.allocate(layout) as *mut usize };
+    unsafe {
+      *kept = 0;
+      *x = y as usize;
+      *y = x as usize;
+    }
+
+    let stack = [kept as usize];
+    let report = unsafe { allocator.collect_garbage(&[roots(&stack)]) }.unwrap();
+    assert_eq!((report.marked, report.freed), (1, 2));
+    assert_eq!(allocator.live_blocks(), 1);
+    assert!(report.to_string().starts_with("gc: scanned 2 words, kept 1 blocks, freed 2"));
+  }
+
+  #[test]
+  fn no_roots_free_everything() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    for _ in 0..3 {
+      unsafe { allocator.allocate(Layout::new::<u64>()) };
+    }
+    let report = unsafe { allocator.collect_garbage(&[]) }.unwrap();
+    assert_eq!((report.freed, allocator.live_blocks()), (3, 0));
+  }
+}
@@ -0,0 +1,382 @@
+//! # Arena Graphs
+//!
+//! An [`ArenaGraph`] is a directed graph whose nodes and edges are values
+//! placed with [`alloc`](BumpAllocator::alloc). Edges are plain pointers
+//! between arena nodes, so cycles cost nothing: there is no reference count
+//! to leak, and the whole graph goes with the next `reset`. That is the
+//! shape of a compiler's control-flow graph or SSA IR:
+//!
+//! ```text
+//!   entry ──► loop ──► exit            nodes: entry ─► loop ─► body ─► exit
+//!              ▲  │                           (intrusive list, insertion order)
+//!              │  ▼
+//!              body                   loop.edges: body ─► exit
+//! ```
+//!
+//! Every node keeps its outgoing edges in an intrusive list of edge cells,
+//! and the graph links all nodes in insertion order. Traversals need no
+//! heap either: [`reachable`](ArenaGraph::reachable) stamps nodes with a
+//! visit number and threads its work stack through the nodes themselves.
+//!
+//! As for [`ArenaList`](crate::ArenaList), creating a graph is `unsafe`:
+//! the arena must keep the nodes while the graph is used. Values are never
+//! dropped.
+
+use core::ptr::NonNull;
+
+use crate::{AllocFailure, BumpAllocator};
+
+/// A node of an [`ArenaGraph`].
+struct GraphNode<T> {
+  value: T,
+
+  /// First outgoing edge.
+  edges: Option<NonNull<Edge<T>>>,
+
+  /// Next node of the graph, in insertion order.
+  next: Option<NonNull<GraphNode<T>>>,
+
+  /// Number of the last traversal that reached this node.
+  visit: u32,
+
+  /// Next node on a traversal's work stack.
+  stack: Option<NonNull<GraphNode<T>>>,
+}
+
+/// An outgoing edge of a [`GraphNode`].
+struct Edge<T> {
+  to: NonNull<GraphNode<T>>,
+  next: Option<NonNull<Edge<T>>>,
+}
+
+/// A node of an [`ArenaGraph`], to link or read from.
+pub struct GraphRef<T>(NonNull<GraphNode<T>>);
+
+impl<T> Clone for GraphRef<T> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<T> Copy for GraphRef<T> {}
+
+impl<T> PartialEq for GraphRef<T> {
+  fn eq(
+    &self,
+    other: &Self,
+  ) -> bool {
+    self.0 == other.0
+  }
+}
+
+impl<T> Eq for GraphRef<T> {}
+
+impl<T> core::fmt::Debug for GraphRef<T> {
+  fn fmt(
+    &self,
+    f: &mut core::fmt::Formatter<'_>,
+  ) -> core::fmt::Result {
+    write!(f, "GraphRef({:p})", self.0)
+  }
+}
+
+/// A directed graph whose nodes and edges live in an arena, see the
+/// `graph` module.
+pub struct ArenaGraph<T> {
+  /// First node added.
+  first: Option<NonNull<GraphNode<T>>>,
+
+  /// Last node added.
+  last: Option<NonNull<GraphNode<T>>>,
+
+  /// Number of nodes.
+  node_count: usize,
+
+  /// Number of edges.
+  edge_count: usize,
+
+  /// Number of the last traversal.
+  visit: u32,
+}
+
+impl<T: 'static> ArenaGraph<T> {
+  /// An empty graph.
+  ///
+  /// # Safety
+  ///
+  /// Every allocator the graph grows with must keep its memory while the
+  /// graph is used, as for [`ArenaList::new`](crate::ArenaList::new). Node
+  /// references passed to the graph must come from it.
+  pub const unsafe fn new() -> Self {
+    Self {
+      first: None,
+      last: None,
+      node_count: 0,
+      edge_count: 0,
+      visit: 0,
+    }
+  }
+
+  /// Number of nodes.
+  pub fn node_count(&self) -> usize {
+    self.node_count
+  }
+
+  /// Number of edges.
+  pub fn edge_count(&self) -> usize {
+    self.edge_count
+  }
+
+  /// Adds a node holding `value`, from `allocator`. Returns `None`,
+  /// dropping `value`, if the allocator is out of memory.
+  pub fn add_node(
+    &mut self,
+    allocator: &mut BumpAllocator,
+    value: T,
+  ) -> Option<GraphRef<T>> {
+    self.try_add_node(allocator, value).ok()
+  }
+
+  /// Like [`add_node`](Self::add_node), but says why the node could not be
+  /// allocated.
+  ///
+  /// # Errors
+  ///
+  /// The [`AllocFailure`] of the node; `value` is dropped.
+  pub fn try_add_node(
+    &mut self,
+    allocator: &mut BumpAllocator,
+    value: T,
+  ) -> Result<GraphRef<T>, AllocFailure> {
+    let node = allocator.try_alloc(GraphNode {
+      value,
+      edges: None,
+      next: None,
+      visit: 0,
+      stack: None,
+    })?;
+    // SAFETY: Nodes stay valid by the contract of `new`.
+    unsafe {
+      match self.last {
+        Some(last) => (*last.as_ptr()).next = Some(node),
+        None => self.first = Some(node),
+      }
+    }
+    self.last = Some(node);
+    self.node_count += 1;
+    Ok(GraphRef(node))
+  }
+
+  /// Adds an edge from `from` to `to`, from `allocator`. Self-loops and
+  /// repeated edges are allowed. Returns `None` if the allocator is out of
+  /// memory.
+  pub fn add_edge(
+    &mut self,
+    allocator: &mut BumpAllocator,
+    from: GraphRef<T>,
+    to: GraphRef<T>,
+  ) -> Option<()> {
+    self.try_add_edge(allocator, from, to).ok()
+  }
+
+  /// Like [`add_edge`](Self::add_edge), but says why the edge could not be
+  /// allocated.
+  ///
+  /// # Errors
+  ///
+  /// The [`AllocFailure`] of the edge cell.
+  pub fn try_add_edge(
+    &mut self,
+    allocator: &mut BumpAllocator,
+    from: GraphRef<T>,
+    to: GraphRef<T>,
+  ) -> Result<(), AllocFailure> {
+    // SAFETY: As in `add_node`.
+    unsafe {
+      let from = from.0.as_ptr();
+      // Pushed in front: successors come out newest first
+      (*from).edges = Some(allocator.try_alloc(Edge {
+        to: to.0,
+        next: (*from).edges,
+      })?);
+    }
+    self.edge_count += 1;
+    Ok(())
+  }
+
+  /// The value of `node`.
+  pub fn get(
+    &self,
+    node: GraphRef<T>,
+  ) -> &T {
+    // SAFETY: As in `add_node`.
+    unsafe { &(*node.0.as_ptr()).value }
+  }
+
+  /// Mutable access to the value of `node`.
+  pub fn get_mut(
+    &mut self,
+    node: GraphRef<T>,
+  ) -> &mut T {
+    // SAFETY: As in `add_node`; `&mut self` makes the access exclusive.
+    unsafe { &mut (*node.0.as_ptr()).value }
+  }
+
+  /// Every node, in insertion order.
+  pub fn nodes(&self) -> impl Iterator<Item = GraphRef<T>> + '_ {
+    let mut current = self.first;
+    core::iter::from_fn(move || {
+      let node = current?;
+      // SAFETY: As in `add_node`.
+      current = unsafe { (*node.as_ptr()).next };
+      Some(GraphRef(node))
+    })
+  }
+
+  /// The targets of the edges out of `node`, most recently added first.
+  pub fn successors(
+    &self,
+    node: GraphRef<T>,
+  ) -> impl Iterator<Item = GraphRef<T>> + '_ {
+    // SAFETY: As in `add_node`.
+    let mut current = unsafe { (*node.0.as_ptr()).edges };
+    core::iter::from_fn(move || {
+      let edge = current?;
+      // SAFETY: As in `add_node`.
+      unsafe {
+        current = (*edge.as_ptr()).next;
+        Some(GraphRef((*edge.as_ptr()).to))
+      }
+    })
+  }
+
+  /// Calls `visit` once for every node reachable from `from`, `from`
+  /// included, depth first. Cycles are followed once.
+  ///
+  /// Allocates nothing: visited nodes are stamped with the traversal's
+  /// number, and the work stack is threaded through the nodes.
+  pub fn reachable(
+    &mut self,
+    from: GraphRef<T>,
+    mut visit: impl FnMut(GraphRef<T>, &mut T),
+  ) {
+    self.visit = self.visit.wrapping_add(1);
+    if self.visit == 0 {
+      // Wrapped: clear every stamp so no stale one matches
+      let mut current = self.first;
+      while let Some(node) = current {
+        // SAFETY: As in `add_node`.
+        unsafe {
+          (*node.as_ptr()).visit = 0;
+          current = (*node.as_ptr()).next;
+        }
+      }
+      self.visit = 1;
+    }
+
+    // SAFETY: As in `add_node`; `&mut self` makes the access exclusive, and
+    // a node is on the stack at most once because it is stamped when
+    // pushed.
+    unsafe {
+      (*from.0.as_ptr()).visit = self.visit;
+      (*from.0.as_ptr()).stack = None;
+      let mut stack = Some(from.0);
+
+      while let Some(node) = stack {
+        let node = node.as_ptr();
+        stack = (*node).stack;
+        visit(GraphRef(NonNull::new_unchecked(node)), &mut (*node).value);
+
+        let mut edge = (*node).edges;
+        while let Some(current) = edge {
+          let to = (*current.as_ptr()).to;
+          if (*to.as_ptr()).visit != self.visit {
+            (*to.as_ptr()).visit = self.visit;
+            (*to.as_ptr()).stack = stack;
+            stack = Some(to);
+          }
+          edge = (*current.as_ptr()).next;
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// The control-flow graph of the module docs.
+  fn loop_graph(allocator: &mut BumpAllocator) -> (ArenaGraph<&'static str>, [GraphRef<&'static str>; 4]) {
+    let mut graph = unsafe { ArenaGraph::new() };
+    let [entry, head, body, exit] = ["entry", "loop", "body", "exit"].map(|name| graph.add_node(allocator, name).unwrap());
+    for (from, to) in [(entry, head), (head, exit), (head, body), (body, head)] {
+      graph.add_edge(allocator, from, to).unwrap();
+    }
+    (graph, [entry, head, body, exit])
+  }
+
+  #[test]
+  fn cycles_are_plain_edges() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let (graph, [entry, head, body, exit]) = loop_graph(&mut allocator);
+
+    assert_eq!((graph.node_count(), graph.edge_count()), (4, 4));
+    assert_eq!(graph.nodes().collect::<Vec<_>>(), [entry, head, body, exit]);
+    assert_eq!(graph.successors(head).collect::<Vec<_>>(), [body, exit]);
+    assert_eq!(graph.successors(body).collect::<Vec<_>>(), [head]);
+    assert_eq!(graph.successors(exit).count(), 0);
+    assert_eq!(allocator.live_blocks(), 8);
+  }
+
+  #[test]
+  fn traversals_visit_each_node_once() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let (mut graph, [entry, head, body, exit]) = loop_graph(&mut allocator);
+
+    let mut seen = Vec::new();
+    graph.reachable(entry, |node, _| seen.push(node));
+    assert_eq!(seen.len(), 4);
+    assert_eq!(seen[0], entry);
+
+    // The second traversal is not fooled by the first one's stamps
+    let mut seen = Vec::new();
+    graph.reachable(body, |node, name| {
+      seen.push(node);
+      *name = "seen";
+    });
+    seen.sort_by_key(|node| node.0);
+    let mut expected = vec![head, body, exit];
+    expected.sort_by_key(|node| node.0);
+    assert_eq!(seen, expected);
+    assert_eq!(*graph.get(entry), "entry");
+    assert_eq!(*graph.get(exit), "seen");
+  }
+
+  #[test]
+  fn failures_say_why() {
+    let mut allocator = BumpAllocator::with_capacity(1 << 20);
+    allocator.set_heap_limit(Some(192));
+    let mut graph = unsafe { ArenaGraph::new() };
+    let node = graph.try_add_node(&mut allocator, 0u64).unwrap();
+
+    let mut result = Ok(());
+    while result.is_ok() {
+      result = graph.try_add_edge(&mut allocator, node, node);
+    }
+    assert!(matches!(result, Err(AllocFailure::ExceedsHeapLimit { limit: 192, .. })));
+    assert!(graph.try_add_node(&mut allocator, 1).is_err());
+    assert_eq!(graph.successors(node).count(), graph.edge_count());
+  }
+
+  #[test]
+  fn wrapped_visit_numbers_start_over() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let (mut graph, [entry, ..]) = loop_graph(&mut allocator);
+    // Fresh nodes carry stamp 0, which a wrapped counter would match
+    graph.visit = u32::MAX;
+    let mut count = 0;
+    graph.reachable(entry, |_, _| count += 1);
+    assert_eq!(count, 4);
+  }
+}
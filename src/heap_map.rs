@@ -0,0 +1,129 @@
+//! # Heap Maps
+//!
+//! A textual rendering of the block list, one line per block, for
+//! teaching, debugging and comparing search strategies:
+//!
+//! ```text
+//!    #  address             state        size
+//!    0  0x000055d0c1e2a020  used           37
+//!    1  0x000055d0c1e2a070  free          128
+//!    2  0x000055d0c1e2a110  used            8
+//!   2 live (45 B) · 1 free (128 B) · heap 312 B
+//! ```
+//!
+//! `#` is the block's position in the list and `address` is the pointer
+//! that was handed to the user (the header sits right before it). The last
+//! line is the allocator's [`Stats`](crate::Stats), with sizes humanized
+//! (see [`ByteSize`](crate::ByteSize)).
+//!
+//! [`HeapMap`] only implements [`Display`](fmt::Display), so it works
+//! without `std` - write it to a UART, a `String`, or `println!`:
+//!
+//! ```rust,ignore
+//! println!("{}", allocator.heap_map());
+//! ```
+
+use core::fmt;
+
+use crate::{
+  BumpAllocator,
+  block::{Block, HEADER_SIZE},
+  units::address_width,
+};
+
+/// Displays the block list of a [`BumpAllocator`].
+///
+/// Created by [`BumpAllocator::heap_map`].
+pub struct HeapMap<'a> {
+  /// The allocator being rendered.
+  allocator: &'a BumpAllocator,
+}
+
+impl BumpAllocator {
+  /// Returns a [`Display`](fmt::Display)able map of the block list.
+  ///
+  /// Rendering walks every block, so it is O(n). Like [`stats`](Self::stats)
+  /// it is a diagnostic and is allowed in real-time mode.
+  pub fn heap_map(&self) -> HeapMap<'_> {
+    HeapMap { allocator: self }
+  }
+}
+
+impl fmt::Display for HeapMap<'_> {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    let width = address_width();
+    writeln!(f, "{:>4}  {:<width$}  {:<5}  {:>10}", "#", "address", "state", "size")?;
+
+    for (index, block) in self.allocator.blocks().enumerate() {
+      let state = if block.is_free { "free" } else { "used" };
+      writeln!(
+        f,
+        "{:>4}  {:#0width$x}  {:<5}  {:>10}",
+        index,
+        content_address(block),
+        state,
+        block.size
+      )?;
+    }
+
+    write!(f, "{}", self.allocator.stats())
+  }
+}
+
+/// Address of the content that follows `block`'s header.
+fn content_address(block: &Block) -> usize {
+  block as *const Block as usize + HEADER_SIZE
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::alloc::Layout;
+
+  #[test]
+  fn empty_map_has_only_header_and_summary() {
+    let allocator = BumpAllocator::with_capacity(1024);
+    let map = allocator.heap_map().to_string();
+    let lines: Vec<&str> = map.lines().collect();
+
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1], "0 live (0 B) · 0 free (0 B) · heap 0 B");
+  }
+
+  #[test]
+  fn summary_humanizes_large_sizes() {
+    let mut allocator = BumpAllocator::with_capacity(8192);
+    unsafe { allocator.allocate(Layout::array::<u8>(3072).unwrap()) };
+
+    let map = allocator.heap_map().to_string();
+    assert!(map.lines().nth(1).unwrap().ends_with(" 3072"), "{map}");
+    assert!(map.ends_with("1 live (3.0 KiB) · 0 free (0 B) · heap 3.0 KiB"), "{map}");
+  }
+
+  #[test]
+  fn map_lists_blocks_in_order() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let (a, b) = unsafe {
+      let a = allocator.allocate(Layout::array::<u8>(37).unwrap());
+      let b = allocator.allocate(Layout::array::<u8>(128).unwrap());
+      allocator.allocate(Layout::new::<u64>());
+      allocator.deallocate(b);
+      (a, b)
+    };
+
+    let map = allocator.heap_map().to_string();
+    let lines: Vec<&str> = map.lines().collect();
+    assert_eq!(lines.len(), 5);
+    // A freed block takes the slack up to the next header
+    let span = crate::bump::grow_request_size(Layout::array::<u8>(128).unwrap()).unwrap() - crate::block::HEADER_SIZE;
+
+    assert!(lines[1].contains(&format!("{:x}", a as usize)));
+    assert!(lines[1].contains("used") && lines[1].ends_with(" 37"));
+    assert!(lines[2].contains(&format!("{:x}", b as usize)));
+    assert!(lines[2].contains("free") && lines[2].ends_with(&format!(" {span}")));
+    assert!(lines[4].starts_with(&format!("2 live (45 B) · 1 free ({span} B)")));
+  }
+}
@@ -0,0 +1,277 @@
+//! # Inline Arenas
+//!
+//! [`BumpAllocator::with_inline`] runs a closure with an allocator whose
+//! memory is an `N`-byte buffer on the caller's stack, SmallVec-style:
+//!
+//! ```text
+//!   stack frame of with_inline::<N>
+//!   ┌──────────────────────────────────────────────┐
+//!   │ buffer: [u8; N]                              │
+//!   │ ┌──────┬──────┬──────┬─────────────────────┐ │
+//!   │ │ blk  │ blk  │ blk  │      available      │ │
+//!   │ └──────┴──────┴──────┴─────────────────────┘ │
+//!   │ allocator ──► Region over buffer             │
+//!   └──────────────────────────────────────────────┘
+//!     popped when the closure returns: nothing to free, no system call
+//!
+//!   once the buffer is full:
+//!   ┌──────────────────────────┐        ┌──────────────────────────┐
+//!   │ buffer (full)            │ spill  │ spill allocator          │
+//!   │ [blk][blk][blk][blk][..] │ ─────► │ platform backend (sbrk)  │
+//!   └──────────────────────────┘        └──────────────────────────┘
+//!     frees are routed by address; the spill is released on return
+//! ```
+//!
+//! Creating and dropping such an arena costs no system call and no heap
+//! allocation, which suits short-lived scratch arenas created in hot loops.
+//! The buffer is only the first chunk: requests it cannot serve spill to an
+//! allocator on the platform backend (see [`BumpAllocator::new`]), which
+//! gives its memory back when the closure returns. Only spilling costs
+//! system calls.
+//!
+//! The closure gets an [`InlineArena`], not a `&mut BumpAllocator`: a
+//! mutable allocator reference could be `mem::swap`ped out of the closure
+//! and outlive the buffer. The arena forwards the allocation methods and
+//! dereferences to the buffer's allocator only for reading. Its lifetime brand
+//! keeps two inline arenas from being swapped with each other.
+
+use core::{
+  alloc::Layout,
+  marker::PhantomData,
+  mem::MaybeUninit,
+  ops::{Deref, Range},
+};
+
+use crate::{
+  BumpAllocator,
+  backend::{Backend, Region},
+};
+
+/// Alignment of the inline buffer: the largest alignment common layouts
+/// need, so they cost no padding.
+#[repr(C, align(16))]
+struct InlineBuffer<const N: usize>([MaybeUninit<u8>; N]);
+
+/// An allocator over a stack buffer, lent to the closure of
+/// [`BumpAllocator::with_inline`].
+///
+/// Dereferences to the buffer's [`BumpAllocator`] for queries; allocation
+/// goes through the methods below, which spill to the platform backend
+/// once the buffer is full.
+pub struct InlineArena<'buf> {
+  /// The allocator bumping through the buffer.
+  arena: BumpAllocator,
+
+  /// Addresses of the buffer, to route frees.
+  buffer: Range<usize>,
+
+  /// The allocator serving what the buffer cannot.
+  spill: BumpAllocator,
+
+  /// Invariant brand of the `with_inline` call that owns the buffer.
+  _buffer: PhantomData<fn(&'buf ()) -> &'buf ()>,
+}
+
+impl InlineArena<'_> {
+  /// Allocates from the buffer, or from the spill allocator once it is
+  /// full, see [`BumpAllocator::allocate`].
+  ///
+  /// # Safety
+  ///
+  /// As [`BumpAllocator::allocate`].
+  pub unsafe fn allocate(
+    &mut self,
+    layout: Layout,
+  ) -> *mut u8 {
+    // SAFETY: As the caller's contract.
+    let address = unsafe { self.arena.allocate(layout) };
+    if !address.is_null() {
+      return address;
+    }
+    // SAFETY: As the caller's contract.
+    unsafe { self.spill.allocate(layout) }
+  }
+
+  /// Frees `address`, see [`BumpAllocator::deallocate`].
+  ///
+  /// # Safety
+  ///
+  /// As [`BumpAllocator::deallocate`].
+  pub unsafe fn deallocate(
+    &mut self,
+    address: *mut u8,
+  ) {
+    // SAFETY: As the caller's contract.
+    unsafe { self.owner_of(address).deallocate(address) }
+  }
+
+  /// Frees `address` allocated with `layout`, see
+  /// [`BumpAllocator::deallocate_with_layout`].
+  ///
+  /// # Safety
+  ///
+  /// As [`BumpAllocator::deallocate_with_layout`].
+  pub unsafe fn deallocate_with_layout(
+    &mut self,
+    address: *mut u8,
+    layout: Layout,
+  ) {
+    // SAFETY: As the caller's contract.
+    unsafe { self.owner_of(address).deallocate_with_layout(address, layout) }
+  }
+
+  /// Frees every block, in the buffer and spilled, see
+  /// [`BumpAllocator::reset`].
+  ///
+  /// # Safety
+  ///
+  /// As [`BumpAllocator::reset`].
+  pub unsafe fn reset(&mut self) {
+    // SAFETY: As the caller's contract.
+    unsafe {
+      self.arena.reset();
+      self.spill.reset();
+    }
+  }
+
+  /// The allocator serving requests the buffer could not, for queries.
+  pub fn spill(&self) -> &BumpAllocator {
+    &self.spill
+  }
+
+  /// The allocator that handed out `address`: the buffer's if it lies in
+  /// the buffer, the spill allocator's otherwise.
+  fn owner_of(
+    &mut self,
+    address: *mut u8,
+  ) -> &mut BumpAllocator {
+    if self.buffer.contains(&(address as usize)) {
+      &mut self.arena
+    } else {
+      &mut self.spill
+    }
+  }
+}
+
+impl Deref for InlineArena<'_> {
+  type Target = BumpAllocator;
+
+  fn deref(&self) -> &BumpAllocator {
+    &self.arena
+  }
+}
+
+impl Drop for InlineArena<'_> {
+  fn drop(&mut self) {
+    // SAFETY: No pointer outlives the `with_inline` closure, so the spilled
+    // blocks are dead and their memory goes back to the system.
+    unsafe { self.spill.reset() };
+  }
+}
+
+impl BumpAllocator {
+  /// Calls `f` with an allocator backed by an `N`-byte buffer on the stack.
+  ///
+  /// Requests that do not fit in the buffer spill to the platform backend.
+  /// Every pointer it hands out dies when `f` returns, so none may escape
+  /// the closure. The allocator itself cannot: `f` only gets an
+  /// [`InlineArena`].
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// for line in input.lines() {
+  ///     let fields = BumpAllocator::with_inline::<1024, _>(|arena| {
+  ///         let scratch = unsafe { arena.allocate(Layout::array::<u32>(16).unwrap()) };
+  ///         parse(line, scratch)
+  ///     });
+  /// }
+  /// ```
+  pub fn with_inline<const N: usize, R>(f: impl for<'buf> FnOnce(&mut InlineArena<'buf>) -> R) -> R {
+    let mut buffer = InlineBuffer::<N>([MaybeUninit::uninit(); N]);
+    let start = buffer.0.as_mut_ptr();
+
+    // SAFETY: The buffer outlives the allocator, which is dropped at the end
+    // of this frame, and nothing else touches it meanwhile. The allocator
+    // writes every header before reading it, so uninitialized bytes are fine.
+    let region = unsafe { Region::borrowed(start.cast(), N) };
+    let mut arena = InlineArena {
+      arena: BumpAllocator::with_backend(Backend::Region(region)),
+      buffer: start as usize..start as usize + N,
+      spill: BumpAllocator::new(),
+      _buffer: PhantomData,
+    };
+    f(&mut arena)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn allocations_come_from_the_stack_buffer() {
+    let marker = 0u8;
+    let stack = &marker as *const u8 as usize;
+
+    BumpAllocator::with_inline::<1024, _>(|arena| {
+      assert_eq!(arena.capacity(), Some(1024));
+
+      let ptr = unsafe { arena.allocate(Layout::new::<u64>()) };
+      assert!(!ptr.is_null());
+      // Near this frame, not on the heap
+      assert!((ptr as usize).abs_diff(stack) < 64 * 1024);
+      assert_eq!(arena.stats().os_limit, None);
+    });
+  }
+
+  #[test]
+  fn full_buffer_spills_to_the_backend() {
+    BumpAllocator::with_inline::<256, _>(|arena| unsafe {
+      let layout = Layout::array::<u8>(32).unwrap();
+      let blocks: Vec<*mut u8> = (0..16).map(|_| arena.allocate(layout)).collect();
+
+      // 512 bytes of payload: past the buffer, yet nothing failed
+      assert!(blocks.iter().all(|ptr| !ptr.is_null()));
+      assert!(arena.live_blocks() < 16);
+      assert_eq!(arena.live_blocks() + arena.spill().live_blocks(), 16);
+
+      // Frees find their allocator by address
+      for ptr in blocks {
+        arena.deallocate(ptr);
+      }
+      assert_eq!(arena.live_blocks(), 0);
+      assert_eq!(arena.spill().live_blocks(), 0);
+    });
+  }
+
+  #[test]
+  fn arena_forwards_frees_and_resets() {
+    BumpAllocator::with_inline::<1024, _>(|arena| unsafe {
+      let layout = Layout::new::<u64>();
+      let a = arena.allocate(layout);
+      let b = arena.allocate(layout);
+      arena.deallocate_with_layout(b, layout);
+      assert_eq!(arena.live_blocks(), 1);
+      arena.deallocate(a);
+      assert!(arena.is_empty());
+
+      arena.allocate(layout);
+      arena.reset();
+      assert_eq!(arena.live_blocks(), 0);
+    });
+  }
+
+  #[test]
+  fn result_is_returned() {
+    let sum = BumpAllocator::with_inline::<512, _>(|arena| unsafe {
+      let ptr = arena.allocate(Layout::array::<u32>(4).unwrap()) as *mut u32;
+      for i in 0..4 {
+        ptr.add(i).write(i as u32 + 1);
+      }
+      (0..4).map(|i| ptr.add(i).read()).sum::<u32>()
+    });
+
+    assert_eq!(sum, 10);
+  }
+}
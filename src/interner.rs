@@ -0,0 +1,347 @@
+//! # String Interning
+//!
+//! An [`Interner`] keeps one copy of each distinct string in its own
+//! arena and hands out [`Symbol`]s: small, `Copy` ids that compare in
+//! O(1), which is why compilers intern every identifier they lex.
+//!
+//! ```text
+//!   intern("x")  intern("len")  intern("x")
+//!        │            │              │
+//!        ▼            ▼              ▼
+//!    Symbol(0)    Symbol(1)      Symbol(0)        (found, not copied again)
+//!
+//!   arena:   [ x ][ len ][ strings: 0 ─► "x", 1 ─► "len" ][ table ]
+//!                                                          hash ─► symbol
+//! ```
+//!
+//! Everything lives in the arena: the string bytes (via
+//! [`alloc_str`](BumpAllocator::alloc_str)), the symbol-to-string array,
+//! and an open-addressing hash table of FNV-1a hashes. Growing either array
+//! allocates a bigger one and frees the old, so no global heap is needed.
+//!
+//! The interner owns its allocator, so a resolved `&str` lives as long as
+//! the borrow of the interner. [`reset`](Interner::reset) drops every
+//! string at once - between compilation units, say. Symbols from before a
+//! reset are stale: they may resolve to a string interned afterwards.
+
+use core::{alloc::Layout, fmt, ptr::NonNull};
+
+use crate::{AllocFailure, BumpAllocator};
+
+/// Slots of the string array allocated first.
+const INITIAL_CAPACITY: usize = 16;
+
+/// An interned string, see [`Interner::intern`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+impl Symbol {
+  /// The symbol's number: symbols are numbered from 0 in interning order.
+  pub fn as_u32(self) -> u32 {
+    self.0
+  }
+}
+
+/// Deduplicated strings in an arena, see the `interner` module.
+pub struct Interner {
+  /// Where the strings, the array and the table live.
+  allocator: BumpAllocator,
+
+  /// String of each symbol; the first `len` are set.
+  strings: *mut NonNull<str>,
+
+  /// Number of interned strings.
+  len: usize,
+
+  /// Slots in `strings`.
+  capacity: usize,
+
+  /// Open-addressing table of `symbol + 1`, `0` when empty.
+  table: *mut u32,
+
+  /// Slots in `table`: a power of two, twice `capacity`.
+  table_size: usize,
+}
+
+impl Interner {
+  /// An empty interner storing its strings in `allocator`.
+  pub const fn new(allocator: BumpAllocator) -> Self {
+    Self {
+      allocator,
+      strings: core::ptr::null_mut(),
+      len: 0,
+      capacity: 0,
+      table: core::ptr::null_mut(),
+      table_size: 0,
+    }
+  }
+
+  /// An empty interner over a `capacity`-byte region.
+  #[cfg(feature = "std")]
+  pub const fn with_capacity(capacity: usize) -> Self {
+    Self::new(BumpAllocator::with_capacity(capacity))
+  }
+
+  /// Number of distinct strings interned.
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  /// Whether nothing is interned.
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  /// The allocator holding the strings, for its statistics.
+  pub fn allocator(&self) -> &BumpAllocator {
+    &self.allocator
+  }
+
+  /// The symbol of `text`, copying it into the arena the first time.
+  /// Returns `None` if the arena is out of memory.
+  pub fn intern(
+    &mut self,
+    text: &str,
+  ) -> Option<Symbol> {
+    self.try_intern(text).ok()
+  }
+
+  /// Like [`intern`](Self::intern), but says why the arena could not take
+  /// the string.
+  ///
+  /// # Errors
+  ///
+  /// The [`AllocFailure`] of the string or of the grown table.
+  pub fn try_intern(
+    &mut self,
+    text: &str,
+  ) -> Result<Symbol, AllocFailure> {
+    if let Some(symbol) = self.get(text) {
+      return Ok(symbol);
+    }
+    if self.len == self.capacity {
+      self.grow(self.len + 1)?;
+    }
+
+    let copy = self.allocator.try_alloc_str(text)?;
+    let symbol = Symbol(self.len as u32);
+    // SAFETY: `len < capacity` after growing, and the table has a free
+    // slot since it is twice as large as the array.
+    unsafe {
+      self.strings.add(self.len).write(copy);
+      *self.table.add(self.free_slot(text)) = symbol.0 + 1;
+    }
+    self.len += 1;
+    Ok(symbol)
+  }
+
+  /// Makes room in the table for `additional` more strings, so interning
+  /// them can only fail on the strings themselves.
+  ///
+  /// # Errors
+  ///
+  /// The [`AllocFailure`] of the grown table; the interner is unchanged.
+  pub fn try_reserve(
+    &mut self,
+    additional: usize,
+  ) -> Result<(), AllocFailure> {
+    let needed = self.len.checked_add(additional).ok_or(AllocFailure::SizeOverflow { size: usize::MAX })?;
+    if needed > self.capacity {
+      self.grow(needed)?;
+    }
+    Ok(())
+  }
+
+  /// The symbol of `text`, if it was interned.
+  pub fn get(
+    &self,
+    text: &str,
+  ) -> Option<Symbol> {
+    if self.table_size == 0 {
+      return None;
+    }
+    let mask = self.table_size - 1;
+    let mut slot = fnv1a(text) as usize & mask;
+    loop {
+      // SAFETY: `slot <= mask < table_size`.
+      let entry = unsafe { *self.table.add(slot) };
+      if entry == 0 {
+        return None;
+      }
+      let symbol = Symbol(entry - 1);
+      if self.resolve(symbol) == Some(text) {
+        return Some(symbol);
+      }
+      slot = (slot + 1) & mask;
+    }
+  }
+
+  /// The string of `symbol`.
+  pub fn resolve(
+    &self,
+    symbol: Symbol,
+  ) -> Option<&str> {
+    let index = symbol.0 as usize;
+    // SAFETY: The first `len` strings are set and live in the owned arena.
+    (index < self.len).then(|| unsafe { (*self.strings.add(index)).as_ref() })
+  }
+
+  /// Every symbol with its string, in interning order.
+  pub fn iter(&self) -> impl Iterator<Item = (Symbol, &str)> {
+    (0..self.len as u32).filter_map(|index| Some((Symbol(index), self.resolve(Symbol(index))?)))
+  }
+
+  /// Forgets every string and gives the arena back in one step.
+  pub fn reset(&mut self) {
+    // SAFETY: Every pointer into the arena is owned by `self`, and
+    // `&mut self` means no resolved string is still borrowed.
+    unsafe { self.allocator.reset() };
+    self.strings = core::ptr::null_mut();
+    self.table = core::ptr::null_mut();
+    self.len = 0;
+    self.capacity = 0;
+    self.table_size = 0;
+  }
+
+  /// First empty table slot on `text`'s probe sequence.
+  fn free_slot(
+    &self,
+    text: &str,
+  ) -> usize {
+    let mask = self.table_size - 1;
+    let mut slot = fnv1a(text) as usize & mask;
+    // SAFETY: `slot <= mask < table_size`.
+    while unsafe { *self.table.add(slot) } != 0 {
+      slot = (slot + 1) & mask;
+    }
+    slot
+  }
+
+  /// Grows the string array to at least `needed` slots (doubling, in
+  /// powers of two) and rebuilds the table at twice that size.
+  fn grow(
+    &mut self,
+    needed: usize,
+  ) -> Result<(), AllocFailure> {
+    let capacity = needed.max(self.capacity * 2).max(INITIAL_CAPACITY).next_power_of_two();
+    let overflow = AllocFailure::SizeOverflow { size: capacity };
+    if capacity > u32::MAX as usize {
+      return Err(overflow);
+    }
+    let strings_layout = Layout::array::<NonNull<str>>(capacity).map_err(|_| overflow)?;
+    let table_layout = Layout::array::<u32>(capacity * 2).map_err(|_| overflow)?;
+
+    // SAFETY: Fresh blocks of the owned arena; the old ones are copied
+    // from, then freed, and nothing else points into them.
+    unsafe {
+      let strings = self.allocator.allocate_for_helper(strings_layout)?.as_ptr() as *mut NonNull<str>;
+      let table = match self.allocator.allocate_for_helper(table_layout) {
+        Ok(table) => table.as_ptr() as *mut u32,
+        Err(failure) => {
+          self.allocator.deallocate(strings as *mut u8);
+          return Err(failure);
+        }
+      };
+      table.write_bytes(0, capacity * 2);
+      if self.len > 0 {
+        strings.copy_from_nonoverlapping(self.strings, self.len);
+      }
+
+      self.allocator.deallocate(self.strings as *mut u8);
+      self.allocator.deallocate(self.table as *mut u8);
+      self.strings = strings;
+      self.table = table;
+      self.capacity = capacity;
+      self.table_size = capacity * 2;
+
+      for index in 0..self.len {
+        let text = (*strings.add(index)).as_ref();
+        *table.add(self.free_slot(text)) = index as u32 + 1;
+      }
+    }
+    Ok(())
+  }
+}
+
+impl fmt::Debug for Interner {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    f.debug_map().entries(self.iter().map(|(symbol, text)| (symbol.0, text))).finish()
+  }
+}
+
+/// 64-bit FNV-1a: short strings hash in a few cycles, no state needed.
+fn fnv1a(text: &str) -> u64 {
+  text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+    (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn equal_strings_share_a_symbol() {
+    let mut interner = Interner::with_capacity(4096);
+    let x = interner.intern("x").unwrap();
+    let len = interner.intern("len").unwrap();
+    assert_eq!(interner.intern(&String::from("x")), Some(x));
+    assert_ne!(x, len);
+
+    assert_eq!((x.as_u32(), len.as_u32()), (0, 1));
+    assert_eq!(interner.resolve(len), Some("len"));
+    assert_eq!(interner.get("len"), Some(len));
+    assert_eq!(interner.get("missing"), None);
+    assert_eq!(interner.len(), 2);
+    assert_eq!(interner.iter().collect::<Vec<_>>(), [(x, "x"), (len, "len")]);
+  }
+
+  #[test]
+  fn growing_keeps_every_symbol() {
+    let mut interner = Interner::with_capacity(1 << 20);
+    let symbols: Vec<_> = (0..1000).map(|i| interner.intern(&format!("name{i}")).unwrap()).collect();
+    for (i, &symbol) in symbols.iter().enumerate() {
+      assert_eq!(interner.resolve(symbol), Some(format!("name{i}").as_str()));
+      assert_eq!(interner.get(&format!("name{i}")), Some(symbol));
+    }
+    assert_eq!(interner.intern(""), interner.intern(""));
+    assert_eq!(interner.len(), 1001);
+  }
+
+  #[test]
+  fn resets_forget_everything() {
+    let mut interner = Interner::with_capacity(4096);
+    let old = interner.intern("old").unwrap();
+    interner.reset();
+    assert!(interner.is_empty());
+    assert_eq!(interner.get("old"), None);
+    assert_eq!(interner.resolve(old), None);
+    assert_eq!(interner.allocator().live_blocks(), 0);
+
+    interner.intern("new").unwrap();
+    assert_eq!(interner.resolve(old), Some("new"));
+  }
+
+  #[test]
+  fn reserving_keeps_symbols_and_reports_failures() {
+    let mut interner = Interner::with_capacity(4096);
+    let a = interner.intern("a").unwrap();
+    interner.try_reserve(100).unwrap();
+    assert_eq!(interner.get("a"), Some(a));
+    assert!(matches!(interner.try_reserve(1 << 20), Err(AllocFailure::ExceedsHeadroom { .. })));
+    assert_eq!(interner.try_reserve(usize::MAX), Err(AllocFailure::SizeOverflow { size: usize::MAX }));
+    assert_eq!(interner.resolve(a), Some("a"));
+  }
+
+  #[test]
+  fn full_arenas_give_none() {
+    let mut interner = Interner::with_capacity(1024);
+    let long = "x".repeat(1024);
+    assert_eq!(interner.intern(&long), None);
+    assert!(interner.is_empty());
+    assert!(interner.intern("short").is_some());
+  }
+}
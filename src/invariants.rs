@@ -0,0 +1,274 @@
+//! # Block List Invariants
+//!
+//! Structural checks over the block list, used to turn silent heap
+//! corruption into an immediate, explained failure:
+//!
+//! ```text
+//!   null ◄── [A] ◄──► [B] ◄──► [C] ──► null
+//!            ▲                  ▲
+//!          first               last
+//!
+//!   1. first.prev is null
+//!   2. for every link X ──► Y:  Y.prev == X
+//!   3. blocks sit at increasing addresses (the list is in heap order)
+//!   4. the block reached by following `next` to the end is `last`
+//!   5. the NextFit cursor, if set, is a free block of the list
+//!   6. every live block's canary, if it has one, is intact
+//!   7. with the `shadow` feature, the shadow memory agrees with the list
+//! ```
+//!
+//! Rule 3 also catches cycles: a loop would have to jump back to a lower
+//! address at some point.
+
+use core::{fmt, ptr};
+
+use crate::{
+  BumpAllocator,
+  block::{Block, HEADER_SIZE},
+  canary::canary_intact,
+};
+#[cfg(feature = "shadow")]
+use crate::ShadowState;
+
+/// A violated block list invariant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corruption {
+  /// The first block has a predecessor.
+  HeadHasPrev,
+
+  /// Block `index` does not point back at its predecessor.
+  BrokenBackLink {
+    /// Position of the block in the list.
+    index: usize,
+  },
+
+  /// Block `index` is not above its predecessor in memory.
+  OutOfOrder {
+    /// Position of the block in the list.
+    index: usize,
+  },
+
+  /// The list does not end at `last`.
+  WrongTail,
+
+  /// The NextFit cursor points at a block that is in use or no longer in
+  /// the list, so the next search would start from stale memory.
+  StaleSearchCursor,
+
+  /// The canary after a live block's payload was overwritten: something
+  /// wrote past the end of the block.
+  CanaryOverwritten {
+    /// Payload address of the overflowed block.
+    address: usize,
+  },
+
+  /// A heap byte is not in the state the shadow memory expects, see the
+  /// `shadow` module.
+  #[cfg(feature = "shadow")]
+  ShadowMismatch {
+    /// The offending byte.
+    address: usize,
+    /// What the operation or the block list expected there.
+    expected: ShadowState,
+    /// What the shadow has.
+    found: ShadowState,
+  },
+
+  /// The shadow holds a different number of user bytes than the live
+  /// blocks of the list.
+  #[cfg(feature = "shadow")]
+  ShadowAccounting {
+    /// User bytes in the shadow.
+    shadowed: usize,
+    /// Payload bytes of live blocks in the list.
+    live: usize,
+  },
+
+  /// A block was deallocated while already free.
+  DoubleFree {
+    /// The address passed to `deallocate`.
+    address: usize,
+  },
+}
+
+impl fmt::Display for Corruption {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    match *self {
+      Corruption::HeadHasPrev => write!(f, "first block has a predecessor"),
+      Corruption::BrokenBackLink { index } => write!(f, "block #{index} does not link back to its predecessor"),
+      Corruption::OutOfOrder { index } => write!(f, "block #{index} is below its predecessor (cycle or overwrite)"),
+      Corruption::WrongTail => write!(f, "block list does not end at `last`"),
+      Corruption::StaleSearchCursor => write!(f, "NextFit cursor is not a free block of the list"),
+      Corruption::CanaryOverwritten { address } => write!(f, "canary after block {address:#x} overwritten (buffer overflow)"),
+      #[cfg(feature = "shadow")]
+      Corruption::ShadowMismatch { address, expected, found } => {
+        write!(f, "shadow memory: byte {address:#x} is {found:?}, expected {expected:?}")
+      },
+      #[cfg(feature = "shadow")]
+      Corruption::ShadowAccounting { shadowed, live } => {
+        write!(f, "shadow memory holds {shadowed} user bytes, live blocks {live}")
+      },
+      Corruption::DoubleFree { address } => write!(f, "double free of {address:#x}"),
+    }
+  }
+}
+
+impl BumpAllocator {
+  /// Walks the block list and checks its structural invariants.
+  ///
+  /// O(n), allowed in real-time mode like every diagnostic.
+  ///
+  /// # Returns
+  ///
+  /// The first [`Corruption`] found, if any.
+  pub fn check_invariants(&self) -> Result<(), Corruption> {
+    let mut previous: *const Block = ptr::null();
+    let cursor = self.search_cursor() as *const Block;
+    let mut cursor_ok = cursor.is_null();
+
+    for (index, block) in self.blocks().enumerate() {
+      let current = block as *const Block;
+      if current == cursor {
+        cursor_ok = block.is_free;
+      }
+      if previous.is_null() {
+        if !block.prev.is_null() {
+          return Err(Corruption::HeadHasPrev);
+        }
+      } else {
+        if !ptr::eq(block.prev, previous) {
+          return Err(Corruption::BrokenBackLink { index });
+        }
+        if current <= previous {
+          return Err(Corruption::OutOfOrder { index });
+        }
+      }
+      // SAFETY: The block is in the list, and a flagged canary lies within
+      // its memory.
+      if !unsafe { canary_intact(current) } {
+        return Err(Corruption::CanaryOverwritten {
+          address: current as usize + HEADER_SIZE,
+        });
+      }
+      previous = current;
+    }
+
+    if !ptr::eq(previous, self.last_block()) {
+      return Err(Corruption::WrongTail);
+    }
+    if !cursor_ok {
+      return Err(Corruption::StaleSearchCursor);
+    }
+    #[cfg(feature = "shadow")]
+    self.check_shadow()?;
+    Ok(())
+  }
+
+  /// Panics if [`check_invariants`](Self::check_invariants) fails.
+  ///
+  /// With the `flight-recorder` feature the panic message includes the
+  /// most recent operations.
+  pub fn assert_invariants(&self) {
+    if let Err(corruption) = self.check_invariants() {
+      self.report_corruption(corruption);
+    }
+  }
+
+  /// Panics describing `corruption`, with the flight recorder dump when
+  /// it is enabled, after writing the crash dump if one is set.
+  #[cold]
+  pub(crate) fn report_corruption(
+    &self,
+    corruption: Corruption,
+  ) -> ! {
+    #[cfg(feature = "std")]
+    let dump = self.write_crash_dump(&corruption);
+    #[cfg(not(feature = "std"))]
+    let dump = "";
+
+    #[cfg(feature = "flight-recorder")]
+    panic!("heap corruption: {corruption}{dump}\n{}", self.recent_ops());
+    #[cfg(not(feature = "flight-recorder"))]
+    panic!("heap corruption: {corruption}{dump}");
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::alloc::Layout;
+
+  /// An allocator with three live `u64` blocks.
+  fn three_blocks() -> (BumpAllocator, [*mut u8; 3]) {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let layout = Layout::new::<u64>();
+    let ptrs = unsafe { [allocator.allocate(layout), allocator.allocate(layout), allocator.allocate(layout)] };
+    (allocator, ptrs)
+  }
+
+  /// The header in front of `address`.
+  fn header(address: *mut u8) -> *mut Block {
+    unsafe { address.sub(core::mem::size_of::<Block>()) as *mut Block }
+  }
+
+  #[test]
+  fn healthy_lists_pass() {
+    let (mut allocator, ptrs) = three_blocks();
+    assert_eq!(allocator.check_invariants(), Ok(()));
+
+    unsafe {
+      allocator.deallocate(ptrs[1]);
+      allocator.deallocate(ptrs[2]);
+    }
+    assert_eq!(allocator.check_invariants(), Ok(()));
+    assert_eq!(BumpAllocator::with_capacity(64).check_invariants(), Ok(()));
+  }
+
+  #[test]
+  fn broken_back_link_is_reported() {
+    let (allocator, ptrs) = three_blocks();
+    unsafe { (*header(ptrs[2])).prev = ptr::null_mut() };
+    assert_eq!(allocator.check_invariants(), Err(Corruption::BrokenBackLink { index: 2 }));
+  }
+
+  #[test]
+  fn reordered_blocks_are_reported() {
+    let (allocator, ptrs) = three_blocks();
+    let [a, b, c] = ptrs.map(header);
+
+    // Consistent links, but in the order A, C, B
+    unsafe {
+      (*a).next = c;
+      (*c).prev = a;
+      (*c).next = b;
+      (*b).prev = c;
+      (*b).next = ptr::null_mut();
+    }
+    assert_eq!(allocator.check_invariants(), Err(Corruption::OutOfOrder { index: 2 }));
+  }
+
+  #[test]
+  fn head_with_predecessor_is_reported() {
+    let (allocator, ptrs) = three_blocks();
+    unsafe { (*header(ptrs[0])).prev = header(ptrs[2]) };
+    assert_eq!(allocator.check_invariants(), Err(Corruption::HeadHasPrev));
+  }
+
+  #[test]
+  fn truncated_list_is_reported() {
+    let (allocator, ptrs) = three_blocks();
+    unsafe { (*header(ptrs[1])).next = ptr::null_mut() };
+    assert_eq!(allocator.check_invariants(), Err(Corruption::WrongTail));
+  }
+
+  #[test]
+  #[should_panic(expected = "heap corruption")]
+  fn assert_invariants_panics_on_corruption() {
+    let (allocator, ptrs) = three_blocks();
+    unsafe { (*header(ptrs[1])).prev = ptr::null_mut() };
+    allocator.assert_invariants();
+  }
+}
@@ -0,0 +1,136 @@
+//! # Allocation Jitter
+//!
+//! A deterministic bump allocator places every block right after the
+//! previous one, so code that overflows a buffer into its neighbour, or
+//! that computes one object's address from another's, works by accident.
+//! Jitter mode breaks that adjacency:
+//!
+//! ```text
+//!   jitter off:  [hdr|A][hdr|B][hdr|C]
+//!   jitter 64:   [hdr|A]····[hdr|B][hdr|C]········[hdr|D]
+//!                       ▲                 ▲
+//!                       random gaps of 0..=64 bytes, word multiples
+//! ```
+//!
+//! and makes the free block search pick a random fitting block (as
+//! [`SearchMode::Random`](crate::SearchMode::Random) does) whatever the
+//! configured mode, so reuse order is unpredictable too. This is the idea
+//! behind ASLR-style exploit mitigations, on a scale small enough to teach
+//! with.
+//!
+//! Gaps come from the same generator as `SearchMode::Random`, so a fixed
+//! [`random_seed`](crate::Config::random_seed) reproduces a layout. Like
+//! alignment padding, a gap goes back with its block when the block is
+//! popped as the tail, and is reused with the block before it once that
+//! one is freed.
+
+use core::mem;
+
+use crate::BumpAllocator;
+
+impl BumpAllocator {
+  /// Inserts a random gap of up to `max_gap` bytes before every new block;
+  /// `0` (the default) turns jitter mode off. See the `jitter` module.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// allocator.set_jitter(256);
+  /// let a = unsafe { allocator.allocate(layout) };
+  /// let b = unsafe { allocator.allocate(layout) };   // not necessarily right after `a`
+  /// ```
+  pub fn set_jitter(
+    &mut self,
+    max_gap: usize,
+  ) {
+    self.jitter = max_gap;
+  }
+
+  /// The largest gap jitter mode inserts, `0` when it is off.
+  pub fn jitter(&self) -> usize {
+    self.jitter
+  }
+
+  /// Whether jitter mode is on.
+  pub fn is_jittered(&self) -> bool {
+    self.jitter > 0
+  }
+
+  /// Size of the gap before the next block: a random multiple of the word
+  /// size no larger than the jitter, or `0` when it is off.
+  #[inline]
+  pub(crate) fn jitter_gap(&mut self) -> usize {
+    if self.jitter == 0 {
+      return 0;
+    }
+    let word = mem::size_of::<usize>();
+    let steps = (self.jitter / word) as u128 + 1;
+    // A uniform draw in [0, steps), without modulo bias
+    let step = (self.next_random() as u128 * steps) >> 64;
+    step as usize * word
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::block::HEADER_SIZE;
+  use core::alloc::Layout;
+
+  /// Distances between consecutive payloads of `count` word allocations.
+  fn spacing(
+    allocator: &mut BumpAllocator,
+    count: usize,
+  ) -> Vec<usize> {
+    let ptrs: Vec<_> = (0..count).map(|_| unsafe { allocator.allocate(Layout::new::<u64>()) } as usize).collect();
+    ptrs.windows(2).map(|pair| pair[1] - pair[0]).collect()
+  }
+
+  #[test]
+  fn jitter_spreads_blocks_apart() {
+    let mut plain = BumpAllocator::with_capacity(16 * 1024);
+    let adjacent = spacing(&mut plain, 16);
+    assert!(adjacent.iter().all(|&distance| distance == adjacent[0]));
+
+    let mut jittered = BumpAllocator::with_capacity(16 * 1024);
+    jittered.set_jitter(128);
+    assert!(jittered.is_jittered());
+    let spread = spacing(&mut jittered, 16);
+
+    assert!(spread.iter().any(|&distance| distance != adjacent[0]));
+    // Never closer than without jitter, never further than the largest gap
+    assert!(spread.iter().all(|&distance| (adjacent[0]..=adjacent[0] + 128).contains(&distance)));
+    assert!(spread.iter().all(|&distance| distance % mem::size_of::<usize>() == 0));
+    jittered.assert_invariants();
+  }
+
+  #[test]
+  fn same_seed_gives_the_same_layout() {
+    let layout = |seed| {
+      let mut allocator = BumpAllocator::with_capacity(16 * 1024);
+      allocator.set_random_seed(seed);
+      allocator.set_jitter(256);
+      spacing(&mut allocator, 16)
+    };
+
+    assert_eq!(layout(7), layout(7));
+    assert_ne!(layout(7), layout(8));
+  }
+
+  #[test]
+  fn jittered_heap_stays_consistent() {
+    let mut allocator = BumpAllocator::with_capacity(16 * 1024);
+    allocator.set_jitter(64);
+
+    unsafe {
+      let ptrs: Vec<_> = (0..8).map(|_| allocator.allocate(Layout::new::<[u8; 24]>())).collect();
+      for &ptr in ptrs.iter().step_by(2) {
+        allocator.deallocate(ptr);
+      }
+      allocator.deallocate(*ptrs.last().unwrap());
+    }
+    allocator.assert_invariants();
+    assert_eq!(allocator.stats().live_blocks, 3);
+    assert!(allocator.stats().heap_bytes >= 8 * HEADER_SIZE);
+  }
+}
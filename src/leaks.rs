@@ -0,0 +1,718 @@
+//! # Probable Leaks
+//!
+//! A service at steady state should hold roughly the same blocks from one
+//! request to the next. Blocks that stay live across many of them are the
+//! usual suspects. Every block is stamped with the allocator's *epoch*, a
+//! counter the program advances at its own pace - per request, per frame,
+//! once a second from a [`Clock`](crate::Clock) - and
+//! [`BumpAllocator::probable_leaks`] lists the live blocks at least
+//! `min_age` epochs old:
+//!
+//! ```text
+//!   epoch   0        1        2        3        4   (now)
+//!           A B      C        D E      F
+//!                    free B   free C   free D
+//!
+//!   probable_leaks(3)  ──►  A (age 4)
+//!   probable_leaks(2)  ──►  A (age 4), E (age 2)
+//! ```
+//!
+//! The report lists the oldest blocks first, with their address, size
+//! and, with `std`, the tag they were allocated under (see below) and the
+//! call site that allocated them, once
+//! [`set_callsite_tracking`](BumpAllocator::set_callsite_tracking) is on:
+//!
+//! ```text
+//!   block  address             size    age  tag        callsite
+//!       0  0x00005621a0c3e020    64      4  request-A  src/cache.rs:112
+//! ```
+//!
+//! Call sites come from `#[track_caller]` on `allocate` and the typed
+//! helpers, so they name the caller's line, not the allocator's. Code
+//! that allocates through a wrapper of its own should mark the wrapper
+//! `#[track_caller]` too.
+//!
+//! The epoch is a `u16` kept in header padding, so ages are counted
+//! modulo 65 536: advance it coarsely enough that nothing legitimate
+//! lives that long. Blocks are stamped when allocated, or reused; `reset`
+//! does not restart the count.
+//!
+//! Like [`TopAllocations`](crate::TopAllocations), the report holds at
+//! most `N` entries inline and needs no heap of its own.
+//!
+//! ## Tags
+//!
+//! With `std`, [`set_tag`](BumpAllocator::set_tag) gives the blocks
+//! allocated from then on a caller-chosen tag - a request id, a
+//! connection name - and [`free_tag`](BumpAllocator::free_tag) frees every
+//! live block of one tag, wherever it sits in the list: region semantics
+//! for work that does not nest the way
+//! [`checkpoint`](BumpAllocator::checkpoint) scopes must:
+//!
+//! ```text
+//!   set_tag("request-A")    [A1]      [A2]            [A3]
+//!   set_tag("request-B")         [B1]       [B2][B3]
+//!
+//!   free_tag("request-A")   [ ─ ][B1][ ─ ][B2][B3]    (A3 popped)
+//! ```
+//!
+//! Tags live in a side map by address, like reference counts, so untagged
+//! blocks cost nothing and tags never wrap. The map is on the global heap:
+//! do not tag blocks of an allocator installed as the global allocator.
+//!
+//! Without `std`, [`free_epoch`](BumpAllocator::free_epoch) does the same
+//! for the blocks of one epoch. An epoch comes round again after 65 536
+//! advances, and its blocks are then freed with the new ones: free it
+//! well before that.
+
+use core::fmt;
+#[cfg(feature = "std")]
+use core::panic::Location;
+#[cfg(feature = "std")]
+use std::{sync::Arc, vec::Vec};
+
+use crate::{BlockInfo, BumpAllocator, ByteSize, block::HEADER_SIZE, units::address_width};
+
+/// The oldest live blocks of an allocator past an age, oldest first.
+///
+/// Created by [`BumpAllocator::probable_leaks`]. Displays as a table.
+pub struct ProbableLeaks<const N: usize> {
+  /// The oldest blocks; only the first `len` are meaningful.
+  entries: [Option<BlockInfo>; N],
+
+  /// Tag and call site of each entry's block.
+  #[cfg(feature = "std")]
+  attributions: [Attribution; N],
+
+  /// Number of entries filled.
+  len: usize,
+
+  /// Epoch of the allocator when the report was made.
+  epoch: u16,
+
+  /// The age the blocks were checked against.
+  min_age: u16,
+
+  /// Number of live blocks at least `min_age` old, listed or not.
+  count: usize,
+
+  /// Bytes requested by those blocks.
+  bytes: usize,
+}
+
+/// What a [`ProbableLeaks`] entry's block was allocated under.
+#[cfg(feature = "std")]
+#[derive(Clone)]
+struct Attribution {
+  /// Tag set with `set_tag`, if any.
+  tag: Option<Arc<str>>,
+
+  /// Caller of `allocate`, if call sites were tracked.
+  callsite: Option<&'static Location<'static>>,
+}
+
+#[cfg(feature = "std")]
+impl Attribution {
+  /// Neither a tag nor a call site.
+  const NONE: Self = Self { tag: None, callsite: None };
+}
+
+impl<const N: usize> ProbableLeaks<N> {
+  /// The listed blocks, oldest first.
+  pub fn iter(&self) -> impl Iterator<Item = &BlockInfo> {
+    self.entries[..self.len].iter().flatten()
+  }
+
+  /// Number of blocks in the report (at most `N`).
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  /// Whether no live block was old enough.
+  pub fn is_empty(&self) -> bool {
+    self.count == 0
+  }
+
+  /// Number of live blocks at least `min_age` epochs old, including those
+  /// past the first `N`.
+  pub fn count(&self) -> usize {
+    self.count
+  }
+
+  /// Bytes requested by all of those blocks.
+  pub fn bytes(&self) -> usize {
+    self.bytes
+  }
+
+  /// Epochs since `info`'s block was allocated.
+  pub fn age_of(
+    &self,
+    info: &BlockInfo,
+  ) -> u16 {
+    self.epoch.wrapping_sub(info.epoch)
+  }
+
+  /// The tag `info`'s block was allocated under, if any.
+  #[cfg(feature = "std")]
+  pub fn tag_of(
+    &self,
+    info: &BlockInfo,
+  ) -> Option<&str> {
+    self.attribution(info)?.tag.as_deref()
+  }
+
+  /// Where `info`'s block was allocated, if call sites were tracked.
+  #[cfg(feature = "std")]
+  pub fn callsite_of(
+    &self,
+    info: &BlockInfo,
+  ) -> Option<&'static Location<'static>> {
+    self.attribution(info)?.callsite
+  }
+
+  /// The attribution of the entry for `info`'s block.
+  #[cfg(feature = "std")]
+  fn attribution(
+    &self,
+    info: &BlockInfo,
+  ) -> Option<&Attribution> {
+    let index = self.iter().position(|entry| entry.address == info.address)?;
+    Some(&self.attributions[index])
+  }
+
+  /// Inserts `info` behind every entry at least as old, dropping the
+  /// youngest entry when full.
+  fn insert(
+    &mut self,
+    info: BlockInfo,
+    #[cfg(feature = "std")] attribution: Attribution,
+  ) {
+    let age = self.age_of(&info);
+    let at = self.iter().position(|entry| self.age_of(entry) < age).unwrap_or(self.len);
+    if at == N {
+      return;
+    }
+    let end = self.len.min(N - 1);
+    self.entries[at..=end].rotate_right(1);
+    self.entries[at] = Some(info);
+    #[cfg(feature = "std")]
+    {
+      self.attributions[at..=end].rotate_right(1);
+      self.attributions[at] = attribution;
+    }
+    self.len = end + 1;
+  }
+}
+
+impl<const N: usize> fmt::Display for ProbableLeaks<N> {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    let width = address_width();
+    write!(f, "{:>5}  {:<width$}  {:>10}  {:>5}", "block", "address", "size", "age")?;
+    #[cfg(feature = "std")]
+    write!(f, "  {:<10}  callsite", "tag")?;
+    writeln!(f)?;
+
+    for info in self.iter() {
+      write!(
+        f,
+        "{:>5}  {:#0width$x}  {:>10}  {:>5}",
+        info.index,
+        info.address,
+        info.size,
+        self.age_of(info)
+      )?;
+      #[cfg(feature = "std")]
+      {
+        write!(f, "  {:<10}", self.tag_of(info).unwrap_or("-"))?;
+        match self.callsite_of(info) {
+          Some(callsite) => write!(f, "  {}:{}", callsite.file(), callsite.line())?,
+          None => write!(f, "  -")?,
+        }
+      }
+      writeln!(f)?;
+    }
+
+    write!(
+      f,
+      "{} live blocks at least {} epochs old: {}",
+      self.count,
+      self.min_age,
+      ByteSize(self.bytes)
+    )
+  }
+}
+
+impl BumpAllocator {
+  /// Moves to the next epoch; blocks allocated from now on are one epoch
+  /// younger than those before. Wraps after 65 535.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// loop {
+  ///     handle(next_request(), &mut allocator);
+  ///     allocator.advance_epoch();
+  /// }
+  /// ```
+  pub fn advance_epoch(&mut self) {
+    self.epoch = self.epoch.wrapping_add(1);
+  }
+
+  /// The current epoch, see [`advance_epoch`](Self::advance_epoch).
+  pub fn epoch(&self) -> u16 {
+    self.epoch
+  }
+
+  /// Frees every live block allocated in `epoch`, in one pass over the
+  /// list. Returns the number of blocks freed.
+  ///
+  /// Epochs wrap, so this also frees blocks 65 536 epochs older; with
+  /// `std`, [`free_tag`](Self::free_tag) keys on tags that never do.
+  ///
+  /// # Safety
+  ///
+  /// No pointer into those blocks may be used afterwards.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// let request = allocator.epoch();
+  /// allocator.advance_epoch();          // later blocks belong to the next one
+  /// respond(&mut allocator, request);
+  /// unsafe { allocator.free_epoch(request) };
+  /// ```
+  pub unsafe fn free_epoch(
+    &mut self,
+    epoch: u16,
+  ) -> usize {
+    let mut freed = 0;
+    // Backwards, so popping the tail never skips a block
+    let mut current = self.last_block();
+    while !current.is_null() {
+      // SAFETY: `current` is a block of the list; freeing it leaves its
+      // predecessor in place.
+      unsafe {
+        let prev = (*current).prev;
+        if !(*current).is_free && (*current).epoch == epoch {
+          self.deallocate((current as *mut u8).add(HEADER_SIZE));
+          freed += 1;
+        }
+        current = prev;
+      }
+    }
+    freed
+  }
+
+  /// Tags the blocks allocated from now on with `tag`, or stops tagging
+  /// with `None`. Blocks allocated before keep the tag they had.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// allocator.set_tag(Some(&request.id));
+  /// handle(request, &mut allocator);
+  /// allocator.set_tag(None);
+  /// ```
+  #[cfg(feature = "std")]
+  pub fn set_tag(
+    &mut self,
+    tag: Option<&str>,
+  ) {
+    self.tag = tag.map(Arc::from);
+  }
+
+  /// The tag the live block at `ptr` was allocated under, if any.
+  #[cfg(feature = "std")]
+  pub fn tag_of(
+    &self,
+    ptr: *const u8,
+  ) -> Option<&str> {
+    self.tags.get(&(ptr as usize)).map(|tag| &**tag)
+  }
+
+  /// Frees every live block allocated under `tag`. Returns the number of
+  /// blocks freed.
+  ///
+  /// O(t) in the number of tagged blocks, plus the frees.
+  ///
+  /// # Safety
+  ///
+  /// No pointer into those blocks may be used afterwards.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// allocator.set_tag(Some("request-123"));
+  /// respond(&mut allocator);
+  /// allocator.set_tag(None);
+  /// unsafe { allocator.free_tag("request-123") };
+  /// ```
+  #[cfg(feature = "std")]
+  pub unsafe fn free_tag(
+    &mut self,
+    tag: &str,
+  ) -> usize {
+    let doomed: Vec<usize> = self
+      .tags
+      .iter()
+      .filter(|(_, tagged)| &***tagged == tag)
+      .map(|(&address, _)| address)
+      .collect();
+    // Highest first, so a freed tail is popped before the blocks below it
+    for &address in doomed.iter().rev() {
+      // SAFETY: Tagged addresses are live blocks, by the caller's contract
+      // for their pointers.
+      unsafe { self.deallocate(address as *mut u8) };
+    }
+    doomed.len()
+  }
+
+  /// Starts or stops recording the call site of each block allocated
+  /// from now on. Stopping forgets every recorded call site.
+  ///
+  /// Costs a map entry per allocation on the global heap, so like tags it
+  /// is not for an allocator installed as the global allocator.
+  #[cfg(feature = "std")]
+  pub fn set_callsite_tracking(
+    &mut self,
+    enabled: bool,
+  ) {
+    if !enabled {
+      self.callsites = None;
+    } else if self.callsites.is_none() {
+      self.callsites = Some(std::collections::BTreeMap::new());
+    }
+  }
+
+  /// Whether call sites are being recorded, see
+  /// [`set_callsite_tracking`](Self::set_callsite_tracking).
+  #[cfg(feature = "std")]
+  pub fn tracks_callsites(&self) -> bool {
+    self.callsites.is_some()
+  }
+
+  /// Where the live block at `ptr` was allocated, if call sites were
+  /// being recorded then.
+  #[cfg(feature = "std")]
+  pub fn callsite_of(
+    &self,
+    ptr: *const u8,
+  ) -> Option<&'static Location<'static>> {
+    self.callsites.as_ref()?.get(&(ptr as usize)).copied()
+  }
+
+  /// Records the current tag and, if tracked, `callsite` for the block
+  /// just allocated at `address`.
+  #[cfg(feature = "std")]
+  pub(crate) fn attribute(
+    &mut self,
+    address: *mut u8,
+    callsite: &'static Location<'static>,
+  ) {
+    if address.is_null() {
+      return;
+    }
+    if let Some(tag) = &self.tag {
+      self.tags.insert(address as usize, tag.clone());
+    }
+    if let Some(callsites) = &mut self.callsites {
+      callsites.insert(address as usize, callsite);
+    }
+  }
+
+  /// Forgets the tag and call site of a block being freed.
+  #[cfg(feature = "std")]
+  pub(crate) fn forget_attribution(
+    &mut self,
+    address: *mut u8,
+  ) {
+    if !self.tags.is_empty() {
+      self.tags.remove(&(address as usize));
+    }
+    if let Some(callsites) = &mut self.callsites {
+      callsites.remove(&(address as usize));
+    }
+  }
+
+  /// Forgets every tag and call site at or past `address`, when the heap
+  /// is cut back there.
+  #[cfg(feature = "std")]
+  pub(crate) fn forget_attributions_from(
+    &mut self,
+    address: usize,
+  ) {
+    self.tags.split_off(&address);
+    if let Some(callsites) = &mut self.callsites {
+      callsites.split_off(&address);
+    }
+  }
+
+  /// Moves the tagged and tracked addresses by `offset`, for a copy of the
+  /// heap that starts `offset` bytes from the original.
+  #[cfg(feature = "std")]
+  pub(crate) fn relocate_attributions(
+    &mut self,
+    offset: isize,
+  ) {
+    self.tags = self
+      .tags
+      .iter()
+      .map(|(&address, tag)| (address.wrapping_add_signed(offset), tag.clone()))
+      .collect();
+    if let Some(callsites) = &mut self.callsites {
+      *callsites = callsites
+        .iter()
+        .map(|(&address, &callsite)| (address.wrapping_add_signed(offset), callsite))
+        .collect();
+    }
+  }
+
+  /// The (up to) `N` oldest live blocks allocated at least `min_age`
+  /// epochs ago, oldest first.
+  ///
+  /// O(n·N) in the number of blocks; a diagnostic, allowed in real-time
+  /// mode.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// let leaks = allocator.probable_leaks::<10>(100);
+  /// if !leaks.is_empty() {
+  ///     eprintln!("{leaks}");
+  /// }
+  /// ```
+  pub fn probable_leaks<const N: usize>(
+    &self,
+    min_age: u16,
+  ) -> ProbableLeaks<N> {
+    let mut leaks = ProbableLeaks {
+      entries: [None; N],
+      #[cfg(feature = "std")]
+      attributions: [const { Attribution::NONE }; N],
+      len: 0,
+      epoch: self.epoch,
+      min_age,
+      count: 0,
+      bytes: 0,
+    };
+
+    // Reused blocks are younger than their neighbours, so list order is
+    // not age order: keep the oldest seen so far
+    for (index, block) in self.blocks().enumerate() {
+      if block.is_free || self.epoch.wrapping_sub(block.epoch) < min_age {
+        continue;
+      }
+      leaks.count += 1;
+      leaks.bytes += block.size;
+      let info = self.describe(index, block, 0);
+      #[cfg(feature = "std")]
+      let attribution = Attribution {
+        tag: self.tags.get(&info.address).cloned(),
+        callsite: self.callsite_of(info.address as *const u8),
+      };
+      leaks.insert(
+        info,
+        #[cfg(feature = "std")]
+        attribution,
+      );
+    }
+    leaks
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::alloc::Layout;
+
+  /// The example of the module docs: returns the allocator and A..F.
+  fn five_epochs() -> (BumpAllocator, [*mut u8; 6]) {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let layout = |size| Layout::array::<u8>(size).unwrap();
+    unsafe {
+      let a = allocator.allocate(layout(10));
+      let b = allocator.allocate(layout(20));
+      allocator.advance_epoch();
+      let c = allocator.allocate(layout(30));
+      allocator.deallocate(b);
+      allocator.advance_epoch();
+      let d = allocator.allocate(layout(40));
+      let e = allocator.allocate(layout(50));
+      allocator.deallocate(c);
+      allocator.advance_epoch();
+      let f = allocator.allocate(layout(60));
+      allocator.deallocate(d);
+      allocator.advance_epoch();
+      (allocator, [a, b, c, d, e, f])
+    }
+  }
+
+  #[test]
+  fn old_live_blocks_are_listed_oldest_first() {
+    let (allocator, [a, .., e, _]) = five_epochs();
+    assert_eq!(allocator.epoch(), 4);
+
+    let leaks = allocator.probable_leaks::<4>(3);
+    let listed: Vec<_> = leaks.iter().map(|info| (info.address, leaks.age_of(info))).collect();
+    assert_eq!(listed, [(a as usize, 4)]);
+
+    let leaks = allocator.probable_leaks::<4>(2);
+    let listed: Vec<_> = leaks.iter().map(|info| (info.address, leaks.age_of(info))).collect();
+    assert_eq!(listed, [(a as usize, 4), (e as usize, 2)]);
+    assert_eq!((leaks.count(), leaks.bytes()), (2, 60));
+
+    assert!(allocator.probable_leaks::<4>(5).is_empty());
+  }
+
+  #[test]
+  fn totals_cover_unlisted_blocks() {
+    let (allocator, _) = five_epochs();
+    let leaks = allocator.probable_leaks::<1>(0);
+    assert_eq!(leaks.len(), 1);
+    assert_eq!((leaks.count(), leaks.bytes()), (3, 120));
+
+    let text = format!("{leaks}");
+    assert!(text.ends_with("3 live blocks at least 0 epochs old: 120 B"), "{text}");
+  }
+
+  #[test]
+  fn freeing_an_epoch_frees_only_its_blocks() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let layout = Layout::new::<[u64; 2]>();
+    let allocate = |allocator: &mut BumpAllocator, epoch| {
+      allocator.epoch = epoch;
+      unsafe { allocator.allocate(layout) }
+    };
+
+    let a1 = allocate(&mut allocator, 7);
+    let b1 = allocate(&mut allocator, 8);
+    allocate(&mut allocator, 7);
+    let b2 = allocate(&mut allocator, 8);
+    allocate(&mut allocator, 7);
+
+    assert_eq!(unsafe { allocator.free_epoch(7) }, 3);
+    assert_eq!(allocator.live_blocks(), 2);
+    assert_eq!(allocator.block_count(), 4);
+    assert!(allocator.allocation_info(a1).unwrap().is_free);
+    assert!(!allocator.allocation_info(b1).unwrap().is_free);
+    assert_eq!(allocator.check_invariants(), Ok(()));
+
+    // Only the tail is popped; the free blocks below it merge into one
+    assert_eq!(unsafe { allocator.free_epoch(8) }, 2);
+    assert_eq!(allocator.live_blocks(), 0);
+    assert_eq!(allocator.block_count(), 1);
+    assert!(allocator.allocation_info(b2).is_none_or(|info| info.is_free));
+    assert_eq!(unsafe { allocator.free_epoch(8) }, 0);
+  }
+
+  #[test]
+  fn reused_blocks_are_listed_by_age() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let layout = Layout::new::<[u64; 4]>();
+
+    unsafe {
+      let a = allocator.allocate(layout);
+      let b = allocator.allocate(layout);
+      allocator.allocate(layout);
+      allocator.advance_epoch();
+      allocator.deallocate(a);
+      assert_eq!(allocator.allocate(layout), a);
+      allocator.advance_epoch();
+
+      let leaks = allocator.probable_leaks::<2>(1);
+      let listed: Vec<_> = leaks.iter().map(|info| (info.address, leaks.age_of(info))).collect();
+      assert_eq!(listed[0], (b as usize, 2));
+      assert_eq!(listed[1].1, 2);
+      assert_eq!(leaks.count(), 3);
+    }
+  }
+
+  #[test]
+  #[cfg(feature = "std")]
+  fn freeing_a_tag_frees_only_its_blocks() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let layout = Layout::new::<[u64; 2]>();
+    let allocate = |allocator: &mut BumpAllocator, tag| {
+      allocator.set_tag(Some(tag));
+      unsafe { allocator.allocate(layout) }
+    };
+
+    let a1 = allocate(&mut allocator, "request-A");
+    let b1 = allocate(&mut allocator, "request-B");
+    allocate(&mut allocator, "request-A");
+    allocate(&mut allocator, "request-B");
+    allocate(&mut allocator, "request-A");
+    allocator.set_tag(None);
+    let untagged = unsafe { allocator.allocate(layout) };
+    assert_eq!(allocator.tag_of(b1), Some("request-B"));
+    assert_eq!(allocator.tag_of(untagged), None);
+
+    assert_eq!(unsafe { allocator.free_tag("request-A") }, 3);
+    assert_eq!(allocator.live_blocks(), 3);
+    assert!(allocator.allocation_info(a1).unwrap().is_free);
+    assert_eq!(allocator.tag_of(a1), None);
+    assert_eq!(unsafe { allocator.free_tag("request-A") }, 0);
+    assert_eq!(allocator.check_invariants(), Ok(()));
+
+    // A tag outlives any number of epochs
+    for _ in 0..=u16::MAX {
+      allocator.advance_epoch();
+    }
+    let leaks = allocator.probable_leaks::<4>(0);
+    let tags: Vec<_> = leaks.iter().map(|info| leaks.tag_of(info)).collect();
+    assert_eq!(tags, [Some("request-B"), Some("request-B"), None]);
+    assert!(format!("{leaks}").contains("request-B"));
+
+    assert_eq!(unsafe { allocator.free_tag("request-B") }, 2);
+    assert_eq!(allocator.live_blocks(), 1);
+
+    allocator.set_tag(Some("request-C"));
+    unsafe { allocator.allocate(layout) };
+    unsafe { allocator.reset() };
+    assert!(allocator.tags.is_empty());
+  }
+
+  #[test]
+  #[cfg(feature = "std")]
+  fn reports_name_the_allocating_line() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let layout = Layout::new::<u64>();
+    let untracked = unsafe { allocator.allocate(layout) };
+    allocator.set_callsite_tracking(true);
+
+    let line = line!() + 1;
+    let leaked = unsafe { allocator.allocate(layout) };
+    let typed = allocator.alloc(7u32).unwrap();
+    let callsite = allocator.callsite_of(leaked).unwrap();
+    assert_eq!((callsite.file(), callsite.line()), (file!(), line));
+    assert_eq!(allocator.callsite_of(typed.as_ptr().cast()).unwrap().line(), line + 1);
+    assert!(allocator.callsite_of(untracked).is_none());
+
+    allocator.advance_epoch();
+    let leaks = allocator.probable_leaks::<4>(1);
+    let callsites: Vec<_> = leaks.iter().map(|info| leaks.callsite_of(info).map(|site| site.line())).collect();
+    assert_eq!(callsites, [None, Some(line), Some(line + 1)]);
+    assert!(format!("{leaks}").contains(&format!("{}:{line}", file!())));
+
+    unsafe { allocator.deallocate(leaked) };
+    assert!(allocator.callsite_of(leaked).is_none());
+    allocator.set_callsite_tracking(false);
+    assert!(allocator.callsite_of(typed.as_ptr().cast()).is_none());
+  }
+
+  #[test]
+  fn ages_wrap_with_the_epoch() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.epoch = u16::MAX;
+    unsafe { allocator.allocate(Layout::new::<u64>()) };
+    allocator.advance_epoch();
+    allocator.advance_epoch();
+
+    assert_eq!(allocator.epoch(), 1);
+    let leaks = allocator.probable_leaks::<1>(2);
+    assert_eq!(leaks.iter().map(|info| leaks.age_of(info)).collect::<Vec<_>>(), [2]);
+  }
+}
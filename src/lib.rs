@@ -31,9 +31,70 @@
 //!
 //! ```text
 //!   rallocator
-//!   ├── align      - Alignment macros (align!, align_to!)
+//!   ├── adopt      - adopt(ptr, len): foreign regions as blocks of the allocator (`std`)
+//!   ├── align      - Alignment macros (align!, align_to!) and const fns
+//!   ├── api2       - allocator-api2 Allocator impl (feature `allocator-api2`)
+//!   ├── append_log - AppendLog: one writer, lock-free readers of published entries
+//!   ├── backend    - Memory sources: sbrk or a fixed region (internal)
 //!   ├── block      - Block metadata structure (internal)
-//!   └── bump       - BumpAllocator implementation
+//!   ├── block_cap  - set_max_blocks: untracked bumps past a block count
+//!   ├── block_info - BlockInfo: per-pointer allocation queries
+//!   ├── builder    - BumpAllocatorBuilder: one-expression setup
+//!   ├── cold       - mark_cold/allocate_cold: a separate chunk for rarely used blocks (`std`)
+//!   ├── collections - ArenaHashMap/ArenaHashSet aliases (feature `hashbrown`)
+//!   ├── bump       - BumpAllocator implementation
+//!   ├── canary     - CanaryPolicy: sampled buffer overflow canaries
+//!   ├── checkpoint - checkpoint/shrink_to: bulk rollback to an earlier block
+//!   ├── clock      - Clock: time sources for timing features
+//!   ├── components - ComponentArena: generation-checked, densely packed components
+//!   ├── config     - Config: allocator policies in one struct
+//!   ├── counters   - Counters: running operation totals (feature `counters`)
+//!   ├── crash_dump - Heap dump to a file on detected corruption (`std`)
+//!   ├── critical   - Interrupt-safe GlobalAlloc wrapper (feature `critical-section`)
+//!   ├── donate     - donate_to: move memory between arenas without the OS
+//!   ├── forbid     - Allocation-free sections
+//!   ├── fork       - ForkedArena: copies of an allocator for speculative work (`std`)
+//!   ├── free_blocks - FreeBlocks: iteration over reuse candidates
+//!   ├── frozen     - FrozenArena: immutable, shareable allocators
+//!   ├── gc         - collect_garbage: conservative mark-and-sweep (feature `gc`)
+//!   ├── graph      - ArenaGraph: directed graphs with cycle-friendly edges
+//!   ├── heap_map   - HeapMap: printable view of the block list
+//!   ├── inline     - InlineArena: stack-buffer arenas that spill when full
+//!   ├── interner   - Interner: deduplicated strings behind Symbol ids
+//!   ├── invariants - Block list consistency checks
+//!   ├── jitter     - Random gaps between blocks and random reuse
+//!   ├── leaks      - ProbableLeaks: live blocks older than an epoch count
+//!   ├── limits     - RLIMIT_DATA headroom, failure reasons and FailurePolicy
+//!   ├── linked     - ArenaList/ArenaTree: linked structures of arena nodes
+//!   ├── model      - Fit selection as pure functions, HeapModel for differential tests
+//!   ├── owner      - Owning-thread checks (feature `thread-check`)
+//!   ├── pool       - ArenaPool: many arenas from one reservation
+//!   ├── preheat    - preheat(profile): free slots and blocks prepared at startup
+//!   ├── pressure   - relieve_pressure and PressureWatch: give the cache back under PSI
+//!   ├── rate       - RateLimit: allocation throttle for debugging storms
+//!   ├── raw_bump   - RawBump: headerless arena with only a cursor and reset
+//!   ├── recorder   - Flight recorder of recent operations (feature `flight-recorder`)
+//!   ├── refcount   - retain/release: per-block reference counts (`std`)
+//!   ├── registry   - global_stats(): stats summed over registered shared arenas (`std`)
+//!   ├── reserve    - Emergency reserve unlocked when the heap runs out
+//!   ├── ring       - Fixed-capacity ring buffer (internal)
+//!   ├── sampler    - Sampler: heap usage time series with CSV export
+//!   ├── scope      - StatsScope: counters of one stretch of code (feature `counters`)
+//!   ├── shadow     - Per-byte shadow memory checks (feature `shadow`)
+//!   ├── sized      - allocate_sized: headerless small objects freed by layout
+//!   ├── size_classes - SizeClasses: allocation histogram (feature `counters`)
+//!   ├── shrink     - ShrinkPolicy: delayed release of freed tail memory
+//!   ├── size_index - Size-ordered free block index for BestFitIndexed (internal)
+//!   ├── snapshot   - export/import of live blocks in a binary format (`std`)
+//!   ├── stats      - Stats snapshot of the block list
+//!   ├── sub_arena  - SubArena: quota-limited child allocators
+//!   ├── sweep      - free_all_unreachable: free every block outside a live set
+//!   ├── sync       - SendableArena/SharedArena/SharedBumpAllocator wrappers
+//!   ├── testing    - assert_no_leaks!/assert_allocates_at_most! for test suites
+//!   ├── top        - TopAllocations: the largest live blocks
+//!   ├── typed      - alloc<T> and iter_of::<T>(): typed values, by type
+//!   ├── units      - ByteSize: human-readable sizes for reports
+//!   └── verify     - Exhaustive and Kani checks of the heap model (feature `verify`)
 //! ```
 //!
 //! ## Quick Start
@@ -96,34 +157,211 @@
 //!   │  │ size: N         │  │  ┌──────────────────────────┐  │
 //!   │  │ is_free: false  │  │  │                          │  │
 //!   │  │ next: null/ptr  │  │  │     N bytes usable       │  │
+//!   │  │ prev: null/ptr  │  │  │                          │  │
 //!   │  └─────────────────┘  │  │                          │  │
-//!   │      24 bytes         │  └──────────────────────────┘  │
+//!   │      32 bytes         │  └──────────────────────────┘  │
 //!   └───────────────────────┴────────────────────────────────┘
 //!                           ▲
 //!                           └── Pointer returned to user
 //! ```
 //!
+//! ## Backends
+//!
+//! | Constructor                        | Memory source                          |
+//! |------------------------------------|----------------------------------------|
+//! | [`BumpAllocator::new`]             | `sbrk` on Unix, default region elsewhere |
+//! | [`BumpAllocator::from_buffer`]     | A caller-provided `&'static mut [u8]`  |
+//! | [`BumpAllocator::from_raw_region`] | Raw memory (linker symbols, `mmap`)    |
+//! | `BumpAllocator::with_capacity`     | A region from the system allocator (`std`) |
+//!
+//! On non-Unix targets `new()` uses a lazily reserved 1 MiB region when `std`
+//! is enabled, and an empty region (every allocation fails) without it.
+//!
 //! ## Features
 //!
 //! - **Simple implementation**: Easy to understand and modify
 //! - **Direct OS interaction**: Uses `sbrk` for memory management
 //! - **Proper alignment**: Respects layout alignment requirements
 //! - **Linked list tracking**: Maintains metadata for all allocations
+//! - **Real-time mode**: O(1) allocate/deallocate with no list traversal
+//! - **`no_std` support**: Disable the default `std` feature for embedded targets
+//!
+//! ## Cargo Features
+//!
+//! | Feature            | Default | Description                                        |
+//! |--------------------|---------|----------------------------------------------------|
+//! | `std`              | yes     | Links `std`; enables [`print_alloc`]               |
+//! | `critical-section` | no      | [`CriticalSectionAllocator`] for `#[global_allocator]` |
+//! | `allocator-api2`   | no      | [`LocalBumpAllocator`] for `allocator-api2` collections |
+//! | `hashbrown`        | no      | [`collections`] with arena-backed hash maps and sets |
+//! | `flight-recorder`  | no      | Log of recent operations, dumped on detected corruption |
+//! | `thread-check`     | no      | Panic when an allocator is used from a second thread |
+//! | `counters`         | no      | [`Counters`] with running totals; compiled out otherwise |
+//! | `shadow`           | no      | Per-byte shadow memory checked on every operation (slow) |
+//! | `verify`           | no      | Exhaustive and Kani checks of the heap model (development) |
 //!
 //! ## Limitations
 //!
-//! - **Single-threaded only**: No synchronization primitives
+//! - **Single-threaded core**: `BumpAllocator` is `!Send + !Sync`; use
+//!   [`SendableArena`] or `SharedArena` to cross threads
 //! - **Limited deallocation**: Only the last block can be freed to the OS
 //! - **No block reuse**: Currently doesn't reuse freed middle blocks
-//! - **Unix-only**: Requires `libc` and `sbrk` (POSIX systems)
+//! - **`sbrk` on Unix only**: Other targets fall back to a fixed region
+//!   (see [Backends](#backends))
+//! - **32/64-bit only**: 16-bit targets are rejected at compile time
 //!
 //! ## Safety
 //!
 //! This crate is inherently unsafe as it deals with raw memory management.
 //! All allocation and deallocation operations require `unsafe` blocks.
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(feature = "std")]
+mod adopt;
 pub mod align;
+#[cfg(feature = "allocator-api2")]
+mod api2;
+mod append_log;
+mod backend;
 mod block;
+mod block_cap;
+mod block_info;
+mod builder;
+#[cfg(feature = "hashbrown")]
+pub mod collections;
+#[cfg(feature = "std")]
+mod cold;
 mod bump;
+mod config;
+#[cfg(feature = "counters")]
+mod counters;
+mod canary;
+mod checkpoint;
+mod clock;
+mod components;
+#[cfg(feature = "std")]
+mod crash_dump;
+#[cfg(feature = "critical-section")]
+mod critical;
+mod donate;
+mod forbid;
+#[cfg(feature = "std")]
+mod fork;
+mod free_blocks;
+mod frozen;
+#[cfg(feature = "gc")]
+mod gc;
+mod graph;
+mod heap_map;
+mod inline;
+mod interner;
+mod invariants;
+mod jitter;
+mod leaks;
+mod limits;
+mod linked;
+pub mod model;
+#[cfg(feature = "thread-check")]
+mod owner;
+mod pool;
+mod preheat;
+mod pressure;
+mod rate;
+mod raw_bump;
+#[cfg(feature = "flight-recorder")]
+mod recorder;
+#[cfg(feature = "std")]
+mod refcount;
+#[cfg(feature = "std")]
+mod registry;
+mod reserve;
+mod ring;
+mod sampler;
+#[cfg(feature = "counters")]
+mod scope;
+#[cfg(feature = "shadow")]
+mod shadow;
+mod shrink;
+mod sized;
+#[cfg(feature = "counters")]
+mod size_classes;
+mod size_index;
+#[cfg(feature = "std")]
+mod snapshot;
+mod stats;
+mod sub_arena;
+mod sweep;
+mod sync;
+pub mod testing;
+mod top;
+mod typed;
+mod units;
+#[cfg(feature = "verify")]
+pub mod verify;
+
+// Size math and the block header assume at least 32-bit pointers.
+#[cfg(not(any(target_pointer_width = "32", target_pointer_width = "64")))]
+compile_error!("rallocator supports only 32-bit and 64-bit targets");
 
-pub use bump::{BumpAllocator, SearchMode, print_alloc};
+pub use append_log::{AppendLog, LogWriter};
+pub use block_info::BlockInfo;
+pub use builder::BumpAllocatorBuilder;
+pub use canary::CanaryPolicy;
+pub use bump::{BumpAllocator, OomHandler, SearchMode};
+pub use config::Config;
+pub use free_blocks::{FreeBlock, FreeBlocks};
+pub use frozen::FrozenArena;
+pub use graph::{ArenaGraph, GraphRef};
+pub use heap_map::HeapMap;
+pub use inline::InlineArena;
+pub use interner::{Interner, Symbol};
+pub use invariants::Corruption;
+pub use leaks::ProbableLeaks;
+pub use limits::{AllocFailure, FailurePolicy};
+pub use linked::{ArenaList, ArenaTree, ListIter, NodeRef};
+pub use pool::{ArenaPool, PooledArena};
+pub use preheat::Preheated;
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub use pressure::PressureWatch;
+pub use clock::{Clock, ManualClock, TickClock};
+pub use components::{ComponentArena, Handle};
+#[cfg(feature = "std")]
+pub use clock::{MonotonicClock, monotonic_clock};
+pub use rate::{RateExceeded, RateLimit, RateLimitHandler};
+pub use raw_bump::RawBump;
+pub use sampler::{Sample, Sampler};
+pub use shrink::ShrinkPolicy;
+pub use sized::MAX_HEADERLESS_SIZE;
+pub use stats::Stats;
+pub use sub_arena::SubArena;
+pub use sweep::SweepError;
+pub use sync::SendableArena;
+pub use top::TopAllocations;
+pub use units::ByteSize;
+#[cfg(feature = "std")]
+pub use fork::{FORK_ALIGN, ForkedArena};
+#[cfg(feature = "shadow")]
+pub use shadow::ShadowState;
+#[cfg(feature = "std")]
+pub use snapshot::{MAX_SNAPSHOT_ALIGN, SNAPSHOT_VERSION};
+#[cfg(feature = "std")]
+pub use registry::{GlobalStats, global_stats};
+#[cfg(feature = "std")]
+pub use sync::{SharedArena, SharedBumpAllocator};
+#[cfg(all(feature = "std", unix))]
+pub use bump::print_alloc;
+#[cfg(feature = "allocator-api2")]
+pub use api2::LocalBumpAllocator;
+#[cfg(feature = "critical-section")]
+pub use critical::CriticalSectionAllocator;
+#[cfg(feature = "counters")]
+pub use counters::Counters;
+#[cfg(feature = "gc")]
+pub use gc::GcReport;
+#[cfg(feature = "counters")]
+pub use scope::{ScopeReport, StatsScope};
+#[cfg(feature = "counters")]
+pub use size_classes::{SIZE_CLASSES, SizeClasses};
+#[cfg(feature = "flight-recorder")]
+pub use recorder::{OpKind, OpRecord, RECORDED_OPS, RecentOps};
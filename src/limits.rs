@@ -0,0 +1,338 @@
+//! # Memory Limits
+//!
+//! `sbrk` answers every failure with the same `(void *) -1`, whether the
+//! request overflowed, the process hit `RLIMIT_DATA`, or the system is out
+//! of memory. The allocator reads `RLIMIT_DATA` on first use and rejects
+//! requests that obviously cannot fit before calling `sbrk`, then records
+//! *why* an allocation failed:
+//!
+//! ```text
+//!   RLIMIT_DATA ───────────────────────────────────────────┐
+//!   ┌───────────────────────────────┬──────────────────────┤
+//!   │ obtained by this allocator    │ headroom             │
+//!   └───────────────────────────────┴──────────────────────┘
+//!
+//!   request > headroom   ──► ExceedsHeadroom  (no sbrk call)
+//!   sbrk fails anyway    ──► BackendRefused   (memory used elsewhere)
+//! ```
+//!
+//! The same check applies to region backends, whose headroom is their
+//! unused capacity, and to a heap limit set by the program itself
+//! ([`BumpAllocator::set_heap_limit`]), which fails with `ExceedsHeapLimit`.
+//!
+//! ## Failure Policy
+//!
+//! `allocate` always answers a failure with null. The typed helpers built
+//! on it - `alloc`, `alloc_str`, the interner, arena lists, trees and
+//! graphs - follow the allocator's [`FailurePolicy`] instead, so a library
+//! can return errors while an application using the same code panics:
+//!
+//! ```text
+//!   helper ──► allocate ──► null   (the OomHandler, if any, already ran)
+//!                            │
+//!              ┌─────────────┼─────────────────┐
+//!              ▼             ▼                 ▼
+//!            Error         Panic        HandleAllocError
+//!         Err(failure)   panic!(...)   std::alloc::handle_alloc_error
+//!          or None                     (the process-wide OOM hook,
+//!                                       aborts by default)
+//! ```
+
+use core::{alloc::Layout, fmt, ptr::NonNull};
+
+use crate::BumpAllocator;
+
+/// Why an allocation failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocFailure {
+  /// The layout plus header and padding is larger than `isize::MAX`, the
+  /// most a pointer offset (and `sbrk`'s signed increment) can span.
+  SizeOverflow {
+    /// Requested payload size.
+    size: usize,
+  },
+
+  /// The request is larger than the backend could ever still provide.
+  ExceedsHeadroom {
+    /// Bytes the backend was asked for (header and padding included).
+    requested: usize,
+    /// Bytes left before the limit.
+    headroom: usize,
+    /// `RLIMIT_DATA`, or `None` for a region backend.
+    os_limit: Option<usize>,
+  },
+
+  /// The request would take the heap over its configured limit, see
+  /// [`BumpAllocator::set_heap_limit`].
+  ExceedsHeapLimit {
+    /// Bytes the backend would have been asked for.
+    requested: usize,
+    /// Bytes left before the limit.
+    headroom: usize,
+    /// The heap limit.
+    limit: usize,
+  },
+
+  /// The backend refused a request within the headroom.
+  BackendRefused {
+    /// Bytes the backend was asked for (header and padding included).
+    requested: usize,
+  },
+
+  /// The allocation exceeded the rate limit, see
+  /// [`RateLimit`](crate::RateLimit).
+  RateLimited {
+    /// Allocations in the window, the rejected one included.
+    allocations: usize,
+    /// Bytes in the window, the rejected allocation included.
+    bytes: usize,
+  },
+}
+
+impl fmt::Display for AllocFailure {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    match *self {
+      AllocFailure::SizeOverflow { size } => write!(f, "a {size}-byte allocation overflows the address space"),
+      AllocFailure::ExceedsHeadroom {
+        requested,
+        headroom,
+        os_limit: Some(limit),
+      } => write!(
+        f,
+        "requested {requested} bytes but only {headroom} remain under RLIMIT_DATA ({limit} bytes)"
+      ),
+      AllocFailure::ExceedsHeadroom {
+        requested,
+        headroom,
+        os_limit: None,
+      } => write!(f, "requested {requested} bytes but only {headroom} remain in the region"),
+      AllocFailure::ExceedsHeapLimit {
+        requested,
+        headroom,
+        limit,
+      } => write!(
+        f,
+        "requested {requested} bytes but only {headroom} remain under the heap limit ({limit} bytes)"
+      ),
+      AllocFailure::BackendRefused { requested } => {
+        write!(f, "the backend refused {requested} bytes (memory exhausted or used elsewhere)")
+      }
+      AllocFailure::RateLimited { allocations, bytes } => {
+        write!(f, "rate limit exceeded: {allocations} allocations, {bytes} bytes in the current window")
+      }
+    }
+  }
+}
+
+/// What the typed helpers do when an allocation fails, see the `limits`
+/// module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FailurePolicy {
+  /// Return `Err(AllocFailure)` from `try_` helpers, `None` from the others.
+  #[default]
+  Error,
+
+  /// Panic with the [`AllocFailure`].
+  Panic,
+
+  /// Call [`std::alloc::handle_alloc_error`], like the standard
+  /// collections: the process-wide out-of-memory hook, which aborts unless
+  /// the program replaced it. Panics without `std`.
+  HandleAllocError,
+}
+
+impl BumpAllocator {
+  /// The data segment limit (`RLIMIT_DATA`) the heap grows against.
+  ///
+  /// `None` if it is unlimited or the backend is a region.
+  pub fn os_limit(&self) -> Option<usize> {
+    self.backend.os_limit()
+  }
+
+  /// Upper bound on how many more bytes the heap can grow by.
+  ///
+  /// For the program break this is [`os_limit`](Self::os_limit) minus what
+  /// this allocator obtained; other users of the data segment reduce it
+  /// further. For a region it is the unused capacity. A
+  /// [heap limit](Self::set_heap_limit) caps both.
+  pub fn headroom(&self) -> Option<usize> {
+    let below_limit = self
+      .heap_limit
+      .map(|limit| limit.saturating_sub(self.backend.used_bytes()));
+    match (self.backend.headroom(), below_limit) {
+      (Some(backend), Some(limit)) => Some(backend.min(limit)),
+      (backend, limit) => backend.or(limit),
+    }
+  }
+
+  /// Caps the bytes the heap may obtain from the backend (headers and
+  /// padding included), or lifts the cap with `None`.
+  ///
+  /// Unlike `RLIMIT_DATA` the cap is private to this allocator: other
+  /// allocators and libc `malloc` are unaffected. Lowering it below the
+  /// current heap size only stops further growth.
+  pub fn set_heap_limit(
+    &mut self,
+    limit: Option<usize>,
+  ) {
+    self.heap_limit = limit;
+  }
+
+  /// The cap set by [`set_heap_limit`](Self::set_heap_limit), if any.
+  pub fn heap_limit(&self) -> Option<usize> {
+    self.heap_limit
+  }
+
+  /// Chooses what the typed helpers do when an allocation fails; raw
+  /// [`allocate`](Self::allocate) keeps returning null whatever the policy.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// // An application that treats running out of arena as a bug
+  /// allocator.set_failure_policy(FailurePolicy::Panic);
+  /// let node = allocator.alloc(Node::default()).unwrap(); // never `None`
+  /// ```
+  pub fn set_failure_policy(
+    &mut self,
+    policy: FailurePolicy,
+  ) {
+    self.failure_policy = policy;
+  }
+
+  /// The policy set by [`set_failure_policy`](Self::set_failure_policy).
+  pub fn failure_policy(&self) -> FailurePolicy {
+    self.failure_policy
+  }
+
+  /// Allocates `layout` for a typed helper, applying the failure policy
+  /// if the allocator returns null.
+  #[track_caller]
+  pub(crate) fn allocate_for_helper(
+    &mut self,
+    layout: Layout,
+  ) -> Result<NonNull<u8>, AllocFailure> {
+    // SAFETY: Allocating hands out fresh memory; nothing else is touched.
+    if let Some(address) = NonNull::new(unsafe { self.allocate(layout) }) {
+      return Ok(address);
+    }
+
+    let failure = self.last_failure.unwrap_or(AllocFailure::BackendRefused {
+      requested: layout.size(),
+    });
+    match self.failure_policy {
+      FailurePolicy::Error => Err(failure),
+      #[cfg(feature = "std")]
+      FailurePolicy::HandleAllocError => std::alloc::handle_alloc_error(layout),
+      _ => panic!("allocation of {} bytes failed: {failure}", layout.size()),
+    }
+  }
+
+  /// Reads `RLIMIT_DATA` again, e.g. after the process changed it with
+  /// `setrlimit`. The limit is otherwise read once, on first use.
+  pub fn refresh_os_limit(&mut self) {
+    self.backend.refresh_os_limit();
+  }
+
+  /// Why an allocation last failed, if one ever did.
+  ///
+  /// Not cleared by later successes: check it right after `allocate`
+  /// returns null.
+  ///
+  /// ```rust,ignore
+  /// if ptr.is_null() {
+  ///     panic!("allocation failed: {}", allocator.last_failure().unwrap());
+  /// }
+  /// ```
+  pub fn last_failure(&self) -> Option<AllocFailure> {
+    self.last_failure
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn oversized_requests_fail_before_reaching_the_backend() {
+    let mut allocator = BumpAllocator::with_capacity(1024);
+    assert_eq!(allocator.last_failure(), None);
+
+    assert!(unsafe { allocator.allocate(Layout::array::<u8>(2048).unwrap()) }.is_null());
+    let Some(AllocFailure::ExceedsHeadroom {
+      requested,
+      headroom: 1024,
+      os_limit: None,
+    }) = allocator.last_failure()
+    else {
+      panic!("unexpected failure {:?}", allocator.last_failure());
+    };
+    assert!(requested > 2048);
+    assert_eq!(allocator.stats().heap_bytes, 0);
+  }
+
+  #[test]
+  fn helpers_follow_the_failure_policy() {
+    let mut allocator = BumpAllocator::with_capacity(256);
+    assert_eq!(allocator.failure_policy(), FailurePolicy::Error);
+    assert!(matches!(allocator.try_alloc([0u8; 1024]), Err(AllocFailure::ExceedsHeadroom { .. })));
+    assert!(allocator.alloc([0u8; 1024]).is_none());
+
+    allocator.set_failure_policy(FailurePolicy::Panic);
+    let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| allocator.alloc([0u8; 1024])));
+    let message = panicked.unwrap_err().downcast::<String>().unwrap();
+    assert!(message.starts_with("allocation of 1024 bytes failed: requested"), "{message}");
+
+    // Raw allocations keep returning null
+    assert!(unsafe { allocator.allocate(Layout::array::<u8>(1024).unwrap()) }.is_null());
+    assert!(allocator.alloc(1u64).is_some());
+  }
+
+  #[test]
+  fn headroom_shrinks_as_the_heap_grows() {
+    let mut allocator = BumpAllocator::with_capacity(1024);
+    assert_eq!(allocator.headroom(), Some(1024));
+
+    unsafe { allocator.allocate(Layout::new::<u64>()) };
+    let stats = allocator.stats();
+    assert_eq!(stats.headroom, Some(1024 - stats.heap_bytes));
+    assert_eq!(stats.os_limit, None);
+  }
+
+  #[test]
+  fn heap_limit_caps_growth_before_the_backend() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_heap_limit(Some(256));
+    assert_eq!(allocator.headroom(), Some(256));
+
+    assert!(!unsafe { allocator.allocate(Layout::array::<u8>(100).unwrap()) }.is_null());
+    let used = allocator.stats().heap_bytes;
+    assert_eq!(allocator.headroom(), Some(256 - used));
+
+    assert!(unsafe { allocator.allocate(Layout::array::<u8>(200).unwrap()) }.is_null());
+    let Some(AllocFailure::ExceedsHeapLimit { headroom, limit: 256, .. }) = allocator.last_failure() else {
+      panic!("unexpected failure {:?}", allocator.last_failure());
+    };
+    assert_eq!(headroom, 256 - used);
+
+    allocator.set_heap_limit(None);
+    assert!(!unsafe { allocator.allocate(Layout::array::<u8>(200).unwrap()) }.is_null());
+  }
+
+  #[test]
+  fn failures_explain_themselves() {
+    let failure = AllocFailure::ExceedsHeadroom {
+      requested: 4096,
+      headroom: 100,
+      os_limit: Some(1 << 20),
+    };
+    assert_eq!(
+      failure.to_string(),
+      "requested 4096 bytes but only 100 remain under RLIMIT_DATA (1048576 bytes)"
+    );
+    assert!(AllocFailure::BackendRefused { requested: 64 }.to_string().contains("refused 64 bytes"));
+  }
+}
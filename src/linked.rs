@@ -0,0 +1,579 @@
+//! # Arena Lists and Trees
+//!
+//! Linked data structures whose nodes are values placed with
+//! [`alloc`](BumpAllocator::alloc): each push is one bump, and nothing is
+//! freed or dropped per node. The whole structure goes away when the arena
+//! is reset, which is what an AST, a scene graph or a per-request work
+//! queue wants anyway.
+//!
+//! ```text
+//!   ArenaList
+//!   head ──►┌──────┐ next ┌──────┐ next ┌──────┐◄── tail
+//!           │  a   │─────►│  b   │─────►│  c   │
+//!           │      │◄─────│      │◄─────│      │
+//!           └──────┘ prev └──────┘ prev └──────┘
+//!            (three blocks of the arena)
+//!
+//!   ArenaTree                       root
+//!                                  ┌──┴──────────┐
+//!   first_child / next_sibling     fn            fn
+//!   links, plus parent            ┌─┴─┐          │
+//!                                 arg body       body
+//! ```
+//!
+//! The structures only hold pointers into the arena, so they are not tied
+//! to it by a lifetime: creating one is `unsafe`, with the promise that the
+//! arena keeps the nodes until the structure is no longer used. The
+//! `reset`, `shrink_to` and `deallocate` calls that could break that
+//! promise are `unsafe` already. Values are never dropped - keep types
+//! that own resources out, or drop them by hand.
+
+use core::{iter::FusedIterator, marker::PhantomData, ptr::NonNull};
+
+use crate::{AllocFailure, BumpAllocator};
+
+/// A node of an [`ArenaList`].
+struct ListNode<T> {
+  value: T,
+  prev: Option<NonNull<ListNode<T>>>,
+  next: Option<NonNull<ListNode<T>>>,
+}
+
+/// A doubly-linked list whose nodes live in an arena, see the `linked`
+/// module.
+pub struct ArenaList<T> {
+  /// First node.
+  head: Option<NonNull<ListNode<T>>>,
+
+  /// Last node.
+  tail: Option<NonNull<ListNode<T>>>,
+
+  /// Number of nodes.
+  len: usize,
+}
+
+impl<T: 'static> ArenaList<T> {
+  /// An empty list.
+  ///
+  /// # Safety
+  ///
+  /// Every allocator the list is pushed with must keep its memory - no
+  /// `reset`, `shrink_to` below the nodes, or drop - while the list is used.
+  pub const unsafe fn new() -> Self {
+    Self {
+      head: None,
+      tail: None,
+      len: 0,
+    }
+  }
+
+  /// Number of values in the list.
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  /// Whether the list is empty.
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  /// Appends `value` in a node from `allocator`. Returns `None`, dropping
+  /// `value`, if the allocator is out of memory.
+  pub fn push_back(
+    &mut self,
+    allocator: &mut BumpAllocator,
+    value: T,
+  ) -> Option<&mut T> {
+    self.try_push_back(allocator, value).ok()
+  }
+
+  /// Like [`push_back`](Self::push_back), but says why the node could not be
+  /// allocated.
+  ///
+  /// # Errors
+  ///
+  /// The [`AllocFailure`] of the node; `value` is dropped.
+  pub fn try_push_back(
+    &mut self,
+    allocator: &mut BumpAllocator,
+    value: T,
+  ) -> Result<&mut T, AllocFailure> {
+    let mut node = allocator.try_alloc(ListNode {
+      value,
+      prev: self.tail,
+      next: None,
+    })?;
+    // SAFETY: Nodes stay valid by the contract of `new`.
+    unsafe {
+      match self.tail {
+        Some(mut tail) => tail.as_mut().next = Some(node),
+        None => self.head = Some(node),
+      }
+      self.tail = Some(node);
+      self.len += 1;
+      Ok(&mut node.as_mut().value)
+    }
+  }
+
+  /// Prepends `value` in a node from `allocator`. Returns `None`, dropping
+  /// `value`, if the allocator is out of memory.
+  pub fn push_front(
+    &mut self,
+    allocator: &mut BumpAllocator,
+    value: T,
+  ) -> Option<&mut T> {
+    self.try_push_front(allocator, value).ok()
+  }
+
+  /// Like [`push_front`](Self::push_front), but says why the node could not be
+  /// allocated.
+  ///
+  /// # Errors
+  ///
+  /// The [`AllocFailure`] of the node; `value` is dropped.
+  pub fn try_push_front(
+    &mut self,
+    allocator: &mut BumpAllocator,
+    value: T,
+  ) -> Result<&mut T, AllocFailure> {
+    let mut node = allocator.try_alloc(ListNode {
+      value,
+      prev: None,
+      next: self.head,
+    })?;
+    // SAFETY: As in `push_back`.
+    unsafe {
+      match self.head {
+        Some(mut head) => head.as_mut().prev = Some(node),
+        None => self.tail = Some(node),
+      }
+      self.head = Some(node);
+      self.len += 1;
+      Ok(&mut node.as_mut().value)
+    }
+  }
+
+  /// Unlinks the first value and moves it out. Its node stays in the arena
+  /// until the arena is reset.
+  pub fn pop_front(&mut self) -> Option<T> {
+    let node = self.head?;
+    // SAFETY: As in `push_back`; the node is unlinked, so its value is
+    // read out exactly once.
+    unsafe {
+      self.head = (*node.as_ptr()).next;
+      match self.head {
+        Some(mut head) => head.as_mut().prev = None,
+        None => self.tail = None,
+      }
+      self.len -= 1;
+      Some((&raw const (*node.as_ptr()).value).read())
+    }
+  }
+
+  /// The first value.
+  pub fn front(&self) -> Option<&T> {
+    // SAFETY: As in `push_back`.
+    self.head.map(|node| unsafe { &(*node.as_ptr()).value })
+  }
+
+  /// The last value.
+  pub fn back(&self) -> Option<&T> {
+    // SAFETY: As in `push_back`.
+    self.tail.map(|node| unsafe { &(*node.as_ptr()).value })
+  }
+
+  /// The values, front to back (or back to front with `rev`).
+  pub fn iter(&self) -> ListIter<'_, T> {
+    ListIter {
+      front: self.head,
+      back: self.tail,
+      remaining: self.len,
+      _list: PhantomData,
+    }
+  }
+
+  /// Mutable access to every value, front to back.
+  pub fn for_each_mut(
+    &mut self,
+    mut update: impl FnMut(&mut T),
+  ) {
+    let mut current = self.head;
+    while let Some(node) = current {
+      // SAFETY: As in `push_back`; `&mut self` makes the access exclusive.
+      unsafe {
+        update(&mut (*node.as_ptr()).value);
+        current = (*node.as_ptr()).next;
+      }
+    }
+  }
+}
+
+/// Iterator over an [`ArenaList`], see [`ArenaList::iter`].
+pub struct ListIter<'l, T> {
+  front: Option<NonNull<ListNode<T>>>,
+  back: Option<NonNull<ListNode<T>>>,
+  remaining: usize,
+  _list: PhantomData<&'l ArenaList<T>>,
+}
+
+impl<'l, T> Iterator for ListIter<'l, T> {
+  type Item = &'l T;
+
+  fn next(&mut self) -> Option<&'l T> {
+    if self.remaining == 0 {
+      return None;
+    }
+    let node = self.front?;
+    self.remaining -= 1;
+    // SAFETY: The list is borrowed, and its nodes are valid by the
+    // contract of `ArenaList::new`.
+    unsafe {
+      self.front = (*node.as_ptr()).next;
+      Some(&(*node.as_ptr()).value)
+    }
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.remaining, Some(self.remaining))
+  }
+}
+
+impl<T> DoubleEndedIterator for ListIter<'_, T> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    if self.remaining == 0 {
+      return None;
+    }
+    let node = self.back?;
+    self.remaining -= 1;
+    // SAFETY: As in `next`.
+    unsafe {
+      self.back = (*node.as_ptr()).prev;
+      Some(&(*node.as_ptr()).value)
+    }
+  }
+}
+
+impl<T> ExactSizeIterator for ListIter<'_, T> {}
+
+impl<T> FusedIterator for ListIter<'_, T> {}
+
+/// A node of an [`ArenaTree`].
+struct TreeNode<T> {
+  value: T,
+  parent: Option<NonNull<TreeNode<T>>>,
+  first_child: Option<NonNull<TreeNode<T>>>,
+  last_child: Option<NonNull<TreeNode<T>>>,
+  next_sibling: Option<NonNull<TreeNode<T>>>,
+}
+
+/// A node of an [`ArenaTree`], to add children to or read from.
+pub struct NodeRef<T>(NonNull<TreeNode<T>>);
+
+impl<T> Clone for NodeRef<T> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<T> Copy for NodeRef<T> {}
+
+impl<T> PartialEq for NodeRef<T> {
+  fn eq(
+    &self,
+    other: &Self,
+  ) -> bool {
+    self.0 == other.0
+  }
+}
+
+impl<T> Eq for NodeRef<T> {}
+
+impl<T> core::fmt::Debug for NodeRef<T> {
+  fn fmt(
+    &self,
+    f: &mut core::fmt::Formatter<'_>,
+  ) -> core::fmt::Result {
+    write!(f, "NodeRef({:p})", self.0)
+  }
+}
+
+/// A tree whose nodes live in an arena, see the `linked` module.
+pub struct ArenaTree<T> {
+  /// The root node.
+  root: NodeRef<T>,
+
+  /// Number of nodes, root included.
+  len: usize,
+}
+
+impl<T: 'static> ArenaTree<T> {
+  /// A tree holding only `root`, in a node from `allocator`. Returns
+  /// `None` if the allocator is out of memory.
+  ///
+  /// # Safety
+  ///
+  /// Every allocator the tree grows with must keep its memory while the
+  /// tree is used, as for [`ArenaList::new`]. Node references passed to the
+  /// tree must come from it.
+  pub unsafe fn new(
+    allocator: &mut BumpAllocator,
+    root: T,
+  ) -> Option<Self> {
+    let root = allocator.alloc(TreeNode {
+      value: root,
+      parent: None,
+      first_child: None,
+      last_child: None,
+      next_sibling: None,
+    })?;
+    Some(Self {
+      root: NodeRef(root),
+      len: 1,
+    })
+  }
+
+  /// The root node.
+  pub fn root(&self) -> NodeRef<T> {
+    self.root
+  }
+
+  /// Number of nodes, root included.
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  /// Always `false`: a tree has its root.
+  pub fn is_empty(&self) -> bool {
+    false
+  }
+
+  /// Adds `value` as the last child of `parent`, in a node from
+  /// `allocator`. Returns `None`, dropping `value`, if the allocator is out
+  /// of memory.
+  pub fn append_child(
+    &mut self,
+    allocator: &mut BumpAllocator,
+    parent: NodeRef<T>,
+    value: T,
+  ) -> Option<NodeRef<T>> {
+    self.try_append_child(allocator, parent, value).ok()
+  }
+
+  /// Like [`append_child`](Self::append_child), but says why the node
+  /// could not be allocated.
+  ///
+  /// # Errors
+  ///
+  /// The [`AllocFailure`] of the node; `value` is dropped.
+  pub fn try_append_child(
+    &mut self,
+    allocator: &mut BumpAllocator,
+    parent: NodeRef<T>,
+    value: T,
+  ) -> Result<NodeRef<T>, AllocFailure> {
+    let child = allocator.try_alloc(TreeNode {
+      value,
+      parent: Some(parent.0),
+      first_child: None,
+      last_child: None,
+      next_sibling: None,
+    })?;
+    // SAFETY: Nodes stay valid by the contract of `new`.
+    unsafe {
+      let parent = parent.0.as_ptr();
+      match (*parent).last_child {
+        Some(mut last) => last.as_mut().next_sibling = Some(child),
+        None => (*parent).first_child = Some(child),
+      }
+      (*parent).last_child = Some(child);
+    }
+    self.len += 1;
+    Ok(NodeRef(child))
+  }
+
+  /// The value of `node`.
+  pub fn get(
+    &self,
+    node: NodeRef<T>,
+  ) -> &T {
+    // SAFETY: As in `append_child`.
+    unsafe { &(*node.0.as_ptr()).value }
+  }
+
+  /// Mutable access to the value of `node`.
+  pub fn get_mut(
+    &mut self,
+    node: NodeRef<T>,
+  ) -> &mut T {
+    // SAFETY: As in `append_child`; `&mut self` makes the access exclusive.
+    unsafe { &mut (*node.0.as_ptr()).value }
+  }
+
+  /// The parent of `node`, `None` for the root.
+  pub fn parent(
+    &self,
+    node: NodeRef<T>,
+  ) -> Option<NodeRef<T>> {
+    // SAFETY: As in `append_child`.
+    unsafe { (*node.0.as_ptr()).parent.map(NodeRef) }
+  }
+
+  /// The children of `node`, in insertion order.
+  pub fn children(
+    &self,
+    node: NodeRef<T>,
+  ) -> impl Iterator<Item = NodeRef<T>> + '_ {
+    // SAFETY: As in `append_child`.
+    let mut current = unsafe { (*node.0.as_ptr()).first_child };
+    core::iter::from_fn(move || {
+      let child = current?;
+      // SAFETY: As in `append_child`.
+      current = unsafe { (*child.as_ptr()).next_sibling };
+      Some(NodeRef(child))
+    })
+  }
+
+  /// `node` and everything below it, depth first in pre-order, with each
+  /// node's depth below `node`. Uses no stack: the walk climbs back
+  /// through the parent links.
+  pub fn descendants(
+    &self,
+    node: NodeRef<T>,
+  ) -> impl Iterator<Item = (usize, NodeRef<T>)> + '_ {
+    let mut next = Some((0, node.0));
+    core::iter::from_fn(move || {
+      let (depth, current) = next?;
+      // SAFETY: As in `append_child`.
+      unsafe {
+        next = match (*current.as_ptr()).first_child {
+          Some(child) => Some((depth + 1, child)),
+          None => {
+            // Climb until a node with a next sibling, stopping at `node`
+            let (mut depth, mut up) = (depth, current);
+            loop {
+              if up == node.0 {
+                break None;
+              }
+              if let Some(sibling) = (*up.as_ptr()).next_sibling {
+                break Some((depth, sibling));
+              }
+              up = (*up.as_ptr()).parent?;
+              depth -= 1;
+            }
+          }
+        };
+      }
+      Some((depth, NodeRef(current)))
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn lists_link_both_ways() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let mut list = unsafe { ArenaList::new() };
+    list.push_back(&mut allocator, 2).unwrap();
+    list.push_back(&mut allocator, 3).unwrap();
+    *list.push_front(&mut allocator, 0).unwrap() += 1;
+
+    assert_eq!(list.len(), 3);
+    assert_eq!(list.iter().copied().collect::<Vec<_>>(), [1, 2, 3]);
+    assert_eq!(list.iter().rev().copied().collect::<Vec<_>>(), [3, 2, 1]);
+    assert_eq!((list.front(), list.back()), (Some(&1), Some(&3)));
+
+    list.for_each_mut(|value| *value *= 10);
+    assert_eq!(list.pop_front(), Some(10));
+    assert_eq!(list.iter().copied().collect::<Vec<_>>(), [20, 30]);
+    assert_eq!(allocator.live_blocks(), 3);
+  }
+
+  #[test]
+  fn popping_empties_the_list() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let mut list = unsafe { ArenaList::new() };
+    list.push_back(&mut allocator, "only").unwrap();
+    assert_eq!(list.pop_front(), Some("only"));
+    assert_eq!(list.pop_front(), None);
+    assert!(list.is_empty() && list.back().is_none());
+
+    list.push_front(&mut allocator, "again").unwrap();
+    assert_eq!(list.iter().collect::<Vec<_>>(), [&"again"]);
+  }
+
+  #[test]
+  fn full_arenas_stop_growth() {
+    let mut allocator = BumpAllocator::with_capacity(256);
+    let mut list = unsafe { ArenaList::new() };
+    let mut pushed = 0;
+    while list.push_back(&mut allocator, [0u64; 4]).is_some() {
+      pushed += 1;
+    }
+    assert!(pushed > 0);
+    assert_eq!(list.len(), pushed);
+    assert_eq!(list.iter().count(), pushed);
+  }
+
+  #[test]
+  fn failures_say_why() {
+    let mut allocator = BumpAllocator::with_capacity(1 << 20);
+    allocator.set_heap_limit(Some(256));
+    let mut list = unsafe { ArenaList::new() };
+    while list.try_push_back(&mut allocator, 1u64).is_ok() {}
+    assert!(!list.is_empty());
+    assert!(matches!(
+      list.try_push_front(&mut allocator, 2u64),
+      Err(AllocFailure::ExceedsHeapLimit { limit: 256, .. })
+    ));
+
+    let mut roots = BumpAllocator::with_capacity(4096);
+    let mut tree = unsafe { ArenaTree::new(&mut roots, 0u64).unwrap() };
+    let root = tree.root();
+    assert!(matches!(
+      tree.try_append_child(&mut allocator, root, 1),
+      Err(AllocFailure::ExceedsHeapLimit { .. })
+    ));
+    assert_eq!(tree.len(), 1);
+  }
+
+  #[test]
+  fn trees_walk_depth_first() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let mut tree = unsafe { ArenaTree::new(&mut allocator, "root").unwrap() };
+    let root = tree.root();
+    let f = tree.append_child(&mut allocator, root, "fn").unwrap();
+    let g = tree.append_child(&mut allocator, root, "fn2").unwrap();
+    tree.append_child(&mut allocator, f, "arg").unwrap();
+    let body = tree.append_child(&mut allocator, f, "body").unwrap();
+    tree.append_child(&mut allocator, g, "body2").unwrap();
+    *tree.get_mut(body) = "block";
+
+    let walk: Vec<_> = tree.descendants(root).map(|(depth, node)| (depth, *tree.get(node))).collect();
+    assert_eq!(
+      walk,
+      [(0, "root"), (1, "fn"), (2, "arg"), (2, "block"), (1, "fn2"), (2, "body2")]
+    );
+    assert_eq!(tree.len(), 6);
+
+    let below_f: Vec<_> = tree.descendants(f).map(|(_, node)| *tree.get(node)).collect();
+    assert_eq!(below_f, ["fn", "arg", "block"]);
+    assert_eq!(tree.children(root).collect::<Vec<_>>(), [f, g]);
+    assert_eq!(tree.parent(body), Some(f));
+    assert_eq!(tree.parent(root), None);
+  }
+
+  #[test]
+  fn leaves_walk_alone() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let mut tree = unsafe { ArenaTree::new(&mut allocator, 0).unwrap() };
+    let root = tree.root();
+    let leaf = tree.append_child(&mut allocator, root, 1).unwrap();
+    tree.append_child(&mut allocator, root, 2).unwrap();
+
+    assert_eq!(tree.descendants(leaf).map(|(depth, _)| depth).collect::<Vec<_>>(), [0]);
+    assert_eq!(tree.children(leaf).count(), 0);
+  }
+}
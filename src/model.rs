@@ -0,0 +1,579 @@
+//! # Heap Model
+//!
+//! The decisions the block list makes, as plain functions over sizes and
+//! addresses - no raw pointers, no backend - so they can be tested
+//! exhaustively or by property, independent of `sbrk`:
+//!
+//! ```text
+//!   fit selection     first_fit, next_fit, best_fit, good_fit, random_fit
+//!                     over (key, size) candidates in address order
+//!
+//!   free memory       absorbed_size: a freed block takes the padding up to
+//!                     its successor's header, or merges with free
+//!                     neighbours up to the next live block
+//!                     merged_tail_size: the same when that is the tail
+//!                     split_at: where a reused block's free rest begins
+//!                     kept_end: where the heap ends once the tail is popped
+//! ```
+//!
+//! The search in [`BumpAllocator`](crate::BumpAllocator) calls the fit
+//! functions with block pointers as keys; tests call them with indices or
+//! addresses. A freed middle block takes its trailing padding and merges
+//! with free neighbours, is reused when the first fit is aligned for the
+//! request - with the rest split off when it holds a block of its own -
+//! and free blocks at the tail go back to the backend one pop at a time.
+//!
+//! With `std`, [`HeapModel`] replays allocations and frees on a
+//! [`Vec`] of blocks exactly as a region-backed allocator lays them out,
+//! for differential tests against the real list.
+
+#[cfg(feature = "std")]
+use core::alloc::Layout;
+
+use crate::{
+  align::align_word,
+  block::{HEADER_SIZE, MIN_PAYLOAD},
+};
+#[cfg(feature = "std")]
+use crate::{
+  align::align_up,
+  bump::{effective_align, grow_request_size},
+};
+
+/// The first candidate of at least `size` bytes.
+///
+/// ```text
+///   first_fit([64, 128, 200], 100)  ──►  128
+/// ```
+#[inline]
+pub fn first_fit<K>(
+  candidates: impl IntoIterator<Item = (K, usize)>,
+  size: usize,
+) -> Option<K> {
+  candidates.into_iter().find(|&(_, candidate)| candidate >= size).map(|(key, _)| key)
+}
+
+/// [`first_fit`] starting at a cursor: the first fit among `after` (the
+/// cursor and what follows it), else among `before` (from the head up to
+/// the cursor).
+#[inline]
+pub fn next_fit<K>(
+  after: impl IntoIterator<Item = (K, usize)>,
+  before: impl IntoIterator<Item = (K, usize)>,
+  size: usize,
+) -> Option<K> {
+  first_fit(after, size).or_else(|| first_fit(before, size))
+}
+
+/// The smallest candidate of at least `size` bytes, the first among equal
+/// sizes. Stops at an exact fit.
+///
+/// ```text
+///   best_fit([128, 256, 110, 64], 100)  ──►  110
+/// ```
+#[inline]
+pub fn best_fit<K>(
+  candidates: impl IntoIterator<Item = (K, usize)>,
+  size: usize,
+) -> Option<K> {
+  good_fit(candidates, size, 0)
+}
+
+/// [`best_fit`] that stops at the first candidate within
+/// `tolerance_percent` of `size`.
+///
+/// ```text
+///   good_enough = size + size * tolerance_percent / 100
+///
+///   good_fit([300, 128, 105], 100, 30)  ──►  128   (≤ 130, first one)
+/// ```
+#[inline]
+pub fn good_fit<K>(
+  candidates: impl IntoIterator<Item = (K, usize)>,
+  size: usize,
+  tolerance_percent: u8,
+) -> Option<K> {
+  // 128-bit product: the bound saturates instead of overflowing
+  let slack = (size as u128 * tolerance_percent as u128 / 100) as usize;
+  let good_enough = size.saturating_add(slack);
+
+  let mut best = None;
+  let mut best_size = usize::MAX;
+  for (key, candidate) in candidates {
+    if candidate >= size && candidate < best_size {
+      if candidate <= good_enough {
+        return Some(key);
+      }
+      best = Some(key);
+      best_size = candidate;
+    }
+  }
+  best
+}
+
+/// A random candidate of at least `size` bytes, in one pass.
+///
+/// Reservoir sampling: each fitting candidate weighs `1`, or its size if
+/// `size_weighted`, and ends up picked with probability weight / total.
+/// `random` is called once per fitting candidate.
+#[inline]
+pub fn random_fit<K>(
+  candidates: impl IntoIterator<Item = (K, usize)>,
+  size: usize,
+  size_weighted: bool,
+  mut random: impl FnMut() -> u64,
+) -> Option<K> {
+  let mut pick = None;
+  let mut total: u128 = 0;
+  for (key, candidate) in candidates {
+    if candidate >= size {
+      // Zero-sized blocks still count when weighting by size
+      let weight = if size_weighted { candidate.max(1) as u128 } else { 1 };
+      total += weight;
+      // A uniform draw in [0, total), without modulo bias
+      let draw = (random() as u128 * total) >> 64;
+      if draw < weight {
+        pick = Some(key);
+      }
+    }
+  }
+  pick
+}
+
+/// Payload size of a block freed in the middle of the list: everything
+/// from its payload to the header of the next block.
+#[inline]
+pub const fn absorbed_size(
+  payload: usize,
+  next_header: usize,
+) -> usize {
+  next_header - payload
+}
+
+/// Payload size of a free block merged with the free tail after it: up to
+/// the end of the tail's payload area.
+#[inline]
+pub const fn merged_tail_size(
+  payload: usize,
+  tail_payload: usize,
+  tail_size: usize,
+) -> usize {
+  let extent = if tail_size > MIN_PAYLOAD { tail_size } else { MIN_PAYLOAD };
+  tail_payload + extent - payload
+}
+
+/// Header address of the free block split off when a free block with
+/// `span` bytes at `payload` is reused for `used` bytes, if the rest can
+/// hold a header and [`MIN_PAYLOAD`]:
+///
+/// ```text
+///   payload                              payload + span
+///   [ used ... │pad][hdr│ ≥ MIN_PAYLOAD ]
+///                  ▲ split_at
+/// ```
+#[inline]
+pub const fn split_at(
+  payload: usize,
+  used: usize,
+  span: usize,
+) -> Option<usize> {
+  let used = if used > MIN_PAYLOAD { used } else { MIN_PAYLOAD };
+  let header = align_word(payload + used);
+  if header + HEADER_SIZE + MIN_PAYLOAD <= payload + span {
+    Some(header)
+  } else {
+    None
+  }
+}
+
+/// End of the heap once everything past the tail block is released:
+/// word-aligned after the tail's payload `extent`, or `start` without a
+/// tail.
+#[inline]
+pub const fn kept_end(
+  tail: Option<(usize, usize)>,
+  start: usize,
+) -> usize {
+  match tail {
+    Some((payload, extent)) => align_word(payload + extent),
+    None => start,
+  }
+}
+
+/// One block of a [`HeapModel`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelBlock {
+  /// Start of the payload.
+  pub address: usize,
+
+  /// Requested size, or the absorbed size once freed.
+  pub size: usize,
+
+  /// Whether the block was freed.
+  pub is_free: bool,
+}
+
+/// A region-backed block list as a [`Vec`], with addresses as plain
+/// integers.
+///
+/// Mirrors [`BumpAllocator`](crate::BumpAllocator) with the default
+/// configuration: no canaries, jitter, block cap, minimum alignment or
+/// shrink caching.
+///
+/// ```rust,ignore
+/// let mut model = HeapModel::new(region_start);
+/// let a = model.allocate(Layout::new::<u64>());
+/// model.deallocate(a);
+/// assert_eq!(model.free_blocks().collect::<Vec<_>>(), real.free_blocks()...);
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeapModel {
+  /// Address of the first byte of the heap.
+  start: usize,
+
+  /// Address one past the last byte of the heap.
+  end: usize,
+
+  /// Every block, in address order.
+  blocks: Vec<ModelBlock>,
+}
+
+#[cfg(feature = "std")]
+impl HeapModel {
+  /// An empty heap starting at `start`.
+  pub const fn new(start: usize) -> Self {
+    Self {
+      start,
+      end: start,
+      blocks: Vec::new(),
+    }
+  }
+
+  /// Reuses the first free block that fits `layout` if its payload is
+  /// aligned for it, splitting off the rest, else appends a block;
+  /// returns the payload address.
+  ///
+  /// # Panics
+  ///
+  /// If the request size overflows.
+  pub fn allocate(
+    &mut self,
+    layout: Layout,
+  ) -> usize {
+    let free = self.blocks.iter().enumerate().filter(|(_, block)| block.is_free);
+    let fit = first_fit(free.map(|(index, block)| (index, block.size)), layout.size());
+    if let Some(index) = fit.filter(|&index| self.blocks[index].address.is_multiple_of(effective_align(layout))) {
+      let block = self.blocks[index];
+      self.blocks[index] = ModelBlock {
+        size: layout.size(),
+        is_free: false,
+        ..block
+      };
+      if let Some(header) = split_at(block.address, layout.size(), block.size) {
+        let tail = ModelBlock {
+          address: header + HEADER_SIZE,
+          size: block.address + block.size - header - HEADER_SIZE,
+          is_free: true,
+        };
+        self.blocks.insert(index + 1, tail);
+      }
+      return block.address;
+    }
+
+    let grow = grow_request_size(layout).expect("request size overflows");
+    let address = align_up(self.end + HEADER_SIZE, effective_align(layout));
+    self.end += grow;
+    self.blocks.push(ModelBlock {
+      address,
+      size: layout.size(),
+      is_free: false,
+    });
+    address
+  }
+
+  /// Frees the block at `address`: pops it if it is the tail, otherwise
+  /// marks it free with its trailing padding absorbed and merges it with
+  /// free neighbours.
+  ///
+  /// # Panics
+  ///
+  /// If no live block starts at `address`.
+  pub fn deallocate(
+    &mut self,
+    address: usize,
+  ) {
+    let index = self
+      .blocks
+      .iter()
+      .position(|block| block.address == address && !block.is_free)
+      .expect("no live block at this address");
+
+    if index + 1 < self.blocks.len() {
+      let next_header = self.blocks[index + 1].address - HEADER_SIZE;
+      let block = &mut self.blocks[index];
+      block.size = absorbed_size(address, next_header);
+      block.is_free = true;
+      if self.blocks[index + 1].is_free {
+        self.merge_next(index);
+      }
+      if index > 0 && self.blocks[index - 1].is_free {
+        self.merge_next(index - 1);
+      }
+      return;
+    }
+
+    self.blocks.pop();
+    let tail = self.blocks.last().map(|block| (block.address, block.size.max(MIN_PAYLOAD)));
+    self.end = kept_end(tail, self.start);
+  }
+
+  /// Folds the free block after `index` into the free block at `index`.
+  fn merge_next(
+    &mut self,
+    index: usize,
+  ) {
+    let next = self.blocks.remove(index + 1);
+    let payload = self.blocks[index].address;
+    self.blocks[index].size = match self.blocks.get(index + 1) {
+      Some(after) => absorbed_size(payload, after.address - HEADER_SIZE),
+      None => merged_tail_size(payload, next.address, next.size),
+    };
+  }
+
+  /// Every block, in address order.
+  pub fn blocks(&self) -> &[ModelBlock] {
+    &self.blocks
+  }
+
+  /// `(address, size)` of every free block, in address order - the
+  /// candidates for the fit functions.
+  pub fn free_blocks(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+    self
+      .blocks
+      .iter()
+      .filter(|block| block.is_free)
+      .map(|block| (block.address, block.size))
+  }
+
+  /// Bytes of the heap, headers and padding included.
+  pub fn heap_size(&self) -> usize {
+    self.end - self.start
+  }
+
+  /// Checks that the blocks are well formed and never overlap:
+  ///
+  /// ```text
+  ///   start ≤ [hdr│payload..extent] ≤ [hdr│payload..extent] ≤ ... ≤ end
+  ///
+  ///   1. every header starts at or after the previous payload extent
+  ///   2. the first header is in the heap, the last extent ends within it
+  /// ```
+  ///
+  /// A free block reaches the next header when it is freed, but not
+  /// always later: once the blocks after it are popped, the next block
+  /// appended may leave alignment padding that belongs to no block. The
+  /// tail may be free for the same reason.
+  pub fn check_invariants(&self) -> Result<(), ModelViolation> {
+    let mut floor = self.start;
+    for (index, block) in self.blocks.iter().enumerate() {
+      let header = block.address - HEADER_SIZE;
+      if header < floor {
+        return Err(if index == 0 {
+          ModelViolation::OutsideHeap { index }
+        } else {
+          ModelViolation::Overlap { index }
+        });
+      }
+      floor = block.address + block.size.max(MIN_PAYLOAD);
+    }
+    if floor > self.end {
+      return Err(ModelViolation::OutsideHeap {
+        index: self.blocks.len() - 1,
+      });
+    }
+    Ok(())
+  }
+}
+
+/// A violated [`HeapModel`] invariant, see
+/// [`HeapModel::check_invariants`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelViolation {
+  /// Block `index` starts inside its predecessor's payload.
+  Overlap {
+    /// Position of the block in the list.
+    index: usize,
+  },
+
+  /// Block `index` starts before the heap or ends after it.
+  OutsideHeap {
+    /// Position of the block in the list.
+    index: usize,
+  },
+}
+
+#[cfg(feature = "std")]
+impl core::fmt::Display for ModelViolation {
+  fn fmt(
+    &self,
+    f: &mut core::fmt::Formatter<'_>,
+  ) -> core::fmt::Result {
+    match *self {
+      ModelViolation::Overlap { index } => write!(f, "block #{index} overlaps its predecessor"),
+      ModelViolation::OutsideHeap { index } => write!(f, "block #{index} lies outside the heap"),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Every list of up to four sizes below five.
+  fn size_lists() -> Vec<Vec<usize>> {
+    let mut lists = vec![vec![]];
+    for length in 1..=4u32 {
+      for code in 0..5usize.pow(length) {
+        lists.push((0..length).map(|digit| code / 5usize.pow(digit) % 5).collect());
+      }
+    }
+    lists
+  }
+
+  fn indexed(sizes: &[usize]) -> impl Iterator<Item = (usize, usize)> + '_ {
+    sizes.iter().copied().enumerate()
+  }
+
+  #[test]
+  fn fits_match_their_definitions_exhaustively() {
+    for sizes in size_lists() {
+      for size in 0..6 {
+        let fitting = || indexed(&sizes).filter(|&(_, candidate)| candidate >= size);
+
+        assert_eq!(first_fit(indexed(&sizes), size), fitting().map(|(i, _)| i).next());
+
+        // Smallest, lowest index among equals
+        let best = fitting().min_by_key(|&(i, candidate)| (candidate, i)).map(|(i, _)| i);
+        assert_eq!(best_fit(indexed(&sizes), size), best);
+        assert_eq!(good_fit(indexed(&sizes), size, 0), best);
+
+        // Any tolerance returns a fitting block, within it when one exists
+        let good = good_fit(indexed(&sizes), size, 50);
+        assert_eq!(good.is_some(), best.is_some());
+        if fitting().any(|(_, candidate)| candidate * 2 <= size * 3) {
+          assert!(sizes[good.unwrap()] * 2 <= size * 3);
+        }
+
+        for cursor in 0..=sizes.len() {
+          let found = next_fit(indexed(&sizes).skip(cursor), indexed(&sizes).take(cursor), size);
+          let expected = fitting().find(|&(i, _)| i >= cursor).or_else(|| fitting().next());
+          assert_eq!(found, expected.map(|(i, _)| i));
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn random_fit_picks_only_fitting_blocks() {
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    let mut random = || {
+      state ^= state << 13;
+      state ^= state >> 7;
+      state ^= state << 17;
+      state
+    };
+
+    for sizes in size_lists() {
+      for size in 0..6 {
+        for size_weighted in [false, true] {
+          let pick = random_fit(indexed(&sizes), size, size_weighted, &mut random);
+          assert_eq!(pick.is_some(), sizes.iter().any(|&candidate| candidate >= size));
+          assert!(pick.is_none_or(|i| sizes[i] >= size));
+        }
+      }
+    }
+
+    // A draw of zero keeps the latest fit, the largest draw the first
+    assert_eq!(random_fit(indexed(&[4, 9, 6]), 5, false, || 0), Some(2));
+    assert_eq!(random_fit(indexed(&[4, 9, 6]), 5, false, || u64::MAX), Some(1));
+  }
+
+  #[test]
+  fn the_model_matches_the_allocator() {
+    let mut state = 0x2545_F491_4F6C_DD1Du64;
+    let mut random = move |bound: usize| {
+      state ^= state << 13;
+      state ^= state >> 7;
+      state ^= state << 17;
+      state as usize % bound
+    };
+
+    for _ in 0..20 {
+      let mut allocator = crate::BumpAllocator::with_capacity(64 * 1024);
+      let mut model = None::<HeapModel>;
+      let mut live = Vec::new();
+
+      for _ in 0..200 {
+        if live.is_empty() || random(3) > 0 {
+          let layout = Layout::from_size_align(random(100), 1 << random(6)).unwrap();
+          let address = unsafe { allocator.allocate(layout) } as usize;
+          // The region is reserved on first use
+          let model = model.get_or_insert_with(|| HeapModel::new(allocator.heap_range().start));
+          assert_eq!(model.allocate(layout), address);
+          live.push(address);
+        } else {
+          let address = live.swap_remove(random(live.len()));
+          unsafe { allocator.deallocate(address as *mut u8) };
+          model.as_mut().unwrap().deallocate(address);
+        }
+
+        let model = model.as_ref().unwrap();
+        let blocks: Vec<_> = allocator
+          .blocks()
+          .map(|block| ModelBlock {
+            address: block as *const _ as usize + HEADER_SIZE,
+            size: block.size,
+            is_free: block.is_free,
+          })
+          .collect();
+        assert_eq!(blocks, model.blocks());
+        assert_eq!(allocator.heap_size(), model.heap_size());
+
+        let free: Vec<_> = allocator.free_blocks().map(|block| (block.address, block.size)).collect();
+        assert_eq!(free, model.free_blocks().collect::<Vec<_>>());
+        assert_eq!(model.check_invariants(), Ok(()));
+      }
+    }
+  }
+
+  #[test]
+  fn broken_models_are_caught() {
+    let layout = Layout::new::<[u64; 4]>();
+    let mut model = HeapModel::new(0x1000);
+    let a = model.allocate(layout);
+    model.allocate(layout);
+    model.deallocate(a);
+    assert_eq!(model.check_invariants(), Ok(()));
+
+    let mut broken = model.clone();
+    broken.blocks[0].is_free = false;
+    broken.blocks[1].address = broken.blocks[0].address + 8;
+    assert_eq!(broken.check_invariants(), Err(ModelViolation::Overlap { index: 1 }));
+
+    let mut broken = model.clone();
+    broken.blocks[0].size += 8;
+    assert_eq!(broken.check_invariants(), Err(ModelViolation::Overlap { index: 1 }));
+
+    let mut broken = model.clone();
+    broken.blocks[0].address = 0x1000;
+    assert_eq!(broken.check_invariants(), Err(ModelViolation::OutsideHeap { index: 0 }));
+
+    let mut broken = model.clone();
+    broken.end = broken.blocks[1].address + 31;
+    assert_eq!(broken.check_invariants(), Err(ModelViolation::OutsideHeap { index: 1 }));
+    assert_eq!(broken.check_invariants().unwrap_err().to_string(), "block #1 lies outside the heap");
+  }
+}
@@ -0,0 +1,154 @@
+//! # Owning-Thread Checks
+//!
+//! [`BumpAllocator`] is single-threaded, but nothing stops `unsafe` code
+//! from calling it from two threads at once - a data race that silently
+//! corrupts the block list. With the `thread-check` feature every
+//! allocator remembers its owning thread and panics when another thread
+//! calls `allocate`, `deallocate` or `reset`:
+//!
+//! ```text
+//!   Unclaimed ── first call from thread A ──► Thread(A)
+//!                                               │
+//!            call from thread A: ok ◄───────────┤
+//!            call from thread B: panic ◄────────┘
+//!
+//!   Shared: accessed through a synchronizing wrapper, never checked
+//! ```
+//!
+//! Constructors are `const` (so allocators can live in `static`s), which
+//! rules out reading the current thread at construction time. The owner is
+//! therefore the thread that *first uses* the allocator. To hand an
+//! allocator to another thread on purpose, call
+//! [`release_owner`](BumpAllocator::release_owner) first.
+//!
+//! [`CriticalSectionAllocator`](crate::CriticalSectionAllocator) marks its
+//! allocator as shared: the critical section already serializes access.
+
+use std::thread::{self, ThreadId};
+
+use crate::BumpAllocator;
+
+/// Which thread may use an allocator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Owner {
+  /// Not used yet; the next caller becomes the owner.
+  Unclaimed,
+
+  /// Only this thread may use the allocator.
+  Thread(ThreadId),
+
+  /// Access is serialized by a wrapper; no check.
+  Shared,
+}
+
+impl BumpAllocator {
+  /// The thread that owns this allocator, if it has been claimed.
+  pub fn owner_thread(&self) -> Option<ThreadId> {
+    match self.owner {
+      Owner::Thread(id) => Some(id),
+      Owner::Unclaimed | Owner::Shared => None,
+    }
+  }
+
+  /// Gives up ownership, so the next thread to use the allocator claims it.
+  ///
+  /// Call this before deliberately moving an allocator to another thread.
+  pub fn release_owner(&mut self) {
+    if self.owner != Owner::Shared {
+      self.owner = Owner::Unclaimed;
+    }
+  }
+
+  /// Marks the allocator as accessed only through a synchronizing wrapper.
+  pub(crate) const fn into_shared(mut self) -> Self {
+    self.owner = Owner::Shared;
+    self
+  }
+
+  /// Claims the allocator for the current thread, or panics if another
+  /// thread owns it.
+  #[track_caller]
+  pub(crate) fn check_owner(&mut self) {
+    let current = thread::current().id();
+    match self.owner {
+      Owner::Unclaimed => self.owner = Owner::Thread(current),
+      Owner::Thread(owner) => assert!(
+        owner == current,
+        "BumpAllocator owned by {owner:?} used from {current:?}; use a thread-safe wrapper or call release_owner()"
+      ),
+      Owner::Shared => {}
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::alloc::Layout;
+  use std::panic::{AssertUnwindSafe, catch_unwind};
+
+  /// Lets a test hand an allocator to another thread on purpose.
+  struct SendPtr(*mut BumpAllocator);
+  unsafe impl Send for SendPtr {}
+
+  /// Runs `f` on a new thread with access to `allocator`.
+  fn on_other_thread(
+    allocator: &mut BumpAllocator,
+    f: fn(&mut BumpAllocator),
+  ) -> std::thread::Result<()> {
+    let ptr = SendPtr(allocator);
+    thread::spawn(move || {
+      let ptr = ptr;
+      // SAFETY: The spawning thread is blocked in `join` meanwhile.
+      catch_unwind(AssertUnwindSafe(|| f(unsafe { &mut *ptr.0 })))
+    })
+    .join()
+    .unwrap()
+  }
+
+  #[test]
+  fn first_use_claims_the_allocator() {
+    let mut allocator = BumpAllocator::with_capacity(1024);
+    assert_eq!(allocator.owner_thread(), None);
+
+    unsafe { allocator.allocate(Layout::new::<u64>()) };
+    assert_eq!(allocator.owner_thread(), Some(thread::current().id()));
+  }
+
+  #[test]
+  fn use_from_another_thread_panics() {
+    let mut allocator = BumpAllocator::with_capacity(1024);
+    unsafe { allocator.allocate(Layout::new::<u64>()) };
+
+    let result = on_other_thread(&mut allocator, |allocator| unsafe {
+      allocator.allocate(Layout::new::<u64>());
+    });
+    assert!(result.is_err());
+    assert_eq!(allocator.stats().live_blocks, 1);
+  }
+
+  #[test]
+  fn released_allocator_can_change_threads() {
+    let mut allocator = BumpAllocator::with_capacity(1024);
+    unsafe { allocator.allocate(Layout::new::<u64>()) };
+    allocator.release_owner();
+
+    let result = on_other_thread(&mut allocator, |allocator| unsafe {
+      allocator.allocate(Layout::new::<u64>());
+    });
+    assert!(result.is_ok());
+    assert_ne!(allocator.owner_thread(), Some(thread::current().id()));
+  }
+
+  #[test]
+  fn shared_allocators_are_never_checked() {
+    let mut allocator = BumpAllocator::with_capacity(1024).into_shared();
+    unsafe { allocator.allocate(Layout::new::<u64>()) };
+
+    let result = on_other_thread(&mut allocator, |allocator| unsafe {
+      allocator.allocate(Layout::new::<u64>());
+    });
+    assert!(result.is_ok());
+    assert_eq!(allocator.owner_thread(), None);
+  }
+}
@@ -0,0 +1,370 @@
+//! # Arena Pools
+//!
+//! An [`ArenaPool`] owns one large reservation and hands out independent
+//! arenas from it at runtime. Creating or destroying an arena is a couple
+//! of pointer writes - no system call - so a server can give every request
+//! or connection its own arena cheaply.
+//!
+//! ```text
+//!   ArenaPool (one reservation, split into equal slots)
+//!   ┌──────────────┬──────────────┬──────────────┬──────────────┐
+//!   │   slot 0     │   slot 1     │   slot 2     │   slot 3     │
+//!   │  (in use)    │   (free)     │  (in use)    │   (free)     │
+//!   └──────────────┴──────────────┴──────────────┴──────────────┘
+//!          ▲              │                              ▲
+//!   PooledArena #1        │ next free ───────────────────┘
+//!                         │
+//!                 free_head
+//! ```
+//!
+//! Free slots form an intrusive singly-linked stack: the first word of a
+//! free slot stores the index of the next free slot, so the pool needs no
+//! bookkeeping memory of its own. `acquire` pops the stack, dropping a
+//! [`PooledArena`] pushes its slot back.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use rallocator::ArenaPool;
+//!
+//! let pool = ArenaPool::new(64, 16 * 1024).unwrap(); // 64 arenas × 16 KiB
+//!
+//! let mut request_a = pool.acquire().unwrap();
+//! let mut request_b = pool.acquire().unwrap();
+//! // ... each request allocates from its own arena ...
+//! drop(request_a); // slot immediately reusable
+//! ```
+//!
+//! The pool is single-threaded: it hands out arenas through `&self` using
+//! `Cell`s, so it is neither `Send` nor `Sync`.
+
+use core::{alloc::Layout, cell::Cell, ops::Deref, ptr};
+
+use crate::{BumpAllocator, align::align_up, backend::Region};
+
+/// Marks the end of the free-slot stack.
+const NO_SLOT: usize = usize::MAX;
+
+/// Slots are aligned (and sized) to this many bytes.
+const SLOT_ALIGN: usize = 16;
+
+/// A fixed set of equally sized arenas carved from one reservation.
+pub struct ArenaPool {
+  /// The reservation. Only held so owned regions are freed with the pool.
+  _memory: Region,
+
+  /// First byte of slot 0.
+  start: *mut u8,
+
+  /// Size of each slot in bytes (a multiple of `SLOT_ALIGN`).
+  slot_size: usize,
+
+  /// Number of slots in the reservation.
+  slot_count: usize,
+
+  /// Index of the first free slot, or `NO_SLOT`.
+  free_head: Cell<usize>,
+
+  /// Number of slots currently handed out.
+  in_use: Cell<usize>,
+}
+
+impl ArenaPool {
+  /// Creates a pool of `slot_count` arenas of `slot_size` bytes each,
+  /// reserved with a single request to the system allocator.
+  ///
+  /// `slot_size` is rounded up to a multiple of 16.
+  ///
+  /// # Returns
+  ///
+  /// `None` if the reservation size overflows or cannot be obtained.
+  #[cfg(feature = "std")]
+  pub fn new(
+    slot_count: usize,
+    slot_size: usize,
+  ) -> Option<Self> {
+    let slot_size = Self::round_slot_size(slot_size)?;
+    let total = slot_size.checked_mul(slot_count)?;
+    Self::from_region(Region::owned(total), slot_count, slot_size)
+  }
+
+  /// Creates a pool over `buffer`, split into as many `slot_size`-byte
+  /// arenas as fit.
+  ///
+  /// `slot_size` is rounded up to a multiple of 16.
+  pub fn from_buffer(
+    buffer: &'static mut [u8],
+    slot_size: usize,
+  ) -> Option<Self> {
+    let slot_size = Self::round_slot_size(slot_size)?;
+
+    // Skip leading bytes so slot 0 is aligned
+    let start = buffer.as_mut_ptr();
+    let skip = align_up(start as usize, SLOT_ALIGN) - start as usize;
+    let usable = buffer.len().checked_sub(skip)?;
+    let slot_count = usable / slot_size;
+
+    // SAFETY: The exclusive 'static borrow keeps the memory valid and unused.
+    let region = unsafe { Region::borrowed(start.add(skip), usable) };
+    Self::from_region(region, slot_count, slot_size)
+  }
+
+  /// Rounds a requested slot size up to `SLOT_ALIGN`, rejecting zero.
+  fn round_slot_size(slot_size: usize) -> Option<usize> {
+    if slot_size == 0 {
+      return None;
+    }
+    slot_size.checked_next_multiple_of(SLOT_ALIGN)
+  }
+
+  /// Takes `slot_count * slot_size` bytes from `memory` and threads every
+  /// slot onto the free stack.
+  fn from_region(
+    mut memory: Region,
+    slot_count: usize,
+    slot_size: usize,
+  ) -> Option<Self> {
+    if slot_count == 0 {
+      return None;
+    }
+    let start = memory.grow(slot_count * slot_size)?;
+
+    let pool = Self {
+      _memory: memory,
+      start,
+      slot_size,
+      slot_count,
+      free_head: Cell::new(NO_SLOT),
+      in_use: Cell::new(0),
+    };
+
+    // Push in reverse so slot 0 is handed out first
+    for slot in (0..slot_count).rev() {
+      pool.push_free(slot);
+    }
+    Some(pool)
+  }
+
+  /// Size in bytes of each arena handed out by the pool.
+  pub fn slot_size(&self) -> usize {
+    self.slot_size
+  }
+
+  /// Total number of arenas the pool can hand out at once.
+  pub fn slot_count(&self) -> usize {
+    self.slot_count
+  }
+
+  /// Number of arenas that can still be acquired.
+  pub fn available(&self) -> usize {
+    self.slot_count - self.in_use.get()
+  }
+
+  /// Hands out a fresh, empty arena, or `None` if every slot is in use.
+  ///
+  /// The arena keeps the pool borrowed, and its slot returns to the pool
+  /// when it is dropped.
+  pub fn acquire(&self) -> Option<PooledArena<'_>> {
+    let slot = self.free_head.get();
+    if slot == NO_SLOT {
+      return None;
+    }
+
+    // SAFETY: Free slots store the next free index in their first word.
+    let next = unsafe { ptr::read(self.slot_ptr(slot) as *const usize) };
+    self.free_head.set(next);
+    self.in_use.set(self.in_use.get() + 1);
+
+    // SAFETY: The slot is exclusively ours until the PooledArena is dropped.
+    let arena = unsafe { BumpAllocator::from_raw_region(self.slot_ptr(slot), self.slot_size) };
+    Some(PooledArena { arena, slot, pool: self })
+  }
+
+  /// Address of the first byte of `slot`.
+  fn slot_ptr(
+    &self,
+    slot: usize,
+  ) -> *mut u8 {
+    // SAFETY: `slot < slot_count`, so the offset stays inside the reservation.
+    unsafe { self.start.add(slot * self.slot_size) }
+  }
+
+  /// Pushes `slot` onto the free stack.
+  fn push_free(
+    &self,
+    slot: usize,
+  ) {
+    // SAFETY: The slot is free, so its first word is ours to overwrite.
+    // Slots are SLOT_ALIGN-aligned, which satisfies `usize` alignment.
+    unsafe { ptr::write(self.slot_ptr(slot) as *mut usize, self.free_head.get()) };
+    self.free_head.set(slot);
+  }
+}
+
+/// An arena borrowed from an [`ArenaPool`].
+///
+/// Dereferences to a [`BumpAllocator`] whose region is the slot, for
+/// queries; allocation goes through the methods below, so the slot's
+/// allocator cannot be swapped out. Dropping it returns the slot to the
+/// pool, invalidating every pointer it handed out.
+pub struct PooledArena<'pool> {
+  /// The allocator bumping through the slot.
+  arena: BumpAllocator,
+
+  /// Index of the slot in the pool.
+  slot: usize,
+
+  /// The pool the slot is returned to.
+  pool: &'pool ArenaPool,
+}
+
+impl PooledArena<'_> {
+  /// Index of this arena's slot within the pool.
+  pub fn slot(&self) -> usize {
+    self.slot
+  }
+
+  /// Allocates from the slot, see [`BumpAllocator::allocate`].
+  ///
+  /// # Safety
+  ///
+  /// As [`BumpAllocator::allocate`].
+  pub unsafe fn allocate(
+    &mut self,
+    layout: Layout,
+  ) -> *mut u8 {
+    // SAFETY: As the caller's contract.
+    unsafe { self.arena.allocate(layout) }
+  }
+
+  /// Frees `address`, see [`BumpAllocator::deallocate`].
+  ///
+  /// # Safety
+  ///
+  /// As [`BumpAllocator::deallocate`].
+  pub unsafe fn deallocate(
+    &mut self,
+    address: *mut u8,
+  ) {
+    // SAFETY: As the caller's contract.
+    unsafe { self.arena.deallocate(address) }
+  }
+
+  /// Frees `address` allocated with `layout`, see
+  /// [`BumpAllocator::deallocate_with_layout`].
+  ///
+  /// # Safety
+  ///
+  /// As [`BumpAllocator::deallocate_with_layout`].
+  pub unsafe fn deallocate_with_layout(
+    &mut self,
+    address: *mut u8,
+    layout: Layout,
+  ) {
+    // SAFETY: As the caller's contract.
+    unsafe { self.arena.deallocate_with_layout(address, layout) }
+  }
+
+  /// Frees every block, see [`BumpAllocator::reset`].
+  ///
+  /// # Safety
+  ///
+  /// As [`BumpAllocator::reset`].
+  pub unsafe fn reset(&mut self) {
+    // SAFETY: As the caller's contract.
+    unsafe { self.arena.reset() }
+  }
+}
+
+impl Deref for PooledArena<'_> {
+  type Target = BumpAllocator;
+
+  fn deref(&self) -> &BumpAllocator {
+    &self.arena
+  }
+}
+
+impl Drop for PooledArena<'_> {
+  fn drop(&mut self) {
+    self.pool.push_free(self.slot);
+    self.pool.in_use.set(self.pool.in_use.get() - 1);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn arenas_are_disjoint_and_independent() {
+    let pool = ArenaPool::new(4, 1000).unwrap();
+    assert_eq!(pool.slot_size(), 1008);
+
+    let mut a = pool.acquire().unwrap();
+    let mut b = pool.acquire().unwrap();
+    assert_ne!(a.slot(), b.slot());
+
+    unsafe {
+      let pa = a.allocate(Layout::new::<u64>()) as usize;
+      let pb = b.allocate(Layout::new::<u64>()) as usize;
+      b.allocate(Layout::new::<u64>());
+
+      let slot_a = pool.slot_ptr(a.slot()) as usize;
+      let slot_b = pool.slot_ptr(b.slot()) as usize;
+      assert!((slot_a..slot_a + pool.slot_size()).contains(&pa));
+      assert!((slot_b..slot_b + pool.slot_size()).contains(&pb));
+    }
+
+    assert_eq!(a.stats().live_blocks, 1);
+    assert_eq!(b.stats().live_blocks, 2);
+  }
+
+  #[test]
+  fn pool_exhausts_and_recycles_slots() {
+    let pool = ArenaPool::new(2, 256).unwrap();
+
+    let a = pool.acquire().unwrap();
+    let b = pool.acquire().unwrap();
+    assert_eq!(pool.available(), 0);
+    assert!(pool.acquire().is_none());
+
+    let freed = a.slot();
+    drop(a);
+    assert_eq!(pool.available(), 1);
+
+    let c = pool.acquire().unwrap();
+    assert_eq!(c.slot(), freed);
+    drop(b);
+    drop(c);
+    assert_eq!(pool.available(), 2);
+  }
+
+  #[test]
+  fn recycled_arena_starts_empty() {
+    let pool = ArenaPool::new(1, 512).unwrap();
+
+    let first = unsafe { pool.acquire().unwrap().allocate(Layout::new::<u32>()) };
+    let mut again = pool.acquire().unwrap();
+    assert_eq!(again.stats().total_blocks(), 0);
+    assert_eq!(unsafe { again.allocate(Layout::new::<u32>()) }, first);
+  }
+
+  #[test]
+  fn from_buffer_splits_the_buffer() {
+    let buffer = Box::leak(vec![0u8; 4096 + 15].into_boxed_slice());
+    let range = buffer.as_ptr_range();
+    let pool = ArenaPool::from_buffer(buffer, 1024).unwrap();
+    assert_eq!(pool.slot_count(), 4);
+
+    let mut arena = pool.acquire().unwrap();
+    let ptr = unsafe { arena.allocate(Layout::new::<u64>()) };
+    assert!(range.contains(&(ptr as *const u8)));
+  }
+
+  #[test]
+  fn invalid_pools_are_rejected() {
+    assert!(ArenaPool::new(0, 1024).is_none());
+    assert!(ArenaPool::new(4, 0).is_none());
+    assert!(ArenaPool::new(usize::MAX, 1024).is_none());
+  }
+}
@@ -0,0 +1,212 @@
+//! # Preheating
+//!
+//! A fresh allocator reaches its steady state slowly: the first requests
+//! of every size grow the heap, and only later ones find freed memory to
+//! reuse. Latency-sensitive services would rather pay that at startup.
+//! [`preheat`](BumpAllocator::preheat) takes a size profile - recorded in
+//! an earlier run, say from [`SizeClasses`](crate::SizeClasses) - and
+//! prepares the memory it describes up front:
+//!
+//! ```text
+//!   profile: [(16, 4), (256, 2)]          set_headerless_max(32)
+//!
+//!   16 B  (headerless class)   free stack ──► [··] ──► [··] ──► [··] ──► [··]
+//!   256 B (normal blocks)      heap: [hdr│ free 256 + hdr + 256     ]
+//!                                     ▲ merged, linked for the free-block search
+//! ```
+//!
+//! Sizes a headerless class serves (see
+//! [`set_headerless_max`](BumpAllocator::set_headerless_max)) get slots
+//! pushed on their free stack, so
+//! [`allocate_sized`](BumpAllocator::allocate_sized) pops them without
+//! touching the break. Every other size gets blocks in the block list,
+//! tail included, freed like [`deallocate`](BumpAllocator::deallocate)
+//! frees them: merged into one free run, which the free-block search
+//! splits back into blocks of the requested sizes.
+//!
+//! Preheating goes straight to the heap: no rate limit, OOM handler,
+//! donation or emergency reserve is involved, and it stops at the first
+//! size the backend cannot provide, reporting what it prepared.
+
+use core::{alloc::Layout, mem};
+
+use crate::BumpAllocator;
+
+/// What [`preheat`](BumpAllocator::preheat) prepared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Preheated {
+  /// Slots pushed on headerless free stacks.
+  pub headerless_slots: usize,
+
+  /// Blocks created in the block list, before merging into free runs.
+  pub free_blocks: usize,
+
+  /// Requested bytes of both.
+  pub bytes: usize,
+
+  /// Whether the whole profile was prepared, rather than stopping when the
+  /// heap ran out.
+  pub complete: bool,
+}
+
+impl BumpAllocator {
+  /// Prepares `count` free slots or blocks of `size` bytes for each
+  /// `(size, count)` of `profile`, see the `preheat` module.
+  ///
+  /// Call it on a fresh allocator: blocks allocated before stay where they
+  /// are, and the prepared memory sits above them.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// // Sizes and peak counts recorded by a previous run
+  /// const PROFILE: &[(usize, usize)] = &[(16, 4096), (64, 1024), (4096, 32)];
+  ///
+  /// allocator.set_headerless_max(32);
+  /// let prepared = allocator.preheat(PROFILE.iter().copied());
+  /// assert!(prepared.complete);
+  /// ```
+  pub fn preheat(
+    &mut self,
+    profile: impl IntoIterator<Item = (usize, usize)>,
+  ) -> Preheated {
+    let mut done = Preheated::default();
+    for (size, count) in profile {
+      let Ok(layout) = Layout::from_size_align(size, mem::align_of::<usize>()) else {
+        return done;
+      };
+      // SAFETY: Every slot and block is created here and never handed to
+      // the user before it is free again.
+      let prepared = unsafe {
+        if size <= self.headerless_max() {
+          self.preheat_headerless(layout, count)
+        } else {
+          self.preheat_blocks(layout, count)
+        }
+      };
+      let full = prepared == count;
+      if size <= self.headerless_max() {
+        done.headerless_slots += prepared;
+      } else {
+        done.free_blocks += prepared;
+      }
+      done.bytes += prepared * size;
+      if !full {
+        return done;
+      }
+    }
+    done.complete = true;
+    done
+  }
+
+  /// Pushes up to `count` slots of `layout` on their headerless free
+  /// stack. Returns how many it pushed.
+  unsafe fn preheat_headerless(
+    &mut self,
+    layout: Layout,
+    count: usize,
+  ) -> usize {
+    // Taken all at once, chained through their first words, then freed:
+    // freeing each right away would hand the same slot back every time
+    let mut taken: *mut u8 = core::ptr::null_mut();
+    let mut pushed = 0;
+    while pushed < count {
+      // SAFETY: A fresh slot, at least a word and word-aligned.
+      unsafe {
+        let slot = self.allocate_sized(layout);
+        if slot.is_null() {
+          break;
+        }
+        *(slot as *mut *mut u8) = taken;
+        taken = slot;
+      }
+      pushed += 1;
+    }
+    while !taken.is_null() {
+      // SAFETY: Each slot came from `allocate_sized` with `layout`.
+      unsafe {
+        let next = *(taken as *mut *mut u8);
+        self.deallocate_sized(taken, layout);
+        taken = next;
+      }
+    }
+    pushed
+  }
+
+  /// Creates up to `count` free blocks of `layout` in the block list.
+  /// Returns how many it created.
+  unsafe fn preheat_blocks(
+    &mut self,
+    layout: Layout,
+    count: usize,
+  ) -> usize {
+    let mut created = 0;
+    while created < count {
+      // SAFETY: A new block of the list, freed before anyone sees it.
+      unsafe {
+        let address = self.place(layout);
+        if address.is_null() {
+          break;
+        }
+        if self.is_untracked(address) {
+          // Past the block cap: no header to keep it by
+          break;
+        }
+        self.free_in_place(self.last_block());
+      }
+      created += 1;
+    }
+    created
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn small_sizes_fill_headerless_stacks() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_headerless_max(32);
+    let prepared = allocator.preheat([(16, 4)]);
+    assert_eq!((prepared.headerless_slots, prepared.bytes, prepared.complete), (4, 64, true));
+
+    // The prepared slots are popped without growing the heap
+    let heap = allocator.heap_size();
+    let layout = Layout::array::<u8>(16).unwrap();
+    for _ in 0..4 {
+      assert!(!unsafe { allocator.allocate_sized(layout) }.is_null());
+    }
+    assert_eq!(allocator.heap_size(), heap);
+  }
+
+  #[test]
+  fn other_sizes_become_free_blocks() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let prepared = allocator.preheat([(256, 2), (100, 3)]);
+    assert_eq!((prepared.free_blocks, prepared.bytes, prepared.complete), (5, 812, true));
+
+    // Merged as they were freed: one run covering all five
+    let sizes: Vec<_> = allocator.free_blocks().map(|block| block.size).collect();
+    assert_eq!(sizes.len(), 1);
+    assert!(sizes[0] >= 812);
+    assert_eq!(allocator.live_blocks(), 0);
+    allocator.assert_invariants();
+
+    // The run is split back into the profiled sizes without growing the heap
+    let heap = allocator.heap_size();
+    for size in [256, 256, 100, 100, 100] {
+      assert!(!unsafe { allocator.allocate(Layout::array::<u8>(size).unwrap()) }.is_null());
+    }
+    assert_eq!(allocator.heap_size(), heap);
+  }
+
+  #[test]
+  fn preheating_stops_when_the_heap_runs_out() {
+    let mut allocator = BumpAllocator::with_capacity(1024);
+    let prepared = allocator.preheat([(64, 4), (512, 10), (64, 4)]);
+    assert!(!prepared.complete);
+    assert_eq!(prepared.free_blocks, 4 + 1);
+    assert!(!allocator.emergency_reserve_unlocked());
+  }
+}
@@ -0,0 +1,206 @@
+//! # Memory Pressure
+//!
+//! A long-running service keeps freed tail memory and growth chunk surplus
+//! cached above the heap (see [`ShrinkPolicy`](crate::ShrinkPolicy)). That
+//! is the right call while the machine has memory to spare, and the wrong
+//! one when the kernel is reclaiming pages from everyone else.
+//! [`relieve_pressure`](BumpAllocator::relieve_pressure) gives the cache
+//! back on demand; call it from whatever signal the program already has.
+//!
+//! On Linux, [`PressureWatch`] reads that signal from the kernel's pressure
+//! stall information (PSI), system-wide or for one cgroup:
+//!
+//! ```text
+//!   /proc/pressure/memory                     (or <cgroup>/memory.pressure)
+//!   some avg10=12.50 avg60=3.10 avg300=0.82 total=91827
+//!   full avg10=0.00 avg60=0.00 avg300=0.00 total=1021
+//!         ▲
+//!         share of the last 10 s in which some task stalled on memory
+//!
+//!   watch.poll(&mut allocator)
+//!        │
+//!        ├── avg10 <  threshold ──► nothing
+//!        └── avg10 >= threshold ──► relieve_pressure(): cache ──► sbrk(-cached)
+//! ```
+//!
+//! Polling is explicit - from a timer, a housekeeping task, between
+//! requests - so the allocator never starts threads or reads files on its
+//! own. Live blocks are never touched, and region backends have no cache
+//! to give back.
+
+use crate::BumpAllocator;
+
+impl BumpAllocator {
+  /// Returns the cached memory above the heap to the system right away,
+  /// whatever the shrink policy. Returns the bytes released.
+  ///
+  /// Memory stays cached if something else moved the program break past
+  /// it meanwhile.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// if low_memory_signal() {
+  ///     let freed = allocator.relieve_pressure();
+  ///     log::info!("gave back {}", ByteSize(freed));
+  /// }
+  /// ```
+  pub fn relieve_pressure(&mut self) -> usize {
+    let cached = self.backend.cached_bytes();
+    // SAFETY: The cache holds no handed-out memory.
+    unsafe { self.backend.trim() };
+    self.idle_ops = 0;
+    cached - self.backend.cached_bytes()
+  }
+}
+
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub use watch::PressureWatch;
+
+#[cfg(all(feature = "std", target_os = "linux"))]
+mod watch {
+  use std::{fs, io, path::Path};
+
+  use crate::BumpAllocator;
+
+  /// System-wide memory pressure file.
+  const SYSTEM_PRESSURE: &str = "/proc/pressure/memory";
+
+  /// Relieves an allocator when Linux reports memory pressure, see the
+  /// `pressure` module.
+  ///
+  /// ```rust,ignore
+  /// let watch = PressureWatch::new(Path::new("/sys/fs/cgroup/my.service/memory.pressure"), 5.0);
+  /// loop {
+  ///     serve_requests(&mut allocator);
+  ///     watch.poll(&mut allocator)?;
+  /// }
+  /// ```
+  #[derive(Debug, Clone, Copy, PartialEq)]
+  pub struct PressureWatch {
+    /// PSI file to read.
+    path: &'static Path,
+
+    /// `some avg10` percentage at which to relieve.
+    threshold: f32,
+  }
+
+  impl PressureWatch {
+    /// Watches the whole system, relieving once `some avg10` reaches
+    /// `threshold` percent.
+    pub fn system(threshold: f32) -> Self {
+      Self::new(Path::new(SYSTEM_PRESSURE), threshold)
+    }
+
+    /// Watches a PSI file - `memory.pressure` of a cgroup v2 directory, or
+    /// a file a test writes.
+    pub const fn new(
+      path: &'static Path,
+      threshold: f32,
+    ) -> Self {
+      Self { path, threshold }
+    }
+
+    /// The current `some avg10` percentage.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be read (no PSI support, no such cgroup), or has
+    /// no `some` line.
+    pub fn level(&self) -> io::Result<f32> {
+      let text = fs::read_to_string(self.path)?;
+      some_avg10(&text).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no `some avg10` in pressure file"))
+    }
+
+    /// Reads the pressure and relieves `allocator` if it is at or above
+    /// the threshold. Returns the bytes released.
+    ///
+    /// # Errors
+    ///
+    /// As [`level`](Self::level); the allocator is left alone then.
+    pub fn poll(
+      &self,
+      allocator: &mut BumpAllocator,
+    ) -> io::Result<usize> {
+      if self.level()? >= self.threshold {
+        Ok(allocator.relieve_pressure())
+      } else {
+        Ok(0)
+      }
+    }
+  }
+
+  /// The `avg10` field of the `some` line of a PSI file.
+  pub(super) fn some_avg10(text: &str) -> Option<f32> {
+    let line = text.lines().find_map(|line| line.strip_prefix("some "))?;
+    line
+      .split_whitespace()
+      .find_map(|field| field.strip_prefix("avg10="))?
+      .parse()
+      .ok()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn regions_have_nothing_to_release() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    unsafe { allocator.allocate(core::alloc::Layout::new::<u64>()) };
+    assert_eq!(allocator.relieve_pressure(), 0);
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn the_cache_goes_back_whatever_the_policy() {
+    use crate::ShrinkPolicy;
+    use core::alloc::Layout;
+
+    let mut allocator = BumpAllocator::new();
+    allocator.set_shrink_policy(ShrinkPolicy::keep(1 << 20));
+    unsafe {
+      allocator.allocate(Layout::array::<u8>(4096).unwrap());
+      let tail = allocator.allocate(Layout::array::<u8>(4096).unwrap());
+      allocator.deallocate(tail);
+    }
+    let cached = allocator.stats().cached_bytes;
+    assert!(cached >= 4096);
+
+    // A parallel test may have moved the break past the cache
+    let released = allocator.relieve_pressure();
+    assert!(released == cached || released == 0);
+    assert_eq!(allocator.stats().cached_bytes, cached - released);
+    unsafe { allocator.reset() };
+  }
+
+  #[test]
+  #[cfg(all(feature = "std", target_os = "linux"))]
+  fn psi_files_are_parsed() {
+    let text = "some avg10=12.50 avg60=3.10 avg300=0.82 total=91827\n\
+                full avg10=0.00 avg60=0.00 avg300=0.00 total=1021\n";
+    assert_eq!(watch::some_avg10(text), Some(12.5));
+    assert_eq!(watch::some_avg10("full avg10=3.00 total=1\n"), None);
+    assert_eq!(watch::some_avg10("some avg10=high\n"), None);
+  }
+
+  #[test]
+  #[cfg(all(feature = "std", target_os = "linux"))]
+  fn watches_read_their_file() {
+    use std::path::PathBuf;
+
+    let path: &'static PathBuf = Box::leak(Box::new(std::env::temp_dir().join(format!(
+      "rallocator-pressure-{}",
+      std::process::id()
+    ))));
+    std::fs::write(path, "some avg10=7.00 avg60=1.00 avg300=0.00 total=10\n").unwrap();
+
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    assert_eq!(PressureWatch::new(path, 5.0).level().unwrap(), 7.0);
+    assert_eq!(PressureWatch::new(path, 5.0).poll(&mut allocator).unwrap(), 0);
+    std::fs::remove_file(path).unwrap();
+
+    assert!(PressureWatch::new(path, 5.0).poll(&mut allocator).is_err());
+  }
+}
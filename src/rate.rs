@@ -0,0 +1,292 @@
+//! # Allocation Rate Limiting
+//!
+//! An accidental allocation in a hot loop is easy to miss when this crate
+//! backs the global allocator: nothing fails, the heap just grows. A
+//! [`RateLimit`] caps allocations and/or bytes per time window and reacts
+//! when a window overflows:
+//!
+//! ```text
+//!   window = 16 ms, max_allocations = 3
+//!
+//!   t(ms)   0    2    5    9   12   |16   17
+//!           a    a    a    a    a   |a    a
+//!           1    2    3    4!   5!  |1    2      ◄── count in window
+//!                          │    │
+//!                          └────┴── over the limit: on_exceeded(...)
+//!                                   or fail with AllocFailure::RateLimited
+//! ```
+//!
+//! The handler sees a [`RateExceeded`] and decides: `true` lets the
+//! allocation through (log, count, break into a debugger), `false` makes it
+//! fail. It may also panic to get a backtrace of the storm. Allocations the
+//! handler itself makes are never throttled.
+//!
+//! Time comes from a plain `fn() -> Duration` - the simplest
+//! [`Clock`](crate::Clock) - so the limiter works without `std` given any
+//! monotonic tick source.
+
+use core::{alloc::Layout, time::Duration};
+
+#[cfg(feature = "std")]
+use crate::monotonic_clock;
+use crate::{AllocFailure, BumpAllocator};
+
+/// Called when an allocation exceeds the [`RateLimit`].
+///
+/// Returns whether to allow the allocation anyway.
+pub type RateLimitHandler = fn(&mut BumpAllocator, RateExceeded) -> bool;
+
+/// Maximum allocation rate of a [`BumpAllocator`].
+///
+/// ```rust,ignore
+/// allocator.set_rate_limit(Some(RateLimit {
+///     max_allocations: Some(10_000),
+///     ..RateLimit::per(Duration::from_millis(16))
+/// }));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+  /// Length of a window; counts start over when it elapses.
+  pub window: Duration,
+
+  /// Most allocations allowed per window, if limited.
+  pub max_allocations: Option<usize>,
+
+  /// Most bytes allowed per window, if limited.
+  pub max_bytes: Option<usize>,
+
+  /// Monotonic time source.
+  pub clock: fn() -> Duration,
+
+  /// Called when a window overflows; without one, the allocation fails.
+  pub on_exceeded: Option<RateLimitHandler>,
+}
+
+impl RateLimit {
+  /// No limits yet over windows of `window`, timed by [`monotonic_clock`].
+  #[cfg(feature = "std")]
+  pub fn per(window: Duration) -> Self {
+    Self::with_clock(window, monotonic_clock)
+  }
+
+  /// No limits yet over windows of `window`, timed by `clock`.
+  pub const fn with_clock(
+    window: Duration,
+    clock: fn() -> Duration,
+  ) -> Self {
+    Self {
+      window,
+      max_allocations: None,
+      max_bytes: None,
+      clock,
+      on_exceeded: None,
+    }
+  }
+}
+
+/// Details of an allocation over the [`RateLimit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateExceeded {
+  /// The allocation being attempted.
+  pub layout: Layout,
+
+  /// Allocations in the current window, this one included.
+  pub allocations: usize,
+
+  /// Bytes in the current window, this allocation included.
+  pub bytes: usize,
+
+  /// Time since the window started.
+  pub elapsed: Duration,
+}
+
+/// A rate limit and its current window.
+pub(crate) struct RateState {
+  /// The configured limit.
+  limit: RateLimit,
+
+  /// When the current window started.
+  window_start: Duration,
+
+  /// Allocations in the current window.
+  allocations: usize,
+
+  /// Bytes in the current window.
+  bytes: usize,
+}
+
+impl BumpAllocator {
+  /// Throttles allocations, or removes the throttle with `None`.
+  ///
+  /// The first window starts now.
+  pub fn set_rate_limit(
+    &mut self,
+    limit: Option<RateLimit>,
+  ) {
+    self.rate_limit = limit.map(|limit| RateState {
+      limit,
+      window_start: (limit.clock)(),
+      allocations: 0,
+      bytes: 0,
+    });
+  }
+
+  /// The current rate limit, if any.
+  pub fn rate_limit(&self) -> Option<RateLimit> {
+    self.rate_limit.as_ref().map(|state| state.limit)
+  }
+
+  /// Counts an allocation of `layout` against the rate limit.
+  ///
+  /// # Returns
+  ///
+  /// Whether the allocation may proceed.
+  #[inline]
+  pub(crate) fn within_rate_limit(
+    &mut self,
+    layout: Layout,
+  ) -> bool {
+    self.rate_limit.is_none() || self.in_handler || self.count_allocation(layout)
+  }
+
+  /// Slow path of [`within_rate_limit`](Self::within_rate_limit).
+  #[cold]
+  fn count_allocation(
+    &mut self,
+    layout: Layout,
+  ) -> bool {
+    let Some(state) = &mut self.rate_limit else {
+      return true;
+    };
+
+    let now = (state.limit.clock)();
+    let mut elapsed = now.saturating_sub(state.window_start);
+    if elapsed >= state.limit.window {
+      state.window_start = now;
+      state.allocations = 0;
+      state.bytes = 0;
+      elapsed = Duration::ZERO;
+    }
+    state.allocations += 1;
+    state.bytes = state.bytes.saturating_add(layout.size());
+
+    let limit = state.limit;
+    let over = limit.max_allocations.is_some_and(|max| state.allocations > max)
+      || limit.max_bytes.is_some_and(|max| state.bytes > max);
+    if !over {
+      return true;
+    }
+
+    let exceeded = RateExceeded {
+      layout,
+      allocations: state.allocations,
+      bytes: state.bytes,
+      elapsed,
+    };
+    let allowed = match limit.on_exceeded {
+      Some(handler) => self.call_handler(|allocator| handler(allocator, exceeded)),
+      None => false,
+    };
+    if !allowed {
+      self.last_failure = Some(AllocFailure::RateLimited {
+        allocations: exceeded.allocations,
+        bytes: exceeded.bytes,
+      });
+    }
+    allowed
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::cell::Cell;
+
+  std::thread_local! {
+    /// Fake time for the current test thread, in milliseconds.
+    static NOW_MS: Cell<u64> = const { Cell::new(0) };
+  }
+
+  fn fake_clock() -> Duration {
+    Duration::from_millis(NOW_MS.with(Cell::get))
+  }
+
+  fn advance(ms: u64) {
+    NOW_MS.with(|now| now.set(now.get() + ms));
+  }
+
+  fn allocate(allocator: &mut BumpAllocator) -> bool {
+    !unsafe { allocator.allocate(Layout::new::<u64>()) }.is_null()
+  }
+
+  #[test]
+  fn allocations_over_the_limit_fail_until_the_window_ends() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_rate_limit(Some(RateLimit {
+      max_allocations: Some(3),
+      ..RateLimit::with_clock(Duration::from_millis(16), fake_clock)
+    }));
+
+    assert!((0..3).all(|_| allocate(&mut allocator)));
+    assert!(!allocate(&mut allocator));
+    assert_eq!(
+      allocator.last_failure(),
+      Some(AllocFailure::RateLimited { allocations: 4, bytes: 32 })
+    );
+
+    advance(16);
+    assert!(allocate(&mut allocator));
+  }
+
+  #[test]
+  fn byte_limits_count_requested_sizes() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_rate_limit(Some(RateLimit {
+      max_bytes: Some(100),
+      ..RateLimit::with_clock(Duration::from_secs(1), fake_clock)
+    }));
+
+    unsafe {
+      assert!(!allocator.allocate(Layout::array::<u8>(60).unwrap()).is_null());
+      assert!(allocator.allocate(Layout::array::<u8>(60).unwrap()).is_null());
+    }
+  }
+
+  #[test]
+  fn handler_decides_and_is_not_throttled_itself() {
+    fn allow_and_log(
+      allocator: &mut BumpAllocator,
+      exceeded: RateExceeded,
+    ) -> bool {
+      // The handler's own allocation is not counted or throttled
+      assert!(allocate(allocator));
+      exceeded.allocations < 4
+    }
+
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_rate_limit(Some(RateLimit {
+      max_allocations: Some(2),
+      on_exceeded: Some(allow_and_log),
+      ..RateLimit::with_clock(Duration::from_secs(1), fake_clock)
+    }));
+
+    let results: Vec<bool> = (0..4).map(|_| allocate(&mut allocator)).collect();
+    assert_eq!(results, [true, true, true, false]);
+    // Two handler calls, one allocation each
+    assert_eq!(allocator.stats().live_blocks, 5);
+  }
+
+  #[test]
+  fn removing_the_limit_stops_throttling() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_rate_limit(Some(RateLimit {
+      max_allocations: Some(0),
+      ..RateLimit::with_clock(Duration::from_secs(1), fake_clock)
+    }));
+    assert!(!allocate(&mut allocator));
+
+    allocator.set_rate_limit(None);
+    assert!(allocator.rate_limit().is_none());
+    assert!(allocate(&mut allocator));
+  }
+}
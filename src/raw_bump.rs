@@ -0,0 +1,163 @@
+//! # Raw Bump Arenas
+//!
+//! [`RawBump`] is the allocator with everything taken out: no headers, no
+//! block list, no free - just a cursor moving through a region towards its
+//! end, and a reset that moves it back:
+//!
+//! ```text
+//!   start                       cursor                          end
+//!   ▼                           ▼                               ▼
+//!   ├───┬──┬────────┬─┬─────────┬───────────────────────────────┤
+//!   │ a │··│   b    │·│    c    │          available            │
+//!   └───┴──┴────────┴─┴─────────┴───────────────────────────────┘
+//!         alignment padding only
+//!
+//!   allocate: align the cursor, check the end, move it     O(1), no header
+//!   reset:    cursor = start                               O(1)
+//! ```
+//!
+//! For workloads that only ever reset - per-frame scratch, per-request
+//! parsing - this is the floor on both memory overhead and allocate cost.
+//! Everything that needs per-allocation metadata (stats by block, leak
+//! reports, canaries, individual frees) belongs to [`BumpAllocator`]; see
+//! also [`BumpAllocator::set_max_blocks`] for a mix of the two.
+//!
+//! [`BumpAllocator`]: crate::BumpAllocator
+//! [`BumpAllocator::set_max_blocks`]: crate::BumpAllocator::set_max_blocks
+
+use core::{alloc::Layout, ptr};
+
+use crate::{align::align_up, backend::Region};
+
+/// A headerless bump arena over a fixed region.
+///
+/// Hands out memory from the region in order and frees it only all at
+/// once. Null means the region is full.
+pub struct RawBump {
+  /// The memory and its cursor.
+  region: Region,
+}
+
+impl RawBump {
+  /// An arena over a caller-provided buffer.
+  pub fn from_buffer(buffer: &'static mut [u8]) -> Self {
+    // SAFETY: The buffer is exclusively borrowed for the program's lifetime.
+    unsafe { Self::from_raw_region(buffer.as_mut_ptr(), buffer.len()) }
+  }
+
+  /// An arena over `start..start + len`.
+  ///
+  /// # Safety
+  ///
+  /// The region must be valid for reads and writes and must not be used by
+  /// anything else for as long as the arena (or any allocation made from
+  /// it) is alive.
+  pub const unsafe fn from_raw_region(
+    start: *mut u8,
+    len: usize,
+  ) -> Self {
+    Self {
+      region: unsafe { Region::borrowed(start, len) },
+    }
+  }
+
+  /// An arena owning `capacity` bytes from the system allocator, reserved
+  /// on first use and freed on drop.
+  #[cfg(feature = "std")]
+  pub const fn with_capacity(capacity: usize) -> Self {
+    Self {
+      region: Region::owned(capacity),
+    }
+  }
+
+  /// Hands out memory for `layout`, or null if the region cannot fit it.
+  #[inline]
+  pub fn allocate(
+    &mut self,
+    layout: Layout,
+  ) -> *mut u8 {
+    let Some(cursor) = self.region.grow(0) else {
+      return ptr::null_mut();
+    };
+    let address = align_up(cursor as usize, layout.align());
+    let Some(size) = (address - cursor as usize).checked_add(layout.size()) else {
+      return ptr::null_mut();
+    };
+    match self.region.grow(size) {
+      Some(_) => address as *mut u8,
+      None => ptr::null_mut(),
+    }
+  }
+
+  /// Frees everything at once.
+  ///
+  /// # Safety
+  ///
+  /// Every pointer previously returned by `allocate` becomes dangling.
+  pub unsafe fn reset(&mut self) {
+    self.region.shrink(self.region.used());
+  }
+
+  /// Bytes handed out since the last reset, alignment padding included.
+  pub fn used(&self) -> usize {
+    self.region.used()
+  }
+
+  /// Size of the region.
+  pub fn capacity(&self) -> usize {
+    self.region.capacity()
+  }
+
+  /// Bytes left before allocations return null, ignoring alignment.
+  pub fn remaining(&self) -> usize {
+    self.capacity() - self.used()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn leaked_buffer(len: usize) -> &'static mut [u8] {
+    Box::leak(vec![0u8; len].into_boxed_slice())
+  }
+
+  #[test]
+  fn allocations_are_packed_without_headers() {
+    let mut arena = RawBump::from_buffer(leaked_buffer(256));
+    let a = arena.allocate(Layout::new::<u64>());
+    let b = arena.allocate(Layout::new::<u64>());
+    assert_eq!(b as usize - a as usize, 8);
+
+    let c = arena.allocate(Layout::from_size_align(1, 1).unwrap());
+    let d = arena.allocate(Layout::from_size_align(16, 16).unwrap());
+    assert_eq!(c as usize, b as usize + 8);
+    assert!((d as usize).is_multiple_of(16));
+    assert_eq!(arena.used(), d as usize + 16 - a as usize);
+  }
+
+  #[test]
+  fn full_arenas_return_null_until_reset() {
+    let mut arena = RawBump::from_buffer(leaked_buffer(64));
+    let layout = Layout::array::<u8>(40).unwrap();
+    let first = arena.allocate(layout);
+    assert!(!first.is_null());
+    assert!(arena.allocate(layout).is_null());
+    assert_eq!(arena.remaining(), 24);
+
+    unsafe { arena.reset() };
+    assert_eq!(arena.used(), 0);
+    assert_eq!(arena.allocate(layout), first);
+  }
+
+  #[test]
+  #[cfg(feature = "std")]
+  fn owned_arenas_reserve_on_first_use() {
+    let mut arena = RawBump::with_capacity(1024);
+    assert_eq!((arena.capacity(), arena.used()), (1024, 0));
+    let ptr = arena.allocate(Layout::array::<u32>(8).unwrap());
+    assert!(!ptr.is_null());
+    unsafe { ptr.cast::<u32>().write_bytes(0xFF, 8) };
+    assert!(arena.allocate(Layout::array::<u8>(2000).unwrap()).is_null());
+  }
+}
@@ -0,0 +1,247 @@
+//! # Flight Recorder
+//!
+//! With the `flight-recorder` feature every allocator keeps a log of its
+//! last [`RECORDED_OPS`] operations. When corruption is detected - a
+//! failed [`assert_invariants`](crate::BumpAllocator::assert_invariants)
+//! or a double free - the log is dumped in the panic message, giving the
+//! context that led up to the failure:
+//!
+//! ```text
+//!   heap corruption: double free of 0x55d0c1e2a070
+//!   random seed 0x853c49e6748fea9b
+//!      #  op           address        size  mode      result
+//!      0  allocate     0x55d0c1e2a020     37  FirstFit  ok
+//!      1  allocate     0x55d0c1e2a070    128  FirstFit  ok
+//!      2  deallocate   0x55d0c1e2a070    128  FirstFit  ok
+//!      3  deallocate   0x55d0c1e2a070    128  FirstFit  FAILED
+//! ```
+//!
+//! The first line is the allocator's
+//! [`random_seed`](BumpAllocator::random_seed): with it, a test can replay
+//! the same operations onto the same layout, jitter gaps included.
+//!
+//! Recording is O(1) and allocation-free, so it is also allowed in
+//! real-time mode. The log is readable at any time through
+//! [`BumpAllocator::recent_ops`].
+
+use core::fmt;
+
+use crate::{BumpAllocator, SearchMode, ring::Ring};
+
+/// Number of operations kept by the flight recorder.
+pub const RECORDED_OPS: usize = 32;
+
+/// Kind of a recorded operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpKind {
+  /// A call to `allocate`.
+  Allocate,
+
+  /// A call to `deallocate`.
+  Deallocate,
+
+  /// A call to `reset`.
+  Reset,
+
+  /// A call to `shrink_to`; the address is the mark, the size the live
+  /// bytes dropped.
+  ShrinkTo,
+}
+
+/// One entry of the flight recorder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpRecord {
+  /// What was called.
+  pub kind: OpKind,
+
+  /// Address returned by `allocate` or passed to `deallocate`
+  /// (0 for a failed allocation or a reset).
+  pub address: usize,
+
+  /// Requested size, or the size of the block being freed.
+  pub size: usize,
+
+  /// Search mode in effect at the time.
+  pub mode: SearchMode,
+
+  /// Whether the operation succeeded.
+  pub ok: bool,
+}
+
+/// The log kept inside each allocator.
+pub(crate) type FlightRecorder = Ring<OpRecord, RECORDED_OPS>;
+
+/// An empty flight recorder.
+pub(crate) const fn flight_recorder() -> FlightRecorder {
+  Ring::new(OpRecord {
+    kind: OpKind::Reset,
+    address: 0,
+    size: 0,
+    mode: SearchMode::FirstFit,
+    ok: true,
+  })
+}
+
+/// The recorded operations of an allocator, oldest first.
+///
+/// Created by [`BumpAllocator::recent_ops`]. Displays as a table.
+pub struct RecentOps<'a> {
+  /// The log being viewed.
+  log: &'a FlightRecorder,
+
+  /// Seed of the allocator's random decisions.
+  seed: u64,
+}
+
+impl<'a> RecentOps<'a> {
+  /// Iterates over the recorded operations, oldest first.
+  pub fn iter(&self) -> impl Iterator<Item = &'a OpRecord> + use<'a> {
+    self.log.iter()
+  }
+
+  /// Number of recorded operations (at most [`RECORDED_OPS`]).
+  pub fn len(&self) -> usize {
+    self.log.len()
+  }
+
+  /// Whether nothing has been recorded yet.
+  pub fn is_empty(&self) -> bool {
+    self.log.len() == 0
+  }
+
+  /// The allocator's [`random_seed`](BumpAllocator::random_seed) when the
+  /// log was taken.
+  pub fn seed(&self) -> u64 {
+    self.seed
+  }
+}
+
+impl fmt::Display for RecentOps<'_> {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    let width = crate::units::address_width();
+    writeln!(f, "random seed {:#018x}", self.seed)?;
+    write!(f, "{:>4}  {:<11}  {:<width$}  {:>10}  {:<8}  result", "#", "op", "address", "size", "mode")?;
+
+    for (index, record) in self.iter().enumerate() {
+      let op = match record.kind {
+        OpKind::Allocate => "allocate",
+        OpKind::Deallocate => "deallocate",
+        OpKind::Reset => "reset",
+        OpKind::ShrinkTo => "shrink_to",
+      };
+      // Derived `Debug` ignores padding, so name the mode explicitly
+      let mode = match record.mode {
+        SearchMode::FirstFit => "FirstFit",
+        SearchMode::NextFit => "NextFit",
+        SearchMode::BestFit => "BestFit",
+        SearchMode::BestFitIndexed => "BestFitIndexed",
+        SearchMode::GoodFit { .. } => "GoodFit",
+        SearchMode::Random { .. } => "Random",
+      };
+      write!(
+        f,
+        "\n{:>4}  {:<11}  {:#0width$x}  {:>10}  {:<8}  {}",
+        index,
+        op,
+        record.address,
+        record.size,
+        mode,
+        if record.ok { "ok" } else { "FAILED" }
+      )?;
+    }
+    Ok(())
+  }
+}
+
+impl BumpAllocator {
+  /// The last [`RECORDED_OPS`] operations on this allocator.
+  pub fn recent_ops(&self) -> RecentOps<'_> {
+    RecentOps {
+      log: &self.recorder,
+      seed: self.random_seed,
+    }
+  }
+
+  /// Appends an operation to the flight recorder.
+  pub(crate) fn record(
+    &mut self,
+    kind: OpKind,
+    address: usize,
+    size: usize,
+    ok: bool,
+  ) {
+    let mode = self.search_mode();
+    self.recorder.push(OpRecord {
+      kind,
+      address,
+      size,
+      mode,
+      ok,
+    });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::alloc::Layout;
+
+  #[test]
+  fn operations_are_recorded_in_order() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let ptr = unsafe { allocator.allocate(Layout::array::<u8>(37).unwrap()) };
+    assert!(unsafe { allocator.allocate(Layout::array::<u8>(1 << 20).unwrap()) }.is_null());
+    unsafe {
+      allocator.deallocate(ptr);
+      allocator.reset();
+    }
+
+    let ops: Vec<OpRecord> = allocator.recent_ops().iter().copied().collect();
+    let kinds: Vec<OpKind> = ops.iter().map(|op| op.kind).collect();
+    assert_eq!(kinds, [OpKind::Allocate, OpKind::Allocate, OpKind::Deallocate, OpKind::Reset]);
+
+    assert_eq!((ops[0].address, ops[0].size, ops[0].ok), (ptr as usize, 37, true));
+    assert_eq!((ops[1].address, ops[1].ok), (0, false));
+    assert_eq!((ops[2].address, ops[2].size), (ptr as usize, 37));
+  }
+
+  #[test]
+  fn recorder_keeps_only_the_latest_ops() {
+    let mut allocator = BumpAllocator::with_capacity(64 * 1024);
+    for size in 1..=RECORDED_OPS + 5 {
+      unsafe { allocator.allocate(Layout::array::<u8>(size).unwrap()) };
+    }
+
+    let recent = allocator.recent_ops();
+    assert_eq!(recent.len(), RECORDED_OPS);
+    assert_eq!(recent.iter().next().unwrap().size, 6);
+    // The seed, the column titles, then one line per operation
+    assert_eq!(recent.to_string().lines().count(), RECORDED_OPS + 2);
+  }
+
+  #[test]
+  fn dump_starts_with_the_seed() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_random_seed(0x5eed);
+    unsafe { allocator.allocate(Layout::new::<u64>()) };
+
+    let recent = allocator.recent_ops();
+    assert_eq!(recent.seed(), 0x5eed);
+    assert_eq!(recent.to_string().lines().next(), Some("random seed 0x0000000000005eed"));
+  }
+
+  #[test]
+  #[should_panic(expected = "double free")]
+  fn double_free_dumps_the_log() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    unsafe {
+      let a = allocator.allocate(Layout::new::<u64>());
+      allocator.allocate(Layout::new::<u64>());
+      allocator.deallocate(a);
+      allocator.deallocate(a);
+    }
+  }
+}
@@ -0,0 +1,176 @@
+//! # Reference-Counted Blocks
+//!
+//! C-style consumers that pass one buffer between components often cannot
+//! agree on who frees it. [`retain`](BumpAllocator::retain) and
+//! [`release`](BumpAllocator::release) let them share the decision with the
+//! allocator: every block starts with one reference, each `retain` adds
+//! one, and the `release` that drops the count to zero frees the block:
+//!
+//! ```text
+//!   p = allocate()       count 1
+//!   retain(p)            count 2      (handed to the decoder)
+//!   release(p)           count 1      (the network layer is done)
+//!   release(p)           freed        (the decoder is done)
+//! ```
+//!
+//! Counts above one live in a side map keyed by address, so blocks that
+//! are never retained cost nothing and the block header stays as it is. A
+//! plain `deallocate` still frees a block at once, whatever its count.
+//! The map lives on the global heap (it needs `std`), so do not retain
+//! blocks of an allocator installed as the global allocator.
+
+use crate::BumpAllocator;
+
+impl BumpAllocator {
+  /// Adds a reference to the block at `ptr`. Returns the new count.
+  ///
+  /// # Safety
+  ///
+  /// `ptr` must be a live pointer returned by this allocator.
+  ///
+  /// # Panics
+  ///
+  /// If the count would overflow `usize`.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// let frame = allocator.allocate(layout);
+  /// unsafe { allocator.retain(frame) };   // the encoder keeps one too
+  /// ```
+  pub unsafe fn retain(
+    &mut self,
+    ptr: *mut u8,
+  ) -> usize {
+    let count = self.refcounts.entry(ptr as usize).or_insert(1);
+    *count = count.checked_add(1).expect("reference count overflow");
+    *count
+  }
+
+  /// Drops a reference to the block at `ptr`, freeing it when this was the
+  /// last one. Returns whether the block was freed.
+  ///
+  /// # Safety
+  ///
+  /// `ptr` must be a live pointer returned by this allocator, and must not
+  /// be used after the call that frees it.
+  pub unsafe fn release(
+    &mut self,
+    ptr: *mut u8,
+  ) -> bool {
+    let address = ptr as usize;
+    match self.refcounts.get_mut(&address) {
+      Some(count) if *count > 2 => *count -= 1,
+      Some(_) => {
+        self.refcounts.remove(&address);
+      },
+      None => {
+        // SAFETY: The last reference, by the caller's contract.
+        unsafe { self.deallocate(ptr) };
+        return true;
+      },
+    }
+    false
+  }
+
+  /// References to the live block at `ptr`: 1 unless it was retained.
+  pub fn ref_count(
+    &self,
+    ptr: *const u8,
+  ) -> usize {
+    self.refcounts.get(&(ptr as usize)).copied().unwrap_or(1)
+  }
+
+  /// Forgets the count of a block being freed.
+  pub(crate) fn forget_refs(
+    &mut self,
+    address: *mut u8,
+  ) {
+    if !self.refcounts.is_empty() {
+      self.refcounts.remove(&(address as usize));
+    }
+  }
+
+  /// Forgets every count at or past `address`, when the heap is cut back
+  /// there.
+  pub(crate) fn forget_refs_from(
+    &mut self,
+    address: usize,
+  ) {
+    self.refcounts.split_off(&address);
+  }
+
+  /// Moves the counted addresses by `offset`, for a copy of the heap that
+  /// starts `offset` bytes from the original.
+  pub(crate) fn relocate_refs(
+    &mut self,
+    offset: isize,
+  ) {
+    self.refcounts = self
+      .refcounts
+      .iter()
+      .map(|(&address, &count)| (address.wrapping_add_signed(offset), count))
+      .collect();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::alloc::Layout;
+
+  #[test]
+  fn the_last_release_frees() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let ptr = unsafe { allocator.allocate(Layout::new::<[u8; 64]>()) };
+    assert_eq!(allocator.ref_count(ptr), 1);
+
+    unsafe {
+      assert_eq!(allocator.retain(ptr), 2);
+      assert_eq!(allocator.retain(ptr), 3);
+      assert!(!allocator.release(ptr));
+      assert!(!allocator.release(ptr));
+      assert_eq!(allocator.ref_count(ptr), 1);
+      assert_eq!(allocator.live_blocks(), 1);
+      assert!(allocator.release(ptr));
+    }
+    assert_eq!(allocator.live_blocks(), 0);
+  }
+
+  #[test]
+  fn deallocating_forgets_the_count() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let layout = Layout::new::<u64>();
+    let kept = unsafe { allocator.allocate(layout) };
+    let ptr = unsafe { allocator.allocate(layout) };
+    unsafe {
+      allocator.retain(ptr);
+      allocator.deallocate(ptr);
+    }
+    assert!(allocator.refcounts.is_empty());
+
+    // A new block starts over, wherever it lands
+    let again = unsafe { allocator.allocate(layout) };
+    assert_eq!(allocator.ref_count(again), 1);
+    assert!(unsafe { allocator.release(again) });
+    assert_eq!(allocator.ref_count(kept), 1);
+  }
+
+  #[test]
+  fn resets_and_rollbacks_drop_counts() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let layout = Layout::new::<u64>();
+    let before = unsafe { allocator.allocate(layout) };
+    unsafe { allocator.retain(before) };
+
+    let mark = allocator.checkpoint();
+    let after = unsafe { allocator.allocate(layout) };
+    unsafe { allocator.retain(after) };
+    unsafe { allocator.shrink_to(mark) };
+    assert_eq!(allocator.ref_count(before), 2);
+    assert_eq!(allocator.refcounts.len(), 1);
+
+    unsafe { allocator.reset() };
+    assert!(allocator.refcounts.is_empty());
+  }
+}
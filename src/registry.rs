@@ -0,0 +1,185 @@
+//! # Arena Registry
+//!
+//! A program with many arenas - one per subsystem, per worker, per cache -
+//! wants one answer to "how much memory do the arenas hold?". Shared
+//! arenas can [`register`](SharedBumpAllocator::register) under a name,
+//! and [`global_stats`] sums their [`Stats`] with a per-arena breakdown:
+//!
+//! ```text
+//!   registry (process-wide, weak)        global_stats()
+//!   ┌──────────┬──────────────┐          ┌──────────┬───────────────────┐
+//!   │ "parser" │ Weak ──► ... │   ───►   │ parser   │ 12 live · heap 4K │
+//!   │ "cache"  │ Weak ──► ... │          │ cache    │ 90 live · heap 1M │
+//!   │ "old"    │ Weak ──► ✗   │ (gone)   ├──────────┼───────────────────┤
+//!   └──────────┴──────────────┘          │ total    │ 102 live · ...    │
+//!                                        └──────────┴───────────────────┘
+//! ```
+//!
+//! The registry holds weak references, so registering never keeps an
+//! arena alive: dropping the last handle takes it out of the totals. Each
+//! arena is locked only while its own stats are taken, never together
+//! with the registry, so a metrics thread can call `global_stats` while
+//! the arenas are in use.
+
+use core::fmt;
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+
+use crate::{SharedBumpAllocator, Stats, sync::SharedArena};
+
+/// Registered arenas, by name.
+static REGISTRY: Mutex<Vec<(&'static str, Weak<SharedArena>)>> = Mutex::new(Vec::new());
+
+impl SharedBumpAllocator {
+  /// Adds this arena to the process-wide registry under `name`, so
+  /// [`global_stats`] counts it until its last handle is dropped.
+  ///
+  /// Names need not be unique; registering the same arena twice counts it
+  /// twice.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// let parser = SharedBumpAllocator::with_capacity(1 << 20);
+  /// parser.register("parser");
+  /// ```
+  pub fn register(
+    &self,
+    name: &'static str,
+  ) {
+    let mut registry = REGISTRY.lock().unwrap_or_else(PoisonError::into_inner);
+    registry.retain(|(_, arena)| arena.strong_count() > 0);
+    registry.push((name, Arc::downgrade(&self.arena)));
+  }
+}
+
+/// Stats of every registered arena and their sum, see [`global_stats`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlobalStats {
+  /// Each live registered arena, in registration order.
+  pub arenas: Vec<(&'static str, Stats)>,
+
+  /// The sum over all of them. `headroom` is summed only if every arena
+  /// has one; `os_limit` is the process's.
+  pub total: Stats,
+}
+
+impl fmt::Display for GlobalStats {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    let width = self.arenas.iter().map(|(name, _)| name.len()).max().unwrap_or(0).max("total".len());
+    for (name, stats) in &self.arenas {
+      writeln!(f, "{name:<width$}  {stats}")?;
+    }
+    write!(f, "{:<width$}  {}", "total", self.total)
+  }
+}
+
+/// Takes the stats of every registered arena that is still alive, and
+/// their sum.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let stats = rallocator::global_stats();
+/// metrics.gauge("arena.bytes_in_use", stats.total.bytes_in_use);
+/// println!("{stats}");
+/// ```
+pub fn global_stats() -> GlobalStats {
+  // Upgrade under the registry lock, take stats after releasing it
+  let arenas: Vec<_> = {
+    let mut registry = REGISTRY.lock().unwrap_or_else(PoisonError::into_inner);
+    registry.retain(|(_, arena)| arena.strong_count() > 0);
+    registry.iter().filter_map(|(name, arena)| Some((*name, arena.upgrade()?))).collect()
+  };
+
+  let arenas: Vec<_> = arenas.into_iter().map(|(name, arena)| (name, arena.with(|allocator| allocator.stats()))).collect();
+  let total = arenas.iter().fold(None, |total: Option<Stats>, (_, stats)| {
+    Some(match total {
+      None => *stats,
+      Some(total) => add(total, stats),
+    })
+  });
+  GlobalStats {
+    total: total.unwrap_or_default(),
+    arenas,
+  }
+}
+
+/// Field-wise sum of two arenas' stats.
+fn add(
+  total: Stats,
+  stats: &Stats,
+) -> Stats {
+  Stats {
+    live_blocks: total.live_blocks + stats.live_blocks,
+    free_blocks: total.free_blocks + stats.free_blocks,
+    bytes_in_use: total.bytes_in_use + stats.bytes_in_use,
+    bytes_free: total.bytes_free + stats.bytes_free,
+    heap_bytes: total.heap_bytes + stats.heap_bytes,
+    header_bytes: total.header_bytes + stats.header_bytes,
+    padding_bytes: total.padding_bytes + stats.padding_bytes,
+    os_limit: total.os_limit.or(stats.os_limit),
+    headroom: total.headroom.zip(stats.headroom).map(|(a, b)| a + b),
+    cached_bytes: total.cached_bytes + stats.cached_bytes,
+    untracked_bytes: total.untracked_bytes + stats.untracked_bytes,
+    adopted_blocks: total.adopted_blocks + stats.adopted_blocks,
+    adopted_bytes: total.adopted_bytes + stats.adopted_bytes,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::alloc::Layout;
+
+  /// The entries of this test's arenas; other tests may register too.
+  fn named(
+    stats: &GlobalStats,
+    prefix: &str,
+  ) -> Vec<(&'static str, Stats)> {
+    stats.arenas.iter().filter(|(name, _)| name.starts_with(prefix)).cloned().collect()
+  }
+
+  #[test]
+  fn registered_arenas_are_summed() {
+    let parser = SharedBumpAllocator::with_capacity(4096);
+    let cache = SharedBumpAllocator::with_capacity(4096);
+    parser.register("sum-parser");
+    cache.register("sum-cache");
+    parser.with(|a| unsafe { a.allocate(Layout::new::<[u8; 100]>()) });
+    cache.with(|a| unsafe {
+      a.allocate(Layout::new::<[u8; 10]>());
+      a.allocate(Layout::new::<[u8; 20]>());
+    });
+
+    let stats = global_stats();
+    let mine = named(&stats, "sum-");
+    assert_eq!(mine.iter().map(|(name, _)| *name).collect::<Vec<_>>(), ["sum-parser", "sum-cache"]);
+    assert_eq!(mine[0].1.bytes_in_use, 100);
+    assert_eq!(mine[1].1.live_blocks, 2);
+
+    let mine_total = mine.iter().fold(Stats::default(), |total, (_, stats)| add(total, stats));
+    assert_eq!(mine_total.bytes_in_use, 130);
+    assert!(stats.total.bytes_in_use >= 130);
+    assert!(format!("{stats}").contains("sum-parser"));
+  }
+
+  #[test]
+  fn dropped_arenas_leave_the_registry() {
+    let kept = SharedBumpAllocator::with_capacity(4096);
+    kept.register("drop-kept");
+    let gone = SharedBumpAllocator::with_capacity(4096);
+    let clone = gone.clone();
+    gone.register("drop-gone");
+
+    drop(gone);
+    assert_eq!(named(&global_stats(), "drop-").len(), 2);
+    drop(clone);
+    assert_eq!(
+      named(&global_stats(), "drop-").iter().map(|(name, _)| *name).collect::<Vec<_>>(),
+      ["drop-kept"]
+    );
+  }
+}
@@ -0,0 +1,293 @@
+//! # Emergency Reserve
+//!
+//! A small region set aside up front and only touched once the main heap
+//! fails, so OOM handlers, leak reports and panic messages still have
+//! memory to work with.
+//!
+//! The reserve is a single live block of the main heap. A nested
+//! [`BumpAllocator`] lives at its start and manages the rest:
+//!
+//! ```text
+//!   main heap
+//!   ┌────────┬──────────────────────────────────────────────┬────────┐
+//!   │ blk A  │ reserve block                                │ blk B  │
+//!   └────────┴──────────────────────────────────────────────┴────────┘
+//!             │                                              │
+//!             ▼                                              ▼
+//!             ┌──────────────┬───────────────────────────────┐
+//!             │ BumpAllocator│ reserve region (`bytes` long) │
+//!             └──────────────┴───────────────────────────────┘
+//! ```
+//!
+//! When the main heap cannot satisfy an allocation:
+//!
+//! ```text
+//!   main fails ──► unlock reserve ──► OOM handler (its allocations may
+//!                                     │            come from the reserve)
+//!                                     ▼
+//!                       retry main ──► still failing? ──► try the reserve
+//! ```
+//!
+//! Pointers from the reserve are freed with the usual
+//! [`deallocate`](BumpAllocator::deallocate): it recognises them by
+//! address.
+
+use core::{alloc::Layout, mem, ptr};
+
+use crate::BumpAllocator;
+
+/// Alignment of the reserve block (and so of the nested allocator).
+const RESERVE_ALIGN: usize = 16;
+
+/// State of an allocator's emergency reserve.
+pub(crate) struct EmergencyReserve {
+  /// The nested allocator at the start of the reserve block, or null.
+  arena: *mut BumpAllocator,
+
+  /// Length of the region managed by `arena`.
+  len: usize,
+
+  /// Whether the main heap has failed since the reserve was armed.
+  unlocked: bool,
+}
+
+impl EmergencyReserve {
+  /// No reserve.
+  pub(crate) const fn none() -> Self {
+    Self {
+      arena: ptr::null_mut(),
+      len: 0,
+      unlocked: false,
+    }
+  }
+
+  /// First byte of the reserve region.
+  fn region_start(&self) -> usize {
+    self.arena as usize + mem::size_of::<BumpAllocator>()
+  }
+
+  /// The nested allocator, if a reserve is armed.
+  pub(crate) fn arena(&self) -> Option<&BumpAllocator> {
+    // SAFETY: A non-null reserve arena is initialized and outlives `self`.
+    unsafe { self.arena.as_ref() }
+  }
+
+  /// Marks the reserve as in use, if there is one.
+  pub(crate) fn unlock(&mut self) {
+    if !self.arena.is_null() {
+      self.unlocked = true;
+    }
+  }
+
+  /// Allocates from the reserve, or returns null if there is none or it is
+  /// exhausted.
+  pub(crate) unsafe fn allocate(
+    &mut self,
+    layout: Layout,
+  ) -> *mut u8 {
+    if self.arena.is_null() {
+      return ptr::null_mut();
+    }
+    // SAFETY: `arena` was initialized by `set_emergency_reserve` and lives
+    // in a block that stays allocated while the reserve is armed.
+    unsafe { (*self.arena).allocate(layout) }
+  }
+
+  /// Whether `address` was handed out by the reserve.
+  pub(crate) fn owns(
+    &self,
+    address: *mut u8,
+  ) -> bool {
+    let start = self.region_start();
+    !self.arena.is_null() && (start..start + self.len).contains(&(address as usize))
+  }
+
+  /// Frees a pointer handed out by the reserve.
+  ///
+  /// # Safety
+  ///
+  /// `address` must satisfy [`owns`](Self::owns) and be live.
+  pub(crate) unsafe fn deallocate(
+    &mut self,
+    address: *mut u8,
+  ) {
+    unsafe { (*self.arena).deallocate(address) }
+  }
+}
+
+impl BumpAllocator {
+  /// Sets aside `bytes` for use only after the main heap fails.
+  ///
+  /// The reserve is taken from this allocator right away (one live block)
+  /// and replaces any previous, unused reserve. [`reset`](Self::reset)
+  /// discards it; arm it again afterwards.
+  ///
+  /// # Returns
+  ///
+  /// `false` if the memory could not be obtained, or if the current
+  /// reserve still has live allocations.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// allocator.set_emergency_reserve(16 * 1024); // enough for a leak report
+  /// ```
+  pub fn set_emergency_reserve(
+    &mut self,
+    bytes: usize,
+  ) -> bool {
+    if !self.reserve.arena.is_null() {
+      // SAFETY: A non-null reserve arena is initialized (see below).
+      if unsafe { (*self.reserve.arena).stats().live_blocks } > 0 {
+        return false;
+      }
+      let old = mem::replace(&mut self.reserve, EmergencyReserve::none());
+      // SAFETY: The reserve block came from `allocate` on this allocator.
+      unsafe { self.deallocate(old.arena as *mut u8) };
+    }
+
+    let Some(total) = mem::size_of::<BumpAllocator>().checked_add(bytes) else {
+      return false;
+    };
+    let Ok(layout) = Layout::from_size_align(total, RESERVE_ALIGN.max(mem::align_of::<BumpAllocator>())) else {
+      return false;
+    };
+
+    // SAFETY: `&mut self` gives exclusive access.
+    let block = unsafe { self.allocate(layout) };
+    if block.is_null() {
+      return false;
+    }
+
+    let arena = block as *mut BumpAllocator;
+    // SAFETY: The block is live, suitably aligned, and large enough for the
+    // allocator followed by `bytes` of region.
+    unsafe {
+      let region = block.add(mem::size_of::<BumpAllocator>());
+      arena.write(BumpAllocator::from_raw_region(region, bytes));
+    }
+    self.reserve = EmergencyReserve {
+      arena,
+      len: bytes,
+      unlocked: false,
+    };
+    true
+  }
+
+  /// Whether the main heap has failed and the reserve is in use.
+  ///
+  /// A good moment to shed load, flush caches, or write a leak report.
+  pub fn emergency_reserve_unlocked(&self) -> bool {
+    self.reserve.unlocked
+  }
+
+  /// Bytes of the reserve not yet handed out (0 without a reserve).
+  pub fn emergency_reserve_remaining(&self) -> usize {
+    if self.reserve.arena.is_null() {
+      return 0;
+    }
+    // SAFETY: A non-null reserve arena is initialized.
+    self.reserve.len - unsafe { (*self.reserve.arena).stats().heap_bytes }
+  }
+
+  /// Marks the reserve as unused again after the pressure has passed.
+  ///
+  /// Live reserve allocations stay valid and can still be deallocated.
+  pub fn relock_emergency_reserve(&mut self) {
+    self.reserve.unlocked = false;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// An allocator with a 256-byte reserve and about `spare` bytes of main
+  /// heap left over (plus worst-case alignment padding).
+  fn with_reserve(spare: usize) -> BumpAllocator {
+    let header = mem::size_of::<crate::block::Block>();
+    let mut allocator = BumpAllocator::with_capacity(header + mem::size_of::<BumpAllocator>() + 256 + RESERVE_ALIGN + spare);
+    assert!(allocator.set_emergency_reserve(256));
+    allocator
+  }
+
+  /// Allocates 64-byte blocks until the main heap fails; returns the
+  /// first block that came from the reserve.
+  fn exhaust_main_heap(allocator: &mut BumpAllocator) -> *mut u8 {
+    loop {
+      let ptr = unsafe { allocator.allocate(Layout::array::<u8>(64).unwrap()) };
+      assert!(!ptr.is_null());
+      if allocator.emergency_reserve_unlocked() {
+        return ptr;
+      }
+    }
+  }
+
+  #[test]
+  fn reserve_is_only_used_after_the_main_heap_fails() {
+    let mut allocator = with_reserve(1024);
+    assert!(!allocator.emergency_reserve_unlocked());
+    assert_eq!(allocator.emergency_reserve_remaining(), 256);
+
+    let ptr = exhaust_main_heap(&mut allocator);
+    assert!(allocator.reserve.owns(ptr));
+    assert!(allocator.emergency_reserve_remaining() < 256);
+  }
+
+  #[test]
+  fn reserve_pointers_are_freed_through_deallocate() {
+    let mut allocator = with_reserve(0);
+
+    unsafe {
+      let ptr = exhaust_main_heap(&mut allocator);
+      assert!(allocator.reserve.owns(ptr));
+
+      let live_main = allocator.stats().live_blocks;
+      allocator.deallocate(ptr);
+      assert_eq!(allocator.emergency_reserve_remaining(), 256);
+      assert_eq!(allocator.stats().live_blocks, live_main);
+    }
+    assert_eq!(allocator.check_invariants(), Ok(()));
+  }
+
+  #[test]
+  fn oom_handler_allocates_from_the_reserve() {
+    fn report(
+      allocator: &mut BumpAllocator,
+      _layout: Layout,
+    ) -> bool {
+      assert!(allocator.emergency_reserve_unlocked());
+      let message = unsafe { allocator.allocate(Layout::array::<u8>(100).unwrap()) };
+      assert!(allocator.reserve.owns(message));
+      false
+    }
+
+    let mut allocator = with_reserve(0);
+    allocator.set_oom_handler(Some(report));
+
+    assert!(unsafe { allocator.allocate(Layout::array::<u8>(8192).unwrap()) }.is_null());
+    assert!(allocator.emergency_reserve_remaining() < 256);
+  }
+
+  #[test]
+  fn reserve_can_be_rearmed_only_when_unused() {
+    let mut allocator = with_reserve(4096);
+    assert!(allocator.set_emergency_reserve(128));
+    assert!(allocator.set_emergency_reserve(512));
+    assert_eq!(allocator.emergency_reserve_remaining(), 512);
+
+    let ptr = unsafe { allocator.reserve.allocate(Layout::new::<u64>()) };
+    assert!(!allocator.set_emergency_reserve(64));
+
+    unsafe { allocator.deallocate(ptr) };
+    allocator.relock_emergency_reserve();
+    assert!(allocator.set_emergency_reserve(64));
+  }
+
+  #[test]
+  fn reset_discards_the_reserve() {
+    let mut allocator = with_reserve(0);
+    unsafe { allocator.reset() };
+    assert_eq!(allocator.emergency_reserve_remaining(), 0);
+  }
+}
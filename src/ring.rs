@@ -0,0 +1,66 @@
+//! Fixed-capacity ring buffer shared by the sampler and the flight recorder.
+//!
+//! ```text
+//!   Ring<T, 4> after pushing t0..t5
+//!
+//!        ┌──────┬──────┬──────┬──────┐
+//!        │  t4  │  t5  │  t2  │  t3  │
+//!        └──────┴──────┴──────┴──────┘
+//!                  ▲      ▲
+//!                  │      └── oldest: iteration starts here
+//!                next
+//! ```
+
+/// The last `N` values pushed, stored inline.
+pub(crate) struct Ring<T: Copy, const N: usize> {
+  /// Storage; only the last `len` written slots are meaningful.
+  items: [T; N],
+
+  /// Slot the next value is written to.
+  next: usize,
+
+  /// Number of meaningful values (at most `N`).
+  len: usize,
+}
+
+impl<T: Copy, const N: usize> Ring<T, N> {
+  /// Creates an empty ring. `fill` only initializes the storage.
+  pub(crate) const fn new(fill: T) -> Self {
+    Self {
+      items: [fill; N],
+      next: 0,
+      len: 0,
+    }
+  }
+
+  /// Appends `value`, overwriting the oldest one when full.
+  pub(crate) fn push(
+    &mut self,
+    value: T,
+  ) {
+    if N == 0 {
+      return;
+    }
+    self.items[self.next] = value;
+    self.next = (self.next + 1) % N;
+    self.len = (self.len + 1).min(N);
+  }
+
+  /// Number of values held.
+  pub(crate) fn len(&self) -> usize {
+    self.len
+  }
+
+  /// Forgets every value.
+  pub(crate) fn clear(&mut self) {
+    self.next = 0;
+    self.len = 0;
+  }
+
+  /// Iterates over the held values, oldest first.
+  pub(crate) fn iter(&self) -> impl Iterator<Item = &T> {
+    // The oldest value sits at `next` once the buffer has wrapped
+    let start = if self.len == N { self.next } else { 0 };
+    (0..self.len).map(move |i| &self.items[(start + i) % N])
+  }
+}
@@ -0,0 +1,257 @@
+//! # Heap Usage Sampler
+//!
+//! A fixed-size ring buffer of `(timestamp, bytes_in_use, live_blocks)`
+//! points, filled by explicit [`Sampler::sample`] calls and exportable as
+//! CSV for plotting heap growth over a run:
+//!
+//! ```text
+//!   time ──►   s0    s1    s2    s3    s4    s5
+//!                          └──── Sampler<4> keeps these ────┘
+//! ```
+//!
+//! Sampling is explicit because the allocator is single-threaded: a
+//! background thread could not read it safely. Call `sample` from the
+//! owning thread wherever it makes sense - once per frame, per request,
+//! per loop iteration.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use rallocator::Sampler;
+//!
+//! let mut sampler = Sampler::<1024>::new();
+//! loop {
+//!     do_work(&mut allocator);
+//!     sampler.sample(&allocator);
+//! }
+//!
+//! let mut csv = String::new();
+//! sampler.write_csv(&mut csv).unwrap();
+//! std::fs::write("heap.csv", csv)?;
+//! ```
+
+use core::fmt;
+
+use crate::{BumpAllocator, Clock, ring::Ring};
+
+/// One point of the heap usage time series.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Sample {
+  /// When the sample was taken. Microseconds since the first sample for
+  /// [`Sampler::sample`], microseconds of the clock for
+  /// [`Sampler::sample_with`], caller-defined units for
+  /// [`Sampler::sample_at`].
+  pub timestamp: u64,
+
+  /// Sum of the requested sizes of all live blocks.
+  pub bytes_in_use: usize,
+
+  /// Number of live blocks.
+  pub live_blocks: usize,
+}
+
+/// Ring buffer keeping the last `N` [`Sample`]s.
+///
+/// Needs no heap: the samples live inline, so a `Sampler` can itself be
+/// placed in a `static` or on the stack.
+pub struct Sampler<const N: usize> {
+  /// The last `N` samples.
+  samples: Ring<Sample, N>,
+
+  /// Time of the first [`Sampler::sample`] call.
+  #[cfg(feature = "std")]
+  epoch: Option<std::time::Instant>,
+}
+
+impl<const N: usize> Sampler<N> {
+  /// Creates an empty sampler.
+  pub const fn new() -> Self {
+    Self {
+      samples: Ring::new(Sample {
+        timestamp: 0,
+        bytes_in_use: 0,
+        live_blocks: 0,
+      }),
+      #[cfg(feature = "std")]
+      epoch: None,
+    }
+  }
+
+  /// Records the current usage of `allocator`, timestamped in
+  /// microseconds since the first call.
+  ///
+  /// Walks the block list (see [`BumpAllocator::stats`]), so it is O(n).
+  #[cfg(feature = "std")]
+  pub fn sample(
+    &mut self,
+    allocator: &BumpAllocator,
+  ) {
+    let epoch = *self.epoch.get_or_insert_with(std::time::Instant::now);
+    let micros = epoch.elapsed().as_micros();
+    self.sample_at(u64::try_from(micros).unwrap_or(u64::MAX), allocator);
+  }
+
+  /// Records the current usage of `allocator`, timestamped in
+  /// microseconds of `clock`.
+  ///
+  /// With a [`TickClock`](crate::TickClock) this times samples on targets
+  /// without `std`; with a [`ManualClock`](crate::ManualClock) it makes
+  /// the series deterministic in tests.
+  pub fn sample_with(
+    &mut self,
+    clock: &impl Clock,
+    allocator: &BumpAllocator,
+  ) {
+    let micros = clock.now().as_micros();
+    self.sample_at(u64::try_from(micros).unwrap_or(u64::MAX), allocator);
+  }
+
+  /// Records the current usage of `allocator` with a caller-provided
+  /// timestamp (e.g. a cycle counter on targets without `std`).
+  pub fn sample_at(
+    &mut self,
+    timestamp: u64,
+    allocator: &BumpAllocator,
+  ) {
+    let stats = allocator.stats();
+    self.samples.push(Sample {
+      timestamp,
+      bytes_in_use: stats.bytes_in_use,
+      live_blocks: stats.live_blocks,
+    });
+  }
+
+  /// Number of samples currently held.
+  pub fn len(&self) -> usize {
+    self.samples.len()
+  }
+
+  /// Whether no sample has been recorded yet.
+  pub fn is_empty(&self) -> bool {
+    self.samples.len() == 0
+  }
+
+  /// Forgets every sample and restarts the clock.
+  pub fn clear(&mut self) {
+    self.samples.clear();
+    #[cfg(feature = "std")]
+    {
+      self.epoch = None;
+    }
+  }
+
+  /// Iterates over the held samples, oldest first.
+  pub fn samples(&self) -> impl Iterator<Item = &Sample> {
+    self.samples.iter()
+  }
+
+  /// Writes the samples as CSV, oldest first, with a header row:
+  ///
+  /// ```text
+  ///   timestamp,bytes_in_use,live_blocks
+  ///   0,64,1
+  ///   153,192,3
+  /// ```
+  pub fn write_csv<W: fmt::Write>(
+    &self,
+    out: &mut W,
+  ) -> fmt::Result {
+    writeln!(out, "timestamp,bytes_in_use,live_blocks")?;
+    for sample in self.samples() {
+      writeln!(out, "{},{},{}", sample.timestamp, sample.bytes_in_use, sample.live_blocks)?;
+    }
+    Ok(())
+  }
+}
+
+impl<const N: usize> Default for Sampler<N> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::alloc::Layout;
+
+  #[test]
+  fn samples_track_allocations() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let mut sampler = Sampler::<8>::new();
+
+    sampler.sample_at(0, &allocator);
+    unsafe { allocator.allocate(Layout::array::<u8>(100).unwrap()) };
+    sampler.sample_at(1, &allocator);
+
+    let samples: Vec<&Sample> = sampler.samples().collect();
+    assert_eq!(samples.len(), 2);
+    assert_eq!(*samples[0], Sample::default());
+    assert_eq!(samples[1].bytes_in_use, 100);
+    assert_eq!(samples[1].live_blocks, 1);
+  }
+
+  #[test]
+  fn ring_keeps_the_newest_samples() {
+    let allocator = BumpAllocator::with_capacity(1024);
+    let mut sampler = Sampler::<3>::new();
+
+    for t in 0..5 {
+      sampler.sample_at(t, &allocator);
+    }
+
+    assert_eq!(sampler.len(), 3);
+    let timestamps: Vec<u64> = sampler.samples().map(|s| s.timestamp).collect();
+    assert_eq!(timestamps, [2, 3, 4]);
+  }
+
+  #[test]
+  fn csv_export_is_oldest_first() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let mut sampler = Sampler::<4>::new();
+
+    sampler.sample_at(10, &allocator);
+    unsafe { allocator.allocate(Layout::new::<u64>()) };
+    sampler.sample_at(20, &allocator);
+
+    let mut csv = String::new();
+    sampler.write_csv(&mut csv).unwrap();
+    assert_eq!(csv, "timestamp,bytes_in_use,live_blocks\n10,0,0\n20,8,1\n");
+  }
+
+  #[test]
+  fn clocked_samples_use_the_clock() {
+    let allocator = BumpAllocator::with_capacity(1024);
+    let mut sampler = Sampler::<4>::new();
+    let clock = crate::ManualClock::new();
+
+    sampler.sample_with(&clock, &allocator);
+    clock.advance(core::time::Duration::from_millis(5));
+    sampler.sample_with(&clock, &allocator);
+    let timestamps: Vec<u64> = sampler.samples().map(|s| s.timestamp).collect();
+    assert_eq!(timestamps, [0, 5_000]);
+  }
+
+  #[test]
+  #[cfg(feature = "std")]
+  fn wall_clock_samples_are_monotonic() {
+    let allocator = BumpAllocator::with_capacity(1024);
+    let mut sampler = Sampler::<4>::new();
+
+    sampler.sample(&allocator);
+    sampler.sample(&allocator);
+    let timestamps: Vec<u64> = sampler.samples().map(|s| s.timestamp).collect();
+    assert!(timestamps[0] <= timestamps[1]);
+
+    sampler.clear();
+    assert!(sampler.is_empty());
+  }
+
+  #[test]
+  fn zero_sized_sampler_records_nothing() {
+    let allocator = BumpAllocator::with_capacity(1024);
+    let mut sampler = Sampler::<0>::new();
+    sampler.sample_at(1, &allocator);
+    assert!(sampler.is_empty());
+  }
+}
@@ -0,0 +1,284 @@
+//! # Statistics Scopes
+//!
+//! [`BumpAllocator::stats_scope`] measures one stretch of code: the guard
+//! it returns remembers the [`Counters`] at its creation and, on drop,
+//! checks what happened since against the limits it was given. Tests can
+//! then pin down allocation behaviour:
+//!
+//! ```text
+//!   let mut scope = allocator.stats_scope().max_bytes(1 << 20);
+//!   parse(&mut scope, input);         scope derefs to the allocator
+//!   drop(scope);                      ──► panic if parsing allocated > 1 MiB
+//! ```
+//!
+//! Scopes are built on the `counters` feature and see exactly what the
+//! counters see. While a scope is open, `peak_bytes_in_use` tracks the
+//! scope's own peak; the allocator-wide peak is restored when it closes.
+
+use core::{
+  fmt,
+  ops::{Deref, DerefMut},
+};
+
+use crate::{BumpAllocator, Counters};
+
+/// What happened inside a [`StatsScope`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ScopeReport {
+  /// Successful allocations.
+  pub allocations: u64,
+
+  /// Allocations that returned null.
+  pub failed_allocations: u64,
+
+  /// Blocks freed.
+  pub deallocations: u64,
+
+  /// Sum of the sizes of the successful allocations.
+  pub bytes_allocated: u64,
+
+  /// Requested bytes still live at the end beyond those live at the start:
+  /// what the scope kept.
+  pub bytes_retained: usize,
+
+  /// Highest number of live requested bytes during the scope, including
+  /// those live before it began.
+  pub peak_bytes_in_use: usize,
+}
+
+impl fmt::Display for ScopeReport {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    write!(
+      f,
+      "{} allocations ({} bytes), {} frees, {} failed, {} bytes retained, peak {} bytes",
+      self.allocations,
+      self.bytes_allocated,
+      self.deallocations,
+      self.failed_allocations,
+      self.bytes_retained,
+      self.peak_bytes_in_use
+    )
+  }
+}
+
+/// Guard returned by [`BumpAllocator::stats_scope`].
+///
+/// Derefs to the allocator, so code under measurement takes the scope
+/// where it would take the allocator. Limits are checked when the scope is
+/// dropped or [`finish`](Self::finish)ed; a scope dropped while already
+/// unwinding only restores the counters.
+pub struct StatsScope<'a> {
+  allocator: &'a mut BumpAllocator,
+
+  /// Counters when the scope opened.
+  start: Counters,
+
+  /// Most bytes the scope may allocate in total.
+  max_bytes: Option<u64>,
+
+  /// Most allocations the scope may make.
+  max_allocations: Option<u64>,
+
+  /// Set once the limits were checked, so dropping a finished scope does
+  /// not check them again.
+  checked: bool,
+}
+
+impl StatsScope<'_> {
+  /// Panics on drop if the scope allocated more than `bytes` in total.
+  pub fn max_bytes(
+    mut self,
+    bytes: usize,
+  ) -> Self {
+    self.max_bytes = Some(bytes as u64);
+    self
+  }
+
+  /// Panics on drop if the scope made more than `count` allocations.
+  pub fn max_allocations(
+    mut self,
+    count: u64,
+  ) -> Self {
+    self.max_allocations = Some(count);
+    self
+  }
+
+  /// What happened since the scope opened. The scope stays open.
+  pub fn report(&self) -> ScopeReport {
+    let now = self.allocator.counters;
+    ScopeReport {
+      allocations: now.allocations - self.start.allocations,
+      failed_allocations: now.failed_allocations - self.start.failed_allocations,
+      deallocations: now.deallocations - self.start.deallocations,
+      bytes_allocated: now.bytes_allocated - self.start.bytes_allocated,
+      bytes_retained: now.bytes_in_use.saturating_sub(self.start.bytes_in_use),
+      peak_bytes_in_use: now.peak_bytes_in_use,
+    }
+  }
+
+  /// Closes the scope, checking its limits, and returns its report.
+  ///
+  /// # Panics
+  ///
+  /// If a limit was exceeded.
+  #[track_caller]
+  pub fn finish(mut self) -> ScopeReport {
+    let report = self.report();
+    self.checked = true;
+    check_limits(&report, self.max_bytes, self.max_allocations);
+    report
+  }
+}
+
+/// Panics with the report if it breaks a limit.
+#[track_caller]
+fn check_limits(
+  report: &ScopeReport,
+  max_bytes: Option<u64>,
+  max_allocations: Option<u64>,
+) {
+  if let Some(max) = max_bytes
+    && report.bytes_allocated > max
+  {
+    panic!("scope allocated {} bytes, limit {max}: {report}", report.bytes_allocated);
+  }
+  if let Some(max) = max_allocations
+    && report.allocations > max
+  {
+    panic!("scope made {} allocations, limit {max}: {report}", report.allocations);
+  }
+}
+
+impl Deref for StatsScope<'_> {
+  type Target = BumpAllocator;
+
+  fn deref(&self) -> &BumpAllocator {
+    self.allocator
+  }
+}
+
+impl DerefMut for StatsScope<'_> {
+  fn deref_mut(&mut self) -> &mut BumpAllocator {
+    self.allocator
+  }
+}
+
+impl Drop for StatsScope<'_> {
+  fn drop(&mut self) {
+    let report = self.report();
+    let counters = &mut self.allocator.counters;
+    counters.peak_bytes_in_use = counters.peak_bytes_in_use.max(self.start.peak_bytes_in_use);
+
+    #[cfg(feature = "std")]
+    if std::thread::panicking() {
+      return;
+    }
+    if !self.checked {
+      check_limits(&report, self.max_bytes, self.max_allocations);
+    }
+  }
+}
+
+impl BumpAllocator {
+  /// Opens a scope measuring the allocations made until it drops.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// let mut scope = allocator.stats_scope().max_bytes(1 << 20);
+  /// let document = parse(&mut scope, input);
+  /// println!("{}", scope.finish()); // 412 allocations (90112 bytes), ...
+  /// ```
+  pub fn stats_scope(&mut self) -> StatsScope<'_> {
+    let start = self.counters;
+    self.counters.peak_bytes_in_use = self.counters.bytes_in_use;
+    StatsScope {
+      allocator: self,
+      start,
+      max_bytes: None,
+      max_allocations: None,
+      checked: false,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::alloc::Layout;
+
+  #[test]
+  fn scope_reports_only_its_own_operations() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let before = unsafe { allocator.allocate(Layout::array::<u8>(200).unwrap()) };
+
+    let mut scope = allocator.stats_scope();
+    unsafe {
+      let a = scope.allocate(Layout::array::<u8>(64).unwrap());
+      scope.allocate(Layout::array::<u8>(32).unwrap());
+      scope.deallocate(a);
+      scope.deallocate(before);
+    }
+    let report = scope.finish();
+
+    assert_eq!(
+      report,
+      ScopeReport {
+        allocations: 2,
+        failed_allocations: 0,
+        deallocations: 2,
+        bytes_allocated: 96,
+        bytes_retained: 0,
+        peak_bytes_in_use: 296,
+      }
+    );
+    assert_eq!(allocator.counters().allocations, 3);
+  }
+
+  #[test]
+  fn scope_peak_is_its_own_and_the_global_peak_survives() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    unsafe {
+      let big = allocator.allocate(Layout::array::<u8>(1000).unwrap());
+      allocator.deallocate(big);
+    }
+
+    let mut scope = allocator.stats_scope();
+    unsafe { scope.allocate(Layout::array::<u8>(10).unwrap()) };
+    let report = scope.report();
+    assert_eq!((report.peak_bytes_in_use, report.bytes_retained), (10, 10));
+    drop(scope);
+
+    assert_eq!(allocator.counters().peak_bytes_in_use, 1000);
+  }
+
+  #[test]
+  fn scopes_within_limits_pass() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let mut scope = allocator.stats_scope().max_bytes(64).max_allocations(2);
+    unsafe { scope.allocate(Layout::array::<u8>(64).unwrap()) };
+  }
+
+  #[test]
+  #[should_panic(expected = "scope allocated 65 bytes, limit 64")]
+  fn exceeding_the_byte_limit_panics_on_drop() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let mut scope = allocator.stats_scope().max_bytes(64);
+    unsafe { scope.allocate(Layout::array::<u8>(65).unwrap()) };
+  }
+
+  #[test]
+  #[should_panic(expected = "scope made 2 allocations, limit 1")]
+  fn exceeding_the_allocation_limit_panics_on_finish() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let mut scope = allocator.stats_scope().max_allocations(1);
+    unsafe {
+      scope.allocate(Layout::new::<u8>());
+      scope.allocate(Layout::new::<u8>());
+    }
+    scope.finish();
+  }
+}
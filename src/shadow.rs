@@ -0,0 +1,392 @@
+//! # Shadow Memory
+//!
+//! With the `shadow` feature, every allocator keeps a second, independent
+//! record of its heap: one state per byte. Every allocation and free checks
+//! the bytes it takes over or gives back against that record before
+//! updating it:
+//!
+//! ```text
+//!   heap    │ hdr │ payload A   │pad│ hdr │ payload B (freed)  │ hdr │ C ...
+//!   shadow  │ HHH │ UUUUUUUUUUU │ · │ HHH │ FFFFFFFFFFFFFFFFFF │ HHH │ U ...
+//!
+//!   H header   U user   F free   C canary   · unused
+//!
+//!   allocate   new header and payload must be ·   ──► H, U (and C)
+//!   free       header must be H, payload U        ──► F, or · for the tail
+//! ```
+//!
+//! The block list validation of
+//! [`check_invariants`](BumpAllocator::check_invariants) can only see what
+//! the list says. The shadow catches what the list cannot: a new block
+//! overlapping a live one, a header written over user data, live bytes the
+//! list lost track of. `check_invariants` also compares the two records
+//! block by block, and the user bytes in the shadow against the live bytes
+//! of the list.
+//!
+//! A heavy debugging aid: one shadow byte per heap byte, and O(size) work
+//! per operation. The shadow itself lives in `Vec`s on the global
+//! allocator, so an allocator with this feature must not *be* the global
+//! allocator.
+
+use std::vec::Vec;
+
+use crate::{
+  BumpAllocator, Corruption,
+  block::{Block, HEADER_SIZE},
+  canary::CANARY_SIZE,
+};
+
+/// What a heap byte holds according to the shadow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ShadowState {
+  /// Not part of any block: not yet used, alignment padding, jitter gaps,
+  /// or memory given back.
+  Unused,
+
+  /// Part of a block header.
+  Header,
+
+  /// Payload of a live block.
+  User,
+
+  /// Payload of a freed block.
+  Free,
+
+  /// A live block's overflow canary.
+  Canary,
+}
+
+/// One [`ShadowState`] per heap byte from `base` on.
+pub(crate) struct ShadowMap {
+  /// Address of the first byte tracked.
+  base: usize,
+
+  /// States of `base..base + states.len()`; bytes past the end are
+  /// [`ShadowState::Unused`].
+  states: Vec<ShadowState>,
+}
+
+impl ShadowMap {
+  /// An empty map.
+  pub(crate) const fn new() -> Self {
+    Self {
+      base: 0,
+      states: Vec::new(),
+    }
+  }
+
+  /// Forgets every byte.
+  pub(crate) fn clear(&mut self) {
+    self.base = 0;
+    self.states = Vec::new();
+  }
+
+  /// State of the byte at `address`.
+  pub(crate) fn get(
+    &self,
+    address: usize,
+  ) -> ShadowState {
+    address
+      .checked_sub(self.base)
+      .and_then(|index| self.states.get(index))
+      .copied()
+      .unwrap_or(ShadowState::Unused)
+  }
+
+  /// First byte of `start..start + len` not in `expected`, with its state.
+  pub(crate) fn find_mismatch(
+    &self,
+    start: usize,
+    len: usize,
+    expected: ShadowState,
+  ) -> Option<(usize, ShadowState)> {
+    (start..start + len).map(|address| (address, self.get(address))).find(|&(_, state)| state != expected)
+  }
+
+  /// Sets `start..start + len` to `state`.
+  pub(crate) fn set(
+    &mut self,
+    start: usize,
+    len: usize,
+    state: ShadowState,
+  ) {
+    if len == 0 {
+      return;
+    }
+    if self.states.is_empty() {
+      self.base = start;
+    } else if start < self.base {
+      // A lower address than any before: shift the map to start there
+      let shift = self.base - start;
+      self.states.splice(0..0, core::iter::repeat_n(ShadowState::Unused, shift));
+      self.base = start;
+    }
+    let end = start - self.base + len;
+    if self.states.len() < end {
+      self.states.resize(end, ShadowState::Unused);
+    }
+    self.states[start - self.base..end].fill(state);
+  }
+
+  /// Number of bytes in `state`.
+  pub(crate) fn count(
+    &self,
+    state: ShadowState,
+  ) -> usize {
+    self.states.iter().filter(|&&byte| byte == state).count()
+  }
+}
+
+impl BumpAllocator {
+  /// What the shadow says about the heap byte at `address`.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// let ptr = unsafe { allocator.allocate(Layout::new::<u64>()) };
+  /// assert_eq!(allocator.shadow_state(ptr as usize), ShadowState::User);
+  /// ```
+  pub fn shadow_state(
+    &self,
+    address: usize,
+  ) -> ShadowState {
+    self.shadow.get(address)
+  }
+
+  /// Checks that `start..start + len` is all `expected`, reporting the
+  /// first byte that is not.
+  fn shadow_expect(
+    &self,
+    start: usize,
+    len: usize,
+    expected: ShadowState,
+  ) {
+    if let Some((address, found)) = self.shadow.find_mismatch(start, len, expected) {
+      self.report_corruption(Corruption::ShadowMismatch {
+        address,
+        expected,
+        found,
+      });
+    }
+  }
+
+  /// Records the freshly placed `block`: its bytes must be unused.
+  ///
+  /// # Safety
+  ///
+  /// `block` must be a valid header.
+  pub(crate) unsafe fn shadow_allocate(
+    &mut self,
+    block: *mut Block,
+  ) {
+    let (header, payload, size, canary) = unsafe { spans(block) };
+    self.shadow_expect(header, HEADER_SIZE + size + canary, ShadowState::Unused);
+    self.shadow.set(header, HEADER_SIZE, ShadowState::Header);
+    self.shadow.set(payload, size, ShadowState::User);
+    self.shadow.set(payload + size, canary, ShadowState::Canary);
+  }
+
+  /// Checks that the live `block` is intact in the shadow, then records it
+  /// as freed - or, if `dropped`, as no longer part of the heap.
+  ///
+  /// # Safety
+  ///
+  /// `block` must be a valid header whose canary flag is still set if it
+  /// had one.
+  pub(crate) unsafe fn shadow_free(
+    &mut self,
+    block: *mut Block,
+    dropped: bool,
+  ) {
+    let (header, payload, size, canary) = unsafe { spans(block) };
+    self.shadow_expect(header, HEADER_SIZE, ShadowState::Header);
+    self.shadow_expect(payload, size, ShadowState::User);
+    self.shadow_expect(payload + size, canary, ShadowState::Canary);
+    if dropped {
+      self.shadow.set(header, HEADER_SIZE + size + canary, ShadowState::Unused);
+    } else {
+      // The canary retires with the free
+      self.shadow.set(payload, size, ShadowState::Free);
+      self.shadow.set(payload + size, canary, ShadowState::Unused);
+    }
+  }
+
+  /// Records the freed `block` as live again, for a search that reuses it:
+  /// its new size is user bytes, the rest of the `span` it had while free
+  /// is unused again.
+  ///
+  /// # Safety
+  ///
+  /// `block` must be a valid header, just taken off the free list with
+  /// its new size and no canary yet.
+  pub(crate) unsafe fn shadow_reuse(
+    &mut self,
+    block: *mut Block,
+    span: usize,
+  ) {
+    let (header, payload, size, _) = unsafe { spans(block) };
+    self.shadow_expect(header, HEADER_SIZE, ShadowState::Header);
+    self.shadow_expect(payload, span, ShadowState::Free);
+    self.shadow.set(payload, size, ShadowState::User);
+    self.shadow.set(payload + size, span - size, ShadowState::Unused);
+  }
+
+  /// Records an already freed `block` as no longer part of the heap.
+  ///
+  /// # Safety
+  ///
+  /// `block` must be a valid, free header.
+  pub(crate) unsafe fn shadow_drop_free(
+    &mut self,
+    block: *mut Block,
+  ) {
+    let (header, payload, size, _) = unsafe { spans(block) };
+    self.shadow_expect(header, HEADER_SIZE, ShadowState::Header);
+    self.shadow_expect(payload, size, ShadowState::Free);
+    self.shadow.set(header, HEADER_SIZE + size, ShadowState::Unused);
+  }
+
+  /// Rebuilds the shadow from the block list, for a heap that appeared
+  /// without going through `allocate` (a fork).
+  pub(crate) fn rebuild_shadow(&mut self) {
+    self.shadow.clear();
+    let blocks: Vec<_> = self.blocks().map(|block| block as *const Block as *mut Block).collect();
+    for block in blocks {
+      // SAFETY: The list yields valid headers.
+      let (header, payload, size, canary) = unsafe { spans(block) };
+      let free = unsafe { (*block).is_free };
+      self.shadow.set(header, HEADER_SIZE, ShadowState::Header);
+      self.shadow.set(payload, size, if free { ShadowState::Free } else { ShadowState::User });
+      self.shadow.set(payload + size, canary, ShadowState::Canary);
+    }
+  }
+
+  /// Compares the block list with the shadow: every block must be where
+  /// the shadow has it, and the shadow must hold no user bytes the list
+  /// does not know about.
+  pub(crate) fn check_shadow(&self) -> Result<(), Corruption> {
+    let mut live = 0;
+    for block in self.blocks() {
+      let block = block as *const Block as *mut Block;
+      // SAFETY: The list yields valid headers.
+      let (header, payload, size, canary) = unsafe { spans(block) };
+      let state = if unsafe { (*block).is_free } {
+        ShadowState::Free
+      } else {
+        live += size;
+        ShadowState::User
+      };
+      for (start, len, expected) in [
+        (header, HEADER_SIZE, ShadowState::Header),
+        (payload, size, state),
+        (payload + size, canary, ShadowState::Canary),
+      ] {
+        if let Some((address, found)) = self.shadow.find_mismatch(start, len, expected) {
+          return Err(Corruption::ShadowMismatch {
+            address,
+            expected,
+            found,
+          });
+        }
+      }
+    }
+
+    let shadowed = self.shadow.count(ShadowState::User);
+    if shadowed != live {
+      return Err(Corruption::ShadowAccounting { shadowed, live });
+    }
+    Ok(())
+  }
+}
+
+/// Header address, payload address, payload size and canary size of
+/// `block`.
+///
+/// # Safety
+///
+/// `block` must be a valid header.
+unsafe fn spans(block: *mut Block) -> (usize, usize, usize, usize) {
+  let header = block as usize;
+  let (size, canary) = unsafe { ((*block).size, if (*block).has_canary() { CANARY_SIZE } else { 0 }) };
+  (header, header + HEADER_SIZE, size, canary)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::alloc::Layout;
+
+  const WORD: Layout = Layout::new::<u64>();
+
+  #[test]
+  fn operations_keep_the_shadow_in_step() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_canary_policy(crate::CanaryPolicy::sample(2));
+
+    unsafe {
+      let ptrs: Vec<_> = (0..6).map(|_| allocator.allocate(WORD)).collect();
+      let a = ptrs[0] as usize;
+      assert_eq!(allocator.shadow_state(a - 1), ShadowState::Header);
+      assert_eq!(allocator.shadow_state(a), ShadowState::User);
+      assert_eq!(allocator.shadow_state(a + 8), ShadowState::Canary);
+
+      allocator.deallocate(ptrs[0]);
+      assert_eq!(allocator.shadow_state(a), ShadowState::Free);
+      allocator.deallocate(ptrs[5]);
+      assert_eq!(allocator.shadow_state(ptrs[5] as usize), ShadowState::Unused);
+      allocator.assert_invariants();
+
+      allocator.shrink_to(ptrs[1]);
+      assert_eq!(allocator.shadow_state(ptrs[3] as usize), ShadowState::Unused);
+      allocator.assert_invariants();
+
+      allocator.reset();
+      assert_eq!(allocator.shadow_state(a), ShadowState::Unused);
+    }
+  }
+
+  #[test]
+  fn lost_block_is_an_accounting_error() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+
+    unsafe {
+      let a = allocator.allocate(WORD).sub(HEADER_SIZE) as *mut Block;
+      allocator.allocate(WORD);
+      // Drop the tail behind the allocator's back: the list stays consistent
+      (*a).next = core::ptr::null_mut();
+      allocator.last = a;
+    }
+    assert_eq!(
+      allocator.check_invariants(),
+      Err(Corruption::ShadowAccounting { shadowed: 16, live: 8 })
+    );
+  }
+
+  #[test]
+  #[should_panic(expected = "shadow")]
+  fn header_over_user_data_is_caught() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+
+    unsafe {
+      let a = allocator.allocate(Layout::array::<u8>(64).unwrap());
+      // Fake a block inside `a`'s payload, as a corrupted list would
+      let fake = a.add(16) as *mut Block;
+      fake.write(Block::new(8, false, core::ptr::null_mut(), core::ptr::null_mut()));
+      allocator.shadow_allocate(fake);
+    }
+  }
+
+  #[test]
+  fn fork_carries_the_shadow() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+
+    unsafe {
+      let ptrs: Vec<_> = (0..3).map(|_| allocator.allocate(WORD)).collect();
+      allocator.deallocate(ptrs[1]);
+      let fork = allocator.fork().unwrap();
+      assert_eq!(fork.shadow_state(fork.translate(ptrs[1]) as usize), ShadowState::Free);
+      fork.assert_invariants();
+    }
+  }
+}
@@ -0,0 +1,211 @@
+//! # Delayed Shrinking
+//!
+//! Freeing the tail block moves the program break back right away. A
+//! program that frees and reallocates around the same size then pays two
+//! `sbrk` calls per cycle. A [`ShrinkPolicy`] adds hysteresis: the freed
+//! tail memory is *cached* above the break and handed out again by the next
+//! allocation, and only goes back to the system once the cache is too large
+//! or has sat unused for a while:
+//!
+//! ```text
+//!   keep_bytes = 8 KiB
+//!
+//!   ┌──────────────┬────────────────────┐
+//!   │ blocks       │ cached (≤ 8 KiB)   │  free tail ──► cache grows, no sbrk
+//!   └──────────────┴────────────────────┘
+//!                  ▲ end                ▲ program break
+//!
+//!   cache > 8 KiB              ──► sbrk(-cached), all of it goes back
+//!   idle_ops operations later  ──► sbrk(-cached), if nothing refilled it
+//! ```
+//!
+//! Idleness is counted in allocator operations, not time, so the policy
+//! needs no clock and behaves the same in every run. The cache also holds
+//! any growth chunk surplus (see
+//! [`set_growth_chunk`](BumpAllocator::set_growth_chunk)); both show up in
+//! [`Stats::cached_bytes`](crate::Stats::cached_bytes).
+//!
+//! Only the program break backend caches: region memory is never returned
+//! to the system, so for regions every policy behaves like
+//! [`ShrinkPolicy::EAGER`].
+
+use crate::{BumpAllocator, block::HEADER_SIZE, model};
+
+/// When freed tail memory goes back to the system, see the `shrink`
+/// module.
+///
+/// ```rust,ignore
+/// allocator.set_shrink_policy(ShrinkPolicy {
+///     keep_bytes: 64 * 1024,
+///     idle_ops: Some(10_000),
+/// });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShrinkPolicy {
+  /// Most bytes kept cached; freeing more releases the whole cache.
+  pub keep_bytes: usize,
+
+  /// Releases the cache after this many allocations and deallocations
+  /// without a tail free refilling it, if set.
+  pub idle_ops: Option<usize>,
+}
+
+impl ShrinkPolicy {
+  /// Release on every tail free, the default.
+  pub const EAGER: Self = Self {
+    keep_bytes: 0,
+    idle_ops: None,
+  };
+
+  /// Keep up to `bytes` cached, with no idle release.
+  pub const fn keep(bytes: usize) -> Self {
+    Self {
+      keep_bytes: bytes,
+      idle_ops: None,
+    }
+  }
+}
+
+impl Default for ShrinkPolicy {
+  fn default() -> Self {
+    Self::EAGER
+  }
+}
+
+impl BumpAllocator {
+  /// Sets when freed tail memory is returned to the system.
+  ///
+  /// Applies from the next free; memory cached beyond a lowered
+  /// `keep_bytes` goes back then.
+  pub fn set_shrink_policy(
+    &mut self,
+    policy: ShrinkPolicy,
+  ) {
+    self.shrink_policy = policy;
+    self.idle_ops = 0;
+  }
+
+  /// The policy set by [`set_shrink_policy`](Self::set_shrink_policy).
+  pub fn shrink_policy(&self) -> ShrinkPolicy {
+    self.shrink_policy
+  }
+
+  /// Gives the memory after the tail block's payload back to the backend,
+  /// caching it as the shrink policy allows.
+  ///
+  /// Everything past the payload goes, including the alignment padding of
+  /// popped blocks; the payload keeps its canary and the room its free
+  /// links would need.
+  ///
+  /// Does nothing while untracked memory past the block cap lies above
+  /// the tail, and keeps headerless slots.
+  ///
+  /// # Safety
+  ///
+  /// Blocks past `last` must have been unlinked, and their memory unused.
+  pub(crate) unsafe fn release_past_tail(&mut self) {
+    if !self.untracked.is_null() {
+      return;
+    }
+    let heap_end = self.backend.current_break() as usize;
+    // SAFETY: `last` is null or a valid block.
+    let tail = unsafe { self.last.as_ref() }.map(|last| (self.last as usize + HEADER_SIZE, last.payload_extent()));
+    let kept_end = model::kept_end(tail, heap_end - self.backend.used_bytes()).max(self.headerless_end as usize);
+    let bytes = heap_end.saturating_sub(kept_end);
+
+    unsafe {
+      self.backend.retract(bytes);
+      self.idle_ops = 0;
+      if self.backend.cached_bytes() > self.shrink_policy.keep_bytes {
+        self.backend.trim();
+      }
+    }
+  }
+
+  /// Counts an operation towards the idle release of the cache.
+  #[inline]
+  pub(crate) fn tick_shrink_idle(&mut self) {
+    if let Some(limit) = self.shrink_policy.idle_ops {
+      self.idle_ops += 1;
+      if self.idle_ops >= limit {
+        self.idle_ops = 0;
+        // SAFETY: The cache holds no handed-out memory.
+        unsafe { self.backend.trim() };
+      }
+    }
+  }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+  use super::*;
+  use core::alloc::Layout;
+
+  const PAGE: usize = 4096;
+
+  /// An sbrk allocator with two page-sized blocks, the tail one freed.
+  fn freed_tail(policy: ShrinkPolicy) -> (BumpAllocator, *mut u8) {
+    let mut allocator = BumpAllocator::new();
+    allocator.set_shrink_policy(policy);
+    unsafe {
+      allocator.allocate(Layout::array::<u8>(PAGE).unwrap());
+      let tail = allocator.allocate(Layout::array::<u8>(PAGE).unwrap());
+      allocator.deallocate(tail);
+      (allocator, tail)
+    }
+  }
+
+  /// Whether the cache went back to the system - or could not, because
+  /// another user (a parallel test) moved the break past it meanwhile.
+  fn cache_released(allocator: &BumpAllocator) -> bool {
+    let cached = allocator.backend.cached_bytes();
+    let committed = allocator.backend.current_break() as usize + cached;
+    cached == 0 || unsafe { libc::sbrk(0) } as usize != committed
+  }
+
+  #[test]
+  fn freed_tail_is_cached_and_reused() {
+    let (mut allocator, tail) = freed_tail(ShrinkPolicy::keep(64 * 1024));
+    assert!(allocator.stats().cached_bytes >= PAGE);
+
+    // The next allocation takes the cached memory back, padding included
+    let again = unsafe { allocator.allocate(Layout::array::<u8>(PAGE).unwrap()) };
+    assert!(again <= tail);
+    assert!(allocator.stats().cached_bytes < PAGE);
+    unsafe { allocator.reset() };
+  }
+
+  #[test]
+  fn cache_over_the_threshold_is_released() {
+    let (mut allocator, _) = freed_tail(ShrinkPolicy::keep(PAGE / 2));
+
+    assert!(cache_released(&allocator));
+    unsafe { allocator.reset() };
+  }
+
+  #[test]
+  fn idle_cache_is_released() {
+    let (mut allocator, _) = freed_tail(ShrinkPolicy {
+      keep_bytes: 64 * 1024,
+      idle_ops: Some(3),
+    });
+    assert!(allocator.stats().cached_bytes >= PAGE);
+
+    // Small allocations come from the cache without refilling it
+    for _ in 0..2 {
+      unsafe { allocator.allocate(Layout::new::<u64>()) };
+    }
+    assert!(allocator.stats().cached_bytes > 0);
+    unsafe { allocator.allocate(Layout::new::<u64>()) };
+    assert!(cache_released(&allocator));
+    unsafe { allocator.reset() };
+  }
+
+  #[test]
+  fn eager_policy_keeps_nothing() {
+    assert_eq!(ShrinkPolicy::default(), ShrinkPolicy::EAGER);
+    let (mut allocator, _) = freed_tail(ShrinkPolicy::EAGER);
+    assert!(cache_released(&allocator));
+    unsafe { allocator.reset() };
+  }
+}
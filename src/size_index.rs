@@ -0,0 +1,220 @@
+//! Size-ordered index of free blocks, used by [`SearchMode::BestFitIndexed`].
+//!
+//! The index is a splay tree threaded through the payloads of free blocks
+//! (the `smaller`/`larger` fields of [`FreeLinks`]), so like the free list
+//! it costs no header space and no allocation. Blocks are keyed by
+//! `(size, address)`: keys are unique and, among equal sizes, the lowest
+//! address wins, exactly as the linear best-fit search would pick.
+//!
+//! ```text
+//!   best_fit(100):  splay (100, 0) to the root, then take it or its successor
+//!
+//!                  (128, B)                      (110, D)  ◄── root
+//!                 /        \       splay        /        \
+//!           (64, A)      (256, C)   ───►    (64, A)    (128, B)
+//!                        /                                  \
+//!                  (110, D)                               (256, C)
+//!
+//!   (110, D) >= (100, 0)  ──►  D, the smallest block that fits
+//! ```
+//!
+//! Splaying moves recently used keys to the root, giving O(log n) amortized
+//! insert, remove and search with only two links per node.
+//!
+//! [`SearchMode::BestFitIndexed`]: crate::SearchMode::BestFitIndexed
+//! [`FreeLinks`]: crate::block::FreeLinks
+
+use core::ptr;
+
+use crate::block::Block;
+
+/// Ordering key of a free block.
+type Key = (usize, usize);
+
+/// Key of `block`: its size, then its address.
+unsafe fn key(block: *mut Block) -> Key {
+  unsafe { ((*block).size, block as usize) }
+}
+
+/// Link to blocks with smaller keys.
+unsafe fn smaller(block: *mut Block) -> *mut *mut Block {
+  unsafe { &raw mut (*Block::free_links(block)).smaller }
+}
+
+/// Link to blocks with larger keys.
+unsafe fn larger(block: *mut Block) -> *mut *mut Block {
+  unsafe { &raw mut (*Block::free_links(block)).larger }
+}
+
+/// Free blocks ordered by size.
+pub(crate) struct SizeIndex {
+  /// Root of the splay tree, or null when empty.
+  root: *mut Block,
+}
+
+impl SizeIndex {
+  /// An empty index.
+  pub(crate) const fn new() -> Self {
+    Self { root: ptr::null_mut() }
+  }
+
+  /// Forgets every block. The blocks themselves are not touched.
+  pub(crate) fn clear(&mut self) {
+    self.root = ptr::null_mut();
+  }
+
+  /// Adds the free `block`.
+  ///
+  /// # Safety
+  ///
+  /// `block` must be free, with room for its free links, and not
+  /// already in the index.
+  pub(crate) unsafe fn insert(
+    &mut self,
+    block: *mut Block,
+  ) {
+    unsafe {
+      if self.root.is_null() {
+        *smaller(block) = ptr::null_mut();
+        *larger(block) = ptr::null_mut();
+      } else {
+        // Split the tree around the new key, which becomes the root
+        let root = splay(self.root, key(block));
+        if key(block) < key(root) {
+          *smaller(block) = *smaller(root);
+          *larger(block) = root;
+          *smaller(root) = ptr::null_mut();
+        } else {
+          *larger(block) = *larger(root);
+          *smaller(block) = root;
+          *larger(root) = ptr::null_mut();
+        }
+      }
+      self.root = block;
+    }
+  }
+
+  /// Removes `block`.
+  ///
+  /// # Safety
+  ///
+  /// `block` must be in the index.
+  pub(crate) unsafe fn remove(
+    &mut self,
+    block: *mut Block,
+  ) {
+    unsafe {
+      let root = splay(self.root, key(block));
+      debug_assert!(ptr::eq(root, block), "block is not in the size index");
+
+      self.root = if (*smaller(root)).is_null() {
+        *larger(root)
+      } else {
+        // The largest smaller key has no larger child: hang the rest there
+        let replacement = splay(*smaller(root), key(block));
+        *larger(replacement) = *larger(root);
+        replacement
+      };
+    }
+  }
+
+  /// The smallest block of at least `size` bytes (lowest address among
+  /// equals), or null.
+  ///
+  /// # Safety
+  ///
+  /// Every block in the index must still be free and valid.
+  pub(crate) unsafe fn best_fit(
+    &mut self,
+    size: usize,
+  ) -> *mut Block {
+    unsafe {
+      if self.root.is_null() {
+        return ptr::null_mut();
+      }
+
+      // No block lives at address 0, so the root ends up as the
+      // neighbour of the key on one side or the other
+      self.root = splay(self.root, (size, 0));
+      if (*self.root).size >= size {
+        return self.root;
+      }
+
+      // The root is the largest block that is too small: take its successor
+      let mut current = *larger(self.root);
+      while !current.is_null() && !(*smaller(current)).is_null() {
+        current = *smaller(current);
+      }
+      current
+    }
+  }
+}
+
+/// Top-down splay: rearranges the tree under `root` so that the node with
+/// `target`'s key, or the last node visited looking for it, becomes the
+/// root, and returns it.
+///
+/// Nodes passed on the way down are hung on a left tree (keys below
+/// `target`) and a right tree (keys above), which are reattached below
+/// the new root at the end.
+unsafe fn splay(
+  mut root: *mut Block,
+  target: Key,
+) -> *mut Block {
+  unsafe {
+    let mut left_tree: *mut Block = ptr::null_mut();
+    let mut right_tree: *mut Block = ptr::null_mut();
+    // Where the next node goes: right of the left tree's maximum, and
+    // left of the right tree's minimum
+    let mut left_slot: *mut *mut Block = &mut left_tree;
+    let mut right_slot: *mut *mut Block = &mut right_tree;
+
+    loop {
+      if target < key(root) {
+        let mut child = *smaller(root);
+        if child.is_null() {
+          break;
+        }
+        if target < key(child) {
+          // Zig-zig: rotate right before descending
+          *smaller(root) = *larger(child);
+          *larger(child) = root;
+          root = child;
+          child = *smaller(root);
+          if child.is_null() {
+            break;
+          }
+        }
+        *right_slot = root;
+        right_slot = smaller(root);
+        root = child;
+      } else if target > key(root) {
+        let mut child = *larger(root);
+        if child.is_null() {
+          break;
+        }
+        if target > key(child) {
+          // Zag-zag: rotate left before descending
+          *larger(root) = *smaller(child);
+          *smaller(child) = root;
+          root = child;
+          child = *larger(root);
+          if child.is_null() {
+            break;
+          }
+        }
+        *left_slot = root;
+        left_slot = larger(root);
+        root = child;
+      } else {
+        break;
+      }
+    }
+
+    *left_slot = *smaller(root);
+    *right_slot = *larger(root);
+    *smaller(root) = left_tree;
+    *larger(root) = right_tree;
+    root
+  }
+}
@@ -0,0 +1,308 @@
+//! # Headerless Sized Allocations
+//!
+//! A block header costs [`HEADER_SIZE`] bytes - more than the payload of
+//! most small objects. Callers that hand the `Layout` back when freeing,
+//! as `GlobalAlloc` and `Allocator` do, do not need the header to find the
+//! size, so [`allocate_sized`](BumpAllocator::allocate_sized) serves small
+//! word-aligned layouts without one, from per-size free stacks:
+//!
+//! ```text
+//!   set_headerless_max(32)            (64-bit: classes of 8, 16, 24, 32 B)
+//!
+//!   class 0 (8 B)    free ──► [·] ──► [·] ──► null
+//!   class 1 (16 B)   free ──► [····] ──► null
+//!   ...              links live in the first word of each freed slot
+//!
+//!   allocate_sized(16 B)      pop class 1, or bump 16 B at the break
+//!   deallocate_sized(p, 16 B) push p on class 1
+//!   allocate_sized(100 B)     too big: a normal block with a header
+//! ```
+//!
+//! Headerless slots are never part of the block list: they count as padding
+//! in [`Stats`](crate::Stats), are never absorbed or merged into a freed
+//! neighbour, never return to the backend before a reset,
+//! and only the sized pair of calls may touch them - `deallocate` or
+//! `deallocate_with_layout` on a headerless pointer reads a header that is
+//! not there. [`CriticalSectionAllocator`] and the `allocator-api2`
+//! wrappers use the sized pair, so installing a global allocator with a
+//! headerless class is one call away.
+//!
+//! [`HEADER_SIZE`]: crate::block::HEADER_SIZE
+//! [`CriticalSectionAllocator`]: crate::CriticalSectionAllocator
+
+use core::{alloc::Layout, mem, ptr};
+
+use crate::{BumpAllocator, align::align_up};
+#[cfg(feature = "flight-recorder")]
+use crate::recorder::OpKind;
+
+/// Number of headerless size classes, one per word of payload.
+pub(crate) const HEADERLESS_CLASSES: usize = 8;
+
+/// Machine word size, the step between classes.
+const WORD: usize = mem::size_of::<usize>();
+
+/// Largest size [`BumpAllocator::set_headerless_max`] accepts.
+pub const MAX_HEADERLESS_SIZE: usize = HEADERLESS_CLASSES * WORD;
+
+impl BumpAllocator {
+  /// Serves layouts of at most `max` bytes and at most word alignment
+  /// without a header, when allocated with
+  /// [`allocate_sized`](Self::allocate_sized). `0` (the default) turns
+  /// headerless allocation off.
+  ///
+  /// # Panics
+  ///
+  /// If `max` exceeds [`MAX_HEADERLESS_SIZE`], or if headerless memory was
+  /// handed out since the last reset: the class of a pointer must not
+  /// change while it is live.
+  pub fn set_headerless_max(
+    &mut self,
+    max: usize,
+  ) {
+    assert!(max <= MAX_HEADERLESS_SIZE, "headerless allocations are at most {MAX_HEADERLESS_SIZE} bytes");
+    assert!(self.headerless_end.is_null(), "headerless memory is in use");
+    self.headerless_max = max;
+  }
+
+  /// The limit set by [`set_headerless_max`](Self::set_headerless_max).
+  pub fn headerless_max(&self) -> usize {
+    self.headerless_max
+  }
+
+  /// Allocates memory for `layout`, without a header if the layout is
+  /// small enough (see [`set_headerless_max`](Self::set_headerless_max)).
+  ///
+  /// # Safety
+  ///
+  /// As [`allocate`](Self::allocate); in addition the memory must be freed
+  /// with [`deallocate_sized`](Self::deallocate_sized) and the same layout.
+  #[track_caller]
+  pub unsafe fn allocate_sized(
+    &mut self,
+    layout: Layout,
+  ) -> *mut u8 {
+    let Some(class) = self.headerless_class(layout) else {
+      return unsafe { self.allocate(layout) };
+    };
+    if self.forbid_depth > 0 {
+      self.forbidden_allocation(layout);
+    }
+
+    let mut address = self.headerless_free[class];
+    if address.is_null() {
+      address = unsafe { self.bump_headerless(class) };
+    } else {
+      // SAFETY: Free slots store the next free slot in their first word.
+      self.headerless_free[class] = unsafe { *(address as *mut *mut u8) };
+      #[cfg(feature = "counters")]
+      self.count_reuse(layout.size());
+    }
+
+    #[cfg(feature = "flight-recorder")]
+    self.record(OpKind::Allocate, address as usize, layout.size(), !address.is_null());
+    #[cfg(feature = "counters")]
+    self.count_allocate(layout.size(), !address.is_null());
+    address
+  }
+
+  /// Frees memory from [`allocate_sized`](Self::allocate_sized).
+  ///
+  /// # Safety
+  ///
+  /// `address` must come from `allocate_sized` on this allocator with
+  /// `layout`, and must not have been freed already.
+  pub unsafe fn deallocate_sized(
+    &mut self,
+    address: *mut u8,
+    layout: Layout,
+  ) {
+    let Some(class) = self.headerless_class(layout) else {
+      return unsafe { self.deallocate_with_layout(address, layout) };
+    };
+    if address.is_null() {
+      return;
+    }
+
+    // SAFETY: The slot is at least a word and word-aligned, and now ours.
+    unsafe { *(address as *mut *mut u8) = self.headerless_free[class] };
+    self.headerless_free[class] = address;
+
+    #[cfg(feature = "flight-recorder")]
+    self.record(OpKind::Deallocate, address as usize, layout.size(), true);
+    #[cfg(feature = "counters")]
+    self.count_deallocate(layout.size());
+  }
+
+  /// The headerless class serving `layout`, if any.
+  #[inline]
+  fn headerless_class(
+    &self,
+    layout: Layout,
+  ) -> Option<usize> {
+    let fits = layout.size() <= self.headerless_max && layout.align() <= WORD && self.min_align <= WORD;
+    fits.then(|| layout.size().max(1).div_ceil(WORD) - 1)
+  }
+
+  /// Bumps a fresh slot for `class` at the break.
+  #[cold]
+  unsafe fn bump_headerless(
+    &mut self,
+    class: usize,
+  ) -> *mut u8 {
+    let brk = self.backend.current_break() as usize;
+    let padding = align_up(brk, WORD) - brk;
+    let size = padding + (class + 1) * WORD;
+
+    let raw = unsafe { self.grow_heap(size) };
+    if raw.is_null() {
+      return raw;
+    }
+    let address = align_up(raw as usize, WORD) as *mut u8;
+    self.headerless_end = address.wrapping_add((class + 1) * WORD);
+    address
+  }
+
+  /// Forgets every headerless slot, for a reset.
+  pub(crate) fn clear_headerless(&mut self) {
+    self.headerless_free = [ptr::null_mut(); HEADERLESS_CLASSES];
+    self.headerless_end = ptr::null_mut();
+  }
+
+  /// Moves the headerless state of a byte-for-byte copy of this heap by
+  /// `offset` bytes.
+  ///
+  /// # Safety
+  ///
+  /// The free stacks must be those of the copy, which starts `offset`
+  /// bytes from the original.
+  #[cfg(feature = "std")]
+  pub(crate) unsafe fn relocate_headerless(
+    &mut self,
+    offset: isize,
+  ) {
+    if !self.headerless_end.is_null() {
+      self.headerless_end = self.headerless_end.wrapping_offset(offset);
+    }
+    for head in &mut self.headerless_free {
+      let mut link: *mut *mut u8 = head;
+      // SAFETY: Every slot on a stack lies in the copy once relocated.
+      unsafe {
+        while !(*link).is_null() {
+          *link = (*link).wrapping_offset(offset);
+          link = *link as *mut *mut u8;
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn small_layouts_skip_the_header() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_headerless_max(32);
+    let layout = Layout::new::<[u64; 2]>();
+
+    unsafe {
+      let a = allocator.allocate_sized(layout);
+      let b = allocator.allocate_sized(layout);
+      assert_eq!(b as usize - a as usize, 16);
+      assert_eq!(allocator.block_count(), 0);
+
+      // Too big, or too aligned: a normal block
+      let big = allocator.allocate_sized(Layout::array::<u8>(100).unwrap());
+      let aligned = allocator.allocate_sized(Layout::from_size_align(16, 32).unwrap());
+      assert_eq!(allocator.block_count(), 2);
+
+      // Freed slots are reused by the same class, newest first
+      allocator.deallocate_sized(a, layout);
+      allocator.deallocate_sized(b, layout);
+      assert_eq!(allocator.allocate_sized(layout), b);
+      assert_eq!(allocator.allocate_sized(layout), a);
+      assert_ne!(allocator.allocate_sized(Layout::new::<u64>()), a);
+
+      allocator.deallocate_sized(big, Layout::array::<u8>(100).unwrap());
+      allocator.deallocate_sized(aligned, Layout::from_size_align(16, 32).unwrap());
+      assert_eq!(allocator.check_invariants(), Ok(()));
+    }
+  }
+
+  #[test]
+  fn popping_the_tail_keeps_headerless_slots() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_headerless_max(8);
+
+    unsafe {
+      let block = allocator.allocate(Layout::array::<u8>(64).unwrap());
+      let slot = allocator.allocate_sized(Layout::new::<u64>()) as *mut u64;
+      slot.write(0xDEAD_BEEF);
+      let heap = allocator.heap_size();
+
+      allocator.deallocate(block);
+      assert_eq!(allocator.heap_size(), heap);
+      assert_eq!(slot.read(), 0xDEAD_BEEF);
+
+      allocator.reset();
+    }
+    assert!(allocator.is_empty());
+    allocator.set_headerless_max(16);
+  }
+
+  #[test]
+  fn freed_neighbours_leave_headerless_slots_alone() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_headerless_max(16);
+    let layout = Layout::array::<u8>(64).unwrap();
+
+    unsafe {
+      let x = allocator.allocate(layout);
+      let slot = allocator.allocate_sized(Layout::new::<[u64; 2]>()) as *mut u64;
+      slot.write(0xDEAD_BEEF);
+      let y = allocator.allocate(layout);
+      let z = allocator.allocate(layout);
+
+      // Neither absorbing the gap up to `y` nor merging with it takes the slot
+      allocator.deallocate(x);
+      allocator.deallocate(y);
+      let reused = allocator.allocate(Layout::array::<u8>(80).unwrap());
+      assert_ne!(reused, x);
+      assert!(reused as usize + 80 <= slot as usize || reused as usize > slot as usize);
+      assert_eq!(slot.read(), 0xDEAD_BEEF);
+
+      allocator.deallocate(z);
+      assert_eq!(allocator.check_invariants(), Ok(()));
+    }
+  }
+
+  #[test]
+  #[should_panic(expected = "headerless memory is in use")]
+  fn the_limit_is_fixed_while_slots_are_live() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_headerless_max(8);
+    unsafe { allocator.allocate_sized(Layout::new::<u64>()) };
+    allocator.set_headerless_max(16);
+  }
+
+  #[test]
+  #[cfg(feature = "std")]
+  fn forks_relocate_the_free_stacks() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_headerless_max(8);
+    let layout = Layout::new::<u64>();
+
+    unsafe {
+      let a = allocator.allocate_sized(layout);
+      let b = allocator.allocate_sized(layout);
+      allocator.deallocate_sized(a, layout);
+      allocator.deallocate_sized(b, layout);
+
+      let mut fork = allocator.fork().unwrap();
+      assert_eq!(fork.allocate_sized(layout), fork.translate(b));
+      assert_eq!(fork.allocate_sized(layout), fork.translate(a));
+    }
+  }
+}
@@ -0,0 +1,331 @@
+//! # Heap Snapshots
+//!
+//! [`BumpAllocator::export`] writes the live blocks of an allocator to a
+//! small, versioned binary format; [`BumpAllocator::import`] rebuilds an
+//! equivalent allocator from it. Useful for golden-heap tests (compare a
+//! heap against a checked-in snapshot) and for crash-dump style debugging
+//! (save the heap, load it in a debugger session later).
+//!
+//! ## Format
+//!
+//! All integers are little-endian, so snapshots move between machines:
+//!
+//! ```text
+//!   header
+//!   ┌──────────┬─────────┬─────────────┬──────────────┬─────────────┐
+//!   │ "RALC"   │ version │ block_count │ capacity     │ random_seed │
+//!   │ 4 bytes  │ u32 = 2 │ u64         │ u64          │ u64         │
+//!   └──────────┴─────────┴─────────────┴──────────────┴─────────────┘
+//!   block_count times, oldest block first
+//!   ┌──────────┬─────────┬─────────────┬──────────────────────┐
+//!   │ offset   │ size    │ align       │ payload              │
+//!   │ u64      │ u64     │ u64         │ `size` bytes         │
+//!   └──────────┴─────────┴─────────────┴──────────────────────┘
+//! ```
+//!
+//! * `offset` - payload offset from the start of the heap, for reference;
+//!   the importing allocator places blocks wherever its own memory is
+//! * `align` - the largest power of two dividing the payload address,
+//!   capped at [`MAX_SNAPSHOT_ALIGN`], so imported blocks stay at least as
+//!   aligned as their layouts required
+//! * `capacity` - bytes the importing allocator reserves for all blocks
+//! * `random_seed` - the exporter's [`random_seed`](BumpAllocator::random_seed),
+//!   which seeds the imported allocator: replaying the operations that
+//!   built the heap on an allocator with this seed (and the same jitter
+//!   and search mode) reproduces its layout exactly. Version 1 snapshots,
+//!   which predate the field, import with the default seed
+//!
+//! Free blocks and the emergency reserve are not exported. Blocks carry no
+//! type information, so pointers stored inside payloads come back as plain
+//! bytes.
+//!
+//! Export stops at the first block that lies outside the heap or below its
+//! predecessor, so even a corrupted list (see the `crash_dump` module)
+//! yields a finite, readable snapshot of its intact prefix.
+
+use core::alloc::Layout;
+use std::io::{self, Read, Write};
+
+use crate::{
+  BumpAllocator,
+  block::{Block, HEADER_SIZE},
+  bump::grow_request_size,
+};
+
+/// Magic bytes opening every snapshot.
+const MAGIC: [u8; 4] = *b"RALC";
+
+/// Current format version; `import` also reads version 1 and rejects any
+/// other.
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+/// Largest alignment recorded for a block.
+pub const MAX_SNAPSHOT_ALIGN: usize = 4096;
+
+impl BumpAllocator {
+  /// Writes every live block to `out`, see the `snapshot` module for the
+  /// format.
+  pub fn export<W: Write>(
+    &self,
+    out: &mut W,
+  ) -> io::Result<()> {
+    let start = self.backend.current_break() as usize - self.backend.used_bytes();
+    let reserve = self.reserve.arena().map_or(0, |arena| arena as *const BumpAllocator as usize);
+
+    let mut count = 0u64;
+    let mut capacity = 0usize;
+    for block in self.exported_blocks(reserve) {
+      count += 1;
+      capacity += grow_request_size(block_layout(block)).ok_or(io::ErrorKind::InvalidData)?;
+    }
+
+    out.write_all(&MAGIC)?;
+    out.write_all(&SNAPSHOT_VERSION.to_le_bytes())?;
+    out.write_all(&count.to_le_bytes())?;
+    out.write_all(&(capacity as u64).to_le_bytes())?;
+    out.write_all(&self.random_seed.to_le_bytes())?;
+
+    for block in self.exported_blocks(reserve) {
+      let layout = block_layout(block);
+      let payload = payload_address(block);
+      out.write_all(&(payload.wrapping_sub(start) as u64).to_le_bytes())?;
+      out.write_all(&(layout.size() as u64).to_le_bytes())?;
+      out.write_all(&(layout.align() as u64).to_le_bytes())?;
+      // SAFETY: The payload of a live block is `size` readable bytes.
+      out.write_all(unsafe { core::slice::from_raw_parts(payload as *const u8, layout.size()) })?;
+    }
+    Ok(())
+  }
+
+  /// Rebuilds an allocator from a snapshot written by
+  /// [`export`](Self::export).
+  ///
+  /// The new allocator owns a region just large enough for the blocks,
+  /// which come back in the same order, with the same sizes and contents,
+  /// and at least the same alignment. Fails with
+  /// [`io::ErrorKind::InvalidData`] on a malformed snapshot or an unknown
+  /// version.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// let mut golden = Vec::new();
+  /// allocator.export(&mut golden)?;
+  ///
+  /// let restored = BumpAllocator::import(&mut golden.as_slice())?;
+  /// assert_eq!(restored.heap_map().to_string(), expected_map);
+  /// ```
+  pub fn import<R: Read>(input: &mut R) -> io::Result<BumpAllocator> {
+    let mut magic = [0; 4];
+    input.read_exact(&mut magic)?;
+    if magic != MAGIC {
+      return Err(invalid("not a heap snapshot"));
+    }
+    let mut version = [0; 4];
+    input.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if !(1..=SNAPSHOT_VERSION).contains(&version) {
+      return Err(invalid("unsupported snapshot version"));
+    }
+
+    let count = read_usize(input)?;
+    let capacity = read_usize(input)?;
+    let mut allocator = BumpAllocator::with_capacity(capacity);
+    if version >= 2 {
+      let mut seed = [0; 8];
+      input.read_exact(&mut seed)?;
+      allocator.set_random_seed(u64::from_le_bytes(seed));
+    }
+
+    for _ in 0..count {
+      let _offset = read_usize(input)?;
+      let size = read_usize(input)?;
+      let align = read_usize(input)?;
+      let layout = Layout::from_size_align(size, align).map_err(|_| invalid("bad block layout"))?;
+
+      // SAFETY: `allocate` needs exclusive access, which the new allocator has.
+      let payload = unsafe { allocator.allocate(layout) };
+      if payload.is_null() {
+        return Err(invalid("blocks exceed the snapshot capacity"));
+      }
+      // SAFETY: The block was just allocated with `size` bytes.
+      input.read_exact(unsafe { core::slice::from_raw_parts_mut(payload, size) })?;
+    }
+    Ok(allocator)
+  }
+
+  /// Live blocks other than the emergency reserve's, up to the first
+  /// block that is out of order or out of bounds.
+  fn exported_blocks(
+    &self,
+    reserve: usize,
+  ) -> impl Iterator<Item = &Block> {
+    let end = self.backend.current_break() as usize;
+    let mut previous = 0;
+    let mut current = self.first;
+
+    // Checks each header's address before reading it, so a wild `next`
+    // pointer ends the walk instead of faulting
+    core::iter::from_fn(move || {
+      let address = current as usize;
+      if current.is_null() || address <= previous || address.saturating_add(HEADER_SIZE) > end {
+        return None;
+      }
+      // SAFETY: The header lies inside the heap, above the previous one.
+      let block = unsafe { &*current };
+      if payload_address(block).checked_add(block.size).is_none_or(|stop| stop > end) {
+        return None;
+      }
+      previous = address;
+      current = block.next;
+      Some(block)
+    })
+    .filter(move |block| !block.is_free && payload_address(block) != reserve)
+  }
+}
+
+/// Address of the payload after `block`'s header.
+fn payload_address(block: &Block) -> usize {
+  block as *const Block as usize + HEADER_SIZE
+}
+
+/// A layout for `block`: its size, and the alignment its payload has.
+fn block_layout(block: &Block) -> Layout {
+  let payload = payload_address(block);
+  let align = (1 << payload.trailing_zeros()).min(MAX_SNAPSHOT_ALIGN);
+  // Both parts come from a block `allocate` accepted, so they are valid
+  Layout::from_size_align(block.size, align).unwrap_or(Layout::new::<u8>())
+}
+
+/// Reads a little-endian `u64` that must fit in a `usize`.
+fn read_usize<R: Read>(input: &mut R) -> io::Result<usize> {
+  let mut bytes = [0; 8];
+  input.read_exact(&mut bytes)?;
+  usize::try_from(u64::from_le_bytes(bytes)).map_err(|_| invalid("value too large for this target"))
+}
+
+/// An `InvalidData` error with `message`.
+fn invalid(message: &'static str) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn sample_heap() -> (BumpAllocator, Vec<*mut u8>) {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let ptrs = unsafe {
+      let layouts = [Layout::array::<u8>(5).unwrap(), Layout::new::<u64>(), Layout::from_size_align(32, 64).unwrap()];
+      let ptrs: Vec<_> = layouts.iter().map(|&layout| allocator.allocate(layout)).collect();
+      for (i, (&ptr, layout)) in ptrs.iter().zip(layouts).enumerate() {
+        ptr.write_bytes(i as u8 + 1, layout.size());
+      }
+      ptrs
+    };
+    (allocator, ptrs)
+  }
+
+  #[test]
+  fn import_reproduces_the_heap() {
+    let (allocator, _) = sample_heap();
+
+    let mut snapshot = Vec::new();
+    allocator.export(&mut snapshot).unwrap();
+    let restored = BumpAllocator::import(&mut snapshot.as_slice()).unwrap();
+
+    let original: Vec<_> = allocator.blocks().map(|block| block.size).collect();
+    let copy: Vec<_> = restored.blocks().map(|block| block.size).collect();
+    assert_eq!(original, copy);
+
+    for (block, expected) in restored.blocks().zip(1u8..) {
+      let payload = unsafe { core::slice::from_raw_parts(payload_address(block) as *const u8, block.size) };
+      assert!(payload.iter().all(|&byte| byte == expected));
+    }
+    assert!(payload_address(restored.blocks().last().unwrap()).is_multiple_of(64));
+
+    // Exporting the copy gives the same blocks and bytes
+    let mut again = Vec::new();
+    restored.export(&mut again).unwrap();
+    assert_eq!(again.len(), snapshot.len());
+  }
+
+  #[test]
+  fn free_blocks_are_not_exported() {
+    let (mut allocator, ptrs) = sample_heap();
+    unsafe { allocator.deallocate(ptrs[1]) };
+
+    let mut snapshot = Vec::new();
+    allocator.export(&mut snapshot).unwrap();
+    assert_eq!(u64::from_le_bytes(snapshot[8..16].try_into().unwrap()), 2);
+
+    let restored = BumpAllocator::import(&mut snapshot.as_slice()).unwrap();
+    assert_eq!(restored.stats().live_blocks, 2);
+    assert_eq!(restored.stats().bytes_in_use, 5 + 32);
+  }
+
+  #[test]
+  fn malformed_snapshots_are_rejected() {
+    let (allocator, _) = sample_heap();
+    let mut snapshot = Vec::new();
+    allocator.export(&mut snapshot).unwrap();
+
+    let mut bad_magic = snapshot.clone();
+    bad_magic[0] = b'X';
+    let mut bad_version = snapshot.clone();
+    bad_version[4] = 3;
+
+    for bad in [bad_magic, bad_version] {
+      let error = BumpAllocator::import(&mut bad.as_slice()).err().unwrap();
+      assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+    assert!(BumpAllocator::import(&mut &snapshot[..snapshot.len() - 1]).is_err());
+  }
+
+  #[test]
+  fn seed_travels_with_the_snapshot() {
+    // Builds a jittered heap, as a test replaying a bug report would. The
+    // recorded alignments depend on the addresses, so both heaps use the
+    // same page-aligned region; the payloads are never written and export
+    // what it held, so it is zeroed for every build - the replay runs on
+    // the bytes the first build left behind
+    let layout = Layout::from_size_align(16 * 1024, MAX_SNAPSHOT_ALIGN).unwrap();
+    let region = unsafe { std::alloc::alloc(layout) };
+    assert!(!region.is_null());
+    let build = |seed| {
+      unsafe { region.write_bytes(0, layout.size()) };
+      let mut allocator = unsafe { BumpAllocator::from_raw_region(region, layout.size()) };
+      allocator.set_random_seed(seed);
+      allocator.set_jitter(256);
+      for size in 1..=8 {
+        unsafe { allocator.allocate(Layout::array::<u64>(size).unwrap()) };
+      }
+      let mut snapshot = Vec::new();
+      allocator.export(&mut snapshot).unwrap();
+      snapshot
+    };
+
+    let observed = build(0x5eed);
+    let restored = BumpAllocator::import(&mut observed.as_slice()).unwrap();
+    assert_eq!(restored.random_seed(), 0x5eed);
+    let replayed = build(restored.random_seed());
+    unsafe { std::alloc::dealloc(region, layout) };
+
+    // The recorded seed reproduces the layout, offsets included
+    assert_eq!(replayed, observed);
+  }
+
+  #[test]
+  fn version_1_snapshots_still_import() {
+    let (allocator, _) = sample_heap();
+    let mut snapshot = Vec::new();
+    allocator.export(&mut snapshot).unwrap();
+
+    // Version 1 is version 2 without the seed
+    snapshot[4] = 1;
+    snapshot.drain(24..32);
+    let restored = BumpAllocator::import(&mut snapshot.as_slice()).unwrap();
+    assert_eq!(restored.stats().live_blocks, 3);
+    assert_eq!(restored.random_seed(), crate::bump::DEFAULT_RANDOM_SEED);
+  }
+}
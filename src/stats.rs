@@ -0,0 +1,138 @@
+//! Allocator statistics.
+//!
+//! A [`Stats`] value is a snapshot of the block list taken by
+//! [`BumpAllocator::stats`](crate::BumpAllocator::stats):
+//!
+//! ```text
+//!   [A: 64, used] ──► [B: 128, free] ──► [C: 32, used]
+//!
+//!   live_blocks  = 2        bytes_in_use = 64 + 32 = 96
+//!   free_blocks  = 1        bytes_free   = 128
+//!   heap_bytes   = bytes obtained from the backend (headers and padding included)
+//!   header_bytes = 3 × header size
+//!   padding_bytes = heap_bytes - header_bytes - 96 - 128
+//!   headroom     = how much more the backend can provide, at most
+//!   cached_bytes = freed or surplus memory kept above the heap for reuse
+//!   untracked_bytes = bumped past the block cap, freed only by a reset
+//! ```
+//!
+//! `Stats` displays as a one-line summary with humanized sizes:
+//!
+//! ```text
+//!   2 live (96 B) · 1 free (128 B) · heap 352 B
+//!   1200 live (1.8 MiB) · 37 free (12.5 KiB) · heap 1.9 MiB
+//! ```
+//!
+//! ## Overhead
+//!
+//! Every heap byte is a user byte (live or free payload), a header byte, or
+//! a padding byte:
+//!
+//! ```text
+//!   ┌────────┬─────┬────────────────┬────────┬──────────────┐
+//!   │ header │ pad │ payload        │ header │ payload      │ ...
+//!   └────────┴─────┴────────────────┴────────┴──────────────┘
+//!     header   padding  user           header   user
+//! ```
+//!
+//! Padding covers alignment, the room kept for free-list links in payloads
+//! smaller than two pointers, and jitter gaps.
+//! [`overhead_ratio`](Stats::overhead_ratio) is the share of the heap that
+//! is not user bytes - the number to compare when trying a different
+//! header layout or growth chunk.
+
+use core::fmt;
+
+use crate::ByteSize;
+
+/// Snapshot of an allocator's block list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Stats {
+  /// Number of blocks currently handed out to the user.
+  pub live_blocks: usize,
+
+  /// Number of blocks marked free but still in the list.
+  pub free_blocks: usize,
+
+  /// Sum of the requested sizes of all live blocks.
+  pub bytes_in_use: usize,
+
+  /// Sum of the payload sizes of all free blocks.
+  pub bytes_free: usize,
+
+  /// Bytes currently obtained from the backend, including headers and
+  /// alignment padding.
+  pub heap_bytes: usize,
+
+  /// Bytes taken by block headers, one per block.
+  pub header_bytes: usize,
+
+  /// Heap bytes that are neither headers nor payload: alignment padding,
+  /// minimum payload rounding and jitter gaps around live blocks. A freed
+  /// block takes the padding after it into `bytes_free`.
+  pub padding_bytes: usize,
+
+  /// `RLIMIT_DATA`, if the backend is the program break and the limit is
+  /// finite.
+  pub os_limit: Option<usize>,
+
+  /// Upper bound on the bytes the backend can still provide, if bounded.
+  pub headroom: Option<usize>,
+
+  /// Bytes obtained from the system but not part of the heap: growth
+  /// chunk surplus and freed tail memory kept by the
+  /// [`ShrinkPolicy`](crate::ShrinkPolicy). Reused before the break moves
+  /// again.
+  pub cached_bytes: usize,
+
+  /// Heap bytes bumped past the block cap without a header, see
+  /// [`BumpAllocator::set_max_blocks`](crate::BumpAllocator::set_max_blocks).
+  /// Live until the next reset.
+  pub untracked_bytes: usize,
+
+  /// Foreign regions registered with
+  /// [`BumpAllocator::adopt`](crate::BumpAllocator::adopt). Not part of the
+  /// heap, nor of the block counts above.
+  pub adopted_blocks: usize,
+
+  /// Bytes of the adopted regions.
+  pub adopted_bytes: usize,
+}
+
+impl fmt::Display for Stats {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    write!(
+      f,
+      "{} live ({}) · {} free ({}) · heap {}",
+      self.live_blocks,
+      ByteSize(self.bytes_in_use),
+      self.free_blocks,
+      ByteSize(self.bytes_free),
+      ByteSize(self.heap_bytes)
+    )
+  }
+}
+
+impl Stats {
+  /// Total number of blocks in the list, live or free.
+  pub fn total_blocks(&self) -> usize {
+    self.live_blocks + self.free_blocks
+  }
+
+  /// Headers and padding together.
+  pub fn overhead_bytes(&self) -> usize {
+    self.header_bytes + self.padding_bytes
+  }
+
+  /// Share of the heap spent on headers and padding, from `0.0` to `1.0`;
+  /// `0.0` for an empty heap.
+  pub fn overhead_ratio(&self) -> f64 {
+    if self.heap_bytes == 0 {
+      return 0.0;
+    }
+    self.overhead_bytes() as f64 / self.heap_bytes as f64
+  }
+}
@@ -0,0 +1,280 @@
+//! # Sub-Arenas
+//!
+//! Hierarchical memory budgeting: a [`SubArena`] is a child allocator that
+//! lives inside a single block reserved from its parent.
+//!
+//! ```text
+//!   parent BumpAllocator
+//!   ┌────────┬────────┬───────────────────────────────────┬────────┐
+//!   │ blk A  │ blk B  │ reservation (one parent block)    │ blk C  │
+//!   └────────┴────────┴───────────────────────────────────┴────────┘
+//!                      │                                   │
+//!                      ▼ child region                      ▼
+//!                      ┌───────┬───────┬───────────────────┐
+//!                      │ c1    │ c2    │   available       │
+//!                      └───────┴───────┴───────────────────┘
+//!                        SubArena: own block list, stats and reset
+//! ```
+//!
+//! The reservation size is the child's hard quota: once it is used up the
+//! child returns `null`, no matter how much memory the parent has left.
+//! Dropping the child deallocates the reservation in the parent.
+//!
+//! A [`SubArena`] dereferences to its [`BumpAllocator`] for queries, and
+//! forwards allocation, freeing, resets and nesting. It never lends the
+//! allocator out mutably: swapping another one in would hand the parent a
+//! reservation that was never its own. Sub-arenas nest:
+//!
+//! ```rust,ignore
+//! let mut root = BumpAllocator::new();
+//! let mut request = root.sub_arena(64 * 1024).unwrap();
+//! let mut parser = request.sub_arena(4 * 1024).unwrap();
+//! ```
+
+use core::{alloc::Layout, ops::Deref};
+
+use crate::BumpAllocator;
+
+/// Alignment of a sub-arena's reservation inside its parent.
+const RESERVATION_ALIGN: usize = 16;
+
+/// A child allocator carved out of a parent [`BumpAllocator`].
+///
+/// Created by [`BumpAllocator::sub_arena`]. Holds the parent mutably
+/// borrowed, so the parent cannot be used (or reset) while the child is alive.
+pub struct SubArena<'p> {
+  /// The child allocator, bumping through `reservation`.
+  arena: BumpAllocator,
+
+  /// The block reserved from the parent.
+  reservation: *mut u8,
+
+  /// Size of `reservation` in bytes.
+  capacity: usize,
+
+  /// The allocator the reservation is returned to on drop.
+  parent: &'p mut BumpAllocator,
+}
+
+impl BumpAllocator {
+  /// Reserves `capacity` bytes from this allocator and returns a child
+  /// allocator that allocates only from them.
+  ///
+  /// The child inherits this allocator's search mode, keeps its own block
+  /// list, stats and [`reset`](BumpAllocator::reset), and gives the
+  /// reservation back when dropped.
+  ///
+  /// # Returns
+  ///
+  /// `None` if this allocator cannot provide `capacity` bytes.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// let mut parent = BumpAllocator::new();
+  /// {
+  ///     let mut child = parent.sub_arena(4096).unwrap();
+  ///     let ptr = unsafe { child.allocate(Layout::new::<u64>()) };
+  ///     assert_eq!(child.stats().live_blocks, 1);
+  /// } // reservation returned to `parent` here
+  /// ```
+  pub fn sub_arena(
+    &mut self,
+    capacity: usize,
+  ) -> Option<SubArena<'_>> {
+    let layout = Layout::from_size_align(capacity, RESERVATION_ALIGN).ok()?;
+
+    // SAFETY: `&mut self` gives the exclusive access `allocate` requires.
+    let reservation = unsafe { self.allocate(layout) };
+    if reservation.is_null() {
+      return None;
+    }
+
+    // SAFETY: The reservation is a live block of `capacity` bytes that only
+    // the child uses, and it outlives the child (released in `Drop`).
+    let mut arena = unsafe { BumpAllocator::from_raw_region(reservation, capacity) };
+    arena.set_search_mode(self.search_mode());
+
+    Some(SubArena {
+      arena,
+      reservation,
+      capacity,
+      parent: self,
+    })
+  }
+}
+
+impl SubArena<'_> {
+  /// Size of the reservation taken from the parent - the child's quota.
+  pub fn quota(&self) -> usize {
+    self.capacity
+  }
+
+  /// Bytes of the quota not yet handed out by the child's backend.
+  pub fn remaining(&self) -> usize {
+    self.capacity - self.arena.stats().heap_bytes
+  }
+
+  /// Read-only access to the parent allocator.
+  pub fn parent(&self) -> &BumpAllocator {
+    self.parent
+  }
+
+  /// Allocates from the reservation, see [`BumpAllocator::allocate`].
+  ///
+  /// # Safety
+  ///
+  /// As [`BumpAllocator::allocate`].
+  pub unsafe fn allocate(
+    &mut self,
+    layout: Layout,
+  ) -> *mut u8 {
+    // SAFETY: As the caller's contract.
+    unsafe { self.arena.allocate(layout) }
+  }
+
+  /// Frees `address`, see [`BumpAllocator::deallocate`].
+  ///
+  /// # Safety
+  ///
+  /// As [`BumpAllocator::deallocate`].
+  pub unsafe fn deallocate(
+    &mut self,
+    address: *mut u8,
+  ) {
+    // SAFETY: As the caller's contract.
+    unsafe { self.arena.deallocate(address) }
+  }
+
+  /// Frees `address` allocated with `layout`, see
+  /// [`BumpAllocator::deallocate_with_layout`].
+  ///
+  /// # Safety
+  ///
+  /// As [`BumpAllocator::deallocate_with_layout`].
+  pub unsafe fn deallocate_with_layout(
+    &mut self,
+    address: *mut u8,
+    layout: Layout,
+  ) {
+    // SAFETY: As the caller's contract.
+    unsafe { self.arena.deallocate_with_layout(address, layout) }
+  }
+
+  /// Frees every block, see [`BumpAllocator::reset`].
+  ///
+  /// # Safety
+  ///
+  /// As [`BumpAllocator::reset`].
+  pub unsafe fn reset(&mut self) {
+    // SAFETY: As the caller's contract.
+    unsafe { self.arena.reset() }
+  }
+
+  /// Reserves a nested sub-arena, see [`BumpAllocator::sub_arena`].
+  pub fn sub_arena(
+    &mut self,
+    capacity: usize,
+  ) -> Option<SubArena<'_>> {
+    self.arena.sub_arena(capacity)
+  }
+}
+
+impl Deref for SubArena<'_> {
+  type Target = BumpAllocator;
+
+  fn deref(&self) -> &BumpAllocator {
+    &self.arena
+  }
+}
+
+impl Drop for SubArena<'_> {
+  fn drop(&mut self) {
+    // SAFETY: `reservation` came from `parent.allocate` and is released once.
+    unsafe { self.parent.deallocate(self.reservation) };
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn child_allocates_inside_the_reservation() {
+    let mut parent = BumpAllocator::with_capacity(64 * 1024);
+    let mut child = parent.sub_arena(1024).unwrap();
+    let start = child.reservation as usize;
+
+    unsafe {
+      for _ in 0..8 {
+        let ptr = child.allocate(Layout::new::<u64>()) as usize;
+        assert!(ptr >= start && ptr < start + 1024);
+      }
+    }
+    assert_eq!(child.quota(), 1024);
+    assert_eq!(child.stats().live_blocks, 8);
+    assert_eq!(child.parent().stats().live_blocks, 1);
+  }
+
+  #[test]
+  fn child_quota_is_enforced() {
+    let mut parent = BumpAllocator::with_capacity(64 * 1024);
+    let mut child = parent.sub_arena(256).unwrap();
+
+    unsafe {
+      assert!(child.allocate(Layout::array::<u8>(512).unwrap()).is_null());
+      assert!(!child.allocate(Layout::array::<u8>(64).unwrap()).is_null());
+    }
+    assert!(child.remaining() < 256);
+  }
+
+  #[test]
+  fn child_reset_recycles_the_quota() {
+    let mut parent = BumpAllocator::with_capacity(64 * 1024);
+    let mut child = parent.sub_arena(512).unwrap();
+
+    unsafe {
+      let first = child.allocate(Layout::array::<u8>(200).unwrap());
+      assert!(child.allocate(Layout::array::<u8>(400).unwrap()).is_null());
+
+      child.reset();
+      assert_eq!(child.remaining(), 512);
+      assert_eq!(child.allocate(Layout::array::<u8>(200).unwrap()), first);
+    }
+  }
+
+  #[test]
+  fn dropping_child_returns_memory_to_parent() {
+    let mut parent = BumpAllocator::with_capacity(64 * 1024);
+    unsafe { parent.allocate(Layout::new::<u64>()) };
+
+    {
+      let child = parent.sub_arena(4096).unwrap();
+      assert_eq!(child.parent().stats().live_blocks, 2);
+    }
+
+    // The reservation was the tail block, so it is popped entirely
+    let stats = parent.stats();
+    assert_eq!(stats.live_blocks, 1);
+    assert_eq!(stats.free_blocks, 0);
+  }
+
+  #[test]
+  fn sub_arenas_nest() {
+    let mut root = BumpAllocator::with_capacity(64 * 1024);
+    let mut request = root.sub_arena(8 * 1024).unwrap();
+    let mut parser = request.sub_arena(1024).unwrap();
+
+    unsafe {
+      assert!(!parser.allocate(Layout::new::<u128>()).is_null());
+      assert!(parser.sub_arena(4096).is_none());
+    }
+    assert_eq!(parser.parent().stats().live_blocks, 1);
+  }
+
+  #[test]
+  fn sub_arena_fails_when_parent_is_exhausted() {
+    let mut parent = BumpAllocator::with_capacity(1024);
+    assert!(parent.sub_arena(4096).is_none());
+  }
+}
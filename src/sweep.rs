@@ -0,0 +1,177 @@
+//! # Sweeping Unreachable Blocks
+//!
+//! Test teardown and plugin unload paths often know which pointers they
+//! still hold, but not every block that was allocated on the way there.
+//! [`BumpAllocator::free_all_unreachable`] takes that live set and frees
+//! every other live block - the sweep half of a mark-and-sweep collector,
+//! with the caller doing the marking:
+//!
+//! ```text
+//!   live = [ B, D+8 ]
+//!
+//!   before:  [A][B][C][D      ][E]
+//!   marked:      ▲     ▲  (D+8 is inside D)
+//!   after:   [ ─ ][B][ ─ ][D      ]          (A, C freed; E popped)
+//! ```
+//!
+//! The sweep checks before it frees. The block list must pass
+//! [`check_invariants`](BumpAllocator::check_invariants), and every
+//! pointer in the live set must fall inside a live block of this
+//! allocator; interior pointers keep their block. If anything is off,
+//! nothing is freed and the [`SweepError`] says what.
+//!
+//! Marking needs no heap: each block is tested against the whole live set,
+//! so a sweep costs O(blocks · live pointers). Blocks of the emergency
+//! reserve are never swept, but pointers into it are accepted.
+
+use core::fmt;
+
+use crate::{BumpAllocator, Corruption, block::HEADER_SIZE};
+
+/// Why [`free_all_unreachable`](BumpAllocator::free_all_unreachable)
+/// refused to sweep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SweepError {
+  /// The block list failed its invariant checks.
+  Corrupt(Corruption),
+
+  /// A pointer of the live set is not inside any block of this allocator.
+  ForeignPointer {
+    /// The pointer.
+    address: usize,
+  },
+
+  /// A pointer of the live set is inside a block that is already free.
+  FreedPointer {
+    /// The pointer.
+    address: usize,
+  },
+}
+
+impl fmt::Display for SweepError {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    match *self {
+      SweepError::Corrupt(corruption) => write!(f, "heap corruption: {corruption}"),
+      SweepError::ForeignPointer { address } => write!(f, "live pointer {address:#x} is not from this allocator"),
+      SweepError::FreedPointer { address } => write!(f, "live pointer {address:#x} points into a freed block"),
+    }
+  }
+}
+
+impl BumpAllocator {
+  /// Frees every live block that no pointer in `live` points into, after
+  /// checking the heap and the live set. Returns the number of blocks
+  /// freed.
+  ///
+  /// Null pointers in `live` are ignored.
+  ///
+  /// # Errors
+  ///
+  /// A [`SweepError`] if the block list is corrupt or a live pointer is
+  /// foreign or dangling; nothing is freed then.
+  ///
+  /// # Safety
+  ///
+  /// No pointer into the freed blocks may be used afterwards: every block
+  /// still in use must be reachable from `live`.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// // Plugin unload: keep the host's objects, drop everything else
+  /// let freed = unsafe { allocator.free_all_unreachable(&host_objects)? };
+  /// ```
+  pub unsafe fn free_all_unreachable(
+    &mut self,
+    live: &[*mut u8],
+  ) -> Result<usize, SweepError> {
+    self.check_invariants().map_err(SweepError::Corrupt)?;
+    for &ptr in live.iter().filter(|ptr| !ptr.is_null()) {
+      let address = ptr as usize;
+      match self.allocation_info(ptr) {
+        None => return Err(SweepError::ForeignPointer { address }),
+        Some(info) if info.is_free => return Err(SweepError::FreedPointer { address }),
+        Some(_) => {},
+      }
+    }
+
+    let mut freed = 0;
+    // Backwards, as in `free_epoch`, so popping the tail never skips a block
+    let mut current = self.last_block();
+    while !current.is_null() {
+      // SAFETY: `current` is a block of the list; freeing it leaves its
+      // predecessor in place.
+      unsafe {
+        let prev = (*current).prev;
+        let payload = current as usize + HEADER_SIZE;
+        // A zero-sized block still owns its start address
+        let end = payload + (*current).size.max(1);
+        let reachable = live.iter().any(|&ptr| (payload..end).contains(&(ptr as usize)));
+        if !(*current).is_free && !reachable {
+          self.deallocate(payload as *mut u8);
+          freed += 1;
+        }
+        current = prev;
+      }
+    }
+    Ok(freed)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::alloc::Layout;
+
+  #[test]
+  fn unreachable_blocks_are_freed() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let [a, b, c, d, e] = [8, 16, 8, 32, 8].map(|size| unsafe { allocator.allocate(Layout::array::<u8>(size).unwrap()) });
+
+    let freed = unsafe { allocator.free_all_unreachable(&[b, d.wrapping_add(8), core::ptr::null_mut()]) };
+    assert_eq!(freed, Ok(3));
+    assert_eq!(allocator.live_blocks(), 2);
+    for (ptr, live) in [(a, false), (b, true), (c, false), (d, true)] {
+      assert_eq!(allocator.allocation_info(ptr).unwrap().is_free, !live);
+    }
+    assert!(!allocator.owns(e));
+    allocator.assert_invariants();
+
+    // Nothing left to sweep
+    assert_eq!(unsafe { allocator.free_all_unreachable(&[b, d]) }, Ok(0));
+  }
+
+  #[test]
+  fn bad_live_sets_free_nothing() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let a = unsafe { allocator.allocate(Layout::new::<u64>()) };
+    let b = unsafe { allocator.allocate(Layout::new::<u64>()) };
+    unsafe { allocator.deallocate(a) };
+
+    let mut local = 0u8;
+    let foreign = &mut local as *mut u8;
+    assert_eq!(
+      unsafe { allocator.free_all_unreachable(&[foreign]) },
+      Err(SweepError::ForeignPointer { address: foreign as usize })
+    );
+    assert_eq!(
+      unsafe { allocator.free_all_unreachable(&[a]) },
+      Err(SweepError::FreedPointer { address: a as usize })
+    );
+    assert!(allocator.owns(b));
+    assert_eq!(allocator.live_blocks(), 1);
+  }
+
+  #[test]
+  fn an_empty_live_set_frees_everything() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    for _ in 0..5 {
+      unsafe { allocator.allocate(Layout::new::<u64>()) };
+    }
+    assert_eq!(unsafe { allocator.free_all_unreachable(&[]) }, Ok(5));
+    assert_eq!(allocator.live_blocks(), 0);
+  }
+}
@@ -0,0 +1,386 @@
+//! # Thread-Safety Wrappers
+//!
+//! [`BumpAllocator`] is neither `Send` nor `Sync`: it stores raw pointers
+//! into its heap, and its methods assume exclusive, single-threaded
+//! access. The wrappers below restore each auto trait where it is sound,
+//! and spell out why in their types:
+//!
+//! ```text
+//!   ┌───────────────────┬──────┬──────┬─────────────────────────────────────┐
+//!   │ Type              │ Send │ Sync │ Why it is sound                     │
+//!   ├───────────────────┼──────┼──────┼─────────────────────────────────────┤
+//!   │ BumpAllocator     │  no  │  no  │ (baseline)                          │
+//!   │ SendableArena     │ yes  │  no  │ memory not tied to a thread, and    │
+//!   │                   │      │      │ one owner at a time                 │
+//!   │ SharedArena       │ yes  │ yes  │ every access holds a mutex (std)    │
+//!   │ SharedBump-       │ yes  │ yes  │ Arc<SharedArena>: cloned handles    │
+//!   │   Allocator       │      │      │ all lock the same mutex (std)       │
+//!   │ CriticalSection-  │  -   │ yes  │ every access holds a critical       │
+//!   │   Allocator       │      │      │ section                             │
+//!   └───────────────────┴──────┴──────┴─────────────────────────────────────┘
+//! ```
+//!
+//! Without a wrapper, moving an allocator to another thread is a compile
+//! error:
+//!
+//! ```compile_fail
+//! fn assert_send<T: Send>() {}
+//! assert_send::<rallocator::BumpAllocator>();
+//! ```
+//!
+//! ```compile_fail
+//! fn assert_sync<T: Sync>() {}
+//! assert_sync::<rallocator::BumpAllocator>();
+//! ```
+//!
+//! With the `thread-check` feature, both wrappers mark their allocator as
+//! shared: the type system already rules out concurrent use.
+
+use core::{alloc::Layout, ops::Deref};
+
+use crate::BumpAllocator;
+
+/// A [`BumpAllocator`] that can be moved to another thread.
+///
+/// Its memory comes from a region that does not belong to any thread (the
+/// system allocator, or a `'static` buffer), and ownership - not sharing -
+/// is what crosses the thread boundary, so only one thread can use the
+/// arena at a time. It is `Send` but not `Sync`.
+///
+/// Dereferences to the [`BumpAllocator`] for queries; allocation goes
+/// through the methods below. It never lends the allocator out mutably:
+/// swapping another one in would skip the contract of
+/// [`from_allocator`](Self::from_allocator).
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let mut arena = SendableArena::with_capacity(64 * 1024);
+/// std::thread::spawn(move || {
+///     let ptr = unsafe { arena.allocate(Layout::new::<u64>()) };
+/// });
+/// ```
+pub struct SendableArena {
+  /// The wrapped allocator.
+  inner: BumpAllocator,
+}
+
+// SAFETY: The allocator's pointers target a region no thread owns, and
+// `SendableArena` is not `Sync`, so after a move only the new thread can
+// reach the allocator.
+unsafe impl Send for SendableArena {}
+
+impl SendableArena {
+  /// Creates an arena backed by a `capacity`-byte region from the system
+  /// allocator.
+  #[cfg(feature = "std")]
+  pub fn with_capacity(capacity: usize) -> Self {
+    Self::wrap(BumpAllocator::with_capacity(capacity))
+  }
+
+  /// Creates an arena over `buffer`.
+  pub fn from_buffer(buffer: &'static mut [u8]) -> Self {
+    Self::wrap(BumpAllocator::from_buffer(buffer))
+  }
+
+  /// Wraps an existing allocator.
+  ///
+  /// # Safety
+  ///
+  /// The allocator's memory must be usable from any thread - e.g. not
+  /// thread-local storage - and no other thread may keep using pointers
+  /// into it in a way that races with the new owner.
+  pub unsafe fn from_allocator(allocator: BumpAllocator) -> Self {
+    Self::wrap(allocator)
+  }
+
+  /// Unwraps the arena, returning the underlying allocator.
+  pub fn into_inner(self) -> BumpAllocator {
+    self.inner
+  }
+
+  /// Common constructor: marks the allocator shared for `thread-check`.
+  fn wrap(allocator: BumpAllocator) -> Self {
+    #[cfg(feature = "thread-check")]
+    let allocator = allocator.into_shared();
+    Self { inner: allocator }
+  }
+
+  /// Allocates from the arena, see [`BumpAllocator::allocate`].
+  ///
+  /// # Safety
+  ///
+  /// As [`BumpAllocator::allocate`].
+  pub unsafe fn allocate(
+    &mut self,
+    layout: Layout,
+  ) -> *mut u8 {
+    // SAFETY: As the caller's contract.
+    unsafe { self.inner.allocate(layout) }
+  }
+
+  /// Frees `address`, see [`BumpAllocator::deallocate`].
+  ///
+  /// # Safety
+  ///
+  /// As [`BumpAllocator::deallocate`].
+  pub unsafe fn deallocate(
+    &mut self,
+    address: *mut u8,
+  ) {
+    // SAFETY: As the caller's contract.
+    unsafe { self.inner.deallocate(address) }
+  }
+
+  /// Frees `address` allocated with `layout`, see
+  /// [`BumpAllocator::deallocate_with_layout`].
+  ///
+  /// # Safety
+  ///
+  /// As [`BumpAllocator::deallocate_with_layout`].
+  pub unsafe fn deallocate_with_layout(
+    &mut self,
+    address: *mut u8,
+    layout: Layout,
+  ) {
+    // SAFETY: As the caller's contract.
+    unsafe { self.inner.deallocate_with_layout(address, layout) }
+  }
+
+  /// Frees every block, see [`BumpAllocator::reset`].
+  ///
+  /// # Safety
+  ///
+  /// As [`BumpAllocator::reset`].
+  pub unsafe fn reset(&mut self) {
+    // SAFETY: As the caller's contract.
+    unsafe { self.inner.reset() }
+  }
+}
+
+impl Deref for SendableArena {
+  type Target = BumpAllocator;
+
+  fn deref(&self) -> &BumpAllocator {
+    &self.inner
+  }
+}
+
+/// A [`BumpAllocator`] behind a mutex, usable from many threads at once.
+///
+/// Every access goes through [`SharedArena::with`], which holds the lock
+/// for the duration of the closure. A panic inside the closure does not
+/// poison the arena: allocator operations keep the block list consistent
+/// across unwinding, so the next caller simply proceeds.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// static ARENA: LazyLock<SharedArena> = LazyLock::new(|| SharedArena::with_capacity(1 << 20));
+///
+/// let ptr = ARENA.with(|allocator| unsafe { allocator.allocate(layout) });
+/// ```
+#[cfg(feature = "std")]
+pub struct SharedArena {
+  /// The wrapped allocator. Only reached with the lock held.
+  inner: std::sync::Mutex<SendableArena>,
+}
+
+#[cfg(feature = "std")]
+impl SharedArena {
+  /// Creates an arena backed by a `capacity`-byte region from the system
+  /// allocator.
+  pub fn with_capacity(capacity: usize) -> Self {
+    Self::new(SendableArena::with_capacity(capacity))
+  }
+
+  /// Shares an arena between threads.
+  pub const fn new(arena: SendableArena) -> Self {
+    Self {
+      inner: std::sync::Mutex::new(arena),
+    }
+  }
+
+  /// Runs `f` with exclusive access to the wrapped allocator.
+  pub fn with<R>(
+    &self,
+    f: impl FnOnce(&mut BumpAllocator) -> R,
+  ) -> R {
+    let mut arena = self.inner.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+    f(&mut arena.inner)
+  }
+
+  /// Unwraps the arena, returning the underlying allocator.
+  pub fn into_inner(self) -> BumpAllocator {
+    self.inner.into_inner().unwrap_or_else(std::sync::PoisonError::into_inner).into_inner()
+  }
+}
+
+/// A cloneable handle to a [`SharedArena`].
+///
+/// Every clone allocates from the same arena, so several owners - data
+/// structures, threads, subsystems - can share it without borrowing from
+/// a common parent. The arena is dropped with its last handle.
+///
+/// ```text
+///   SharedBumpAllocator ──┐
+///   SharedBumpAllocator ──┼──► Arc ──► SharedArena ──► Mutex<BumpAllocator>
+///   SharedBumpAllocator ──┘
+/// ```
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let arena = SharedBumpAllocator::with_capacity(1 << 20);
+/// let for_worker = arena.clone();
+/// std::thread::spawn(move || for_worker.with(|a| unsafe { a.allocate(layout) }));
+/// ```
+#[cfg(feature = "std")]
+#[derive(Clone)]
+pub struct SharedBumpAllocator {
+  /// The shared arena.
+  pub(crate) arena: std::sync::Arc<SharedArena>,
+}
+
+#[cfg(feature = "std")]
+impl SharedBumpAllocator {
+  /// Creates a handle to a new arena backed by a `capacity`-byte region
+  /// from the system allocator.
+  pub fn with_capacity(capacity: usize) -> Self {
+    Self::new(SendableArena::with_capacity(capacity))
+  }
+
+  /// Creates a handle to a new shared arena.
+  pub fn new(arena: SendableArena) -> Self {
+    Self {
+      arena: std::sync::Arc::new(SharedArena::new(arena)),
+    }
+  }
+
+  /// Runs `f` with exclusive access to the shared allocator.
+  pub fn with<R>(
+    &self,
+    f: impl FnOnce(&mut BumpAllocator) -> R,
+  ) -> R {
+    self.arena.with(f)
+  }
+
+  /// Number of handles to this arena, including `self`.
+  pub fn handle_count(&self) -> usize {
+    std::sync::Arc::strong_count(&self.arena)
+  }
+
+  /// Whether both handles refer to the same arena.
+  pub fn ptr_eq(
+    &self,
+    other: &Self,
+  ) -> bool {
+    std::sync::Arc::ptr_eq(&self.arena, &other.arena)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::alloc::Layout;
+  use std::{sync::Arc, thread};
+
+  fn assert_send<T: Send>() {}
+  fn assert_sync<T: Sync>() {}
+
+  #[test]
+  fn wrappers_have_the_documented_auto_traits() {
+    assert_send::<SendableArena>();
+    assert_send::<SharedArena>();
+    assert_sync::<SharedArena>();
+    assert_send::<SharedBumpAllocator>();
+    assert_sync::<SharedBumpAllocator>();
+  }
+
+  #[test]
+  fn sendable_arena_moves_between_threads() {
+    let mut arena = SendableArena::with_capacity(4096);
+    unsafe { arena.allocate(Layout::new::<u64>()) };
+
+    let arena = thread::spawn(move || {
+      unsafe { arena.allocate(Layout::new::<u64>()) };
+      arena
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(arena.stats().live_blocks, 2);
+  }
+
+  #[test]
+  fn sendable_arena_forwards_frees_and_resets() {
+    let mut arena = SendableArena::with_capacity(4096);
+    let layout = Layout::new::<u64>();
+
+    unsafe {
+      let a = arena.allocate(layout);
+      let b = arena.allocate(layout);
+      arena.allocate(layout);
+      arena.deallocate(a);
+      arena.deallocate_with_layout(b, layout);
+      assert_eq!(arena.stats().live_blocks, 1);
+      arena.reset();
+    }
+    assert!(arena.is_empty());
+  }
+
+  #[test]
+  fn shared_arena_serves_many_threads() {
+    let arena = Arc::new(SharedArena::with_capacity(64 * 1024));
+
+    let workers: Vec<_> = (0..4)
+      .map(|_| {
+        let arena = Arc::clone(&arena);
+        thread::spawn(move || {
+          for _ in 0..25 {
+            let ptr = arena.with(|allocator| unsafe { allocator.allocate(Layout::new::<u64>()) });
+            assert!(!ptr.is_null());
+          }
+        })
+      })
+      .collect();
+    for worker in workers {
+      worker.join().unwrap();
+    }
+
+    assert_eq!(arena.with(|allocator| allocator.stats().live_blocks), 100);
+    assert_eq!(arena.with(|allocator| allocator.check_invariants()), Ok(()));
+  }
+
+  #[test]
+  fn panic_inside_with_does_not_poison_the_arena() {
+    let arena = SharedArena::with_capacity(1024);
+
+    let result = std::panic::catch_unwind(|| arena.with(|_| panic!("boom")));
+    assert!(result.is_err());
+
+    let ptr = arena.with(|allocator| unsafe { allocator.allocate(Layout::new::<u64>()) });
+    assert!(!ptr.is_null());
+  }
+
+  #[test]
+  fn cloned_handles_share_one_arena() {
+    let first = SharedBumpAllocator::with_capacity(4096);
+    let second = first.clone();
+    assert!(first.ptr_eq(&second));
+    assert!(!first.ptr_eq(&SharedBumpAllocator::with_capacity(64)));
+    assert_eq!(first.handle_count(), 2);
+
+    second.with(|allocator| unsafe { allocator.allocate(Layout::new::<u64>()) });
+    assert_eq!(first.with(|allocator| allocator.stats().live_blocks), 1);
+
+    let worker = first.clone();
+    thread::spawn(move || {
+      worker.with(|allocator| unsafe { allocator.allocate(Layout::new::<u32>()) });
+    })
+    .join()
+    .unwrap();
+    assert_eq!(second.with(|allocator| allocator.stats().live_blocks), 2);
+    assert_eq!(first.handle_count(), 2);
+  }
+}
@@ -0,0 +1,160 @@
+//! # Test Assertions
+//!
+//! One-line checks that make allocation behaviour part of a test suite,
+//! built on [`BumpAllocator::live_blocks`], the
+//! [`top_allocations`](BumpAllocator::top_allocations) report and, with the
+//! `counters` feature, [`stats_scope`](BumpAllocator::stats_scope):
+//!
+//! ```text
+//!   assert_no_leaks!(allocator);                       nothing is live
+//!   assert_no_leaks!(allocator, |a| parse(a, input));  parse frees what it takes
+//!   assert_allocates_at_most!(allocator, 4096, |a| parse(a, input));
+//! ```
+//!
+//! The closure forms return the closure's result. Failures panic at the
+//! assertion with the largest live blocks, or the scope's report, in the
+//! message. The macros forward to the functions below, which can be called
+//! directly as well.
+
+use crate::BumpAllocator;
+#[cfg(feature = "counters")]
+use crate::ScopeReport;
+
+/// Live blocks listed in a leak message.
+const LISTED_LEAKS: usize = 8;
+
+/// Panics if `allocator` has live blocks, listing the largest.
+#[track_caller]
+pub fn assert_empty(allocator: &BumpAllocator) {
+  if allocator.live_blocks() != 0 {
+    panic!("allocator has live blocks:\n{}", allocator.top_allocations::<LISTED_LEAKS>());
+  }
+}
+
+/// Runs `f` and panics if it left more live blocks than it found.
+#[track_caller]
+pub fn assert_no_leaks_in<R>(
+  allocator: &mut BumpAllocator,
+  f: impl FnOnce(&mut BumpAllocator) -> R,
+) -> R {
+  let before = allocator.live_blocks();
+  let result = f(allocator);
+  let after = allocator.live_blocks();
+  if after > before {
+    panic!(
+      "{} blocks leaked ({before} live before, {after} after):\n{}",
+      after - before,
+      allocator.top_allocations::<LISTED_LEAKS>()
+    );
+  }
+  result
+}
+
+/// Runs `f` and panics if it allocated more than `bytes` in total.
+///
+/// Counts every successful allocation, freed or not, the way
+/// [`StatsScope::max_bytes`](crate::StatsScope::max_bytes) does.
+#[cfg(feature = "counters")]
+#[track_caller]
+pub fn assert_allocates_at_most<R>(
+  allocator: &mut BumpAllocator,
+  bytes: usize,
+  f: impl FnOnce(&mut BumpAllocator) -> R,
+) -> (R, ScopeReport) {
+  let mut scope = allocator.stats_scope().max_bytes(bytes);
+  let result = f(&mut scope);
+  (result, scope.finish())
+}
+
+/// Asserts that an allocator has no live blocks or, given a closure, that
+/// the closure leaves none behind.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use rallocator::assert_no_leaks;
+///
+/// let tree = assert_no_leaks!(allocator, |a| build_and_drop_scratch(a));
+/// drop_tree(&mut allocator, tree);
+/// assert_no_leaks!(allocator);
+/// ```
+#[macro_export]
+macro_rules! assert_no_leaks {
+  ($allocator:expr) => {
+    $crate::testing::assert_empty(&$allocator)
+  };
+  ($allocator:expr, $f:expr) => {
+    $crate::testing::assert_no_leaks_in(&mut $allocator, $f)
+  };
+}
+
+/// Asserts that a closure allocates at most a number of bytes, returning
+/// its result. Requires the `counters` feature.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use rallocator::assert_allocates_at_most;
+///
+/// let document = assert_allocates_at_most!(allocator, 1 << 20, |a| parse(a, input));
+/// ```
+#[cfg(feature = "counters")]
+#[macro_export]
+macro_rules! assert_allocates_at_most {
+  ($allocator:expr, $bytes:expr, $f:expr) => {
+    $crate::testing::assert_allocates_at_most(&mut $allocator, $bytes, $f).0
+  };
+}
+
+#[cfg(test)]
+mod tests {
+  use core::alloc::Layout;
+
+  use crate::BumpAllocator;
+
+  #[test]
+  fn balanced_closures_pass() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let value = assert_no_leaks!(allocator, |allocator: &mut BumpAllocator| unsafe {
+      let ptr = allocator.allocate(Layout::new::<u64>());
+      allocator.deallocate(ptr);
+      5
+    });
+    assert_eq!(value, 5);
+    assert_no_leaks!(allocator);
+  }
+
+  #[test]
+  #[should_panic(expected = "1 blocks leaked (0 live before, 1 after)")]
+  fn leaking_closures_panic() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    assert_no_leaks!(allocator, |allocator: &mut BumpAllocator| unsafe {
+      allocator.allocate(Layout::new::<u64>());
+    });
+  }
+
+  #[test]
+  #[should_panic(expected = "allocator has live blocks")]
+  fn live_blocks_fail_the_plain_form() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    unsafe { allocator.allocate(Layout::new::<u64>()) };
+    assert_no_leaks!(allocator);
+  }
+
+  #[cfg(feature = "counters")]
+  #[test]
+  fn allocation_budgets_are_enforced() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let ptr = assert_allocates_at_most!(allocator, 64, |allocator: &mut BumpAllocator| unsafe {
+      allocator.allocate(Layout::array::<u8>(64).unwrap())
+    });
+    assert!(!ptr.is_null());
+
+    let budget = std::panic::catch_unwind(move || {
+      assert_allocates_at_most!(allocator, 64, |allocator: &mut BumpAllocator| unsafe {
+        allocator.allocate(Layout::array::<u8>(65).unwrap());
+      })
+    });
+    assert!(budget.is_err());
+  }
+}
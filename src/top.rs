@@ -0,0 +1,214 @@
+//! # Largest Allocations
+//!
+//! The first question about a full arena is usually "what is eating it?".
+//! [`BumpAllocator::top_allocations`] answers with the `N` biggest live
+//! blocks, largest first:
+//!
+//! ```text
+//!      #  block  address                   size   share
+//!      0      3  0x000055d0c1e2a110       65536   81.2%
+//!      1      0  0x000055d0c1e2a020       12000   14.9%
+//!      2      7  0x000055d0c1e3b5a0        1024    1.3%
+//!   top 3 of 9 live blocks: 76.7 KiB of 78.8 KiB in use
+//! ```
+//!
+//! `N` is a const generic so the report needs no heap of its own and works
+//! without `std`, in the middle of an out-of-memory situation.
+
+use core::fmt;
+
+use crate::{BlockInfo, BumpAllocator, ByteSize, units::address_width};
+
+/// The `N` largest live blocks of an allocator, largest first.
+///
+/// Created by [`BumpAllocator::top_allocations`]. Displays as a table.
+pub struct TopAllocations<const N: usize> {
+  /// The largest blocks; only the first `len` are meaningful.
+  entries: [Option<BlockInfo>; N],
+
+  /// Number of entries filled.
+  len: usize,
+
+  /// Number of live blocks in the allocator.
+  live_blocks: usize,
+
+  /// Bytes requested by all live blocks.
+  bytes_in_use: usize,
+}
+
+impl<const N: usize> TopAllocations<N> {
+  /// The largest blocks, largest first (older first among equal sizes).
+  pub fn iter(&self) -> impl Iterator<Item = &BlockInfo> {
+    self.entries[..self.len].iter().flatten()
+  }
+
+  /// Number of blocks in the report (at most `N`).
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  /// Whether the allocator had no live blocks.
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  /// Bytes requested by all live blocks, for computing shares.
+  pub fn bytes_in_use(&self) -> usize {
+    self.bytes_in_use
+  }
+
+  /// Inserts `info` in size order, dropping the smallest entry when full.
+  fn offer(
+    &mut self,
+    info: BlockInfo,
+  ) {
+    // First slot holding a strictly smaller block: ties keep the older one
+    let position = self.entries[..self.len]
+      .iter()
+      .flatten()
+      .position(|entry| entry.size < info.size)
+      .unwrap_or(self.len);
+    if position == N {
+      return;
+    }
+
+    let last = self.len.min(N - 1);
+    self.entries.copy_within(position..last, position + 1);
+    self.entries[position] = Some(info);
+    self.len = (self.len + 1).min(N);
+  }
+}
+
+impl<const N: usize> fmt::Display for TopAllocations<N> {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    let width = address_width();
+    writeln!(f, "{:>4}  {:>5}  {:<width$}  {:>10}  {:>6}", "#", "block", "address", "size", "share")?;
+
+    for (rank, info) in self.iter().enumerate() {
+      // Tenths of a percent, without floating point
+      let permille = (info.size as u128 * 1000)
+        .checked_div(self.bytes_in_use as u128)
+        .unwrap_or(0);
+      writeln!(
+        f,
+        "{:>4}  {:>5}  {:#0width$x}  {:>10}  {:>3}.{}%",
+        rank,
+        info.index,
+        info.address,
+        info.size,
+        permille / 10,
+        permille % 10
+      )?;
+    }
+
+    let shown: usize = self.iter().map(|info| info.size).sum();
+    write!(
+      f,
+      "top {} of {} live blocks: {} of {} in use",
+      self.len,
+      self.live_blocks,
+      ByteSize(shown),
+      ByteSize(self.bytes_in_use)
+    )
+  }
+}
+
+impl BumpAllocator {
+  /// The `N` largest live blocks, largest first.
+  ///
+  /// O(n · N) in the number of blocks; a diagnostic, allowed in real-time
+  /// mode.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// println!("{}", allocator.top_allocations::<10>());
+  /// ```
+  pub fn top_allocations<const N: usize>(&self) -> TopAllocations<N> {
+    let mut top = TopAllocations {
+      entries: [None; N],
+      len: 0,
+      live_blocks: 0,
+      bytes_in_use: 0,
+    };
+
+    for (index, block) in self.blocks().enumerate() {
+      if block.is_free {
+        continue;
+      }
+      top.live_blocks += 1;
+      top.bytes_in_use += block.size;
+      if N > 0 {
+        top.offer(self.describe(index, block, 0));
+      }
+    }
+    top
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::alloc::Layout;
+
+  /// An allocator with live blocks of the given sizes, in order.
+  fn with_blocks(sizes: &[usize]) -> BumpAllocator {
+    let mut allocator = BumpAllocator::with_capacity(64 * 1024);
+    for &size in sizes {
+      unsafe { allocator.allocate(Layout::array::<u8>(size).unwrap()) };
+    }
+    allocator
+  }
+
+  fn sizes<const N: usize>(top: &TopAllocations<N>) -> Vec<usize> {
+    top.iter().map(|info| info.size).collect()
+  }
+
+  #[test]
+  fn largest_blocks_come_first() {
+    let allocator = with_blocks(&[10, 500, 30, 2000, 70, 400]);
+    let top = allocator.top_allocations::<3>();
+
+    assert_eq!(sizes(&top), [2000, 500, 400]);
+    assert_eq!(top.iter().map(|info| info.index).collect::<Vec<_>>(), [3, 1, 5]);
+    assert_eq!(top.bytes_in_use(), 3010);
+  }
+
+  #[test]
+  fn free_blocks_are_ignored() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    unsafe {
+      let big = allocator.allocate(Layout::array::<u8>(1000).unwrap());
+      allocator.allocate(Layout::array::<u8>(8).unwrap());
+      allocator.deallocate(big);
+    }
+    assert_eq!(sizes(&allocator.top_allocations::<4>()), [8]);
+  }
+
+  #[test]
+  fn ties_keep_the_older_block() {
+    let allocator = with_blocks(&[64, 64, 64]);
+    let top = allocator.top_allocations::<2>();
+    assert_eq!(top.iter().map(|info| info.index).collect::<Vec<_>>(), [0, 1]);
+  }
+
+  #[test]
+  fn short_lists_and_empty_reports() {
+    let allocator = with_blocks(&[5, 9]);
+    assert_eq!(sizes(&allocator.top_allocations::<8>()), [9, 5]);
+    assert!(allocator.top_allocations::<0>().is_empty());
+    assert!(BumpAllocator::with_capacity(64).top_allocations::<3>().is_empty());
+  }
+
+  #[test]
+  fn report_shows_shares() {
+    let allocator = with_blocks(&[750, 250]);
+    let report = allocator.top_allocations::<1>().to_string();
+
+    assert!(report.lines().nth(1).unwrap().ends_with("750   75.0%"), "{report}");
+    assert!(report.ends_with("top 1 of 2 live blocks: 750 B of 1000 B in use"), "{report}");
+  }
+}
@@ -0,0 +1,318 @@
+//! # Typed Allocation
+//!
+//! [`alloc`](BumpAllocator::alloc) moves a value into the arena and returns
+//! a typed pointer to it, so simple object graphs need no `Layout` or raw
+//! casts. The value is never dropped: the arena frees memory, not objects,
+//! so types that own resources need their destructors run by hand.
+//!
+//! With `std`, an allocator can also remember the type of every value it
+//! placed, and hand back all the live ones of a type - to dump them while
+//! debugging, serialize a world, or update every component in one loop:
+//!
+//! ```text
+//!   set_type_tracking(true)
+//!
+//!   alloc(Pos)  alloc(Vel)  alloc(Pos)  free(·)  alloc(Pos)
+//!     [P1]        [V1]        [P2]      [P2]─►✗    [P3]
+//!
+//!   types: { P1 ─► Pos, V1 ─► Vel, P3 ─► Pos }
+//!
+//!   iter_of::<Pos>()  ──►  P1, P3      (address order)
+//! ```
+//!
+//! Tracking is off by default and costs a map entry per value while on.
+//! Only values placed with `alloc` are recorded: raw `allocate` calls have
+//! no type to record. The map itself lives on the global heap, so leave
+//! tracking off on an allocator installed as the global allocator.
+
+#[cfg(feature = "std")]
+use core::any::TypeId;
+use core::{
+  alloc::Layout,
+  ptr::{self, NonNull},
+};
+
+use crate::{AllocFailure, BumpAllocator};
+
+impl BumpAllocator {
+  /// Moves `value` into the arena. Returns `None` if the arena is out of
+  /// memory, dropping `value` - or panics, if the
+  /// [`FailurePolicy`](crate::FailurePolicy) says so.
+  ///
+  /// The value is never dropped by the allocator, not even by `reset`.
+  /// `T` must be `'static` so its type can be recorded; link values to
+  /// each other with pointers rather than references.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// let point = allocator.alloc(Point { x: 1, y: 2 }).unwrap();
+  /// assert_eq!(unsafe { point.as_ref() }.x, 1);
+  /// unsafe { allocator.deallocate(point.as_ptr().cast()) };
+  /// ```
+  #[track_caller]
+  pub fn alloc<T: 'static>(
+    &mut self,
+    value: T,
+  ) -> Option<NonNull<T>> {
+    self.try_alloc(value).ok()
+  }
+
+  /// Like [`alloc`](Self::alloc), but says why the allocation failed -
+  /// a heap limit, a quota, the rate limit - for callers that report
+  /// errors instead of unwrapping.
+  ///
+  /// # Errors
+  ///
+  /// The [`AllocFailure`] of the failed allocation; `value` is dropped.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// let node = allocator.try_alloc(Node::new(id)).map_err(Error::OutOfMemory)?;
+  /// ```
+  #[track_caller]
+  pub fn try_alloc<T: 'static>(
+    &mut self,
+    value: T,
+  ) -> Result<NonNull<T>, AllocFailure> {
+    let address = self.allocate_for_helper(Layout::new::<T>())?.cast::<T>();
+    // SAFETY: The block fits a `T` and is aligned for one.
+    unsafe { address.write(value) };
+
+    #[cfg(feature = "std")]
+    if let Some(types) = &mut self.types {
+      types.insert(address.as_ptr() as usize, TypeId::of::<T>());
+    }
+    Ok(address)
+  }
+
+  /// Copies `text` into the arena. Returns `None` if the arena is out of
+  /// memory; the empty string takes no memory.
+  ///
+  /// Strings have no type to record, so they never show up in `iter_of`.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// let name = allocator.alloc_str(&line[start..end]).unwrap();
+  /// println!("{}", unsafe { name.as_ref() });
+  /// ```
+  #[track_caller]
+  pub fn alloc_str(
+    &mut self,
+    text: &str,
+  ) -> Option<NonNull<str>> {
+    self.try_alloc_str(text).ok()
+  }
+
+  /// Like [`alloc_str`](Self::alloc_str), but says why the allocation
+  /// failed.
+  ///
+  /// # Errors
+  ///
+  /// The [`AllocFailure`] of the failed allocation.
+  #[track_caller]
+  pub fn try_alloc_str(
+    &mut self,
+    text: &str,
+  ) -> Result<NonNull<str>, AllocFailure> {
+    if text.is_empty() {
+      return Ok(NonNull::from(""));
+    }
+    let address = self.allocate_for_helper(Layout::for_value(text))?;
+    // SAFETY: The block holds `text.len()` bytes and cannot overlap `text`,
+    // which the caller already owns.
+    unsafe {
+      ptr::copy_nonoverlapping(text.as_ptr(), address.as_ptr(), text.len());
+      let bytes = NonNull::slice_from_raw_parts(address, text.len());
+      Ok(NonNull::new_unchecked(bytes.as_ptr() as *mut str))
+    }
+  }
+}
+
+#[cfg(feature = "std")]
+impl BumpAllocator {
+  /// Starts or stops recording the type of each value placed with
+  /// [`alloc`](Self::alloc). Stopping forgets every recorded type.
+  pub fn set_type_tracking(
+    &mut self,
+    enabled: bool,
+  ) {
+    if !enabled {
+      self.types = None;
+    } else if self.types.is_none() {
+      self.types = Some(std::collections::BTreeMap::new());
+    }
+  }
+
+  /// Whether types are being recorded, see
+  /// [`set_type_tracking`](Self::set_type_tracking).
+  pub fn tracks_types(&self) -> bool {
+    self.types.is_some()
+  }
+
+  /// Every live value of type `T` placed with [`alloc`](Self::alloc) while
+  /// tracking was on, in address order. Empty if tracking is off.
+  ///
+  /// # Example
+  ///
+  /// ```rust,ignore
+  /// allocator.set_type_tracking(true);
+  /// // ... alloc(Position { .. }) all over the frame ...
+  /// for mut position in allocator.iter_of::<Position>() {
+  ///     unsafe { position.as_mut() }.x += 1.0;
+  /// }
+  /// ```
+  pub fn iter_of<T: 'static>(&self) -> impl Iterator<Item = NonNull<T>> + '_ {
+    let id = TypeId::of::<T>();
+    self
+      .types
+      .iter()
+      .flatten()
+      .filter(move |&(_, &type_id)| type_id == id)
+      // SAFETY: Recorded addresses come from successful allocations.
+      .map(|(&address, _)| unsafe { NonNull::new_unchecked(address as *mut T) })
+  }
+
+  /// Forgets the value at `address`, freed or about to be.
+  pub(crate) fn forget_type(
+    &mut self,
+    address: *mut u8,
+  ) {
+    if let Some(types) = &mut self.types {
+      types.remove(&(address as usize));
+    }
+  }
+
+  /// Forgets every value at or past `address`, when the heap is cut back
+  /// there.
+  pub(crate) fn forget_types_from(
+    &mut self,
+    address: usize,
+  ) {
+    if let Some(types) = &mut self.types {
+      types.split_off(&address);
+    }
+  }
+
+  /// Moves the recorded addresses by `offset`, for a copy of the heap that
+  /// starts `offset` bytes from the original.
+  pub(crate) fn relocate_types(
+    &mut self,
+    offset: isize,
+  ) {
+    if let Some(types) = &mut self.types {
+      *types = types
+        .iter()
+        .map(|(&address, &id)| (address.wrapping_add_signed(offset), id))
+        .collect();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[derive(Debug, Clone, Copy, PartialEq)]
+  struct Position(f32, f32);
+
+  #[derive(Debug, Clone, Copy, PartialEq)]
+  struct Velocity(f32, f32);
+
+  #[test]
+  fn values_are_moved_into_the_arena() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let position = allocator.alloc(Position(1.0, 2.0)).unwrap();
+    assert_eq!(unsafe { *position.as_ptr() }, Position(1.0, 2.0));
+    assert_eq!(position.as_ptr() as usize % align_of::<Position>(), 0);
+    assert!(allocator.allocation_info(position.as_ptr().cast()).is_some());
+  }
+
+  #[test]
+  fn strings_are_copied() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    let source = String::from("héllo");
+    let copy = allocator.alloc_str(&source).unwrap();
+    drop(source);
+    assert_eq!(unsafe { copy.as_ref() }, "héllo");
+    assert_eq!(unsafe { allocator.alloc_str("").unwrap().as_ref() }, "");
+    assert_eq!(allocator.live_blocks(), 1);
+  }
+
+  #[test]
+  fn full_arenas_give_none() {
+    let mut allocator = BumpAllocator::with_capacity(64);
+    assert!(allocator.alloc([0u8; 1024]).is_none());
+  }
+
+  #[test]
+  fn failures_say_why() {
+    let mut allocator = BumpAllocator::with_capacity(1 << 20);
+    allocator.set_heap_limit(Some(256));
+    assert!(allocator.try_alloc(7u64).is_ok());
+    assert!(matches!(
+      allocator.try_alloc([0u8; 1024]),
+      Err(AllocFailure::ExceedsHeapLimit { limit: 256, .. })
+    ));
+    assert!(matches!(
+      allocator.try_alloc_str(&"x".repeat(1024)),
+      Err(AllocFailure::ExceedsHeapLimit { .. })
+    ));
+  }
+
+  #[test]
+  fn live_values_are_iterated_by_type() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_type_tracking(true);
+
+    let p1 = allocator.alloc(Position(1.0, 1.0)).unwrap();
+    let v1 = allocator.alloc(Velocity(0.5, 0.0)).unwrap();
+    let p2 = allocator.alloc(Position(2.0, 2.0)).unwrap();
+    let p3 = allocator.alloc(Position(3.0, 3.0)).unwrap();
+    unsafe { allocator.deallocate(p2.as_ptr().cast()) };
+
+    assert_eq!(allocator.iter_of::<Position>().collect::<Vec<_>>(), [p1, p3]);
+    assert_eq!(allocator.iter_of::<Velocity>().collect::<Vec<_>>(), [v1]);
+    assert_eq!(allocator.iter_of::<u64>().count(), 0);
+
+    for mut position in allocator.iter_of::<Position>() {
+      unsafe { position.as_mut().0 += 10.0 };
+    }
+    assert_eq!(unsafe { *p3.as_ptr() }, Position(13.0, 3.0));
+  }
+
+  #[test]
+  fn only_tracked_values_are_listed() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.alloc(Position(0.0, 0.0)).unwrap();
+    assert_eq!(allocator.iter_of::<Position>().count(), 0);
+
+    allocator.set_type_tracking(true);
+    let tracked = allocator.alloc(Position(1.0, 0.0)).unwrap();
+    assert_eq!(allocator.iter_of::<Position>().collect::<Vec<_>>(), [tracked]);
+
+    allocator.set_type_tracking(false);
+    assert!(!allocator.tracks_types());
+    assert_eq!(allocator.iter_of::<Position>().count(), 0);
+  }
+
+  #[test]
+  fn rewinding_forgets_values() {
+    let mut allocator = BumpAllocator::with_capacity(4096);
+    allocator.set_type_tracking(true);
+    let kept = allocator.alloc(Position(1.0, 0.0)).unwrap();
+    let mark = allocator.checkpoint();
+    allocator.alloc(Position(2.0, 0.0)).unwrap();
+    allocator.alloc(Velocity(0.0, 0.0)).unwrap();
+
+    unsafe { allocator.shrink_to(mark) };
+    assert_eq!(allocator.iter_of::<Position>().collect::<Vec<_>>(), [kept]);
+    assert_eq!(allocator.iter_of::<Velocity>().count(), 0);
+
+    unsafe { allocator.reset() };
+    assert_eq!(allocator.iter_of::<Position>().count(), 0);
+    assert!(allocator.tracks_types());
+  }
+}
@@ -0,0 +1,125 @@
+//! # Human-Readable Sizes
+//!
+//! Shared formatting for the diagnostic reports ([`Stats`](crate::Stats),
+//! [`HeapMap`](crate::HeapMap), [`TopAllocations`](crate::TopAllocations)),
+//! so they all print sizes and addresses the same way:
+//!
+//! ```text
+//!   ByteSize(45)          "45 B"
+//!   ByteSize(1536)        "1.5 KiB"
+//!   ByteSize(268435456)   "256.0 MiB"
+//! ```
+//!
+//! Per-block columns keep exact byte counts; totals are humanized. Nothing
+//! here allocates, so the reports still work without `std` and in the
+//! middle of an out-of-memory situation.
+
+use core::{fmt, mem, str};
+
+/// A byte count that displays with binary units (`B`, `KiB`, `MiB`, ...).
+///
+/// Counts below 1 KiB print exactly; larger ones with one decimal. Width
+/// and alignment flags apply, so it fits in table columns:
+///
+/// ```rust,ignore
+/// assert_eq!(format!("[{:>9}]", ByteSize(1536)), "[  1.5 KiB]");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct ByteSize(pub usize);
+
+/// Unit suffixes, each 1024 times the previous one.
+const UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+impl fmt::Display for ByteSize {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    let mut unit = 0;
+    while unit + 1 < UNITS.len() && self.0 as u128 >= 1u128 << (10 * (unit + 1)) {
+      unit += 1;
+    }
+
+    // Render into a stack buffer first so `pad` can apply the width
+    let mut buffer = StackBuffer::new();
+    if unit == 0 {
+      fmt::write(&mut buffer, format_args!("{} B", self.0))?;
+    } else {
+      // Tenths of the unit, rounded, without floating point
+      let divisor = 1u128 << (10 * unit);
+      let tenths = (self.0 as u128 * 10 + divisor / 2) / divisor;
+      fmt::write(&mut buffer, format_args!("{}.{} {}", tenths / 10, tenths % 10, UNITS[unit]))?;
+    }
+    f.pad(buffer.as_str())
+  }
+}
+
+/// Width of a `{:#0x}` address column: `0x` plus two digits per byte.
+pub(crate) const fn address_width() -> usize {
+  2 * mem::size_of::<usize>() + 2
+}
+
+/// Fixed-size [`fmt::Write`] target, large enough for any [`ByteSize`].
+pub(crate) struct StackBuffer {
+  /// Written bytes; only the first `len` are meaningful.
+  bytes: [u8; 32],
+
+  /// Number of bytes written.
+  len: usize,
+}
+
+impl StackBuffer {
+  /// An empty buffer.
+  pub(crate) const fn new() -> Self {
+    Self { bytes: [0; 32], len: 0 }
+  }
+
+  /// The text written so far.
+  pub(crate) fn as_str(&self) -> &str {
+    // Only whole `str`s are ever written
+    str::from_utf8(&self.bytes[..self.len]).unwrap_or_default()
+  }
+}
+
+impl fmt::Write for StackBuffer {
+  fn write_str(
+    &mut self,
+    text: &str,
+  ) -> fmt::Result {
+    let end = self.len + text.len();
+    let slot = self.bytes.get_mut(self.len..end).ok_or(fmt::Error)?;
+    slot.copy_from_slice(text.as_bytes());
+    self.len = end;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn small_sizes_are_exact() {
+    assert_eq!(ByteSize(0).to_string(), "0 B");
+    assert_eq!(ByteSize(1023).to_string(), "1023 B");
+  }
+
+  #[test]
+  fn larger_sizes_use_binary_units() {
+    assert_eq!(ByteSize(1024).to_string(), "1.0 KiB");
+    assert_eq!(ByteSize(1536).to_string(), "1.5 KiB");
+    assert_eq!(ByteSize(256 << 20).to_string(), "256.0 MiB");
+    assert_eq!(ByteSize(3 << 30).to_string(), "3.0 GiB");
+    assert!(ByteSize(usize::MAX).to_string().ends_with(if cfg!(target_pointer_width = "64") {
+      "16.0 EiB"
+    } else {
+      "4.0 GiB"
+    }));
+  }
+
+  #[test]
+  fn width_and_alignment_apply() {
+    assert_eq!(format!("[{:>9}]", ByteSize(1536)), "[  1.5 KiB]");
+    assert_eq!(format!("[{:<6}]", ByteSize(7)), "[7 B   ]");
+  }
+}